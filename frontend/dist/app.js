@@ -6,6 +6,8 @@
     { id: "boundaries", label: "Boundaries", stage: 1, available: true },
     { id: "distance", label: "Distance", stage: 1, available: true },
     { id: "heightmap", label: "Heightmap", stage: 1, available: true },
+    { id: "normals", label: "Normals", stage: 1, available: true },
+    { id: "ao", label: "AO", stage: 1, available: true },
     { id: "map", label: "Map", stage: 1, available: true },
     // Stage 2
     { id: "temperature", label: "Temperature", stage: 2, available: true },
@@ -15,33 +17,143 @@
     { id: "biomes", label: "Biomes", stage: 3, available: false },
     { id: "final", label: "Final", stage: 4, available: false }
   ];
-  var ELEVATION_PARAMS = [
-    "num_macroplates",
-    "num_microplates",
-    "boundary_noise",
-    "blur_sigma",
-    "mountain_scale",
-    "trench_scale",
-    "mountain_width",
-    "coast_amp",
-    "interior_amp",
-    "detail_amp",
-    "shelf_width",
-    "rainfall_scale",
-    "river_threshold"
-  ];
-  var ALL_CONTROLS = ["seed", "size", "fraction", ...ELEVATION_PARAMS];
+  var TOP_BAR_CATEGORY = "plates";
+  var FIXED_CONTROLS = ["seed", "size", "preset"];
   var STORAGE_KEY = "worldgen_params";
+  var SESSION = Math.random().toString(36).slice(2) + Date.now().toString(36);
+  var HEADERS = { "Content-Type": "application/json", "X-Session": SESSION };
+  async function errorMessage(res) {
+    try {
+      const body = await res.json();
+      if (body.message) return body.message;
+    } catch {
+    }
+    return `${res.status} ${res.statusText}`;
+  }
+  function formatValue(value, step) {
+    if (Math.abs(value) >= 1e6) return value.toExponential(1);
+    const decimals = (String(step).split(".")[1] ?? "").length;
+    return value.toFixed(decimals);
+  }
   var App = class {
     constructor() {
       this.activeLayer = "map";
       this.layerData = /* @__PURE__ */ new Map();
       this.generating = false;
       this.riversLoading = false;
+      this.schema = [];
+      this.presets = [];
+      this.shown = null;
+      this.shownTag = null;
       this.buildTabs();
       this.bindEvents();
+      this.loadControls();
+    }
+    async loadControls() {
+      try {
+        const [schema, presets] = await Promise.all([
+          fetch("/api/params/schema").then((r) => r.json()),
+          fetch("/api/presets").then((r) => r.json())
+        ]);
+        this.schema = schema;
+        this.presets = presets;
+      } catch (err) {
+        document.getElementById("status").textContent = `error: ${err}`;
+        return;
+      }
+      this.buildControls();
       this.loadParams();
     }
+    buildControls() {
+      const topBar = document.getElementById("plate-controls");
+      const panel = document.getElementById("elevation-controls");
+      let category = "";
+      for (const info of this.schema) {
+        const inTopBar = info.category === TOP_BAR_CATEGORY;
+        if (!inTopBar && category && info.category !== category) {
+          const separator = document.createElement("div");
+          separator.className = "separator";
+          panel.appendChild(separator);
+        }
+        if (!inTopBar) category = info.category;
+        (inTopBar ? topBar : panel).appendChild(this.buildControl(info));
+        this.setParam(info, info.default);
+      }
+      const select = document.getElementById("preset");
+      for (const name of ["", ...this.presets.map((p) => p.name)]) {
+        const option = document.createElement("option");
+        option.value = name;
+        option.textContent = name || "custom";
+        select.appendChild(option);
+      }
+      select.value = "earthlike";
+    }
+    buildControl(info) {
+      const group = document.createElement("div");
+      group.className = "control-group";
+      group.title = info.description;
+      const label = document.createElement("label");
+      label.textContent = info.label;
+      group.appendChild(label);
+      let input;
+      if (info.type === "choice") {
+        input = document.createElement("select");
+        for (const name of info.options) {
+          const option = document.createElement("option");
+          option.value = option.textContent = name;
+          input.appendChild(option);
+        }
+      } else if (info.type === "bool") {
+        input = document.createElement("input");
+        input.type = "checkbox";
+      } else {
+        input = document.createElement("input");
+        input.type = "range";
+        input.min = String(info.min);
+        input.max = String(info.max);
+        input.step = String(info.step);
+      }
+      input.id = info.name;
+      group.appendChild(input);
+      if (info.type === "int" || info.type === "float") {
+        const display = document.createElement("span");
+        display.className = "val";
+        display.id = `${info.name}-val`;
+        group.appendChild(display);
+      }
+      input.addEventListener("input", () => {
+        this.updateDisplay(info);
+        document.getElementById("preset").value = "";
+      });
+      return group;
+    }
+    updateDisplay(info) {
+      const display = document.getElementById(`${info.name}-val`);
+      if (display) display.textContent = formatValue(this.readParam(info), info.step);
+    }
+    readParam(info) {
+      const el = document.getElementById(info.name);
+      if (info.type === "bool") return el.checked;
+      if (info.type === "choice") return el.value;
+      return parseFloat(el.value);
+    }
+    setParam(info, value) {
+      const el = document.getElementById(info.name);
+      if (!el) return;
+      if (info.type === "bool") {
+        el.checked = value === true;
+      } else {
+        el.value = String(value);
+      }
+      this.updateDisplay(info);
+    }
+    applyPreset(name) {
+      const preset = this.presets.find((p) => p.name === name);
+      if (!preset) return;
+      for (const info of this.schema) {
+        this.setParam(info, preset.params[info.name]);
+      }
+    }
     buildTabs() {
       const bar = document.getElementById("tab-bar");
       for (const def of LAYERS) {
@@ -73,24 +185,9 @@
           this.setActiveLayer(tab.dataset.layer);
         }
       });
-      document.getElementById("fraction").addEventListener("input", (e) => {
-        document.getElementById("fraction-val").textContent = parseFloat(
-          e.target.value
-        ).toFixed(2);
+      document.getElementById("preset").addEventListener("change", (e) => {
+        this.applyPreset(e.target.value);
       });
-      for (const id of ELEVATION_PARAMS) {
-        const el = document.getElementById(id);
-        if (el) {
-          el.addEventListener("input", (e) => {
-            const val = e.target.value;
-            const display = document.getElementById(`${id}-val`);
-            if (display) {
-              const num = parseFloat(val);
-              display.textContent = num % 1 === 0 ? val : num < 0.1 ? num.toFixed(3) : num.toFixed(1);
-            }
-          });
-        }
-      }
       document.getElementById("btn-toggle-elevation").addEventListener("click", () => {
         const panel = document.getElementById("elevation-controls");
         const btn = document.getElementById("btn-toggle-elevation");
@@ -112,9 +209,11 @@
     }
     saveParams() {
       const params = {};
-      for (const id of ALL_CONTROLS) {
-        const el = document.getElementById(id);
-        if (el) params[id] = el.value;
+      for (const id of FIXED_CONTROLS) {
+        params[id] = document.getElementById(id).value;
+      }
+      for (const info of this.schema) {
+        params[info.name] = this.readParam(info);
       }
       localStorage.setItem(STORAGE_KEY, JSON.stringify(params));
       const status = document.getElementById("status");
@@ -125,19 +224,12 @@
       if (!raw) return;
       try {
         const params = JSON.parse(raw);
-        for (const [id, val] of Object.entries(params)) {
+        for (const id of FIXED_CONTROLS) {
           const el = document.getElementById(id);
-          if (!el) continue;
-          el.value = val;
-          const display = document.getElementById(`${id}-val`);
-          if (display) {
-            const num = parseFloat(val);
-            if (id === "fraction") {
-              display.textContent = num.toFixed(2);
-            } else {
-              display.textContent = num % 1 === 0 ? val : num < 0.1 ? num.toFixed(3) : num.toFixed(1);
-            }
-          }
+          if (typeof params[id] === "string") el.value = params[id];
+        }
+        for (const info of this.schema) {
+          if (info.name in params) this.setParam(info, params[info.name]);
         }
       } catch {
       }
@@ -179,26 +271,14 @@
         tab.classList.remove("loading");
       }
     }
-    readSlider(id) {
-      const el = document.getElementById(id);
-      return el ? parseFloat(el.value) : 0;
-    }
     buildRequestBody() {
       const seed = parseInt(
         document.getElementById("seed").value
       ) || 42;
       const [width, height] = document.getElementById("size").value.split("x").map(Number);
-      const continental_fraction = parseFloat(
-        document.getElementById("fraction").value
-      );
-      const body = {
-        seed,
-        width,
-        height,
-        continental_fraction
-      };
-      for (const id of ELEVATION_PARAMS) {
-        body[id] = this.readSlider(id);
+      const body = { seed, width, height };
+      for (const info of this.schema) {
+        body[info.name] = this.readParam(info);
       }
       return body;
     }
@@ -215,16 +295,27 @@
       const body = this.buildRequestBody();
       try {
         const t0 = performance.now();
+        const headers = { ...HEADERS };
+        if (this.shownTag) headers["If-None-Match"] = this.shownTag;
         const baseRes = await fetch("/api/generate", {
           method: "POST",
-          headers: { "Content-Type": "application/json" },
+          headers,
           body: JSON.stringify(body)
         });
+        if (baseRes.status === 304 && this.shown) {
+          status.textContent = "unchanged | rivers computing...";
+          btn.disabled = false;
+          this.generating = false;
+          this.fetchRivers(this.shown.key, this.shown.timings, performance.now());
+          return;
+        }
         if (!baseRes.ok) {
-          throw new Error(`${baseRes.status} ${baseRes.statusText}`);
+          throw new Error(await errorMessage(baseRes));
         }
         const baseData = await baseRes.json();
         const baseRoundtrip = performance.now() - t0;
+        this.shown = baseData;
+        this.shownTag = baseRes.headers.get("ETag");
         this.layerData.clear();
         for (const layer of baseData.layers) {
           this.layerData.set(layer.name, layer.data_url);
@@ -236,7 +327,7 @@
         this.updateTimings(baseData.timings);
         btn.disabled = false;
         this.generating = false;
-        this.fetchRivers(body, baseData.timings, performance.now());
+        this.fetchRivers(baseData.key, baseData.timings, performance.now());
       } catch (err) {
         status.textContent = `error: ${err}`;
         btn.disabled = false;
@@ -245,16 +336,21 @@
         this.updateRiversTab();
       }
     }
-    async fetchRivers(_body, baseTimings, t0) {
+    async fetchRivers(key, baseTimings, t0) {
       const status = document.getElementById("status");
+      let superseded = false;
       try {
         const res = await fetch("/api/rivers", {
           method: "POST",
-          headers: { "Content-Type": "application/json" },
-          body: "{}"
+          headers: HEADERS,
+          body: JSON.stringify({ key })
         });
+        if (res.status === 409) {
+          superseded = true;
+          return;
+        }
         if (!res.ok) {
-          throw new Error(`rivers: ${res.status}`);
+          throw new Error(await errorMessage(res));
         }
         const data = await res.json();
         if (data) {
@@ -269,10 +365,12 @@
       } catch (err) {
         status.textContent += ` | rivers error: ${err}`;
       } finally {
-        this.riversLoading = false;
-        this.updateRiversTab();
-        if (this.activeLayer === "rivers") {
-          this.updateImage();
+        if (!superseded) {
+          this.riversLoading = false;
+          this.updateRiversTab();
+          if (this.activeLayer === "rivers") {
+            this.updateImage();
+          }
         }
       }
     }
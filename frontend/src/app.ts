@@ -9,6 +9,7 @@ interface TimingEntry {
 }
 
 interface GenerateResponse {
+  key: string;
   layers: Layer[];
   timings: TimingEntry[];
   width: number;
@@ -20,6 +21,26 @@ interface RiversResponse {
   timing: TimingEntry;
 }
 
+// One entry of /api/params/schema
+interface ParamInfo {
+  name: string;
+  label: string;
+  description: string;
+  category: string;
+  type: "int" | "float" | "bool" | "choice";
+  min?: number;
+  max?: number;
+  step?: number;
+  options?: string[];
+  default: number | boolean | string;
+}
+
+// One entry of /api/presets
+interface PresetInfo {
+  name: string;
+  params: Record<string, number | boolean | string>;
+}
+
 interface LayerDef {
   id: string;
   label: string;
@@ -32,6 +53,8 @@ const LAYERS: LayerDef[] = [
   { id: "boundaries", label: "Boundaries", stage: 1, available: true },
   { id: "distance", label: "Distance", stage: 1, available: true },
   { id: "heightmap", label: "Heightmap", stage: 1, available: true },
+  { id: "normals", label: "Normals", stage: 1, available: true },
+  { id: "ao", label: "AO", stage: 1, available: true },
   { id: "map", label: "Map", stage: 1, available: true },
   // Stage 2
   { id: "temperature", label: "Temperature", stage: 2, available: true },
@@ -42,40 +65,172 @@ const LAYERS: LayerDef[] = [
   { id: "final", label: "Final", stage: 4, available: false },
 ];
 
-// All parameter IDs (sliders in the Tune panel)
-const ELEVATION_PARAMS = [
-  "num_macroplates",
-  "num_microplates",
-  "boundary_noise",
-  "blur_sigma",
-  "mountain_scale",
-  "trench_scale",
-  "mountain_width",
-  "coast_amp",
-  "interior_amp",
-  "detail_amp",
-  "shelf_width",
-  "rainfall_scale",
-  "river_threshold",
-];
+// Parameters of this category get top-bar controls; the rest go in the
+// Tune panel
+const TOP_BAR_CATEGORY = "plates";
 
-// All saveable control IDs (includes top-bar controls)
-const ALL_CONTROLS = ["seed", "size", "fraction", ...ELEVATION_PARAMS];
+// Saveable controls besides the parameters
+const FIXED_CONTROLS = ["seed", "size", "preset"];
 
 const STORAGE_KEY = "worldgen_params";
 
+// Each tab is its own server session, so tabs (and users) don't cancel
+// each other's generations
+const SESSION = Math.random().toString(36).slice(2) + Date.now().toString(36);
+const HEADERS = { "Content-Type": "application/json", "X-Session": SESSION };
+
+// The server answers failures with a JSON `{ code, message }` body
+async function errorMessage(res: Response): Promise<string> {
+  try {
+    const body: { message?: string } = await res.json();
+    if (body.message) return body.message;
+  } catch {
+    // not JSON: fall back to the status line
+  }
+  return `${res.status} ${res.statusText}`;
+}
+
+// A slider value with as many decimals as its step
+function formatValue(value: number, step: number): string {
+  if (Math.abs(value) >= 1e6) return value.toExponential(1);
+  const decimals = (String(step).split(".")[1] ?? "").length;
+  return value.toFixed(decimals);
+}
+
 class App {
   private activeLayer = "map";
   private layerData = new Map<string, string>();
   private generating = false;
   private riversLoading = false;
+  private schema: ParamInfo[] = [];
+  private presets: PresetInfo[] = [];
+  // The response shown, and its ETag for conditional requests
+  private shown: GenerateResponse | null = null;
+  private shownTag: string | null = null;
 
   constructor() {
     this.buildTabs();
     this.bindEvents();
+    this.loadControls();
+  }
+
+  // Parameter controls and presets come from the server
+  private async loadControls() {
+    try {
+      const [schema, presets] = await Promise.all([
+        fetch("/api/params/schema").then((r) => r.json()),
+        fetch("/api/presets").then((r) => r.json()),
+      ]);
+      this.schema = schema;
+      this.presets = presets;
+    } catch (err) {
+      document.getElementById("status")!.textContent = `error: ${err}`;
+      return;
+    }
+    this.buildControls();
     this.loadParams();
   }
 
+  private buildControls() {
+    const topBar = document.getElementById("plate-controls")!;
+    const panel = document.getElementById("elevation-controls")!;
+    let category = "";
+    for (const info of this.schema) {
+      const inTopBar = info.category === TOP_BAR_CATEGORY;
+      if (!inTopBar && category && info.category !== category) {
+        const separator = document.createElement("div");
+        separator.className = "separator";
+        panel.appendChild(separator);
+      }
+      if (!inTopBar) category = info.category;
+      (inTopBar ? topBar : panel).appendChild(this.buildControl(info));
+      this.setParam(info, info.default);
+    }
+
+    const select = document.getElementById("preset") as HTMLSelectElement;
+    for (const name of ["", ...this.presets.map((p) => p.name)]) {
+      const option = document.createElement("option");
+      option.value = name;
+      option.textContent = name || "custom";
+      select.appendChild(option);
+    }
+    select.value = "earthlike";
+  }
+
+  private buildControl(info: ParamInfo): HTMLElement {
+    const group = document.createElement("div");
+    group.className = "control-group";
+    group.title = info.description;
+    const label = document.createElement("label");
+    label.textContent = info.label;
+    group.appendChild(label);
+
+    let input: HTMLInputElement | HTMLSelectElement;
+    if (info.type === "choice") {
+      input = document.createElement("select");
+      for (const name of info.options!) {
+        const option = document.createElement("option");
+        option.value = option.textContent = name;
+        input.appendChild(option);
+      }
+    } else if (info.type === "bool") {
+      input = document.createElement("input");
+      input.type = "checkbox";
+    } else {
+      input = document.createElement("input");
+      input.type = "range";
+      input.min = String(info.min);
+      input.max = String(info.max);
+      input.step = String(info.step);
+    }
+    input.id = info.name;
+    group.appendChild(input);
+    if (info.type === "int" || info.type === "float") {
+      const display = document.createElement("span");
+      display.className = "val";
+      display.id = `${info.name}-val`;
+      group.appendChild(display);
+    }
+
+    // Editing a parameter leaves the preset
+    input.addEventListener("input", () => {
+      this.updateDisplay(info);
+      (document.getElementById("preset") as HTMLSelectElement).value = "";
+    });
+    return group;
+  }
+
+  private updateDisplay(info: ParamInfo) {
+    const display = document.getElementById(`${info.name}-val`);
+    if (display) display.textContent = formatValue(this.readParam(info) as number, info.step!);
+  }
+
+  private readParam(info: ParamInfo): number | boolean | string {
+    const el = document.getElementById(info.name) as HTMLInputElement;
+    if (info.type === "bool") return el.checked;
+    if (info.type === "choice") return el.value;
+    return parseFloat(el.value);
+  }
+
+  private setParam(info: ParamInfo, value: unknown) {
+    const el = document.getElementById(info.name) as HTMLInputElement | null;
+    if (!el) return;
+    if (info.type === "bool") {
+      el.checked = value === true;
+    } else {
+      el.value = String(value);
+    }
+    this.updateDisplay(info);
+  }
+
+  private applyPreset(name: string) {
+    const preset = this.presets.find((p) => p.name === name);
+    if (!preset) return;
+    for (const info of this.schema) {
+      this.setParam(info, preset.params[info.name]);
+    }
+  }
+
   private buildTabs() {
     const bar = document.getElementById("tab-bar")!;
     for (const def of LAYERS) {
@@ -114,27 +269,10 @@ class App {
       }
     });
 
-    document.getElementById("fraction")!.addEventListener("input", (e) => {
-      document.getElementById("fraction-val")!.textContent = parseFloat(
-        (e.target as HTMLInputElement).value
-      ).toFixed(2);
+    document.getElementById("preset")!.addEventListener("change", (e) => {
+      this.applyPreset((e.target as HTMLSelectElement).value);
     });
 
-    // Elevation parameter sliders
-    for (const id of ELEVATION_PARAMS) {
-      const el = document.getElementById(id);
-      if (el) {
-        el.addEventListener("input", (e) => {
-          const val = (e.target as HTMLInputElement).value;
-          const display = document.getElementById(`${id}-val`);
-          if (display) {
-            const num = parseFloat(val);
-            display.textContent = num % 1 === 0 ? val : num < 0.1 ? num.toFixed(3) : num.toFixed(1);
-          }
-        });
-      }
-    }
-
     // Toggle elevation controls
     document.getElementById("btn-toggle-elevation")!.addEventListener("click", () => {
       const panel = document.getElementById("elevation-controls")!;
@@ -161,10 +299,12 @@ class App {
   }
 
   private saveParams() {
-    const params: Record<string, string> = {};
-    for (const id of ALL_CONTROLS) {
-      const el = document.getElementById(id) as HTMLInputElement | HTMLSelectElement | null;
-      if (el) params[id] = el.value;
+    const params: Record<string, unknown> = {};
+    for (const id of FIXED_CONTROLS) {
+      params[id] = (document.getElementById(id) as HTMLInputElement | HTMLSelectElement).value;
+    }
+    for (const info of this.schema) {
+      params[info.name] = this.readParam(info);
     }
     localStorage.setItem(STORAGE_KEY, JSON.stringify(params));
     const status = document.getElementById("status")!;
@@ -175,20 +315,13 @@ class App {
     const raw = localStorage.getItem(STORAGE_KEY);
     if (!raw) return;
     try {
-      const params: Record<string, string> = JSON.parse(raw);
-      for (const [id, val] of Object.entries(params)) {
-        const el = document.getElementById(id) as HTMLInputElement | HTMLSelectElement | null;
-        if (!el) continue;
-        el.value = val;
-        const display = document.getElementById(`${id}-val`);
-        if (display) {
-          const num = parseFloat(val);
-          if (id === "fraction") {
-            display.textContent = num.toFixed(2);
-          } else {
-            display.textContent = num % 1 === 0 ? val : num < 0.1 ? num.toFixed(3) : num.toFixed(1);
-          }
-        }
+      const params: Record<string, unknown> = JSON.parse(raw);
+      for (const id of FIXED_CONTROLS) {
+        const el = document.getElementById(id) as HTMLInputElement | HTMLSelectElement;
+        if (typeof params[id] === "string") el.value = params[id] as string;
+      }
+      for (const info of this.schema) {
+        if (info.name in params) this.setParam(info, params[info.name]);
       }
     } catch {}
   }
@@ -239,12 +372,7 @@ class App {
     }
   }
 
-  private readSlider(id: string): number {
-    const el = document.getElementById(id) as HTMLInputElement | null;
-    return el ? parseFloat(el.value) : 0;
-  }
-
-  private buildRequestBody(): Record<string, number> {
+  private buildRequestBody(): Record<string, unknown> {
     const seed =
       parseInt(
         (document.getElementById("seed") as HTMLInputElement).value
@@ -254,19 +382,10 @@ class App {
     ).value
       .split("x")
       .map(Number);
-    const continental_fraction = parseFloat(
-      (document.getElementById("fraction") as HTMLInputElement).value
-    );
-
-    const body: Record<string, number> = {
-      seed,
-      width,
-      height,
-      continental_fraction,
-    };
-
-    for (const id of ELEVATION_PARAMS) {
-      body[id] = this.readSlider(id);
+
+    const body: Record<string, unknown> = { seed, width, height };
+    for (const info of this.schema) {
+      body[info.name] = this.readParam(info);
     }
 
     return body;
@@ -292,18 +411,30 @@ class App {
       const t0 = performance.now();
 
       // Fire base generation request
+      const headers: Record<string, string> = { ...HEADERS };
+      if (this.shownTag) headers["If-None-Match"] = this.shownTag;
       const baseRes = await fetch("/api/generate", {
         method: "POST",
-        headers: { "Content-Type": "application/json" },
+        headers,
         body: JSON.stringify(body),
       });
 
+      // 304: the world shown already; only its rivers need fetching again
+      if (baseRes.status === 304 && this.shown) {
+        status.textContent = "unchanged | rivers computing...";
+        btn.disabled = false;
+        this.generating = false;
+        this.fetchRivers(this.shown.key, this.shown.timings, performance.now());
+        return;
+      }
       if (!baseRes.ok) {
-        throw new Error(`${baseRes.status} ${baseRes.statusText}`);
+        throw new Error(await errorMessage(baseRes));
       }
 
       const baseData: GenerateResponse = await baseRes.json();
       const baseRoundtrip = performance.now() - t0;
+      this.shown = baseData;
+      this.shownTag = baseRes.headers.get("ETag");
 
       // Store base layer data immediately
       this.layerData.clear();
@@ -327,7 +458,7 @@ class App {
       this.generating = false;
 
       // Fire rivers request in background
-      this.fetchRivers(body, baseData.timings, performance.now());
+      this.fetchRivers(baseData.key, baseData.timings, performance.now());
     } catch (err) {
       status.textContent = `error: ${err}`;
       btn.disabled = false;
@@ -338,20 +469,26 @@ class App {
   }
 
   private async fetchRivers(
-    _body: Record<string, number>,
+    key: string,
     baseTimings: TimingEntry[],
     t0: number
   ) {
     const status = document.getElementById("status")!;
+    let superseded = false;
     try {
       const res = await fetch("/api/rivers", {
         method: "POST",
-        headers: { "Content-Type": "application/json" },
-        body: "{}",
+        headers: HEADERS,
+        body: JSON.stringify({ key }),
       });
 
+      // 409: a newer generation cancelled this one and fetches its own rivers
+      if (res.status === 409) {
+        superseded = true;
+        return;
+      }
       if (!res.ok) {
-        throw new Error(`rivers: ${res.status}`);
+        throw new Error(await errorMessage(res));
       }
 
       const data: RiversResponse | null = await res.json();
@@ -370,11 +507,13 @@ class App {
     } catch (err) {
       status.textContent += ` | rivers error: ${err}`;
     } finally {
-      this.riversLoading = false;
-      this.updateRiversTab();
-      // If user is viewing rivers tab, update the image now
-      if (this.activeLayer === "rivers") {
-        this.updateImage();
+      if (!superseded) {
+        this.riversLoading = false;
+        this.updateRiversTab();
+        // If user is viewing rivers tab, update the image now
+        if (this.activeLayer === "rivers") {
+          this.updateImage();
+        }
       }
     }
   }
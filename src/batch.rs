@@ -0,0 +1,84 @@
+//! Many worlds at one size, for datasets and galleries: a few generations
+//! run at once, each worker reusing its buffers through a `Generator`,
+//! and finished maps go to the caller as they complete.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+use crate::config::Params;
+use crate::{Generator, Map, Timing, WorldgenError, check_size};
+
+/// One finished world of a batch.
+pub struct BatchWorld {
+    /// Position of `seed` in the batch's seed list.
+    pub index: usize,
+    pub seed: u64,
+    pub map: Map,
+    pub timings: Vec<Timing>,
+}
+
+/// Generate a world (as `generate`) for every seed, `parallelism` at a
+/// time, and hand each to `sink` on the calling thread as it finishes.
+/// Workers wait while `sink` is busy, so at most `parallelism` maps are
+/// being built or waiting, plus the one `sink` holds.
+///
+/// Each world depends only on its seed, so the maps are the same
+/// whatever `parallelism` is; only the order `sink` sees them in varies.
+/// Fails up front if the size doesn't pass `check_size` or `params`
+/// don't pass `Params::validate_for_size`.
+pub fn generate_batch_with(
+    seeds: &[u64],
+    w: usize,
+    h: usize,
+    params: &Params,
+    parallelism: usize,
+    mut sink: impl FnMut(BatchWorld),
+) -> Result<(), WorldgenError> {
+    check_size(w, h)?;
+    params.validate_for_size(w, h)?;
+    let workers = parallelism.clamp(1, seeds.len().max(1));
+    let next = AtomicUsize::new(0);
+    // Zero capacity: a finished map stays with its worker until `sink`
+    // takes it
+    let (tx, rx) = mpsc::sync_channel(0);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let (tx, next) = (tx.clone(), &next);
+            scope.spawn(move || {
+                let mut generator = Generator::new(w, h).expect("size checked");
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&seed) = seeds.get(index) else {
+                        break;
+                    };
+                    let (map, timings) = generator.generate(seed, params).expect("params validated");
+                    // The receiver is gone only if `sink` panicked
+                    if tx.send(BatchWorld { index, seed, map, timings }).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+        for world in rx {
+            sink(world);
+        }
+    });
+    Ok(())
+}
+
+/// `generate_batch_with`, collecting the worlds in seed order. Every map
+/// is held at once; stream large batches through `generate_batch_with`.
+pub fn generate_batch(
+    seeds: &[u64],
+    w: usize,
+    h: usize,
+    params: &Params,
+    parallelism: usize,
+) -> Result<Vec<(Map, Vec<Timing>)>, WorldgenError> {
+    let mut worlds: Vec<Option<(Map, Vec<Timing>)>> = (0..seeds.len()).map(|_| None).collect();
+    generate_batch_with(seeds, w, h, params, parallelism, |world| {
+        worlds[world.index] = Some((world.map, world.timings));
+    })?;
+    Ok(worlds.into_iter().map(|w| w.expect("every seed generated")).collect())
+}
@@ -1,258 +1,1942 @@
-use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
 
-use axum::{Json, Router, extract::State, routing::post};
+use axum::body::Bytes;
+use axum::extract::{FromRequest, FromRequestParts, MatchedPath, Path, Query, Request, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
 use base64::Engine;
+use futures_util::{Stream, stream};
 use image::ImageEncoder;
 use image::codecs::png::PngEncoder;
 use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+use tower_http::compression::predicate::{NotForContentType, Predicate};
+use tower_http::compression::{CompressionLayer, DefaultPredicate};
 use tower_http::services::ServeDir;
 
-use worldgen::config::Params;
-use worldgen::render;
-use worldgen::Map;
+use worldgen::cancel::CancelToken;
+use worldgen::config::{ParamError, ParamInfo, Params, Preset, WorldFile};
+use worldgen::export;
+use worldgen::grid::cell_latlon;
+use worldgen::layers::LayerView;
+use worldgen::plates::boundary;
+use worldgen::query::CellInfo;
+use worldgen::render::tiles::{MAX_ZOOM, TILE_SIZE};
+use worldgen::render::{self, LayerKind, Palette, PalettePreset, RenderStyle};
+use worldgen::{Map, Pipeline, Stage, WorldgenError};
 
-#[derive(Deserialize, Clone)]
+#[derive(Deserialize, Clone, Default)]
 struct GenerateRequest {
     seed: Option<u64>,
+    /// Palette preset name (atlas, satellite, grayscale, colorblind).
+    palette: Option<String>,
+    /// Render layers to return (`LayerKind` names); `BASE_LAYERS` if left
+    /// out. Layers of later stages (rivers, population, ...) come back
+    /// empty, as those stages haven't run.
+    layers: Option<Vec<String>>,
+    /// Overlays for every returned layer, the rivers layer and tiles.
+    style: Option<StyleRequest>,
+    /// World preset name (earthlike, pangaea, archipelago, ...); the
+    /// parameters below override it.
+    preset: Option<String>,
     width: Option<usize>,
     height: Option<usize>,
-    num_macroplates: Option<usize>,
-    num_microplates: Option<usize>,
-    continental_fraction: Option<f32>,
-    boundary_noise: Option<f32>,
-    // Elevation profile
-    blur_sigma: Option<f32>,
-    mountain_scale: Option<f32>,
-    trench_scale: Option<f32>,
-    mountain_width: Option<f32>,
-    // Noise
-    coast_amp: Option<f32>,
-    interior_amp: Option<f32>,
-    detail_amp: Option<f32>,
-    // Features
-    shelf_width: Option<f32>,
-    ridge_height: Option<f32>,
-    rift_depth: Option<f32>,
-    // Climate / hydrology
-    rainfall_scale: Option<f32>,
-    river_threshold: Option<f32>,
+    /// `Params` fields; those left out keep the preset's (or default) value.
+    #[serde(flatten)]
+    params: serde_json::Map<String, serde_json::Value>,
 }
 
+impl GenerateRequest {
+    /// Whether the request sets nothing at all (`{}`).
+    fn is_empty(&self) -> bool {
+        self.seed.is_none()
+            && self.palette.is_none()
+            && self.preset.is_none()
+            && self.width.is_none()
+            && self.height.is_none()
+            && self.layers.is_none()
+            && self.style.is_none()
+            && self.params.is_empty()
+    }
+}
+
+/// Body of /api/rivers: a cached map's `key`, or the settings of a
+/// generation as for /api/generate, whose map is looked up by its key and
+/// generated first if it isn't cached. An empty body (or none) picks the
+/// session's latest map.
+#[derive(Deserialize, Default)]
+struct RiversRequest {
+    key: Option<CacheKey>,
+    #[serde(flatten)]
+    generation: GenerateRequest,
+}
+
+/// Body of /api/render: layers, palette and style as for /api/generate,
+/// a projection, and the cached map's `key` (the session's latest if left
+/// out).
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RenderRequest {
+    key: Option<CacheKey>,
+    layers: Option<Vec<String>>,
+    palette: Option<String>,
+    style: Option<StyleRequest>,
+    projection: Option<Projection>,
+}
+
+/// How /api/render lays a layer out: the map's own equirectangular grid,
+/// or a `size` x `size` orthographic globe (`size` defaults to the map's
+/// height) centered on `lat`, `lon`.
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Projection {
+    #[default]
+    Equirectangular,
+    Orthographic {
+        #[serde(default)]
+        lat: f32,
+        #[serde(default)]
+        lon: f32,
+        size: Option<usize>,
+    },
+}
+
+/// `RenderStyle` fields; those left out keep their defaults.
+#[derive(Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+struct StyleRequest {
+    ambient_occlusion: Option<f32>,
+    hillshade: Option<f32>,
+    river_overlay: Option<bool>,
+    boundaries: Option<bool>,
+    graticule: Option<f32>,
+}
+
+/// Layers /api/generate returns when the request doesn't pick any.
+const BASE_LAYERS: [LayerKind; 9] = [
+    LayerKind::Plates,
+    LayerKind::Boundaries,
+    LayerKind::Distance,
+    LayerKind::Heightmap,
+    LayerKind::Normals,
+    LayerKind::AmbientOcclusion,
+    LayerKind::Map,
+    LayerKind::Temperature,
+    LayerKind::Precipitation,
+];
+
 #[derive(Serialize)]
 struct GenerateResponse {
+    /// Names the cached map for /api/rivers and the export endpoints.
+    key: CacheKey,
     layers: Vec<Layer>,
     timings: Vec<TimingEntry>,
     width: usize,
     height: usize,
 }
 
+/// A generation's PNG-encoded layers and timings, before they're packed
+/// into a response.
+struct Rendered {
+    key: CacheKey,
+    layers: Vec<(&'static str, Vec<u8>)>,
+    timings: Vec<TimingEntry>,
+    width: usize,
+    height: usize,
+}
+
+/// The first part of a multipart response: a `GenerateResponse` with
+/// layer names in place of the images, which follow in this order.
 #[derive(Serialize)]
+struct MultipartMeta<'a> {
+    key: CacheKey,
+    layers: Vec<&'static str>,
+    timings: &'a [TimingEntry],
+    width: usize,
+    height: usize,
+}
+
+impl Rendered {
+    fn into_json(self) -> GenerateResponse {
+        let layers = self
+            .layers
+            .into_iter()
+            .map(|(name, png)| Layer { name: name.into(), data_url: data_url(&png) })
+            .collect();
+        GenerateResponse { key: self.key, layers, timings: self.timings, width: self.width, height: self.height }
+    }
+
+    /// `multipart/mixed`: `meta.json` (a `MultipartMeta`), then one
+    /// `<layer>.png` part per layer, raw.
+    fn into_multipart(self) -> Response {
+        let meta = MultipartMeta {
+            key: self.key,
+            layers: self.layers.iter().map(|(name, _)| *name).collect(),
+            timings: &self.timings,
+            width: self.width,
+            height: self.height,
+        };
+        let meta = serde_json::to_vec(&meta).expect("meta serializes");
+        let mut parts = vec![("meta.json".to_string(), "application/json", meta.as_slice())];
+        parts.extend(self.layers.iter().map(|(name, png)| (format!("{}.png", name), "image/png", png.as_slice())));
+        multipart(&parts)
+    }
+
+    /// Multipart if the client accepts `multipart/mixed`, else JSON with
+    /// base64 data URLs.
+    fn respond(self, headers: &HeaderMap) -> Response {
+        if wants_multipart(headers) {
+            self.into_multipart()
+        } else {
+            Json(self.into_json()).into_response()
+        }
+    }
+}
+
+fn wants_multipart(headers: &HeaderMap) -> bool {
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or("");
+    accept.contains("multipart/mixed")
+}
+
+/// `parts` (file name, content type, body) as one `multipart/mixed`
+/// response, with a boundary that none of them contains.
+fn multipart(parts: &[(String, &str, &[u8])]) -> Response {
+    let boundary = (0..)
+        .map(|i| format!("worldgen-part-boundary-{}", i))
+        .find(|b| parts.iter().all(|(_, _, body)| !body.windows(b.len()).any(|w| w == b.as_bytes())))
+        .expect("some boundary is free");
+    let mut body = Vec::with_capacity(parts.iter().map(|(_, _, b)| b.len() + 128).sum());
+    for (name, content_type, data) in parts {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Disposition: inline; filename=\"{}\"\r\n\r\n",
+                boundary, content_type, name
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    ([(header::CONTENT_TYPE, format!("multipart/mixed; boundary={}", boundary))], body).into_response()
+}
+
+#[derive(Serialize, Clone)]
 struct RiversResponse {
+    /// The map the rivers were computed for.
+    key: CacheKey,
     layer: Layer,
     timing: TimingEntry,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct Layer {
     name: String,
     data_url: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct TimingEntry {
     name: String,
     ms: f64,
 }
 
-/// Shared state: cached base map + generation params for the rivers endpoint.
+/// Picks a cached map: the one a /api/generate response named, or the
+/// most recently used one when left out.
+#[derive(Deserialize, Default)]
+struct KeyQuery {
+    key: Option<CacheKey>,
+}
+
+/// Query of `/api/inspect`: a cell (`x`, `y`) or a geographic point
+/// (`lat`, `lon`), and optionally the cached map's key.
+#[derive(Deserialize)]
+struct InspectQuery {
+    key: Option<CacheKey>,
+    x: Option<usize>,
+    y: Option<usize>,
+    lat: Option<f32>,
+    lon: Option<f32>,
+}
+
+#[derive(Serialize)]
+struct InspectResponse {
+    #[serde(flatten)]
+    cell: CellInfo,
+    /// Boundary class of the cell (interior, convergent, divergent,
+    /// transform) and whether it's a major boundary.
+    boundary: &'static str,
+    boundary_major: bool,
+    /// Added layers (biomes, soil, ...) at the cell.
+    layers: BTreeMap<String, f32>,
+}
+
+/// One entry of /api/presets: a preset and the parameters it selects.
+#[derive(Serialize)]
+struct PresetInfo {
+    name: &'static str,
+    params: Params,
+}
+
+/// A generated base map + generation params for the rivers endpoint.
 struct CachedGeneration {
     map: Map,
     seed: u64,
     params: Params,
+    style: RenderStyle,
+    /// `map` with hydrology run (carved, with river flow) and the
+    /// /api/rivers response, once computed; `map` itself stays as
+    /// generated.
+    rivers: Option<(Map, RiversResponse)>,
+    tiles: TileCache,
 }
 
-type SharedState = Arc<Mutex<Option<CachedGeneration>>>;
+/// Encoded tiles of one cached map, for /api/tiles.
+#[derive(Default)]
+struct TileCache {
+    tiles: HashMap<TileId, Bytes>,
+    /// Tiles in the order they were rendered, oldest first.
+    order: VecDeque<TileId>,
+}
 
-fn encode_png(rgba: &[u8], w: usize, h: usize) -> String {
+type TileId = (LayerKind, u32, u32, u32);
+
+/// Tiles kept per map; the oldest is dropped past this.
+const CACHED_TILES: usize = 1024;
+
+/// Full equirectangular RGBA renders that tiles are cut from, shared by
+/// every cached map and keyed by the map's ETag (so by rivers and style
+/// too) and layer.
+#[derive(Default)]
+struct LayerRenders {
+    /// Most recently used first.
+    renders: VecDeque<((HeaderValue, LayerKind), Arc<Vec<u8>>)>,
+}
+
+/// Bytes of layer renders kept in `LayerRenders`; the least recently used
+/// ones are dropped past this, though the latest is always kept.
+const LAYER_RENDER_BYTES: usize = 512 << 20;
+
+impl LayerRenders {
+    /// The render for `id`, marked as most recently used.
+    fn get(&mut self, id: &(HeaderValue, LayerKind)) -> Option<Arc<Vec<u8>>> {
+        let i = self.renders.iter().position(|(k, _)| k == id)?;
+        let entry = self.renders.remove(i)?;
+        self.renders.push_front(entry);
+        Some(self.renders[0].1.clone())
+    }
+
+    fn insert(&mut self, id: (HeaderValue, LayerKind), rgba: Arc<Vec<u8>>) {
+        self.renders.retain(|(k, _)| *k != id);
+        self.renders.push_front((id, rgba));
+        let mut bytes: usize = self.renders.iter().map(|(_, rgba)| rgba.len()).sum();
+        while bytes > LAYER_RENDER_BYTES && self.renders.len() > 1 {
+            let (_, oldest) = self.renders.pop_back().expect("more than one render");
+            bytes -= oldest.len();
+        }
+    }
+}
+
+impl CachedGeneration {
+    fn key(&self) -> CacheKey {
+        CacheKey::new(self.seed, self.map.w, self.map.h, &self.params)
+    }
+
+    /// The map with rivers once they are computed, else the base map.
+    fn latest(&self) -> &Map {
+        self.rivers.as_ref().map_or(&self.map, |(map, _)| map)
+    }
+
+    /// ETag of the GET responses about this map, which depend on the key,
+    /// whether rivers are in, and the style tiles are drawn in.
+    fn etag(&self) -> HeaderValue {
+        etag(self.key(), (self.rivers.is_some(), &self.style))
+    }
+
+    /// Draw tiles in `style` from now on, dropping those drawn in another.
+    fn restyle(&mut self, style: RenderStyle) {
+        // RenderStyle has no PartialEq; its Debug form is what ETags hash
+        if format!("{:?}", self.style) != format!("{:?}", style) {
+            self.style = style;
+            self.tiles = TileCache::default();
+        }
+    }
+
+    /// Tile `z/x/y` of `kind` as PNG, rendered on first use from the
+    /// layer's render in `renders` (rendered too if it was dropped).
+    fn tile(&mut self, renders: &Mutex<LayerRenders>, kind: LayerKind, z: u32, x: u32, y: u32) -> Bytes {
+        if let Some(png) = self.tiles.tiles.get(&(kind, z, x, y)) {
+            return png.clone();
+        }
+        let id = (self.etag(), kind);
+        let cached = renders.lock().unwrap().get(&id);
+        let map = self.latest();
+        let rgba = cached.unwrap_or_else(|| {
+            let rgba = Arc::new(render::render_layer(map, kind, &self.style));
+            renders.lock().unwrap().insert(id, rgba.clone());
+            rgba
+        });
+        let tile = render::tiles::render_tile(&rgba, map.w, map.h, z, x, y);
+        let png = Bytes::from(png_bytes(&tile, TILE_SIZE, TILE_SIZE));
+        let cache = &mut self.tiles;
+        if cache.order.len() >= CACHED_TILES
+            && let Some(oldest) = cache.order.pop_front()
+        {
+            cache.tiles.remove(&oldest);
+        }
+        cache.order.push_back((kind, z, x, y));
+        cache.tiles.insert((kind, z, x, y), png.clone());
+        png
+    }
+}
+
+/// Seed, size and parameters hashed together (FNV-1a over the seed, the
+/// size and the parameters' JSON), written as 16 hex digits.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+struct CacheKey(u64);
+
+impl CacheKey {
+    fn new(seed: u64, width: usize, height: usize, params: &Params) -> Self {
+        let mut bytes = Vec::new();
+        for n in [seed, width as u64, height as u64] {
+            bytes.extend_from_slice(&n.to_le_bytes());
+        }
+        bytes.extend(serde_json::to_vec(params).expect("params serialize"));
+        CacheKey(fnv1a(&bytes))
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325u64, |h, &b| (h ^ b as u64).wrapping_mul(0x0100_0000_01B3))
+}
+
+/// ETag of a response built from map `key`. A map is a pure function of
+/// its key, so the tag only adds what else shapes the body (`variant`:
+/// layers, style, format, ...) and the server version. Weak, since
+/// timings in a body vary and bodies may be compressed.
+fn etag(key: CacheKey, variant: impl std::fmt::Debug) -> HeaderValue {
+    let variant = format!("{} {:?}", env!("CARGO_PKG_VERSION"), variant);
+    let tag = format!("W/\"{}-{:016x}\"", String::from(key), fnv1a(variant.as_bytes()));
+    HeaderValue::from_str(&tag).expect("tags are ASCII")
+}
+
+/// Whether the request's `If-None-Match` lists `tag` (or is `*`).
+fn fresh(headers: &HeaderMap, tag: &HeaderValue) -> bool {
+    let opaque = |t: &str| t.trim().trim_start_matches("W/").to_string();
+    let tag = opaque(tag.to_str().expect("tags are ASCII"));
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|t| t.trim() == "*" || opaque(t) == tag)
+}
+
+fn not_modified(tag: HeaderValue) -> Response {
+    (StatusCode::NOT_MODIFIED, [(header::ETAG, tag)]).into_response()
+}
+
+fn with_etag(mut response: Response, tag: HeaderValue) -> Response {
+    response.headers_mut().insert(header::ETAG, tag);
+    response
+}
+
+impl From<CacheKey> for String {
+    fn from(key: CacheKey) -> String {
+        format!("{:016x}", key.0)
+    }
+}
+
+impl TryFrom<String> for CacheKey {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        u64::from_str_radix(&s, 16).map(CacheKey).map_err(|_| format!("`{}` is not a map key", s))
+    }
+}
+
+/// Maps generated most recently, most recently used first. Each entry
+/// has its own lock, so hydrology on one map doesn't hold up requests
+/// for the others.
+struct MapCache {
+    entries: VecDeque<(CacheKey, Arc<Mutex<CachedGeneration>>)>,
+    /// Lookups by key that found a map, and that didn't.
+    hits: u64,
+    misses: u64,
+}
+
+/// Maps kept in `MapCache`; the least recently used one is dropped past
+/// this.
+const CACHED_MAPS: usize = 8;
+
+impl MapCache {
+    /// Cache `generation` under `key` as the most recently used. An entry
+    /// already there is kept instead, with its rivers and tiles, and
+    /// returned.
+    fn insert(&mut self, key: CacheKey, generation: CachedGeneration) -> Option<Arc<Mutex<CachedGeneration>>> {
+        if let Some(i) = self.entries.iter().position(|(k, _)| *k == key) {
+            let entry = self.entries.remove(i)?;
+            self.entries.push_front(entry);
+            return Some(self.entries[0].1.clone());
+        }
+        self.entries.push_front((key, Arc::new(Mutex::new(generation))));
+        self.entries.truncate(CACHED_MAPS);
+        None
+    }
+
+    /// The entry for `key`, marked as most recently used.
+    fn get(&mut self, key: CacheKey) -> Option<Arc<Mutex<CachedGeneration>>> {
+        let Some(i) = self.entries.iter().position(|(k, _)| *k == key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let entry = self.entries.remove(i)?;
+        self.entries.push_front(entry);
+        Some(self.entries[0].1.clone())
+    }
+}
+
+/// A failed request, answered with its status and a JSON body:
+/// `{"code": "invalid_params", "message": "...", "problems": [...]}`.
+/// `code` is stable for clients to match on; `problems` lists each invalid
+/// field and is left out when there are none.
+#[derive(Serialize)]
+struct ApiError {
+    #[serde(skip)]
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    problems: Vec<Problem>,
+}
+
+/// One thing wrong with a request's parameters.
+#[derive(Serialize)]
+struct Problem {
+    /// The parameter, when the problem is with a single one.
+    field: Option<&'static str>,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self { status, code, message: message.into(), problems: Vec::new() }
+    }
+
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    /// A `bad_request` about one request field.
+    fn field(field: &'static str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        let problems = vec![Problem { field: Some(field), message: message.clone() }];
+        ApiError { problems, ..Self::bad_request(message) }
+    }
+
+    fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    fn superseded() -> Self {
+        Self::new(StatusCode::CONFLICT, "superseded", "superseded by a newer request")
+    }
+
+    /// A blocking task that panicked; validation should have caught the
+    /// request.
+    fn internal(e: tokio::task::JoinError) -> Self {
+        eprintln!("request failed: {}", e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal", "the server failed to handle this request")
+    }
+}
+
+impl From<WorldgenError> for ApiError {
+    fn from(e: WorldgenError) -> Self {
+        match e {
+            WorldgenError::InvalidParams(invalid) => {
+                let problems = invalid
+                    .errors
+                    .iter()
+                    .map(|e| {
+                        let field = match e {
+                            ParamError::OutOfRange { field, .. } => Some(*field),
+                            _ => None,
+                        };
+                        Problem { field, message: e.to_string() }
+                    })
+                    .collect();
+                ApiError { problems, ..Self::new(StatusCode::BAD_REQUEST, "invalid_params", invalid.to_string()) }
+            }
+            WorldgenError::TooLarge { .. } | WorldgenError::TooManyCells { .. } => {
+                Self::new(StatusCode::BAD_REQUEST, "too_large", e.to_string())
+            }
+            WorldgenError::Cancelled => Self::superseded(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(&self)).into_response()
+    }
+}
+
+/// Extractor `E` with its rejection (malformed JSON, a bad query string,
+/// ...) answered as a `bad_request` `ApiError` instead of plain text.
+struct Checked<E>(E);
+
+/// The message of an extractor rejection.
+async fn rejection_message(rejection: Response) -> String {
+    let body = rejection.into_body();
+    let bytes = axum::body::to_bytes(body, 64 * 1024).await.unwrap_or_default();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+impl<S: Send + Sync, E: FromRequest<S>> FromRequest<S> for Checked<E> {
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, ApiError> {
+        let rejection = match E::from_request(request, state).await {
+            Ok(value) => return Ok(Checked(value)),
+            Err(rejection) => rejection.into_response(),
+        };
+        Err(ApiError::bad_request(rejection_message(rejection).await))
+    }
+}
+
+impl<S: Send + Sync, E: FromRequestParts<S>> FromRequestParts<S> for Checked<E> {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, ApiError> {
+        let rejection = match E::from_request_parts(parts, state).await {
+            Ok(value) => return Ok(Checked(value)),
+            Err(rejection) => rejection.into_response(),
+        };
+        Err(ApiError::bad_request(rejection_message(rejection).await))
+    }
+}
+
+/// The cached map `query` picks (the session's latest if it gives no key).
+fn cached(state: &AppState, session: &Session, query: &KeyQuery) -> Result<Arc<Mutex<CachedGeneration>>, ApiError> {
+    let Some(key) = query.key.or(*session.latest.lock().unwrap()) else {
+        return Err(ApiError::not_found("no map generated yet"));
+    };
+    state.cache.lock().unwrap().get(key).ok_or_else(|| ApiError::not_found("no such map (never generated, or evicted)"))
+}
+
+/// Deployment settings, from flags or `WORLDGEN_*` environment variables
+/// (flags win).
+struct Config {
+    /// `--addr` / `WORLDGEN_ADDR`.
+    addr: IpAddr,
+    /// `--port` / `WORLDGEN_PORT`.
+    port: u16,
+    /// Largest map a request may ask for: `--max-size WxH` /
+    /// `WORLDGEN_MAX_SIZE`.
+    max_width: usize,
+    max_height: usize,
+    /// Generations (and river runs) at once; more wait for a slot until
+    /// their timeout. `--max-generations` / `WORLDGEN_MAX_GENERATIONS`.
+    max_generations: usize,
+    /// How long a generating request may wait and run before it is
+    /// cancelled: `--timeout SECS` / `WORLDGEN_TIMEOUT`.
+    timeout: Duration,
+    /// Where generated maps are kept across restarts (off if unset):
+    /// `--cache-dir` / `WORLDGEN_CACHE_DIR`.
+    cache_dir: Option<PathBuf>,
+    /// Size the cache directory is trimmed to, in bytes; given in MB as
+    /// `--cache-size` / `WORLDGEN_CACHE_SIZE`.
+    cache_bytes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            addr: IpAddr::from([127, 0, 0, 1]),
+            port: 3000,
+            max_width: 8192,
+            max_height: 4096,
+            max_generations: 2,
+            timeout: Duration::from_secs(300),
+            cache_dir: None,
+            cache_bytes: 1 << 30,
+        }
+    }
+}
+
+impl Config {
+    /// Settings from `args` (flag, value pairs, without the program name)
+    /// over the environment over the defaults. Unknown flags are errors;
+    /// `--threads` is taken, and applied, by `main`.
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Config::default();
+        let settings = [
+            ("--addr", "WORLDGEN_ADDR"),
+            ("--port", "WORLDGEN_PORT"),
+            ("--max-size", "WORLDGEN_MAX_SIZE"),
+            ("--max-generations", "WORLDGEN_MAX_GENERATIONS"),
+            ("--timeout", "WORLDGEN_TIMEOUT"),
+            ("--cache-dir", "WORLDGEN_CACHE_DIR"),
+            ("--cache-size", "WORLDGEN_CACHE_SIZE"),
+        ];
+        let mut flags = HashMap::new();
+        let mut rest = args.iter();
+        while let Some(arg) = rest.next() {
+            let known = settings.iter().map(|&(flag, _)| flag).chain(["--threads"]);
+            let Some(flag) = known.clone().find(|flag| flag == arg) else {
+                return Err(format!("unknown argument `{}`; flags are {}", arg, known.collect::<Vec<_>>().join(", ")));
+            };
+            let value = rest.next().ok_or_else(|| format!("{} needs a value", flag))?;
+            flags.insert(flag, value.clone());
+        }
+        for (flag, var) in settings {
+            let value = flags.remove(flag).or_else(|| std::env::var(var).ok());
+            let Some(value) = value else {
+                continue;
+            };
+            let invalid = |what: &str| format!("{} / {}: `{}` is not {}", flag, var, value, what);
+            match flag {
+                "--addr" => config.addr = value.parse().map_err(|_| invalid("an IP address"))?,
+                "--port" => config.port = value.parse().map_err(|_| invalid("a port"))?,
+                "--max-size" => {
+                    let size = value.split_once('x').and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+                    (config.max_width, config.max_height) = size.ok_or_else(|| invalid("a size like 4096x2048"))?;
+                }
+                "--max-generations" => {
+                    config.max_generations =
+                        value.parse().ok().filter(|&n| n > 0).ok_or_else(|| invalid("a positive count"))?;
+                }
+                "--timeout" => {
+                    let secs: f64 = value.parse().ok().filter(|&s| s > 0.0).ok_or_else(|| invalid("seconds"))?;
+                    config.timeout = Duration::from_secs_f64(secs);
+                }
+                "--cache-dir" => config.cache_dir = Some(PathBuf::from(value)),
+                _ => {
+                    let mb: u64 = value.parse().map_err(|_| invalid("a size in MB"))?;
+                    config.cache_bytes = mb << 20;
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Check a requested size against `max_width` x `max_height` and
+    /// `worldgen::check_size`.
+    fn check_size(&self, width: usize, height: usize) -> Result<(), ApiError> {
+        if width > self.max_width || height > self.max_height {
+            let message = format!(
+                "a {}x{} map is larger than this server allows ({}x{})",
+                width, height, self.max_width, self.max_height
+            );
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, "too_large", message));
+        }
+        Ok(worldgen::check_size(width, height)?)
+    }
+}
+
+/// Generated maps saved under `Config::cache_dir`, so they outlive the
+/// process. `<key>.toml` holds the settings, checked on load so files from
+/// other versions (or a colliding key) are ignored; `<key>.map` is the base
+/// map and `<key>.rivers.map` the map with rivers, once computed. Loading
+/// touches the `.toml`, and the keys touched longest ago are removed
+/// whenever the directory grows past `max_bytes`. Failures only cost a
+/// regeneration, so they are logged and otherwise ignored.
+struct DiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// Held while writing or trimming, so trimming never sees half a key.
+    writing: Mutex<()>,
+}
+
+impl DiskCache {
+    fn open(dir: PathBuf, max_bytes: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, max_bytes, writing: Mutex::new(()) })
+    }
+
+    fn path(&self, key: CacheKey, ext: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.{}", key.0, ext))
+    }
+
+    /// The saved map for these settings (with rivers if `rivers`), if any.
+    fn load(&self, key: CacheKey, world: &WorldFile, rivers: bool) -> Option<Map> {
+        let settings = self.path(key, "toml");
+        let saved = WorldFile::load(&settings).ok()?;
+        if !saved.is_current() || saved.to_toml() != world.to_toml() {
+            return None;
+        }
+        let map = Map::load(self.path(key, if rivers { "rivers.map" } else { "map" }))
+            .inspect_err(|e| {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    eprintln!("disk cache: {:016x}: {}", key.0, e);
+                }
+            })
+            .ok()?;
+        let touched = std::fs::File::options().append(true).open(&settings);
+        if let Err(e) = touched.and_then(|f| f.set_modified(std::time::SystemTime::now())) {
+            eprintln!("disk cache: {}: {}", settings.display(), e);
+        }
+        Some(map)
+    }
+
+    /// Save `map` (the base map, or with rivers if `rivers`), then trim.
+    fn save(&self, key: CacheKey, world: &WorldFile, map: &Map, rivers: bool) {
+        let _writing = self.writing.lock().unwrap();
+        let map_path = self.path(key, if rivers { "rivers.map" } else { "map" });
+        // Through a temporary file, so a crash never leaves a torn map
+        let temp = map_path.with_extension("part");
+        let saved = world
+            .save(&self.path(key, "toml"))
+            .and_then(|_| map.save(&temp))
+            .and_then(|_| std::fs::rename(&temp, &map_path));
+        if let Err(e) = saved {
+            eprintln!("disk cache: {:016x}: {}", key.0, e);
+            let _ = std::fs::remove_file(&temp);
+            return;
+        }
+        if let Err(e) = self.trim(key) {
+            eprintln!("disk cache: {}: {}", self.dir.display(), e);
+        }
+    }
+
+    /// Remove the least recently used keys, other than `keep`, until the
+    /// directory fits in `max_bytes`.
+    fn trim(&self, keep: CacheKey) -> std::io::Result<()> {
+        // Per key: bytes and last use (the settings file's mtime)
+        let mut keys: HashMap<String, (u64, Option<std::time::SystemTime>)> = HashMap::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some((stem, ext)) = name.split_once('.') else {
+                continue;
+            };
+            let meta = entry.metadata()?;
+            let slot = keys.entry(stem.to_string()).or_default();
+            slot.0 += meta.len();
+            if ext == "toml" {
+                slot.1 = meta.modified().ok();
+            }
+        }
+        let mut total: u64 = keys.values().map(|&(bytes, _)| bytes).sum();
+        let keep = format!("{:016x}", keep.0);
+        let mut oldest: Vec<_> = keys.into_iter().filter(|(stem, _)| *stem != keep).collect();
+        oldest.sort_by_key(|(_, (_, used))| *used);
+        for (stem, (bytes, _)) in oldest {
+            if total <= self.max_bytes {
+                break;
+            }
+            for ext in ["toml", "map", "rivers.map", "part", "rivers.part"] {
+                match std::fs::remove_file(self.dir.join(format!("{}.{}", stem, ext))) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            total -= bytes;
+        }
+        Ok(())
+    }
+}
+
+struct AppState {
+    config: Config,
+    disk: Option<DiskCache>,
+    /// One permit per `Config::max_generations`.
+    generations: Arc<Semaphore>,
+    /// Shared by every session: the same settings make the same map.
+    cache: Mutex<MapCache>,
+    /// Layer renders behind the cached maps' tiles.
+    layer_renders: Mutex<LayerRenders>,
+    /// Sessions holding a pipeline, most recently used first.
+    pipelines: Mutex<PipelinePool>,
+    /// Most recently used first, at most `SESSIONS`.
+    sessions: Mutex<VecDeque<(String, Arc<Session>)>>,
+    metrics: Metrics,
+}
+
+/// Upper bounds (seconds) of the stage time histogram buckets.
+const STAGE_BUCKETS: [f64; 11] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Counters behind /metrics (map cache lookups are counted by `MapCache`).
+#[derive(Default)]
+struct Metrics {
+    /// Responses by method, route and status.
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    /// Run times of generated (not loaded) stages, by stage name.
+    stages: Mutex<BTreeMap<&'static str, StageHistogram>>,
+    disk_hits: AtomicU64,
+    disk_misses: AtomicU64,
+}
+
+#[derive(Default)]
+struct StageHistogram {
+    /// Runs per `STAGE_BUCKETS` bucket (not cumulative), then above all.
+    buckets: [u64; STAGE_BUCKETS.len() + 1],
+    seconds: f64,
+}
+
+impl Metrics {
+    fn record_stages(&self, timings: &[worldgen::Timing]) {
+        let mut stages = self.stages.lock().unwrap();
+        for t in timings {
+            let seconds = t.ms / 1000.0;
+            let histogram = stages.entry(t.name).or_default();
+            histogram.buckets[STAGE_BUCKETS.partition_point(|&le| le < seconds)] += 1;
+            histogram.seconds += seconds;
+        }
+    }
+
+    fn record_disk(&self, hit: bool) {
+        let counter = if hit { &self.disk_hits } else { &self.disk_misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Middleware counting responses for `Metrics::requests`.
+async fn count_requests(
+    State(state): State<SharedState>,
+    route: MatchedPath,
+    request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let response = next.run(request).await;
+    let key = (method, route.as_str().to_string(), response.status().as_u16());
+    *state.metrics.requests.lock().unwrap().entry(key).or_default() += 1;
+    response
+}
+
+type SharedState = Arc<AppState>;
+
+/// Sessions kept; the least recently used beyond this are dropped, with
+/// their pipelines.
+const SESSIONS: usize = 16;
+
+/// Map cells of the pipelines kept between requests, over every session
+/// (a pipeline holds ~50 bytes a cell); the least recently used ones are
+/// dropped past this, and a larger one isn't kept at all.
+const PIPELINE_CELLS: usize = 1 << 23;
+
+/// The sessions whose `Session::pipeline` is set, with its cells, most
+/// recently used first.
+#[derive(Default)]
+struct PipelinePool {
+    entries: VecDeque<(Weak<Session>, usize)>,
+}
+
+impl PipelinePool {
+    /// Account for `session` keeping a pipeline of `cells` (its pipeline
+    /// slot is held by the caller) and drop the least recently used
+    /// others' pipelines until all fit in `PIPELINE_CELLS`. Sessions
+    /// running a generation are skipped; they come back when it ends.
+    /// Returns whether `session` may keep its own.
+    fn keep(&mut self, session: &Arc<Session>, cells: usize) -> bool {
+        self.entries.retain(|(s, _)| s.strong_count() > 0 && !std::ptr::eq(s.as_ptr(), Arc::as_ptr(session)));
+        if cells > PIPELINE_CELLS {
+            return false;
+        }
+        self.entries.push_front((Arc::downgrade(session), cells));
+        let mut total: usize = self.entries.iter().map(|&(_, cells)| cells).sum();
+        while total > PIPELINE_CELLS {
+            let (oldest, cells) = self.entries.pop_back().expect("the total includes an older session");
+            if let Some(oldest) = oldest.upgrade()
+                && let Ok(mut slot) = oldest.pipeline.try_lock()
+            {
+                *slot = None;
+            }
+            total -= cells;
+        }
+        true
+    }
+}
+
+/// One client's state, picked by the `X-Session` request header (any id of
+/// up to 64 letters, digits, `-` and `_`; requests without one share a
+/// session). Each session's generations only supersede each other.
+#[derive(Default)]
+struct Session {
+    /// Key of the session's last generated map: what requests without a
+    /// `key` use.
+    latest: Mutex<Option<CacheKey>>,
+    /// Intermediates of the last generation, while `PipelinePool` has room
+    /// for them; a request with the same seed and size reruns only the
+    /// stages its parameter changes affect.
+    pipeline: Mutex<Option<Pipeline>>,
+    /// Token of the newest generation; a new request cancels it, so
+    /// superseded work stops instead of running to completion.
+    current: Mutex<CancelToken>,
+}
+
+/// Extractor for the request's `Session`, made on first use.
+struct Client(Arc<Session>);
+
+impl FromRequestParts<SharedState> for Client {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &SharedState) -> Result<Self, ApiError> {
+        let id = match parts.headers.get("x-session") {
+            Some(id) => id
+                .to_str()
+                .ok()
+                .filter(|id| {
+                    (1..=64).contains(&id.len())
+                        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+                })
+                .ok_or_else(|| ApiError::bad_request("X-Session must be 1-64 letters, digits, - or _"))?,
+            None => "",
+        };
+        let mut sessions = state.sessions.lock().unwrap();
+        let session = match sessions.iter().position(|(s, _)| s == id) {
+            Some(i) => sessions.remove(i).expect("position is in range").1,
+            None => Arc::default(),
+        };
+        sessions.push_front((id.to_string(), session.clone()));
+        sessions.truncate(SESSIONS);
+        Ok(Client(session))
+    }
+}
+
+/// Cancel whatever generation the session has in flight and return a
+/// token for a new one.
+fn supersede(session: &Session) -> CancelToken {
+    let token = CancelToken::new();
+    std::mem::replace(&mut *session.current.lock().unwrap(), token.clone()).cancel();
+    token
+}
+
+/// Run `work` on the blocking pool once a generation slot is free, within
+/// the configured timeout. On timeout `cancel` fires, so the work stops
+/// soon and gives back its slot, and a timed-out current generation of
+/// `session` is replaced by a fresh token.
+async fn run_limited<T: Send + 'static>(
+    state: &AppState,
+    session: &Session,
+    cancel: &CancelToken,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, ApiError> {
+    let deadline = tokio::time::Instant::now() + state.config.timeout;
+    let permit = tokio::time::timeout_at(deadline, state.generations.clone().acquire_owned())
+        .await
+        .map_err(|_| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "busy", "every generation slot stayed busy"))?
+        .expect("the semaphore is never closed");
+    let task = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        work()
+    });
+    match tokio::time::timeout_at(deadline, task).await {
+        Ok(result) => result.map_err(ApiError::internal),
+        Err(_) => {
+            cancel.cancel();
+            // Requests that follow up on the current generation (rivers)
+            // shouldn't fail because this one ran out of time
+            let mut current = session.current.lock().unwrap();
+            if current.is_cancelled() {
+                *current = CancelToken::new();
+            }
+            Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "timeout", "generation timed out"))
+        }
+    }
+}
+
+fn png_bytes(rgba: &[u8], w: usize, h: usize) -> Vec<u8> {
     let mut buf = Vec::new();
     let encoder = PngEncoder::new(&mut buf);
     encoder
         .write_image(rgba, w as u32, h as u32, image::ExtendedColorType::Rgba8)
         .expect("PNG encode failed");
-    let b64 = base64::engine::general_purpose::STANDARD.encode(&buf);
-    format!("data:image/png;base64,{}", b64)
+    buf
+}
+
+fn data_url(png: &[u8]) -> String {
+    format!("data:image/png;base64,{}", base64::engine::general_purpose::STANDARD.encode(png))
 }
 
-fn parse_params(req: &GenerateRequest) -> (u64, usize, usize, Params) {
+fn encode_png(rgba: &[u8], w: usize, h: usize) -> String {
+    data_url(&png_bytes(rgba, w, h))
+}
+
+fn parse_params(req: &GenerateRequest, config: &Config) -> Result<(u64, usize, usize, Params), ApiError> {
     let seed = req.seed.unwrap_or(42);
     let width = req.width.unwrap_or(1024);
     let height = req.height.unwrap_or(512);
 
-    let defaults = Params::default();
-    let params = Params {
-        num_macroplates: req.num_macroplates.unwrap_or(defaults.num_macroplates),
-        num_microplates: req.num_microplates.unwrap_or(defaults.num_microplates),
-        continental_fraction: req.continental_fraction.unwrap_or(defaults.continental_fraction),
-        boundary_noise: req.boundary_noise.unwrap_or(defaults.boundary_noise),
-        blur_sigma: req.blur_sigma.unwrap_or(defaults.blur_sigma),
-        mountain_scale: req.mountain_scale.unwrap_or(defaults.mountain_scale),
-        trench_scale: req.trench_scale.unwrap_or(defaults.trench_scale),
-        mountain_width: req.mountain_width.unwrap_or(defaults.mountain_width),
-        coast_amp: req.coast_amp.unwrap_or(defaults.coast_amp),
-        interior_amp: req.interior_amp.unwrap_or(defaults.interior_amp),
-        detail_amp: req.detail_amp.unwrap_or(defaults.detail_amp),
-        shelf_width: req.shelf_width.unwrap_or(defaults.shelf_width),
-        ridge_height: req.ridge_height.unwrap_or(defaults.ridge_height),
-        rift_depth: req.rift_depth.unwrap_or(defaults.rift_depth),
-        rainfall_scale: req.rainfall_scale.unwrap_or(defaults.rainfall_scale),
-        river_threshold: req.river_threshold.unwrap_or(defaults.river_threshold),
+    let base = match req.preset.as_deref() {
+        Some(name) => Params::preset(
+            Preset::from_name(name).ok_or_else(|| ApiError::bad_request(format!("unknown preset `{}`", name)))?,
+        ),
+        None => Params::default(),
+    };
+    let mut fields = match serde_json::to_value(base) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => unreachable!("Params serializes as an object"),
     };
+    fields.extend(req.params.clone());
+    let params: Params = serde_json::from_value(serde_json::Value::Object(fields))
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, "invalid_params", e.to_string()))?;
+    config.check_size(width, height)?;
+    params.validate_for_size(width, height).map_err(WorldgenError::from)?;
 
-    (seed, width, height, params)
+    Ok((seed, width, height, params))
+}
+
+/// The layers a request asks for and the style to render them in.
+fn parse_render(req: &GenerateRequest) -> Result<(Vec<LayerKind>, RenderStyle), ApiError> {
+    let layers = match &req.layers {
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                LayerKind::from_name(name).ok_or_else(|| ApiError::field("layers", format!("unknown layer `{}`", name)))
+            })
+            .collect::<Result<_, _>>()?,
+        None => BASE_LAYERS.to_vec(),
+    };
+
+    let palette = match req.palette.as_deref() {
+        Some(name) => Palette::preset(
+            PalettePreset::from_name(name)
+                .ok_or_else(|| ApiError::field("palette", format!("unknown palette `{}`", name)))?,
+        ),
+        None => Palette::default(),
+    };
+    let mut style = RenderStyle { palette, ..RenderStyle::default() };
+    let requested = req.style.clone().unwrap_or_default();
+    if let Some(ao) = requested.ambient_occlusion {
+        if !(0.0..=1.0).contains(&ao) {
+            return Err(ApiError::field("style.ambient_occlusion", "style.ambient_occlusion must be in 0..=1"));
+        }
+        style.ambient_occlusion = ao;
+    }
+    if let Some(hillshade) = requested.hillshade {
+        if !(0.0..=1.0).contains(&hillshade) {
+            return Err(ApiError::field("style.hillshade", "style.hillshade must be in 0..=1"));
+        }
+        style.hillshade = hillshade;
+    }
+    if let Some(spacing) = requested.graticule {
+        if !(spacing > 0.0 && spacing <= 180.0) {
+            return Err(ApiError::field("style.graticule", "style.graticule must be a spacing in (0, 180] degrees"));
+        }
+        style.graticule = Some(spacing);
+    }
+    style.river_overlay = requested.river_overlay.unwrap_or(style.river_overlay);
+    style.boundaries = requested.boundaries.unwrap_or(style.boundaries);
+    Ok((layers, style))
 }
 
 /// Fast endpoint: generates everything except hydrology (~2s).
 /// Caches the base map so /api/rivers can compute hydrology from it.
+/// Sends raw PNGs instead of base64 JSON to clients that accept
+/// `multipart/mixed` (see `Rendered::respond`).
 async fn generate_handler(
     State(state): State<SharedState>,
-    Json(req): Json<GenerateRequest>,
-) -> Json<GenerateResponse> {
-    let (seed, width, height, params) = parse_params(&req);
-
-    let state_clone = state.clone();
-    let response = tokio::task::spawn_blocking(move || {
-        let (map, timings) = worldgen::generate_base(seed, width, height, &params);
-
-        let layers = vec![
-            Layer {
-                name: "plates".into(),
-                data_url: encode_png(
-                    &render::render_plates(
-                        &map.plate_id,
-                        &map.boundary_type,
-                        &map.boundary_major,
-                        &map.macro_id,
-                        map.num_macro,
-                    ),
-                    width,
-                    height,
-                ),
-            },
-            Layer {
-                name: "boundaries".into(),
-                data_url: encode_png(
-                    &render::render_boundaries(&map.boundary_type, &map.boundary_major),
-                    width,
-                    height,
-                ),
-            },
-            Layer {
-                name: "distance".into(),
-                data_url: encode_png(
-                    &render::render_distance(&map.boundary_dist),
-                    width,
-                    height,
-                ),
-            },
-            Layer {
-                name: "heightmap".into(),
-                data_url: encode_png(&render::render_heightmap(&map.height), width, height),
-            },
-            Layer {
-                name: "map".into(),
-                data_url: encode_png(&map.rgba, width, height),
-            },
-            Layer {
-                name: "temperature".into(),
-                data_url: encode_png(
-                    &render::render_temperature(&map.temperature),
-                    width,
-                    height,
-                ),
-            },
-            Layer {
-                name: "precipitation".into(),
-                data_url: encode_png(
-                    &render::render_precipitation(&map.precipitation),
-                    width,
-                    height,
-                ),
-            },
-        ];
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Json(req)): Checked<Json<GenerateRequest>>,
+) -> Result<Response, ApiError> {
+    let (seed, width, height, params) = parse_params(&req, &state.config)?;
+    let (layers, style) = parse_render(&req)?;
 
-        // Cache the map for rivers endpoint
-        *state_clone.lock().unwrap() = Some(CachedGeneration {
-            map,
-            seed,
-            params,
-        });
+    // A client repeating a request for a map that's still cached already
+    // has the response, and starts no work to supersede the session's
+    let key = CacheKey::new(seed, width, height, &params);
+    let tag = etag(key, (&layers, &style, wants_multipart(&headers)));
+    if fresh(&headers, &tag) && state.cache.lock().unwrap().get(key).is_some() {
+        *session.latest.lock().unwrap() = Some(key);
+        return Ok(not_modified(tag));
+    }
+    let cancel = supersede(&session);
 
-        let timing_entries = timings
-            .iter()
-            .map(|t| TimingEntry {
-                name: t.name.to_string(),
-                ms: t.ms,
-            })
-            .collect();
+    let job = Job { seed, width, height, params, layers, style };
+    let worker = (state.clone(), session.clone(), cancel.clone());
+    let response = run_limited(&state, &session, &cancel, move || {
+        let (state, session, cancel) = worker;
+        generate_base_layers(&state, &session, job, |_, _| {}, &cancel)
+    })
+    .await??;
+
+    Ok(with_etag(response.respond(&headers), tag))
+}
+
+fn json_event(name: &str, data: &impl Serialize) -> Event {
+    Event::default().event(name).json_data(data).expect("events serialize")
+}
+
+#[derive(Serialize)]
+struct ProgressEvent {
+    stage: &'static str,
+    fraction: f32,
+}
+
+/// /api/generate and /api/rivers in one request, as server-sent events:
+/// `progress` ({stage, fraction}, at most one per stage and percent)
+/// throughout, `generated` with the /api/generate response once the base
+/// map is done, `rivers` with the /api/rivers response after hydrology,
+/// and `cancelled` instead if a newer request supersedes this one.
+/// Closing the stream cancels the generation.
+async fn generate_stream_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    Checked(Json(req)): Checked<Json<GenerateRequest>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let (seed, width, height, params) = parse_params(&req, &state.config)?;
+    let (layers, style) = parse_render(&req)?;
+    let cancel = supersede(&session);
+
+    let job = Job { seed, width, height, params, layers, style };
+
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let failed = tx.clone();
+    let worker = (state.clone(), session.clone(), cancel.clone());
+    let work = move || {
+        let (state, session, cancel) = worker;
+        // A send fails only once the client has gone
+        let send = |event: Event| {
+            if tx.send(event).is_err() {
+                cancel.cancel();
+            }
+        };
+        let last = Mutex::new(None);
+        let progress = |stage: Stage, fraction: f32| {
+            let percent = (fraction.clamp(0.0, 1.0) * 100.0) as u32;
+            if last.lock().unwrap().replace((stage, percent)) != Some((stage, percent)) {
+                send(json_event("progress", &ProgressEvent { stage: stage.name(), fraction }));
+            }
+        };
+        let rendered = match generate_base_layers(&state, &session, job, progress, &cancel) {
+            Ok(rendered) => rendered,
+            Err(WorldgenError::Cancelled) => return send(json_event("cancelled", &())),
+            Err(e) => return send(json_event("error", &ApiError::from(e))),
+        };
+        let key = rendered.key;
+        send(json_event("generated", &rendered.into_json()));
+        match compute_rivers(&state, &session, Some(key), progress, &cancel) {
+            Ok(Some(rivers)) => send(json_event("rivers", &rivers)),
+            Ok(None) | Err(_) => send(json_event("cancelled", &())),
+        }
+    };
+    tokio::spawn(async move {
+        if let Err(error) = run_limited(&state, &session, &cancel, work).await {
+            let _ = failed.send(json_event("error", &error));
+        }
+    });
+
+    let events = stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|event| (Ok(event), rx)) });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
 
-        GenerateResponse {
-            layers,
-            timings: timing_entries,
+/// Regenerate a world from a `world.toml` body, as saved by the CLI or by
+/// /api/export/world. Responds like /api/generate.
+async fn world_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(body): Checked<String>,
+) -> Result<Response, ApiError> {
+    let file = WorldFile::from_toml(&body).map_err(ApiError::bad_request)?;
+    let (width, height) = file.generated_size();
+    state.config.check_size(width, height)?;
+    file.params.validate_for_size(width, height).map_err(WorldgenError::from)?;
+    let cancel = supersede(&session);
+
+    let worker = (state.clone(), session.clone(), cancel.clone());
+    let response = run_limited(&state, &session, &cancel, move || {
+        let (state, session, cancel) = worker;
+        let job = Job {
+            seed: file.seed,
             width,
             height,
+            params: file.params,
+            layers: BASE_LAYERS.to_vec(),
+            style: RenderStyle::default(),
+        };
+        generate_base_layers(&state, &session, job, |_, _| {}, &cancel)
+    })
+    .await??;
+
+    Ok(response.respond(&headers))
+}
+
+/// What to generate, with `params` already validated for the size.
+struct Job {
+    seed: u64,
+    width: usize,
+    height: usize,
+    params: Params,
+    layers: Vec<LayerKind>,
+    style: RenderStyle,
+}
+
+/// Generate the base map, render its layers, and cache it for the rivers
+/// endpoint, reporting as `Pipeline::run`. A map still in memory (from
+/// another session, or an earlier request for other layers) is reused as
+/// it is, rivers and tiles included. `WorldgenError::Cancelled` if
+/// `cancel` fired.
+fn generate_base_layers(
+    state: &SharedState,
+    session: &Arc<Session>,
+    job: Job,
+    progress: impl Fn(Stage, f32) + Sync,
+    cancel: &CancelToken,
+) -> Result<Rendered, WorldgenError> {
+    let Job { seed, width, height, params, layers, style } = job;
+    let key = CacheKey::new(seed, width, height, &params);
+    let render_layers = |map: &Map| -> Vec<(&'static str, Vec<u8>)> {
+        let png = |kind: LayerKind| png_bytes(&render::render_layer(map, kind, &style), width, height);
+        layers.iter().map(|&kind| (kind.name(), png(kind))).collect()
+    };
+
+    let start = std::time::Instant::now();
+    let cached = state.cache.lock().unwrap().get(key);
+    let (layers, mut timings, generation) = match cached {
+        Some(entry) => {
+            let ms = start.elapsed().as_secs_f64() * 1000.0;
+            let layers = render_layers(&entry.lock().unwrap().map);
+            (layers, vec![worldgen::Timing { name: "memory cache", ms }], Ok(entry))
         }
+        None => {
+            let world = WorldFile::new(seed, width, height, &params);
+            let (map, timings) = base_map(state, session, &world, progress, cancel)?;
+            let layers = render_layers(&map);
+            let generation =
+                CachedGeneration { map, seed, params, style: style.clone(), rivers: None, tiles: TileCache::default() };
+            (layers, timings, Err(generation))
+        }
+    };
+    timings.push(worldgen::Timing {
+        name: "TOTAL",
+        ms: timings.iter().map(|t| t.ms).sum(),
+    });
+
+    // Cache the map for rivers endpoint, and make it the session's latest,
+    // unless a newer request has already taken over
+    let mut cache = state.cache.lock().unwrap();
+    if cancel.is_cancelled() {
+        return Err(WorldgenError::Cancelled);
+    }
+    *session.latest.lock().unwrap() = Some(key);
+    // Another session may have cached the same map meanwhile
+    let entry = match generation {
+        Ok(entry) => Some(entry),
+        Err(generation) => cache.insert(key, generation),
+    };
+    drop(cache);
+    if let Some(entry) = entry {
+        entry.lock().unwrap().restyle(style);
+    }
+
+    let timing_entries = timings
+        .iter()
+        .map(|t| TimingEntry {
+            name: t.name.to_string(),
+            ms: t.ms,
+        })
+        .collect();
+
+    Ok(Rendered {
+        key,
+        layers,
+        timings: timing_entries,
+        width,
+        height,
     })
-    .await
-    .unwrap();
+}
+
+/// The base map of `world` for `generate_base_layers`: from the disk
+/// cache, else run on the session's pipeline.
+fn base_map(
+    state: &AppState,
+    session: &Arc<Session>,
+    world: &WorldFile,
+    progress: impl Fn(Stage, f32) + Sync,
+    cancel: &CancelToken,
+) -> Result<(Map, Vec<worldgen::Timing>), WorldgenError> {
+    let WorldFile { seed, width, height, ref params, .. } = *world;
+    let key = CacheKey::new(seed, width, height, params);
+    let start = std::time::Instant::now();
+    let loaded = state.disk.as_ref().map(|d| d.load(key, world, false));
+    if let Some(loaded) = &loaded {
+        state.metrics.record_disk(loaded.is_some());
+    }
+    if let Some(map) = loaded.flatten() {
+        let ms = start.elapsed().as_secs_f64() * 1000.0;
+        return Ok((map, vec![worldgen::Timing { name: "disk cache", ms }]));
+    }
 
-    Json(response)
+    // The session's runs take turns on its pipeline: a cancelled run
+    // releases it promptly, keeping the stages it finished for the request
+    // that superseded it. A run that panicked leaves nothing to reuse.
+    let mut slot = session.pipeline.lock().unwrap_or_else(|poisoned| {
+        let mut slot = poisoned.into_inner();
+        *slot = None;
+        slot
+    });
+    let run = run_pipeline(&mut slot, world, progress, cancel);
+    let failed = matches!(run, Err(ref e) if !matches!(e, WorldgenError::Cancelled));
+    if failed || !state.pipelines.lock().unwrap().keep(session, width * height) {
+        *slot = None;
+    }
+    drop(slot);
+    let (map, timings) = run?;
+    state.metrics.record_stages(&timings);
+    if let Some(disk) = &state.disk {
+        disk.save(key, world, &map, false);
+    }
+    Ok((map, timings))
 }
 
-/// Slow endpoint: computes hydrology from cached base map (~8s).
-/// Carves valleys into the cached heightmap along river paths.
+/// Run the base stages of `world` on the pipeline in `slot`, set up for
+/// it (or made, if there is none of its size).
+fn run_pipeline(
+    slot: &mut Option<Pipeline>,
+    world: &WorldFile,
+    progress: impl Fn(Stage, f32) + Sync,
+    cancel: &CancelToken,
+) -> Result<(Map, Vec<worldgen::Timing>), WorldgenError> {
+    let WorldFile { seed, width, height, ref params, .. } = *world;
+    let pipeline = match slot.as_mut() {
+        Some(p) if p.size() == (width, height) => {
+            // A new seed keeps the old outputs' buffers for this run
+            p.reseed(seed);
+            p.set_params(params.clone())?;
+            p
+        }
+        _ => slot.insert(Pipeline::new(seed, width, height, params.clone())?),
+    };
+    let timings = pipeline.run(&Stage::BASE, progress, cancel)?;
+    Ok((pipeline.map(), timings))
+}
+
+/// Slow endpoint: computes hydrology for a cached base map (~8s), the
+/// one a `RiversRequest` picks, generating that first if needed. The
+/// cached map isn't changed: the carved copy is kept beside it, and asking
+/// again returns the same result. A newer generate request cancels it.
 async fn rivers_handler(
     State(state): State<SharedState>,
-) -> Json<Option<RiversResponse>> {
-    let response = tokio::task::spawn_blocking(move || {
-        let mut guard = state.lock().unwrap();
-        guard.as_mut().map(|c| {
-            let (river_flow, timing) = worldgen::generate_rivers(&mut c.map, c.seed, &c.params);
-            let layer = Layer {
-                name: "rivers".into(),
-                data_url: encode_png(
-                    &render::render_rivers(&c.map.height, &river_flow, &c.map.precipitation, &c.map.temperature),
-                    c.map.w,
-                    c.map.h,
-                ),
-            };
-            RiversResponse {
-                layer,
-                timing: TimingEntry {
-                    name: timing.name.to_string(),
-                    ms: timing.ms,
-                },
+    Client(session): Client,
+    body: Bytes,
+) -> Result<Json<Option<RiversResponse>>, ApiError> {
+    let RiversRequest { key, generation } = match body.is_empty() {
+        true => RiversRequest::default(),
+        false => serde_json::from_slice(&body).map_err(|e| ApiError::bad_request(e.to_string()))?,
+    };
+    // The settings' key, and the job that builds their map
+    let job = match key {
+        None if !generation.is_empty() => {
+            let (seed, width, height, params) = parse_params(&generation, &state.config)?;
+            let (_, style) = parse_render(&generation)?;
+            Some(Job { seed, width, height, params, layers: Vec::new(), style })
+        }
+        _ => None,
+    };
+    let cancel = session.current.lock().unwrap().clone();
+    let worker = (state.clone(), session.clone(), cancel.clone());
+    let response = run_limited(&state, &session, &cancel, move || {
+        let (state, session, cancel) = worker;
+        let key = match job {
+            Some(job) => {
+                let key = CacheKey::new(job.seed, job.width, job.height, &job.params);
+                if state.cache.lock().unwrap().get(key).is_none() {
+                    generate_base_layers(&state, &session, job, |_, _| {}, &cancel)?;
+                }
+                Some(key)
             }
-        })
+            None => key,
+        };
+        compute_rivers(&state, &session, key, |_, _| {}, &cancel)
+    })
+    .await?;
+
+    Ok(Json(response?))
+}
+
+/// Hydrology over a cached map (see `MapCache::get`), reporting as
+/// `generate_rivers_with_progress`, or the result of an earlier run.
+/// `None` if the map isn't cached.
+fn compute_rivers(
+    state: &AppState,
+    session: &Session,
+    key: Option<CacheKey>,
+    progress: impl Fn(Stage, f32),
+    cancel: &CancelToken,
+) -> Result<Option<RiversResponse>, WorldgenError> {
+    let Some(key) = key.or(*session.latest.lock().unwrap()) else {
+        return Ok(None);
+    };
+    let Some(entry) = state.cache.lock().unwrap().get(key) else {
+        return Ok(None);
+    };
+    // Work on a copy, so the entry stays available meanwhile
+    let (mut map, seed, params, style) = {
+        let c = entry.lock().unwrap();
+        if let Some((_, response)) = &c.rivers {
+            return Ok(Some(response.clone()));
+        }
+        (c.map.clone(), c.seed, c.params.clone(), c.style.clone())
+    };
+    let key = CacheKey::new(seed, map.w, map.h, &params);
+    let world = WorldFile::new(seed, map.w, map.h, &params);
+    let start = std::time::Instant::now();
+    let loaded = state.disk.as_ref().map(|d| d.load(key, &world, true));
+    if let Some(loaded) = &loaded {
+        state.metrics.record_disk(loaded.is_some());
+    }
+    let timing = match loaded.flatten() {
+        Some(saved) => {
+            map = saved;
+            worldgen::Timing { name: "disk cache", ms: start.elapsed().as_secs_f64() * 1000.0 }
+        }
+        None => {
+            let (river_flow, timing) =
+                worldgen::generate_rivers_with_progress(&mut map, seed, &params, progress, cancel)?;
+            map.river_flow = river_flow;
+            state.metrics.record_stages(std::slice::from_ref(&timing));
+            if let Some(disk) = &state.disk {
+                disk.save(key, &world, &map, true);
+            }
+            timing
+        }
+    };
+    let response = RiversResponse {
+        key,
+        layer: Layer {
+            name: "rivers".into(),
+            data_url: encode_png(&render::render_layer(&map, LayerKind::Rivers, &style), map.w, map.h),
+        },
+        timing: TimingEntry {
+            name: timing.name.to_string(),
+            ms: timing.ms,
+        },
+    };
+    let mut c = entry.lock().unwrap();
+    c.rivers = Some((map, response.clone()));
+    // Carving changed the terrain every layer is shaded from
+    c.tiles = TileCache::default();
+    Ok(Some(response))
+}
+
+/// Full-precision heightmap download from the cached map:
+/// `png16` (16-bit grayscale), `f32` (raw little-endian meters), or
+/// `json` (sidecar with dimensions and min/max).
+async fn heightmap_export_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Path(format)): Checked<Path<String>>,
+    Checked(Query(query)): Checked<Query<KeyQuery>>,
+) -> Result<Response, ApiError> {
+    let entry = cached(&state, &session, &query)?;
+    let c = entry.lock().unwrap();
+    let tag = c.etag();
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+    let height = &c.latest().height;
+    let response = match format.as_str() {
+        "png16" => (
+            [
+                (header::CONTENT_TYPE, "image/png"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"heightmap16.png\""),
+            ],
+            export::heightmap_png16(height),
+        )
+            .into_response(),
+        "f32" => (
+            [
+                (header::CONTENT_TYPE, "application/octet-stream"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"heightmap.f32\""),
+            ],
+            export::heightmap_f32le(height),
+        )
+            .into_response(),
+        "json" => Json(export::heightmap_meta(height)).into_response(),
+        _ => return Err(ApiError::bad_request("format must be png16, f32, or json")),
+    };
+    Ok(with_etag(response, tag))
+}
+
+/// Liveness probe for load balancers and orchestrators.
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Prometheus text exposition: request counts, stage time histograms, map
+/// cache lookups, and cache, session, generation and memory gauges.
+async fn metrics_handler(State(state): State<SharedState>) -> Response {
+    use std::fmt::Write;
+
+    fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+        writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind).unwrap();
+    }
+    let mut out = String::new();
+
+    family(&mut out, "worldgen_requests_total", "counter", "API responses by method, route and status.");
+    for ((method, route, status), count) in state.metrics.requests.lock().unwrap().iter() {
+        let labels = format!("method=\"{}\",route=\"{}\",status=\"{}\"", method, route, status);
+        writeln!(out, "worldgen_requests_total{{{}}} {}", labels, count).unwrap();
+    }
+
+    family(&mut out, "worldgen_stage_seconds", "histogram", "Run time of generated pipeline stages.");
+    for (stage, histogram) in state.metrics.stages.lock().unwrap().iter() {
+        let mut cumulative = 0;
+        let bounds = STAGE_BUCKETS.iter().map(|le| le.to_string()).chain(["+Inf".to_string()]);
+        for (le, count) in bounds.zip(histogram.buckets) {
+            cumulative += count;
+            writeln!(out, "worldgen_stage_seconds_bucket{{stage=\"{}\",le=\"{}\"}} {}", stage, le, cumulative).unwrap();
+        }
+        writeln!(out, "worldgen_stage_seconds_sum{{stage=\"{}\"}} {}", stage, histogram.seconds).unwrap();
+        writeln!(out, "worldgen_stage_seconds_count{{stage=\"{}\"}} {}", stage, cumulative).unwrap();
+    }
+
+    family(&mut out, "worldgen_cache_lookups_total", "counter", "Map cache lookups by cache and result.");
+    let (maps, hits, misses) = {
+        let cache = state.cache.lock().unwrap();
+        (cache.entries.len(), cache.hits, cache.misses)
+    };
+    let mut lookups = vec![("memory", "hit", hits), ("memory", "miss", misses)];
+    if state.disk.is_some() {
+        lookups.push(("disk", "hit", state.metrics.disk_hits.load(Ordering::Relaxed)));
+        lookups.push(("disk", "miss", state.metrics.disk_misses.load(Ordering::Relaxed)));
+    }
+    for (cache, result, count) in lookups {
+        writeln!(out, "worldgen_cache_lookups_total{{cache=\"{}\",result=\"{}\"}} {}", cache, result, count).unwrap();
+    }
+
+    let running = state.config.max_generations - state.generations.available_permits();
+    let mut gauges = vec![
+        ("worldgen_cached_maps", "Maps in the in-memory cache.", maps as u64),
+        ("worldgen_sessions", "Sessions kept.", state.sessions.lock().unwrap().len() as u64),
+        ("worldgen_generations_running", "Generations holding a slot.", running as u64),
+    ];
+    // Linux only; the gauge is left out elsewhere
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let rss_kb: Option<u64> =
+        status.lines().find_map(|l| l.strip_prefix("VmRSS:")?.trim().strip_suffix("kB")?.trim().parse().ok());
+    if let Some(kb) = rss_kb {
+        gauges.push(("process_resident_memory_bytes", "Resident memory size in bytes.", kb * 1024));
+    }
+    for (name, help, value) in gauges {
+        family(&mut out, name, "gauge", help);
+        writeln!(out, "{} {}", name, value).unwrap();
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out).into_response()
+}
+
+/// Re-render layers of a cached map (with rivers once they're computed)
+/// in another palette, style or projection, without regenerating. Responds
+/// like /api/generate, including multipart for clients that accept it.
+async fn render_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Json(req)): Checked<Json<RenderRequest>>,
+) -> Result<Response, ApiError> {
+    let options = GenerateRequest {
+        layers: req.layers,
+        palette: req.palette,
+        style: req.style,
+        ..GenerateRequest::default()
+    };
+    let (layers, style) = parse_render(&options)?;
+    let projection = req.projection.unwrap_or_default();
+    if let Projection::Orthographic { lat, lon, size } = projection {
+        if !(-90.0..=90.0).contains(&lat) || !lon.is_finite() {
+            return Err(ApiError::field("projection", "projection.lat must be in -90..=90 and lon finite"));
+        }
+        let max = state.config.max_width.max(state.config.max_height);
+        if size.is_some_and(|s| s == 0 || s > max) {
+            return Err(ApiError::field("projection.size", format!("projection.size must be in 1..={}", max)));
+        }
+    }
+    let entry = cached(&state, &session, &KeyQuery { key: req.key })?;
+    let tag = {
+        let c = entry.lock().unwrap();
+        etag(c.key(), (c.rivers.is_some(), &layers, &style, projection, wants_multipart(&headers)))
+    };
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+
+    let rendered = tokio::task::spawn_blocking(move || {
+        let start = std::time::Instant::now();
+        let c = entry.lock().unwrap();
+        let map = c.latest();
+        let (width, height) = match projection {
+            Projection::Equirectangular => (map.w, map.h),
+            Projection::Orthographic { size, .. } => {
+                let size = size.unwrap_or(map.h);
+                (size, size)
+            }
+        };
+        let layers = layers
+            .into_iter()
+            .map(|kind| {
+                let rgba = render::render_layer(map, kind, &style);
+                let rgba = match projection {
+                    Projection::Equirectangular => rgba,
+                    Projection::Orthographic { lat, lon, .. } => {
+                        render::animation::render_globe(&rgba, map.w, map.h, width, lat, lon)
+                    }
+                };
+                (kind.name(), png_bytes(&rgba, width, height))
+            })
+            .collect();
+        let timing = TimingEntry { name: "render".into(), ms: start.elapsed().as_secs_f64() * 1000.0 };
+        Rendered { key: c.key(), layers, timings: vec![timing], width, height }
     })
     .await
-    .unwrap();
+    .map_err(ApiError::internal)?;
+    Ok(with_etag(rendered.respond(&headers), tag))
+}
+
+/// One 256px Web Mercator tile (`{y}.png`, y = 0 at the north) of a
+/// render layer of a cached map, rendered on first request and then kept
+/// with the map.
+async fn tile_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Path((layer, z, x, tile))): Checked<Path<(String, u32, u32, String)>>,
+    Checked(Query(query)): Checked<Query<KeyQuery>>,
+) -> Result<Response, ApiError> {
+    let Some(kind) = LayerKind::from_name(&layer) else {
+        return Err(ApiError::not_found(format!("no layer `{}`", layer)));
+    };
+    let y = tile.strip_suffix(".png").and_then(|y| y.parse::<u32>().ok());
+    let Some(y) = y.filter(|&y| z <= MAX_ZOOM && x < 1 << z && y < 1 << z) else {
+        return Err(ApiError::not_found("no such tile"));
+    };
+    let entry = cached(&state, &session, &query)?;
+    let tag = entry.lock().unwrap().etag();
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+    let shared = state.clone();
+    let png = run_limited(&state, &session, &CancelToken::new(), move || {
+        entry.lock().unwrap().tile(&shared.layer_renders, kind, z, x, y)
+    })
+    .await?;
+    Ok(([(header::CONTENT_TYPE, HeaderValue::from_static("image/png")), (header::ETAG, tag)], png).into_response())
+}
+
+/// `Map::statistics` of the cached map (with rivers once they're computed).
+async fn stats_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Query(query)): Checked<Query<KeyQuery>>,
+) -> Result<Response, ApiError> {
+    let entry = cached(&state, &session, &query)?;
+    let tag = entry.lock().unwrap().etag();
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+    let stats = tokio::task::spawn_blocking(move || entry.lock().unwrap().latest().statistics())
+        .await
+        .map_err(ApiError::internal)?;
+    Ok(with_etag(Json(stats).into_response(), tag))
+}
+
+/// Every preset with its full parameters, for a preset picker.
+async fn presets_handler() -> Json<Vec<PresetInfo>> {
+    Json(Preset::ALL.into_iter().map(|p| PresetInfo { name: p.name(), params: Params::preset(p) }).collect())
+}
+
+/// Control, range, default and description of every parameter
+/// (`Params::schema`), so front ends build their controls from it.
+async fn schema_handler() -> Json<Vec<ParamInfo>> {
+    Json(Params::schema())
+}
 
-    Json(response)
+/// Name and cell type of every layer of the cached map.
+async fn layers_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Query(query)): Checked<Query<KeyQuery>>,
+) -> Result<Response, ApiError> {
+    let entry = cached(&state, &session, &query)?;
+    let c = entry.lock().unwrap();
+    let tag = c.etag();
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+    Ok(with_etag(Json(c.latest().layers()).into_response(), tag))
+}
+
+/// Every layer of the cached map at one cell, for hover readouts. Continuous
+/// layers are interpolated at a `lat`/`lon` point and exact at an `x`/`y`
+/// cell (see `Map::sample`).
+async fn inspect_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Query(query)): Checked<Query<InspectQuery>>,
+) -> Result<Response, ApiError> {
+    let entry = cached(&state, &session, &KeyQuery { key: query.key })?;
+    let c = entry.lock().unwrap();
+    let tag = c.etag();
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+    let map = c.latest();
+    let (lat, lon) = match (query.x, query.y, query.lat, query.lon) {
+        (Some(x), Some(y), None, None) if x < map.w && y < map.h => cell_latlon(x, y, map.w, map.h),
+        (Some(_), Some(_), None, None) => {
+            return Err(ApiError::bad_request(format!("the cell is outside the {}x{} map", map.w, map.h)));
+        }
+        (None, None, Some(lat), Some(lon)) if lat.is_finite() && lon.is_finite() => (lat, lon),
+        _ => return Err(ApiError::bad_request("give either x and y or lat and lon")),
+    };
+    let cell = map.sample(lat, lon);
+    let (x, y) = (cell.x, cell.y);
+    let boundary = match map.boundary_type.get(x, y) {
+        boundary::CONVERGENT => "convergent",
+        boundary::DIVERGENT => "divergent",
+        boundary::TRANSFORM => "transform",
+        _ => "interior",
+    };
+    let layers = map.extra_layers.iter().map(|(name, data)| (name.clone(), data.view().value(x, y))).collect();
+    let boundary_major = map.boundary_major.get(x, y) != 0;
+    Ok(with_etag(Json(InspectResponse { cell, boundary, boundary_major, layers }).into_response(), tag))
+}
+
+/// One layer of the cached map as a NumPy `.npy` array.
+async fn layer_export_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Path(name)): Checked<Path<String>>,
+    Checked(Query(query)): Checked<Query<KeyQuery>>,
+) -> Result<Response, ApiError> {
+    let entry = cached(&state, &session, &query)?;
+    let c = entry.lock().unwrap();
+    let tag = c.etag();
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+    let data = match c.latest().layer_view(&name) {
+        Some(LayerView::F32(g)) => export::npy(g.as_ref()),
+        Some(LayerView::U16(g)) => export::npy(g),
+        Some(LayerView::U8(g)) => export::npy(g),
+        None => return Err(ApiError::not_found(format!("no layer `{}`", name))),
+    };
+    let disposition = format!("attachment; filename=\"{}.npy\"", name);
+    let response = (
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        data,
+    )
+        .into_response();
+    Ok(with_etag(response, tag))
+}
+
+/// Seed, size and parameters of the cached map as a `world.toml`
+/// download; POST it to /api/world to rebuild the same world.
+async fn world_export_handler(
+    State(state): State<SharedState>,
+    Client(session): Client,
+    headers: HeaderMap,
+    Checked(Query(query)): Checked<Query<KeyQuery>>,
+) -> Result<Response, ApiError> {
+    let entry = cached(&state, &session, &query)?;
+    let c = entry.lock().unwrap();
+    let tag = c.etag();
+    if fresh(&headers, &tag) {
+        return Ok(not_modified(tag));
+    }
+    let response = (
+        [
+            (header::CONTENT_TYPE, "application/toml"),
+            (header::CONTENT_DISPOSITION, "attachment; filename=\"world.toml\""),
+        ],
+        WorldFile::new(c.seed, c.map.w, c.map.h, &c.params).to_toml(),
+    )
+        .into_response();
+    Ok(with_etag(response, tag))
 }
 
 #[tokio::main]
 async fn main() {
+    // --threads N caps the generation worker threads (default: one per
+    // core); the async runtime has its own threads
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(i) = args.iter().position(|a| a == "--threads") {
+        let threads: usize = args.get(i + 1).and_then(|s| s.parse().ok()).unwrap_or_else(|| {
+            eprintln!("--threads needs a thread count");
+            std::process::exit(1);
+        });
+        #[cfg(feature = "parallel")]
+        worldgen::threads::set_global_threads(threads).expect("thread pool is set up first");
+        #[cfg(not(feature = "parallel"))]
+        let _ = threads;
+    }
+
+    let config = Config::from_args(&args[1..]).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let addr = SocketAddr::new(config.addr, config.port);
+
     let frontend = ServeDir::new("frontend");
-    let state: SharedState = Arc::new(Mutex::new(None));
+    let disk = config.cache_dir.clone().map(|dir| {
+        DiskCache::open(dir.clone(), config.cache_bytes).unwrap_or_else(|e| {
+            eprintln!("cache directory {}: {}", dir.display(), e);
+            std::process::exit(1);
+        })
+    });
+    let state: SharedState = Arc::new(AppState {
+        disk,
+        generations: Arc::new(Semaphore::new(config.max_generations)),
+        config,
+        cache: Mutex::new(MapCache { entries: VecDeque::new(), hits: 0, misses: 0 }),
+        layer_renders: Mutex::new(LayerRenders::default()),
+        pipelines: Mutex::new(PipelinePool::default()),
+        metrics: Metrics::default(),
+        sessions: Mutex::new(VecDeque::new()),
+    });
 
     let app = Router::new()
         .route("/api/generate", post(generate_handler))
+        .route("/api/generate/stream", post(generate_stream_handler))
+        .route("/api/world", post(world_handler))
         .route("/api/rivers", post(rivers_handler))
+        .route("/api/render", post(render_handler))
+        .route("/api/export/heightmap/{format}", get(heightmap_export_handler))
+        .route("/api/export/world", get(world_export_handler))
+        .route("/api/presets", get(presets_handler))
+        .route("/api/params/schema", get(schema_handler))
+        .route("/api/layers", get(layers_handler))
+        .route("/api/inspect", get(inspect_handler))
+        .route("/api/stats", get(stats_handler))
+        .route("/api/tiles/{layer}/{z}/{x}/{tile}", get(tile_handler))
+        .route("/api/export/layer/{name}", get(layer_export_handler))
+        .route("/healthz", get(healthz))
+        .route("/metrics", get(metrics_handler))
+        .route_layer(middleware::from_fn_with_state(state.clone(), count_requests))
         .with_state(state)
-        .fallback_service(frontend);
+        .fallback_service(frontend)
+        // gzip or deflate as the client accepts; the default predicate
+        // already skips images, event streams and tiny bodies, and
+        // multipart bodies are mostly PNGs
+        .layer(CompressionLayer::new().compress_when(
+            DefaultPredicate::new().and(NotForContentType::const_new("multipart/mixed")),
+        ));
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
     eprintln!("worldgen server at http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
@@ -0,0 +1,21 @@
+//! Wall-clock timing for `Timing` reports. `std::time::Instant` panics on
+//! `wasm32-unknown-unknown`, so without the `timing` feature (on by
+//! default) this stand-in is used and every timing reads 0 ms.
+
+#[cfg(feature = "timing")]
+pub use std::time::Instant;
+
+#[cfg(not(feature = "timing"))]
+#[derive(Clone, Copy, Debug)]
+pub struct Instant;
+
+#[cfg(not(feature = "timing"))]
+impl Instant {
+    pub fn now() -> Self {
+        Instant
+    }
+
+    pub fn elapsed(&self) -> std::time::Duration {
+        std::time::Duration::ZERO
+    }
+}
@@ -1,52 +1,1172 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::noise::NoiseBasis;
+use crate::rng::{RngCore, SeedTree};
+
+/// Current `Params::version`. History:
+/// - 1: `blur_sigma`, `mountain_width` and `shelf_width` in cells of a
+///   2048-wide map
+/// - 2: those widths in kilometers (`WorldScale`)
+pub const PARAMS_VERSION: u32 = 2;
+
 /// All tunable parameters — exposed as UI sliders in the frontend.
-#[derive(Clone, Debug)]
+/// Deserializing fills missing fields from `Default`, so parameter files
+/// and requests only need the values they change; unknown fields are
+/// rejected so typos don't pass silently.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Params {
+    /// Format version (`PARAMS_VERSION`). Files without one are version
+    /// 1; loading upgrades older files so they build the same world.
+    pub version: u32,
+
     // Plate tectonics
     pub num_macroplates: usize,
     pub num_microplates: usize,
     pub continental_fraction: f32,
     pub boundary_noise: f32,
+    /// Measure distances on the globe (E-W cell size shrinks toward the
+    /// poles) instead of treating cells as unit squares.
+    pub spherical_metric: bool,
 
     // Elevation profile
+    /// Profile smoothing radius (km).
     pub blur_sigma: f32,
     pub mountain_scale: f32,
     pub trench_scale: f32,
+    /// Gaussian sigma of mountain belts (km).
     pub mountain_width: f32,
 
     // Noise
     pub coast_amp: f32,
     pub interior_amp: f32,
     pub detail_amp: f32,
+    /// Noise basis for terrain (base, interior, coast, detail, ridges).
+    pub elevation_noise: NoiseBasis,
+    /// Noise basis for local temperature variation.
+    pub climate_noise: NoiseBasis,
 
     // Features
+    /// Continental shelf width (km).
     pub shelf_width: f32,
     pub ridge_height: f32,
     pub rift_depth: f32,
 
+    /// Sea level in meters: terrain is lowered by this much before the
+    /// shelf is built, so raising it floods lowlands and 0 stays the
+    /// waterline in the output.
+    pub sea_level: f32,
+
     // Climate / hydrology
+    /// Sea-level temperature (C) at the equator and at the poles.
+    pub equator_temperature: f32,
+    pub pole_temperature: f32,
     pub rainfall_scale: f32,
     pub river_threshold: f32,
+
+    // Hydrology
+    /// Valley depth per unit of log flow (meters): carve depth is
+    /// `carve_depth * ln(1 + flow / threshold)`.
+    pub carve_depth: f32,
+    /// Deepest a valley is carved (meters).
+    pub carve_max: f32,
+    /// Meander noise on plains (meters; fades with elevation).
+    pub meander_amp: f32,
+    /// Wavelengths (km) of the large sweeps and small wiggles in river courses.
+    pub meander_wavelength: f32,
+    pub meander_detail_wavelength: f32,
+    /// Largest upscale factor for the river grid; reduced automatically
+    /// on big maps.
+    pub hydro_upscale: usize,
+    /// How far each river system may grow into its headwaters after
+    /// thresholding, as a fraction of its size (0 disables).
+    pub basin_growth: f32,
+    /// Least flow, as a fraction of the river threshold, a headwater cell
+    /// needs to join its river.
+    pub basin_min_flow: f32,
+
+    // Population / history
+    pub total_population: f32,
+    pub num_nations: usize,
+    pub history_epochs: usize,
+    pub num_cultures: usize,
+
+    /// Physical size of the planet, for converting distances to cells.
+    pub world: WorldScale,
+
+    /// Per-stage seeds replacing the world seed for one stage.
+    pub seeds: StageSeeds,
+}
+
+/// Cell size (km) the built-in terrain shapes were tuned at: Earth's
+/// equator across 2048 cells.
+pub const REFERENCE_CELL_KM: f32 = std::f32::consts::TAU * 6371.0 / 2048.0;
+
+/// Physical scale of the world. Distances in `Params` are in kilometers
+/// and become cells through this, so a value means the same thing at any
+/// map width.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldScale {
+    /// Planet radius (km). The map width spans the equator, so this sets
+    /// the cell size unless `km_per_cell` is given.
+    pub planet_radius_km: f32,
+    /// Cell size at the equator (km), overriding the one from the radius
+    /// (for regional maps that don't wrap the whole planet).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub km_per_cell: Option<f32>,
+    /// Surface gravity (m/s^2). Relief scales inversely: lighter planets
+    /// hold taller mountains and deeper trenches.
+    pub gravity: f32,
+}
+
+impl Default for WorldScale {
+    fn default() -> Self {
+        Self {
+            planet_radius_km: 6371.0,
+            km_per_cell: None,
+            gravity: WorldScale::EARTH_GRAVITY,
+        }
+    }
+}
+
+impl WorldScale {
+    pub const EARTH_GRAVITY: f32 = 9.81;
+
+    /// Cell size (km) on a map `w` cells wide.
+    pub fn cell_km(&self, w: usize) -> f32 {
+        self.km_per_cell
+            .unwrap_or(std::f32::consts::TAU * self.planet_radius_km / w as f32)
+    }
+
+    /// `km` in cells on a map `w` cells wide.
+    pub fn cells(&self, km: f32, w: usize) -> f32 {
+        km / self.cell_km(w)
+    }
+
+    /// Cells per reference cell (`REFERENCE_CELL_KM`), for distances
+    /// authored at the reference scale.
+    pub fn reference_scale(&self, w: usize) -> f32 {
+        REFERENCE_CELL_KM / self.cell_km(w)
+    }
+
+    /// Relief multiplier from gravity (1 on Earth).
+    pub fn relief(&self) -> f32 {
+        Self::EARTH_GRAVITY / self.gravity
+    }
+}
+
+/// Seed overrides for individual stages. A stage left `None` follows the
+/// world seed, so e.g. setting only `hydrology` keeps the continents and
+/// climate of a world while re-rolling its rivers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StageSeeds {
+    /// Plate layout, growth and properties.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plates: Option<u64>,
+    /// Terrain noise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<u64>,
+    /// Temperature and precipitation noise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub climate: Option<u64>,
+    /// River meanders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hydrology: Option<u64>,
 }
 
 impl Default for Params {
     fn default() -> Self {
         Self {
+            version: PARAMS_VERSION,
             num_macroplates: 8,
             num_microplates: 600,
             continental_fraction: 0.54,
             boundary_noise: 2.0,
-            blur_sigma: 3.0,
+            spherical_metric: false,
+            blur_sigma: 60.0,
             mountain_scale: 0.6,
             trench_scale: 1.0,
-            mountain_width: 5.0,
+            mountain_width: 100.0,
             coast_amp: 1.0,
             interior_amp: 1.0,
             detail_amp: 50.0,
-            shelf_width: 50.0,
+            elevation_noise: NoiseBasis::Gradient,
+            climate_noise: NoiseBasis::Gradient,
+            shelf_width: 1000.0,
             ridge_height: 1500.0,
             rift_depth: 600.0,
+            sea_level: 0.0,
+            equator_temperature: 30.0,
+            pole_temperature: -30.0,
             rainfall_scale: 1.0,
             river_threshold: 0.01,
+            carve_depth: 25.0,
+            carve_max: 150.0,
+            meander_amp: 15.0,
+            meander_wavelength: 500.0,
+            meander_detail_wavelength: 150.0,
+            hydro_upscale: 8,
+            basin_growth: 0.5,
+            basin_min_flow: 0.05,
+            total_population: 5.0e9,
+            num_nations: 24,
+            history_epochs: 0,
+            num_cultures: 16,
+            world: WorldScale::default(),
+            seeds: StageSeeds::default(),
+        }
+    }
+}
+
+/// Curated starting points for `Params::preset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// The defaults: about half land, temperate climate.
+    EarthLike,
+    /// Few large plates and one dominant landmass.
+    Pangaea,
+    /// Many small plates, little land, scattered islands.
+    Archipelago,
+    /// Nearly all ocean, raised seas.
+    Waterworld,
+    /// Mostly land, hot and dry.
+    DesertPlanet,
+    /// Cold poles reaching toward the tropics, low seas.
+    IceAge,
+    /// Fast, rugged boundaries: high ridges, deep rifts, sharp relief.
+    YoungVolcanicWorld,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 7] = [
+        Preset::EarthLike,
+        Preset::Pangaea,
+        Preset::Archipelago,
+        Preset::Waterworld,
+        Preset::DesertPlanet,
+        Preset::IceAge,
+        Preset::YoungVolcanicWorld,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::EarthLike => "earthlike",
+            Preset::Pangaea => "pangaea",
+            Preset::Archipelago => "archipelago",
+            Preset::Waterworld => "waterworld",
+            Preset::DesertPlanet => "desert",
+            Preset::IceAge => "iceage",
+            Preset::YoungVolcanicWorld => "volcanic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+impl Params {
+    /// Parameters for a built-in world type. Fields a preset doesn't
+    /// mention keep their defaults.
+    pub fn preset(preset: Preset) -> Self {
+        let defaults = Self::default();
+        match preset {
+            Preset::EarthLike => defaults,
+            Preset::Pangaea => Self {
+                num_macroplates: 4,
+                num_microplates: 400,
+                continental_fraction: 0.40,
+                boundary_noise: 1.0,
+                rainfall_scale: 0.8,
+                ..defaults
+            },
+            Preset::Archipelago => Self {
+                num_macroplates: 14,
+                num_microplates: 1200,
+                continental_fraction: 0.25,
+                coast_amp: 1.6,
+                shelf_width: 1500.0,
+                sea_level: 150.0,
+                rainfall_scale: 1.3,
+                ..defaults
+            },
+            Preset::Waterworld => Self {
+                num_macroplates: 10,
+                num_microplates: 800,
+                continental_fraction: 0.08,
+                sea_level: 400.0,
+                rainfall_scale: 1.5,
+                ..defaults
+            },
+            Preset::DesertPlanet => Self {
+                continental_fraction: 0.80,
+                sea_level: -300.0,
+                equator_temperature: 38.0,
+                pole_temperature: -10.0,
+                rainfall_scale: 0.3,
+                ..defaults
+            },
+            Preset::IceAge => Self {
+                sea_level: -120.0,
+                equator_temperature: 22.0,
+                pole_temperature: -45.0,
+                rainfall_scale: 0.7,
+                ..defaults
+            },
+            Preset::YoungVolcanicWorld => Self {
+                num_macroplates: 12,
+                num_microplates: 900,
+                continental_fraction: 0.35,
+                boundary_noise: 3.0,
+                mountain_scale: 1.2,
+                trench_scale: 1.4,
+                mountain_width: 70.0,
+                interior_amp: 1.5,
+                detail_amp: 90.0,
+                ridge_height: 2500.0,
+                rift_depth: 1000.0,
+                equator_temperature: 34.0,
+                ..defaults
+            },
+        }
+    }
+
+    /// Load parameters from a TOML (`.toml`) or JSON (any other extension)
+    /// file, upgrading older versions. Errors name the file and, from the
+    /// parser, the offending field and position.
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let is_toml = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml"));
+        parse_migrated(&text, is_toml, None).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+}
+
+/// Deserialize `T` from TOML or JSON text holding a parameter table at
+/// the root or, with `params_key`, under that key, upgrading the table to
+/// `PARAMS_VERSION` first. Current files deserialize straight from the
+/// text so parse errors keep their positions.
+pub(crate) fn parse_migrated<T: serde::de::DeserializeOwned>(text: &str, is_toml: bool, params_key: Option<&str>) -> Result<T, String> {
+    let parse = |text: &str| -> Result<T, String> {
+        if is_toml {
+            toml::from_str(text).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(text).map_err(|e| e.to_string())
+        }
+    };
+    let mut root: serde_json::Value = if is_toml {
+        toml::from_str(text).map_err(|e| e.to_string())?
+    } else {
+        serde_json::from_str(text).map_err(|e| e.to_string())?
+    };
+    let table = match params_key {
+        Some(key) => root.get_mut(key),
+        None => Some(&mut root),
+    };
+    let Some(serde_json::Value::Object(fields)) = table else {
+        return parse(text);
+    };
+    let version = match fields.get("version") {
+        None => 1,
+        Some(v) => v.as_u64().ok_or("version must be a whole number")? as u32,
+    };
+    if version == PARAMS_VERSION {
+        return parse(text);
+    }
+    migrate_params(fields, version)?;
+    serde_json::from_value(root).map_err(|e| e.to_string())
+}
+
+/// Upgrade a serialized parameter table from `version` to
+/// `PARAMS_VERSION`, one version at a time. Fields added since need no
+/// step: they take their defaults, which reproduce the older behavior.
+fn migrate_params(fields: &mut serde_json::Map<String, serde_json::Value>, version: u32) -> Result<(), String> {
+    if version > PARAMS_VERSION {
+        return Err(format!(
+            "parameter version {} is newer than this worldgen supports ({})",
+            version, PARAMS_VERSION
+        ));
+    }
+    for from in version..PARAMS_VERSION {
+        match from {
+            // Widths in reference cells become kilometers
+            1 => {
+                for key in ["blur_sigma", "mountain_width", "shelf_width"] {
+                    if let Some(cells) = fields.get(key).and_then(|v| v.as_f64()) {
+                        fields.insert(key.into(), (cells * REFERENCE_CELL_KM as f64).into());
+                    }
+                }
+            }
+            _ => return Err(format!("unknown parameter version {}", from)),
+        }
+    }
+    fields.insert("version".into(), PARAMS_VERSION.into());
+    Ok(())
+}
+
+/// A parameter set `generate` refuses: it would panic or produce a
+/// degenerate world.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamError {
+    /// `value` is outside `min..=max` (for `river_threshold`, `min..max`
+    /// excluding both ends), or not a finite number.
+    OutOfRange { field: &'static str, value: f64, min: f64, max: f64 },
+    /// Macroplates are groups of microplates, so each needs at least one.
+    MicroplatesBelowMacroplates { microplates: usize, macroplates: usize },
+    /// The poles are warmer than the equator.
+    InvertedTemperature { equator: f32, pole: f32 },
+    /// The map has no cells, or fewer cells than microplates.
+    MapTooSmall { w: usize, h: usize },
+    /// `version` isn't `PARAMS_VERSION`; older files are upgraded when
+    /// loaded, but an in-memory set must be current.
+    UnsupportedVersion { version: u32 },
+    /// A `RandomizeConstraints` range that is inverted, not a number, or
+    /// outside the field's schema range, or is for a field that isn't
+    /// numeric.
+    InvalidRange { field: String, min: f64, max: f64 },
+}
+
+impl std::fmt::Display for ParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParamError::OutOfRange { field, value, min, max } => {
+                write!(f, "{} = {} is out of range ({} to {})", field, value, min, max)
+            }
+            ParamError::MicroplatesBelowMacroplates { microplates, macroplates } => write!(
+                f,
+                "num_microplates ({}) must be at least num_macroplates ({})",
+                microplates, macroplates
+            ),
+            ParamError::InvertedTemperature { equator, pole } => write!(
+                f,
+                "pole_temperature ({}) is above equator_temperature ({})",
+                pole, equator
+            ),
+            ParamError::MapTooSmall { w, h } => write!(f, "a {}x{} map is too small for the plate count", w, h),
+            ParamError::UnsupportedVersion { version } => {
+                write!(f, "parameter version {} (expected {})", version, PARAMS_VERSION)
+            }
+            ParamError::InvalidRange { field, min, max } => {
+                write!(f, "{} to {} is not a range of {} within its schema", min, max, field)
+            }
+        }
+    }
+}
+
+/// Every `ParamError` found in one parameter set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidParams {
+    pub errors: Vec<ParamError>,
+}
+
+impl std::fmt::Display for InvalidParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid parameters: ")?;
+        for (i, e) in self.errors.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", e)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidParams {}
+
+/// A legal but probably unintended parameter set.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParamWarning {
+    /// Under 5% or over 95% land: the world is nearly all ocean or all land.
+    ExtremeLandFraction { fraction: f32 },
+    /// Fewer than 4 microplates per macroplate leaves macroplates as
+    /// single blobs without internal boundaries.
+    SparseMicroplates { per_macroplate: f32 },
+    /// No rain means no rivers, and population clings to the coasts.
+    NoRainfall,
+    /// The whole planet is below freezing and will be ice-covered.
+    FrozenWorld { equator: f32 },
+    /// History epochs run but there are no nations to simulate.
+    HistoryWithoutNations,
+}
+
+impl std::fmt::Display for ParamWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParamWarning::ExtremeLandFraction { fraction } => {
+                write!(f, "continental_fraction = {} leaves almost no land or almost no ocean", fraction)
+            }
+            ParamWarning::SparseMicroplates { per_macroplate } => {
+                write!(f, "only {:.1} microplates per macroplate", per_macroplate)
+            }
+            ParamWarning::NoRainfall => write!(f, "rainfall_scale = 0: no rivers will form"),
+            ParamWarning::FrozenWorld { equator } => {
+                write!(f, "equator_temperature = {}: the whole world is below freezing", equator)
+            }
+            ParamWarning::HistoryWithoutNations => write!(f, "history_epochs > 0 but num_nations = 0"),
+        }
+    }
+}
+
+impl Params {
+    /// Check for values that would panic or produce a degenerate world.
+    /// `Ok` carries the warnings, which don't stop generation.
+    pub fn validate(&self) -> Result<Vec<ParamWarning>, InvalidParams> {
+        let mut errors = Vec::new();
+        let mut range = |field: &'static str, value: f64, min: f64, max: f64| {
+            if !(value.is_finite() && value >= min && value <= max) {
+                errors.push(ParamError::OutOfRange { field, value, min, max });
+            }
+        };
+        // Plate and region ids are u16, with u16::MAX reserved as "none"
+        let max_id = (u16::MAX - 1) as f64;
+        range("num_macroplates", self.num_macroplates as f64, 1.0, max_id);
+        range("num_microplates", self.num_microplates as f64, 1.0, max_id);
+        range("continental_fraction", self.continental_fraction as f64, 0.0, 1.0);
+        range("boundary_noise", self.boundary_noise as f64, 0.0, f64::INFINITY);
+        range("blur_sigma", self.blur_sigma as f64, 0.0, f64::INFINITY);
+        range("mountain_scale", self.mountain_scale as f64, 0.0, f64::INFINITY);
+        range("trench_scale", self.trench_scale as f64, 0.0, f64::INFINITY);
+        range("mountain_width", self.mountain_width as f64, f64::MIN_POSITIVE, f64::INFINITY);
+        range("coast_amp", self.coast_amp as f64, 0.0, f64::INFINITY);
+        range("interior_amp", self.interior_amp as f64, 0.0, f64::INFINITY);
+        range("detail_amp", self.detail_amp as f64, 0.0, f64::INFINITY);
+        range("shelf_width", self.shelf_width as f64, f64::MIN_POSITIVE, f64::INFINITY);
+        range("ridge_height", self.ridge_height as f64, 0.0, f64::INFINITY);
+        range("rift_depth", self.rift_depth as f64, 0.0, f64::INFINITY);
+        range("sea_level", self.sea_level as f64, -11000.0, 11000.0);
+        range("equator_temperature", self.equator_temperature as f64, -100.0, 100.0);
+        range("pole_temperature", self.pole_temperature as f64, -100.0, 100.0);
+        range("rainfall_scale", self.rainfall_scale as f64, 0.0, f64::INFINITY);
+        range("carve_depth", self.carve_depth as f64, 0.0, f64::INFINITY);
+        range("carve_max", self.carve_max as f64, 0.0, f64::INFINITY);
+        range("meander_amp", self.meander_amp as f64, 0.0, f64::INFINITY);
+        range("meander_wavelength", self.meander_wavelength as f64, 1.0, f64::INFINITY);
+        range("meander_detail_wavelength", self.meander_detail_wavelength as f64, 1.0, f64::INFINITY);
+        range("hydro_upscale", self.hydro_upscale as f64, 1.0, 16.0);
+        range("basin_growth", self.basin_growth as f64, 0.0, f64::INFINITY);
+        range("basin_min_flow", self.basin_min_flow as f64, 0.0, 1.0);
+        range("total_population", self.total_population as f64, 0.0, f64::INFINITY);
+        range("num_nations", self.num_nations as f64, 0.0, max_id);
+        range("num_cultures", self.num_cultures as f64, 0.0, max_id);
+        range("world.planet_radius_km", self.world.planet_radius_km as f64, 1.0, 1.0e6);
+        if let Some(km) = self.world.km_per_cell {
+            range("world.km_per_cell", km as f64, 1.0e-3, 1.0e4);
+        }
+        range("world.gravity", self.world.gravity as f64, 0.1, 100.0);
+        // A fraction of all flow; at 1 no cell can ever reach it
+        if !(self.river_threshold > 0.0 && self.river_threshold < 1.0) {
+            errors.push(ParamError::OutOfRange { field: "river_threshold", value: self.river_threshold as f64, min: 0.0, max: 1.0 });
+        }
+
+        if self.version != PARAMS_VERSION {
+            errors.push(ParamError::UnsupportedVersion { version: self.version });
+        }
+        if self.num_microplates < self.num_macroplates {
+            errors.push(ParamError::MicroplatesBelowMacroplates {
+                microplates: self.num_microplates,
+                macroplates: self.num_macroplates,
+            });
+        }
+        if self.pole_temperature > self.equator_temperature {
+            errors.push(ParamError::InvertedTemperature {
+                equator: self.equator_temperature,
+                pole: self.pole_temperature,
+            });
+        }
+        if !errors.is_empty() {
+            return Err(InvalidParams { errors });
+        }
+
+        let mut warnings = Vec::new();
+        if !(0.05..=0.95).contains(&self.continental_fraction) {
+            warnings.push(ParamWarning::ExtremeLandFraction { fraction: self.continental_fraction });
+        }
+        let per_macroplate = self.num_microplates as f32 / self.num_macroplates as f32;
+        if per_macroplate < 4.0 {
+            warnings.push(ParamWarning::SparseMicroplates { per_macroplate });
+        }
+        if self.rainfall_scale == 0.0 {
+            warnings.push(ParamWarning::NoRainfall);
+        }
+        if self.equator_temperature < 0.0 {
+            warnings.push(ParamWarning::FrozenWorld { equator: self.equator_temperature });
+        }
+        if self.history_epochs > 0 && self.num_nations == 0 {
+            warnings.push(ParamWarning::HistoryWithoutNations);
+        }
+        Ok(warnings)
+    }
+
+    /// `validate` plus the checks that depend on the map size.
+    pub fn validate_for_size(&self, w: usize, h: usize) -> Result<Vec<ParamWarning>, InvalidParams> {
+        let result = self.validate();
+        if w > 0 && h > 0 && w * h >= self.num_microplates {
+            return result;
+        }
+        let mut errors = result.err().map(|e| e.errors).unwrap_or_default();
+        errors.push(ParamError::MapTooSmall { w, h });
+        Err(InvalidParams { errors })
+    }
+}
+
+/// Fluent construction of `Params`: start from the defaults (or a preset),
+/// set only what differs, and `build` validates. Callers written this way
+/// keep compiling when fields are added.
+///
+/// ```ignore
+/// let params = Params::builder().continental_fraction(0.3).mountain_scale(1.2).build()?;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ParamsBuilder {
+    params: Params,
+}
+
+impl Params {
+    pub fn builder() -> ParamsBuilder {
+        ParamsBuilder::default()
+    }
+}
+
+impl ParamsBuilder {
+    /// Start from a preset instead of the defaults.
+    pub fn from_preset(preset: Preset) -> Self {
+        Self { params: Params::preset(preset) }
+    }
+
+    /// The parameters, if they pass `Params::validate`.
+    pub fn build(self) -> Result<Params, InvalidParams> {
+        self.params.validate()?;
+        Ok(self.params)
+    }
+
+    /// The parameters without validation.
+    pub fn build_unchecked(self) -> Params {
+        self.params
+    }
+}
+
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        impl ParamsBuilder {
+            $(
+                #[doc = concat!("Set `", stringify!($field), "`.")]
+                pub fn $field(mut self, value: $ty) -> Self {
+                    self.params.$field = value;
+                    self
+                }
+            )*
+        }
+    };
+}
+
+setters! {
+    num_macroplates: usize,
+    num_microplates: usize,
+    continental_fraction: f32,
+    boundary_noise: f32,
+    spherical_metric: bool,
+    blur_sigma: f32,
+    mountain_scale: f32,
+    trench_scale: f32,
+    mountain_width: f32,
+    coast_amp: f32,
+    interior_amp: f32,
+    detail_amp: f32,
+    elevation_noise: NoiseBasis,
+    climate_noise: NoiseBasis,
+    shelf_width: f32,
+    ridge_height: f32,
+    rift_depth: f32,
+    sea_level: f32,
+    equator_temperature: f32,
+    pole_temperature: f32,
+    rainfall_scale: f32,
+    river_threshold: f32,
+    carve_depth: f32,
+    carve_max: f32,
+    meander_amp: f32,
+    meander_wavelength: f32,
+    meander_detail_wavelength: f32,
+    hydro_upscale: usize,
+    basin_growth: f32,
+    basin_min_flow: f32,
+    total_population: f32,
+    num_nations: usize,
+    history_epochs: usize,
+    num_cultures: usize,
+    world: WorldScale,
+    seeds: StageSeeds,
+}
+
+/// What kind of control a parameter takes, with its bounds. The bounds
+/// are the useful range for a UI, narrower than what `validate` accepts.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ParamKind {
+    Int { min: usize, max: usize, step: usize },
+    Float { min: f32, max: f32, step: f32 },
+    Bool,
+    /// One of the named values.
+    Choice { options: Vec<&'static str> },
+}
+
+/// Description of one `Params` field for building UIs.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParamInfo {
+    /// Field name, as in parameter files and requests.
+    pub name: &'static str,
+    /// Short label for a control.
+    pub label: &'static str,
+    pub description: &'static str,
+    /// Grouping: plates, elevation, noise, features, climate, hydrology,
+    /// or society.
+    pub category: &'static str,
+    #[serde(flatten)]
+    pub kind: ParamKind,
+    /// Value in `Params::default()`.
+    pub default: serde_json::Value,
+}
+
+impl Params {
+    /// Metadata for every field but `world` and `seeds`, in declaration
+    /// order, so front ends can generate their controls instead of
+    /// hard-coding them.
+    pub fn schema() -> Vec<ParamInfo> {
+        use ParamKind::{Bool, Choice, Float, Int};
+        let int = |min, max, step| Int { min, max, step };
+        let float = |min, max, step| Float { min, max, step };
+        let bases = || Choice { options: NoiseBasis::ALL.iter().map(|b| b.name()).collect() };
+        let specs = [
+            ("num_macroplates", "plates", "plates", int(3, 15, 1),
+                "Number of major tectonic plates (macroplates). These define the big continents and ocean basins."),
+            ("num_microplates", "fracture", "plates", int(15, 1200, 5),
+                "Number of microplates. More = more fracturing, more internal mountain chains, more irregular coastlines."),
+            ("continental_fraction", "land", "plates", float(0.05, 0.95, 0.01),
+                "Fraction of the surface on continental plates."),
+            ("boundary_noise", "boundary", "plates", float(0.0, 5.0, 0.1),
+                "Organic boundary irregularity. 0 = straight Voronoi edges; higher = wigglier plate boundaries."),
+            ("spherical_metric", "spherical", "plates", Bool,
+                "Measure distances on the globe, so features keep their size toward the poles."),
+            ("blur_sigma", "blur", "elevation", float(20.0, 600.0, 10.0),
+                "Profile smoothing radius (km). Higher = smoother transitions between boundary zones."),
+            ("mountain_scale", "mountains", "elevation", float(0.0, 3.0, 0.1),
+                "Scales all convergent mountain heights."),
+            ("trench_scale", "trenches", "elevation", float(0.0, 3.0, 0.1),
+                "Scales all oceanic trench depths."),
+            ("mountain_width", "mtn width", "elevation", float(20.0, 1500.0, 10.0),
+                "Gaussian sigma of mountain profiles (km). Higher = wider, gentler mountain belts."),
+            ("coast_amp", "coast", "noise", float(0.0, 3.0, 0.1),
+                "Coastline noise amplitude: bays, peninsulas and wiggles along plate edges."),
+            ("interior_amp", "interior", "noise", float(0.0, 3.0, 0.1),
+                "Interior terrain variation: hills, plateaus and highlands inside continents."),
+            ("detail_amp", "detail", "noise", float(0.0, 200.0, 5.0),
+                "Fine-scale noise amplitude (meters)."),
+            ("elevation_noise", "terrain noise", "noise", bases(),
+                "Noise basis for terrain."),
+            ("climate_noise", "climate noise", "noise", bases(),
+                "Noise basis for local temperature variation."),
+            ("shelf_width", "shelf", "features", float(100.0, 2000.0, 50.0),
+                "Continental shelf width (km): the shallow-water zone around coastlines."),
+            ("ridge_height", "ridges", "features", float(0.0, 4000.0, 100.0),
+                "Height of mid-ocean ridges at divergent boundaries (meters)."),
+            ("rift_depth", "rifts", "features", float(0.0, 2000.0, 50.0),
+                "Depth of continental rift valleys (meters)."),
+            ("sea_level", "sea level", "features", float(-1000.0, 1000.0, 10.0),
+                "Sea level (meters). Raising it floods lowlands."),
+            ("equator_temperature", "equator", "climate", float(-20.0, 50.0, 1.0),
+                "Sea-level temperature at the equator (C)."),
+            ("pole_temperature", "poles", "climate", float(-60.0, 30.0, 1.0),
+                "Sea-level temperature at the poles (C)."),
+            ("rainfall_scale", "rainfall", "climate", float(0.1, 3.0, 0.1),
+                "Global precipitation multiplier. Higher = wetter world, more rivers."),
+            ("river_threshold", "rivers", "climate", float(0.001, 0.05, 0.001),
+                "Fraction of land cells shown as rivers. 0.002 = only major rivers, 0.02 = dense network."),
+            ("carve_depth", "carving", "hydrology", float(0.0, 100.0, 5.0),
+                "Valley depth per unit of log river flow (meters)."),
+            ("carve_max", "max carve", "hydrology", float(0.0, 500.0, 10.0),
+                "Deepest a river valley is carved (meters)."),
+            ("meander_amp", "meander", "hydrology", float(0.0, 50.0, 1.0),
+                "Meander noise on plains (meters). Higher = more winding rivers."),
+            ("meander_wavelength", "sweeps", "hydrology", float(100.0, 2000.0, 50.0),
+                "Wavelength of large river sweeps (km)."),
+            ("meander_detail_wavelength", "wiggles", "hydrology", float(20.0, 500.0, 10.0),
+                "Wavelength of small river wiggles (km)."),
+            ("hydro_upscale", "river detail", "hydrology", int(1, 8, 1),
+                "Upscale factor of the river grid. Higher = finer river paths, slower."),
+            ("basin_growth", "headwaters", "hydrology", float(0.0, 2.0, 0.1),
+                "How far river systems grow into their headwaters, as a fraction of their size."),
+            ("basin_min_flow", "headwater flow", "hydrology", float(0.0, 0.5, 0.01),
+                "Least flow (fraction of the river threshold) a headwater cell needs to join its river."),
+            ("total_population", "population", "society", float(0.0, 2.0e10, 1.0e8),
+                "World population."),
+            ("num_nations", "nations", "society", int(0, 100, 1),
+                "Number of nations founded."),
+            ("history_epochs", "epochs", "society", int(0, 50, 1),
+                "Epochs of border conflict simulated after founding."),
+            ("num_cultures", "cultures", "society", int(0, 64, 1),
+                "Number of culture areas with their own naming style."),
+        ];
+
+        let defaults = match serde_json::to_value(Params::default()) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => unreachable!("Params serializes as an object"),
+        };
+        specs
+            .into_iter()
+            .map(|(name, label, category, kind, description)| ParamInfo {
+                name,
+                label,
+                description,
+                category,
+                kind,
+                default: defaults[name].clone(),
+            })
+            .collect()
+    }
+}
+
+/// Limits for `Params::randomize`.
+#[derive(Clone, Debug, Default)]
+pub struct RandomizeConstraints {
+    /// Values for the fields in `fixed`.
+    pub base: Params,
+    /// Fields kept at their `base` value.
+    pub fixed: HashSet<String>,
+    /// Ranges `[min, max]` for numeric fields, replacing the built-in ones.
+    pub ranges: HashMap<String, [f64; 2]>,
+}
+
+impl RandomizeConstraints {
+    /// Keep `field` at its `base` value.
+    pub fn fix(mut self, field: &str) -> Self {
+        self.fixed.insert(field.to_string());
+        self
+    }
+
+    /// Draw `field` from `min..=max`.
+    pub fn range(mut self, field: &str, min: f64, max: f64) -> Self {
+        self.ranges.insert(field.to_string(), [min, max]);
+        self
+    }
+}
+
+impl Params {
+    /// A random but plausible parameter set: each field is drawn within
+    /// its range (from `constraints`, else a built-in range narrower than
+    /// the schema's where its extremes make odd worlds), then cross-field
+    /// rules are applied: at least 4 microplates per macroplate and poles
+    /// at least 15C colder than the equator. The same seed and constraints
+    /// give the same result, and each field draws from its own stream, so
+    /// fixing one field doesn't change the others.
+    ///
+    /// Ranges in `constraints` must be `min <= max` within the field's
+    /// schema range; others are `ParamError::InvalidRange`.
+    pub fn randomize(seed: u64, constraints: &RandomizeConstraints) -> Result<Params, InvalidParams> {
+        let schema = Self::schema();
+        let mut ranges: Vec<_> = constraints.ranges.iter().collect();
+        ranges.sort_by(|a, b| a.0.cmp(b.0));
+        let errors: Vec<ParamError> = ranges
+            .into_iter()
+            .filter(|&(field, &[min, max])| {
+                // NaN fails every comparison
+                let fits = schema.iter().find(|info| info.name == field).is_some_and(|info| match info.kind {
+                    ParamKind::Int { min: lo, max: hi, .. } => lo as f64 <= min && min <= max && max <= hi as f64,
+                    // In the field's own precision, so `continental_fraction`
+                    // may go down to 0.05 as written
+                    ParamKind::Float { min: lo, max: hi, .. } => {
+                        lo <= min as f32 && min <= max && max as f32 <= hi
+                    }
+                    ParamKind::Bool | ParamKind::Choice { .. } => false,
+                });
+                !fits
+            })
+            .map(|(field, &[min, max])| ParamError::InvalidRange { field: field.clone(), min, max })
+            .collect();
+        if !errors.is_empty() {
+            return Err(InvalidParams { errors });
+        }
+
+        let sane: HashMap<&str, [f64; 2]> = [
+            ("num_microplates", [100.0, 900.0]),
+            ("continental_fraction", [0.2, 0.7]),
+            ("sea_level", [-200.0, 200.0]),
+            ("equator_temperature", [15.0, 40.0]),
+            ("pole_temperature", [-45.0, 0.0]),
+            ("rainfall_scale", [0.5, 2.0]),
+            ("river_threshold", [0.003, 0.03]),
+            ("total_population", [1.0e9, 1.0e10]),
+            ("num_nations", [4.0, 40.0]),
+            ("history_epochs", [0.0, 10.0]),
+            ("num_cultures", [4.0, 32.0]),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut fields = match serde_json::to_value(&constraints.base) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => unreachable!("Params serializes as an object"),
+        };
+        let seeds = SeedTree::new(seed).child("params");
+        for info in schema {
+            if constraints.fixed.contains(info.name) {
+                continue;
+            }
+            let mut rng = seeds.child(info.name).rng();
+            let range = |min: f64, max: f64| {
+                constraints.ranges.get(info.name).or(sane.get(info.name)).copied().unwrap_or([min, max])
+            };
+            let value = match info.kind {
+                ParamKind::Int { min, max, .. } => {
+                    let [lo, hi] = range(min as f64, max as f64);
+                    let (lo, hi) = (lo.round() as usize, hi.round() as usize);
+                    (lo + rng.range_usize(hi - lo + 1)).into()
+                }
+                ParamKind::Float { min, max, step } => {
+                    let [lo, hi] = range(min as f64, max as f64);
+                    let step = step as f64;
+                    let v = lo + ((rng.next_f32() as f64 * (hi - lo)) / step).round() * step;
+                    v.clamp(lo, hi).into()
+                }
+                ParamKind::Bool => (rng.next_u32() & 1 == 1).into(),
+                ParamKind::Choice { options } => options[rng.range_usize(options.len())].into(),
+            };
+            fields.insert(info.name.to_string(), value);
+        }
+        let mut params: Params =
+            serde_json::from_value(serde_json::Value::Object(fields)).expect("schema values fit their fields");
+
+        let free = |field: &str| !constraints.fixed.contains(field);
+        if free("num_microplates") && params.num_microplates < params.num_macroplates * 4 {
+            params.num_microplates = params.num_macroplates * 4;
+        }
+        if free("pole_temperature") && params.pole_temperature > params.equator_temperature - 15.0 {
+            params.pole_temperature = params.equator_temperature - 15.0;
+        }
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+impl Params {
+    /// Blend of `a` (`t = 0`) and `b` (`t = 1`) for animating between two
+    /// worlds or sweeping a parameter. Real-valued fields interpolate
+    /// linearly; counts are rounded to the nearest integer; switches,
+    /// noise bases and stage seeds take `a`'s value below `t = 0.5` and
+    /// `b`'s from there on. `t` outside 0..=1 extrapolates, so the result
+    /// may need `validate`.
+    pub fn lerp(a: &Params, b: &Params, t: f32) -> Params {
+        let t = t as f64;
+        let mix = |x: f64, y: f64| x + (y - x) * t;
+        let (mut fields, other) = match (serde_json::to_value(a), serde_json::to_value(b)) {
+            (Ok(serde_json::Value::Object(a)), Ok(serde_json::Value::Object(b))) => (a, b),
+            _ => unreachable!("Params serializes as an object"),
+        };
+        for info in Self::schema() {
+            let (x, y) = (&fields[info.name], &other[info.name]);
+            let value = match info.kind {
+                ParamKind::Int { .. } => {
+                    let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+                    (mix(x, y).round().max(0.0) as usize).into()
+                }
+                ParamKind::Float { .. } => mix(x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0)).into(),
+                ParamKind::Bool | ParamKind::Choice { .. } => if t < 0.5 { x.clone() } else { y.clone() },
+            };
+            fields.insert(info.name.to_string(), value);
+        }
+        let mut params: Params =
+            serde_json::from_value(serde_json::Value::Object(fields)).expect("blended values fit their fields");
+
+        let mix = |x: f32, y: f32| mix(x as f64, y as f64) as f32;
+        params.world = WorldScale {
+            planet_radius_km: mix(a.world.planet_radius_km, b.world.planet_radius_km),
+            km_per_cell: match (a.world.km_per_cell, b.world.km_per_cell) {
+                (Some(x), Some(y)) => Some(mix(x, y)),
+                (x, y) => if t < 0.5 { x } else { y },
+            },
+            gravity: mix(a.world.gravity, b.world.gravity),
+        };
+        params.seeds = if t < 0.5 { a.seeds } else { b.seeds };
+        params
+    }
+}
+
+/// Everything needed to rebuild a world exactly: seed, size, the full
+/// parameter set, and the crate version that generated it. Written next
+/// to outputs as `world.toml` and accepted back as input.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorldFile {
+    /// Crate version that wrote the file. Other versions may build a
+    /// different world from the same settings.
+    pub version: String,
+    pub seed: u64,
+    /// Output size; the world itself is `generated_size`.
+    pub width: usize,
+    pub height: usize,
+    /// The world was generated at this many times the output size and
+    /// its images filtered back down (the CLI's `--supersample`).
+    #[serde(default = "one", skip_serializing_if = "is_one")]
+    pub supersample: usize,
+    pub params: Params,
+}
+
+fn one() -> usize {
+    1
+}
+
+fn is_one(n: &usize) -> bool {
+    *n == 1
+}
+
+impl WorldFile {
+    pub fn new(seed: u64, width: usize, height: usize, params: &Params) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            width,
+            height,
+            supersample: 1,
+            params: params.clone(),
+        }
+    }
+
+    /// The same settings for a world generated at `n` times the size.
+    pub fn with_supersample(mut self, n: usize) -> Self {
+        self.supersample = n;
+        self
+    }
+
+    /// Size the world is generated at: the output size times `supersample`.
+    pub fn generated_size(&self) -> (usize, usize) {
+        (self.width.saturating_mul(self.supersample), self.height.saturating_mul(self.supersample))
+    }
+
+    /// Whether the file was written by this version of the crate.
+    pub fn is_current(&self) -> bool {
+        self.version == env!("CARGO_PKG_VERSION")
+    }
+
+    pub fn to_toml(&self) -> String {
+        // Through JSON text so f32 fields print shortest (0.54, not
+        // 0.5400000214576721); `preserve_order` keeps the field order.
+        let json = serde_json::to_string(self).expect("world file serializes");
+        let value: toml::Value = serde_json::from_str(&json).expect("JSON is valid TOML data");
+        toml::to_string(&value).expect("world file serializes")
+    }
+
+    /// Parse a world file, upgrading older parameter versions.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        parse_migrated(text, true, Some("params"))
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_toml())
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml(&text).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_1_files_migrate_to_the_current_version() {
+        // Version 1 had no `version` field and widths in reference cells
+        let text = r#"{"blur_sigma": 10.0, "mountain_width": 20.0, "shelf_width": 5.0, "sea_level": 50.0}"#;
+        let params: Params = parse_migrated(text, false, None).unwrap();
+        assert_eq!(params.version, PARAMS_VERSION);
+        assert_eq!(params.blur_sigma, (10.0 * REFERENCE_CELL_KM as f64) as f32);
+        assert_eq!(params.mountain_width, (20.0 * REFERENCE_CELL_KM as f64) as f32);
+        assert_eq!(params.shelf_width, (5.0 * REFERENCE_CELL_KM as f64) as f32);
+        assert_eq!(params.sea_level, 50.0);
+        params.validate().unwrap();
+
+        // The same under a key, as in world files, and from TOML
+        let text = "seed = 1\n[params]\nversion = 1\nblur_sigma = 10.0\n";
+        let file: toml::Table = parse_migrated(text, true, Some("params")).unwrap();
+        let params: Params = file["params"].clone().try_into().unwrap();
+        assert_eq!(params.blur_sigma, (10.0 * REFERENCE_CELL_KM as f64) as f32);
+    }
+
+    #[test]
+    fn current_files_load_unchanged_and_newer_ones_fail() {
+        let text = serde_json::to_string(&Params::default()).unwrap();
+        let params: Params = parse_migrated(&text, false, None).unwrap();
+        assert_eq!(serde_json::to_string(&params).unwrap(), text);
+        let newer = format!(r#"{{"version": {}}}"#, PARAMS_VERSION + 1);
+        assert!(parse_migrated::<Params>(&newer, false, None).unwrap_err().contains("newer"));
+    }
+
+    fn invalid_ranges(constraints: RandomizeConstraints) -> Vec<ParamError> {
+        Params::randomize(1, &constraints).expect_err("the range is invalid").errors
+    }
+
+    #[test]
+    fn randomize_rejects_inverted_ranges() {
+        let errors = invalid_ranges(RandomizeConstraints::default().range("sea_level", 1.0, 0.0));
+        assert_eq!(errors, [ParamError::InvalidRange { field: "sea_level".into(), min: 1.0, max: 0.0 }]);
+        let errors = invalid_ranges(RandomizeConstraints::default().range("num_nations", 10.0, 5.0));
+        assert_eq!(errors, [ParamError::InvalidRange { field: "num_nations".into(), min: 10.0, max: 5.0 }]);
+    }
+
+    #[test]
+    fn randomize_rejects_nan_ranges() {
+        for (min, max) in [(f64::NAN, 1.0), (0.0, f64::NAN)] {
+            let errors = invalid_ranges(RandomizeConstraints::default().range("sea_level", min, max));
+            assert!(matches!(&errors[..], [ParamError::InvalidRange { field, .. }] if field == "sea_level"));
+        }
+    }
+
+    #[test]
+    fn randomize_rejects_ranges_outside_the_schema() {
+        // Past the schema, and past what the field's integer type holds
+        let errors = invalid_ranges(
+            RandomizeConstraints::default().range("sea_level", -5000.0, 0.0).range("num_nations", 0.0, 1.0e12),
+        );
+        assert_eq!(
+            errors,
+            [
+                ParamError::InvalidRange { field: "num_nations".into(), min: 0.0, max: 1.0e12 },
+                ParamError::InvalidRange { field: "sea_level".into(), min: -5000.0, max: 0.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn randomize_rejects_ranges_of_non_numeric_fields() {
+        let errors = invalid_ranges(RandomizeConstraints::default().range("spherical_metric", 0.0, 1.0));
+        assert_eq!(errors, [ParamError::InvalidRange { field: "spherical_metric".into(), min: 0.0, max: 1.0 }]);
+        let errors = invalid_ranges(RandomizeConstraints::default().range("no_such_field", 0.0, 1.0));
+        assert_eq!(errors, [ParamError::InvalidRange { field: "no_such_field".into(), min: 0.0, max: 1.0 }]);
+    }
+
+    #[test]
+    fn randomize_draws_within_valid_ranges() {
+        let constraints =
+            RandomizeConstraints::default().range("continental_fraction", 0.05, 0.1).range("num_nations", 7.0, 7.0);
+        for seed in 0..16 {
+            let params = Params::randomize(seed, &constraints).unwrap();
+            assert!((0.05..=0.1).contains(&params.continental_fraction), "{}", params.continental_fraction);
+            assert_eq!(params.num_nations, 7);
         }
     }
 }
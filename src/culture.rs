@@ -0,0 +1,221 @@
+use std::collections::BinaryHeap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::grid::Grid;
+use crate::noise::Fbm;
+use crate::rng::{Rng, RngCore, SeedTree, splitmix64};
+
+/// Culture id for ocean and unreached cells.
+pub const NO_CULTURE: u16 = u16::MAX;
+
+/// Phonetic family used by a culture's name generator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameStyle {
+    Nordic,
+    Latinate,
+    Steppe,
+    Tonal,
+    Desert,
+    Island,
+}
+
+impl NameStyle {
+    pub const ALL: [NameStyle; 6] = [
+        NameStyle::Nordic,
+        NameStyle::Latinate,
+        NameStyle::Steppe,
+        NameStyle::Tonal,
+        NameStyle::Desert,
+        NameStyle::Island,
+    ];
+
+    /// (onsets, vowels, codas) for syllable assembly.
+    fn phonemes(self) -> (&'static [&'static str], &'static [&'static str], &'static [&'static str]) {
+        match self {
+            NameStyle::Nordic => (
+                &["b", "d", "f", "g", "h", "k", "sk", "st", "th", "v", "r", "tr"],
+                &["a", "e", "i", "o", "u", "ei", "au", "y"],
+                &["", "", "n", "r", "rd", "ld", "nd", "k", "sson", "heim"],
+            ),
+            NameStyle::Latinate => (
+                &["c", "l", "m", "n", "p", "r", "s", "t", "v", "qu", "fl"],
+                &["a", "e", "i", "o", "u", "ia", "io"],
+                &["", "", "", "s", "m", "n", "ra", "na", "um"],
+            ),
+            NameStyle::Steppe => (
+                &["b", "ch", "d", "g", "k", "kh", "m", "s", "t", "ts", "z"],
+                &["a", "e", "o", "u", "ai", "uu"],
+                &["", "n", "r", "t", "g", "ng", "khan", "tai"],
+            ),
+            NameStyle::Tonal => (
+                &["", "b", "d", "h", "l", "m", "n", "ng", "s", "t", "x", "zh"],
+                &["a", "e", "i", "o", "u", "ao", "ei", "ua"],
+                &["", "", "", "n", "ng"],
+            ),
+            NameStyle::Desert => (
+                &["b", "d", "f", "h", "j", "k", "m", "q", "r", "s", "sh", "z"],
+                &["a", "i", "u", "aa", "ii", "ou"],
+                &["", "d", "l", "m", "r", "n", "sh", "ir", "ah"],
+            ),
+            NameStyle::Island => (
+                &["", "h", "k", "l", "m", "n", "p", "t", "w"],
+                &["a", "e", "i", "o", "u", "ai", "oa"],
+                &["", "", "", ""],
+            ),
+        }
+    }
+
+    /// Generate a capitalized name of 2-3 syllables.
+    pub fn generate(self, rng: &mut Rng) -> String {
+        let (onsets, vowels, codas) = self.phonemes();
+        let syllables = 2 + rng.range_usize(2);
+        let mut name = String::new();
+        for s in 0..syllables {
+            name.push_str(onsets[rng.range_usize(onsets.len())]);
+            name.push_str(vowels[rng.range_usize(vowels.len())]);
+            if s == syllables - 1 {
+                name.push_str(codas[rng.range_usize(codas.len())]);
+            }
+        }
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect(),
+            None => name,
+        }
+    }
+}
+
+/// A culture area: a name style shared by all features inside it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Culture {
+    pub name: String,
+    pub style: NameStyle,
+    pub name_seed: u64,
+}
+
+impl Culture {
+    /// Deterministic name for a feature at cell (x, y) in this culture's style.
+    pub fn name_feature(&self, x: usize, y: usize) -> String {
+        let mut rng = Rng::new(splitmix64(self.name_seed ^ ((y as u64) << 32 | x as u64)));
+        self.style.generate(&mut rng)
+    }
+}
+
+/// Priority queue entry for culture diffusion (min-heap on cost).
+#[derive(PartialEq)]
+struct Entry {
+    cost: f32,
+    idx: usize,
+    culture: u16,
+}
+
+impl Eq for Entry {}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Partition land into culture areas by terrain-barrier-aware diffusion.
+///
+/// Seeds are random spaced land cells. Spread cost rises with relief and
+/// altitude (mountain ranges separate cultures) and open water is
+/// crossable only at high cost, so short straits share a culture but
+/// oceans don't. Low-frequency noise keeps the regions organic.
+pub fn compute_cultures(height: &Grid<f32>, num_cultures: usize, seed: u64) -> (Grid<u16>, Vec<Culture>) {
+    let w = height.w;
+    let h = height.h;
+    let seeds = SeedTree::new(seed).child("culture");
+    let mut rng = seeds.rng();
+    let noise_seed = seeds.child("noise").seed_u32();
+    let organic_noise = Fbm::new(3, 8.0, 2.0, 0.5).with_period(1.0, None);
+
+    // Spaced random land seeds
+    let land: Vec<usize> = (0..w * h).filter(|&i| height.data[i] > 0.0).collect();
+    let mut seeds: Vec<usize> = Vec::with_capacity(num_cultures);
+    if !land.is_empty() && num_cultures > 0 {
+        let mut min_dist = (land.len() as f32 / num_cultures as f32).sqrt();
+        let mut attempts = 0usize;
+        while seeds.len() < num_cultures && attempts < num_cultures * 2000 {
+            let i = land[rng.range_usize(land.len())];
+            let (x, y) = ((i % w) as f32, (i / w) as f32);
+            let ok = seeds.iter().all(|&s| {
+                let dx_raw = (x - (s % w) as f32).abs();
+                let dx = dx_raw.min(w as f32 - dx_raw);
+                let dy = y - (s / w) as f32;
+                (dx * dx + dy * dy).sqrt() >= min_dist
+            });
+            if ok {
+                seeds.push(i);
+            }
+            attempts += 1;
+            if attempts.is_multiple_of(num_cultures * 200) {
+                min_dist *= 0.85;
+            }
+        }
+    }
+
+    let cultures: Vec<Culture> = seeds
+        .iter()
+        .map(|_| {
+            let style = NameStyle::ALL[rng.range_usize(NameStyle::ALL.len())];
+            let name_seed = rng.next_u64();
+            let name = style.generate(&mut rng);
+            Culture { name, style, name_seed }
+        })
+        .collect();
+
+    let mut culture_id = Grid::<u16>::new(w, h);
+    for v in &mut culture_id.data {
+        *v = NO_CULTURE;
+    }
+
+    let mut heap = BinaryHeap::new();
+    for (c, &idx) in seeds.iter().enumerate() {
+        heap.push(Entry { cost: 0.0, idx, culture: c as u16 });
+    }
+
+    while let Some(Entry { cost, idx, culture }) = heap.pop() {
+        if culture_id.data[idx] != NO_CULTURE {
+            continue;
+        }
+        culture_id.data[idx] = culture;
+
+        let (x, y) = (idx % w, idx / w);
+        for (nx, ny) in culture_id.neighbors8(x, y) {
+            let ni = ny * w + nx;
+            if culture_id.data[ni] != NO_CULTURE {
+                continue;
+            }
+            let step = if nx != x && ny != y { 1.414 } else { 1.0 };
+            let e = height.data[ni];
+            let terrain = if e <= 0.0 {
+                8.0
+            } else {
+                let relief = (e - height.data[idx].max(0.0)).abs() / 50.0;
+                1.0 + relief + e / 800.0
+            };
+            let u = nx as f32 / w as f32;
+            let v = ny as f32 / h as f32;
+            let organic = (1.0 + 0.6 * organic_noise.sample(u, v, noise_seed)).max(0.2);
+            heap.push(Entry { cost: cost + step * terrain * organic, idx: ni, culture });
+        }
+    }
+
+    // Ocean carries no culture
+    for (i, v) in culture_id.data.iter_mut().enumerate() {
+        if height.data[i] <= 0.0 {
+            *v = NO_CULTURE;
+        }
+    }
+
+    (culture_id, cultures)
+}
@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use crate::Map;
+use crate::grid::Grid;
+use crate::layers::LayerView;
+use crate::render::render_diff;
+
+/// How one layer changed between two maps.
+#[derive(Clone, Debug, Serialize)]
+pub struct LayerDiff {
+    pub name: String,
+    /// Cells whose value differs at all.
+    pub changed: usize,
+    /// Largest, mean and root-mean-square absolute change over all cells.
+    /// For id and class layers a changed cell counts as 1.
+    pub max_abs: f32,
+    pub mean_abs: f32,
+    pub rms: f32,
+    /// Per-cell `other - self`; for id and class layers 1 where the value
+    /// changed and 0 elsewhere.
+    #[serde(skip)]
+    pub delta: Grid<f32>,
+}
+
+impl LayerDiff {
+    fn new(name: String, delta: Grid<f32>) -> Self {
+        let n = delta.data.len().max(1) as f64;
+        let (mut changed, mut max_abs, mut sum, mut sum_sq) = (0, 0.0f32, 0.0f64, 0.0f64);
+        for &d in &delta.data {
+            let a = d.abs();
+            if a > 0.0 || d.is_nan() {
+                changed += 1;
+            }
+            max_abs = max_abs.max(a);
+            sum += a as f64;
+            sum_sq += a as f64 * a as f64;
+        }
+        Self { name, changed, max_abs, mean_abs: (sum / n) as f32, rms: (sum_sq / n).sqrt() as f32, delta }
+    }
+
+    /// Difference image: blue where the value dropped, red where it rose,
+    /// white where unchanged, scaled to the largest change.
+    pub fn render(&self) -> Vec<u8> {
+        render_diff(&self.delta)
+    }
+}
+
+/// Per-layer differences between two maps of the same size.
+#[derive(Clone, Debug, Serialize)]
+pub struct MapDiff {
+    pub w: usize,
+    pub h: usize,
+    /// Layers present in both maps, in `Map::layers` order.
+    pub layers: Vec<LayerDiff>,
+}
+
+impl MapDiff {
+    pub fn layer(&self, name: &str) -> Option<&LayerDiff> {
+        self.layers.iter().find(|l| l.name == name)
+    }
+
+    /// Whether no compared layer changed anywhere.
+    pub fn is_identical(&self) -> bool {
+        self.layers.iter().all(|l| l.changed == 0)
+    }
+}
+
+impl Map {
+    /// Compare every layer against `other`, e.g. the same seed before and
+    /// after a parameter or code change. None when the sizes differ.
+    /// Layers that either map hasn't generated are left out.
+    pub fn diff(&self, other: &Map) -> Option<MapDiff> {
+        if (self.w, self.h) != (other.w, other.h) {
+            return None;
+        }
+        let layers = self
+            .layers()
+            .into_iter()
+            .filter_map(|info| {
+                let (a, b) = (self.layer_view(&info.name)?, other.layer_view(&info.name)?);
+                layer_diff(info.name, &a, &b, (self.w, self.h))
+            })
+            .collect();
+        Some(MapDiff { w: self.w, h: self.h, layers })
+    }
+}
+
+/// `b - a` for f32 layers; for id and class layers 1 where the value
+/// changed. None if the types differ or either layer isn't map-sized
+/// (wasn't generated).
+fn layer_diff(name: String, a: &LayerView, b: &LayerView, size: (usize, usize)) -> Option<LayerDiff> {
+    fn changed<T: Copy + Default + PartialEq>(a: &Grid<T>, b: &Grid<T>) -> Grid<f32> {
+        a.zip_with(b, |a, b| if a == b { 0.0 } else { 1.0 })
+    }
+    if a.size() != size || b.size() != size {
+        return None;
+    }
+    let delta = match (a, b) {
+        (LayerView::F32(a), LayerView::F32(b)) => b.zip_with(a, |b, a| b - a),
+        (LayerView::U16(a), LayerView::U16(b)) => changed(a, b),
+        (LayerView::U8(a), LayerView::U8(b)) => changed(a, b),
+        _ => return None,
+    };
+    Some(LayerDiff::new(name, delta))
+}
@@ -0,0 +1,197 @@
+//! Local terrain edits on a finished map, for editor-style workflows:
+//! change the heightmap inside a rectangle and bring the layers that
+//! depend on it up to date around it, without regenerating the world.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Params, REFERENCE_CELL_KM};
+use crate::grid::{Grid, Rect};
+use crate::{Map, climate, culture, history, hydrology, render, terrain};
+
+/// A change to the heightmap inside an edit rectangle.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Edit {
+    /// Raise terrain by `meters` (negative lowers) at the center of the
+    /// rectangle, easing to nothing at its edges.
+    Raise { meters: f32 },
+    /// Set every cell to `meters`.
+    SetHeight { meters: f32 },
+    /// Sink land to `depth` meters below sea level, making a lake or inlet.
+    Lake { depth: f32 },
+    /// Lift water to `meters` above sea level; land is left as is.
+    ForceLand { meters: f32 },
+}
+
+impl Edit {
+    /// New elevation of a cell at `e`; `weight` is 1 at the center of the
+    /// rectangle and falls to 0 at its edges.
+    fn apply(self, e: f32, weight: f32) -> f32 {
+        match self {
+            Edit::Raise { meters } => e + meters * weight,
+            Edit::SetHeight { meters } => meters,
+            Edit::Lake { depth } => e.min(-depth.abs()),
+            Edit::ForceLand { meters } => if e <= 0.0 { meters.max(1.0) } else { e },
+        }
+    }
+}
+
+/// Least distance (cells) rivers are re-routed around an edit.
+const MIN_RIVER_MARGIN: usize = 32;
+
+impl Map {
+    /// Apply `edit` to the cells of `rect` (columns wrap) and recompute
+    /// what depends on them nearby: rivers are re-routed around the edit
+    /// (see `hydrology::reroute_window`), then temperature, precipitation,
+    /// ice and colors change inside a window around that by as much as
+    /// their stages' output there changes with the edit. Cells that became
+    /// water lose their population, nation and culture; new land starts
+    /// unclaimed. `seed` and `params` must be the ones the map was
+    /// generated with.
+    ///
+    /// Effects that would reach past the window (rain shadows far
+    /// downwind, a river's new course beyond it) are cut at its edge, so
+    /// the result approximates, rather than equals, regenerating with the
+    /// edit. Returns the window: every changed cell and pixel lies in it.
+    pub fn edit_region(&mut self, rect: Rect, edit: Edit, seed: u64, params: &Params) -> Rect {
+        let (w, h) = (self.w, self.h);
+        let rect = clip(rect, w, h);
+        if rect.w == 0 || rect.h == 0 {
+            return rect;
+        }
+
+        let before = self.height.clone();
+        let mut sunk = Vec::new();
+        for (x, y) in cells(rect, w) {
+            let (u, v) = ((x + w - rect.x) % w, y - rect.y);
+            let weight = bump(u, rect.w) * bump(v, rect.h);
+            let e = self.height.get(x, y);
+            let new = edit.apply(e, weight);
+            if e > 0.0 && new <= 0.0 {
+                sunk.push((x, y));
+            }
+            self.height.set(x, y, new);
+        }
+        for (x, y) in sunk {
+            self.population.set(x, y, 0.0);
+            self.nation_id.set(x, y, history::NO_NATION);
+            self.culture_id.set(x, y, culture::NO_CULTURE);
+        }
+
+        // Rivers first: carving feeds into the layers below
+        let rivers = grow(rect, rect.w.max(rect.h).max(MIN_RIVER_MARGIN), w, h);
+        let precipitation = self.precipitation.decode();
+        hydrology::reroute_window(&mut self.height, &before, &mut self.river_flow, &precipitation, rivers, params);
+
+        // Everything the new terrain can reach through ice shelves, the
+        // rain blur and ambient occlusion
+        let cell_km = params.world.cell_km(w);
+        let shelf = (6.0 * REFERENCE_CELL_KM / cell_km).ceil() as usize;
+        let ao_radius = terrain::default_ao_radius(w);
+        let dirty = grow(rivers, shelf.max(ao_radius).max(climate::PRECIPITATION_BLUR_ROWS), w, h);
+
+        // The stored layers came from the full generation, so each stage
+        // runs on the terrain before and after the edit and the window
+        // takes the difference. Stages only see the window plus the halo
+        // their output there depends on: whole rows for the rain, which is
+        // advected along them, and a shelf's reach for the ice.
+        let climate_seed = params.seeds.climate.unwrap_or(seed);
+        let band = grow(Rect { x: 0, y: dirty.y, w, h: dirty.h }, climate::PRECIPITATION_BLUR_ROWS, w, h);
+        let t0 = climate::compute_temperature_window(&before, band, climate_seed, params);
+        let t1 = climate::compute_temperature_window(&self.height, band, climate_seed, params);
+        let p0 = climate::precipitation_band(&before.crop(band), &t0, band.y, h);
+        let p1 = climate::precipitation_band(&self.height.crop(band), &t1, band.y, h);
+        let in_band = Rect { y: dirty.y - band.y, ..dirty };
+        let (t0, t1, p0, p1) = (t0.crop(in_band), t1.crop(in_band), p0.crop(in_band), p1.crop(in_band));
+
+        // Raw rain to mm/year at the rate the full generation normalized to
+        let stored = precipitation.crop(dirty);
+        let (mut mm, mut raw) = (0.0f64, 0.0f64);
+        for (&v, &r) in stored.data.iter().zip(&p0.data) {
+            if r > 0.0 {
+                mm += v as f64;
+                raw += r as f64;
+            }
+        }
+        let scale = if raw > 0.0 { (mm / raw) as f32 } else { 0.0 };
+        let mut temperature = self.temperature.crop(dirty).decode();
+        let mut rain = stored;
+        for i in 0..temperature.data.len() {
+            temperature.data[i] += t1.data[i] - t0.data[i];
+            rain.data[i] = (rain.data[i] + (p1.data[i] - p0.data[i]) * scale).max(0.0);
+        }
+
+        let halo = grow(dirty, shelf + 1, w, h);
+        let old_temperature = self.temperature.crop(halo).decode();
+        let old_precipitation = self.precipitation.crop(halo).decode();
+        let (mut new_temperature, mut new_precipitation) = (old_temperature.clone(), old_precipitation.clone());
+        let at = |wx: usize, wy: usize| ((dirty.x + w - halo.x + wx) % w % halo.w, dirty.y - halo.y + wy);
+        for (wy, wx) in (0..dirty.h).flat_map(|wy| (0..dirty.w).map(move |wx| (wy, wx))) {
+            let (hx, hy) = at(wx, wy);
+            new_temperature.set(hx, hy, temperature.get(wx, wy));
+            new_precipitation.set(hx, hy, rain.get(wx, wy));
+        }
+        self.temperature.write_window(&temperature, dirty);
+        self.precipitation.write_window(&rain, dirty);
+        let i0 = climate::compute_ice(&crop(&before, halo), &old_temperature, &old_precipitation, cell_km);
+        let i1 = climate::compute_ice(&crop(&self.height, halo), &new_temperature, &new_precipitation, cell_km);
+
+        let palette = render::Palette::default();
+        let colors = |height: &Grid<f32>, ice: &Grid<u8>| {
+            let ao = terrain::compute_ambient_occlusion_window(height, dirty, ao_radius);
+            let mut rgba = render::render_map_window(height, ice, &palette, dirty);
+            render::apply_ambient_occlusion(&mut rgba, &ao, 0.5);
+            rgba
+        };
+        let r0 = colors(&before, &self.ice);
+        for (wy, wx) in (0..dirty.h).flat_map(|wy| (0..dirty.w).map(move |wx| (wy, wx))) {
+            let (hx, hy) = at(wx, wy);
+            if i0.get(hx, hy) != i1.get(hx, hy) {
+                self.ice.set((dirty.x + wx) % w, dirty.y + wy, i1.get(hx, hy));
+            }
+        }
+        let r1 = colors(&self.height, &self.ice);
+        for (wy, wx) in (0..dirty.h).flat_map(|wy| (0..dirty.w).map(move |wx| (wy, wx))) {
+            let (j, i) = ((wy * dirty.w + wx) * 4, ((dirty.y + wy) * w + (dirty.x + wx) % w) * 4);
+            if r0[j..j + 4] != r1[j..j + 4] {
+                self.rgba[i..i + 4].copy_from_slice(&r1[j..j + 4]);
+            }
+        }
+
+        dirty
+    }
+}
+
+/// `rect` with rows clipped to the map and columns folded onto it.
+fn clip(rect: Rect, w: usize, h: usize) -> Rect {
+    let y = rect.y.min(h);
+    let rw = rect.w.min(w);
+    Rect { x: if rw == w { 0 } else { rect.x % w.max(1) }, y, w: rw, h: rect.h.min(h - y) }
+}
+
+/// `rect` grown by `by` cells on every side, clipped to the map.
+fn grow(rect: Rect, by: usize, w: usize, h: usize) -> Rect {
+    let y = rect.y.saturating_sub(by);
+    let bottom = (rect.y + rect.h + by).min(h);
+    let rw = (rect.w + 2 * by).min(w);
+    let x = if rw == w { 0 } else { (rect.x + w - by % w) % w };
+    Rect { x, y, w: rw, h: bottom - y }
+}
+
+/// `grid.crop(rect)`, still wrapping E-W when `rect` spans the map.
+fn crop<T: Copy + Default>(grid: &Grid<T>, rect: Rect) -> Grid<T> {
+    let window = grid.crop(rect);
+    if rect.w == grid.w { window.with_topology(grid.topology) } else { window }
+}
+
+/// Cells of a clipped rectangle, row by row.
+fn cells(rect: Rect, w: usize) -> impl Iterator<Item = (usize, usize)> {
+    (rect.y..rect.y + rect.h).flat_map(move |y| (rect.x..rect.x + rect.w).map(move |x| (x % w, y)))
+}
+
+/// Smooth 1 -> 0 falloff from the middle of `0..n` to its ends.
+fn bump(i: usize, n: usize) -> f32 {
+    let t = (i as f32 + 0.5) / n as f32 * 2.0 - 1.0;
+    let c = (1.0 - t.abs()).clamp(0.0, 1.0);
+    c * c * (3.0 - 2.0 * c)
+}
@@ -1,26 +1,19 @@
-use rayon::prelude::*;
-
-use crate::config::Params;
-use crate::grid::Grid;
-use crate::noise::{fbm, ridged_fbm};
+use crate::config::{Params, REFERENCE_CELL_KM};
+use crate::fmath;
+use crate::grid::{Grid, distance};
+use crate::noise::{Fbm, Warp, warped, warped_batch};
+use crate::par::*;
 use crate::plates::boundary::{CONVERGENT, DIVERGENT, TRANSFORM};
 use crate::plates::properties::PlateSet;
-use crate::rng::seed_u32;
-
-const SALT_DETAIL: u64 = 0xE1E7_DE7A_1100_FACE;
-const SALT_RIDGE: u64 = 0x21D6_E500_CAFE_BABE;
-const SALT_COAST: u64 = 0xC0A5_7FAD_1E51_1A1D;
-const SALT_WARP: u64 = 0xDA12_BEEF_0000_CAFE;
-const SALT_INTERIOR: u64 = 0x1A7E_21A1_0001_0001;
-const SALT_CHAIN: u64 = 0xC4A1_BEEF_DEAD_0042;
-const SALT_BASE: u64 = 0xBA5E_E1EF_DEAD_CAFE;
+use crate::rng::SeedTree;
+use crate::scratch;
 
 /// Build the elevation field from plate properties and boundary distance fields.
 /// Elevation is driven by geology (plate boundaries), not noise.
 /// Noise is used only for texture and coastline irregularity.
 ///
-/// All pixel-based parameters scale with resolution relative to 1024-wide reference,
-/// so the same slider values produce the same geographic features at any resolution.
+/// Distances are in kilometers (`params.world` converts them to cells), so
+/// the same slider values produce the same geographic features at any resolution.
 pub fn build_elevation(
     plate_id: &Grid<u16>,
     plates: &PlateSet,
@@ -34,25 +27,91 @@ pub fn build_elevation(
     seed: u64,
     params: &Params,
 ) -> Grid<f32> {
+    let inputs = ElevationInputs {
+        plate_id,
+        plates,
+        btype_grid,
+        dist_grid,
+        near_bx,
+        near_by,
+        pa_grid,
+        pb_grid,
+        major_grid,
+    };
+    let (field, shelf_width) = elevation_field(&inputs, seed, params);
+    let mut height = Grid::from_vec(plate_id.w, plate_id.h, scratch::take(plate_id.w * plate_id.h, 0.0))
+        .with_metric(dist_grid.metric);
+    height.par_fill_rows(|y, row| field(y, 0, row));
+
+    // Continental shelf: smooth transition from coast to deep ocean
+    add_continental_shelf(&mut height, shelf_width);
+
+    height
+}
+
+/// Plate-stage grids that elevation is built from.
+struct ElevationInputs<'a> {
+    plate_id: &'a Grid<u16>,
+    plates: &'a PlateSet,
+    btype_grid: &'a Grid<u8>,
+    dist_grid: &'a Grid<f32>,
+    near_bx: &'a Grid<u16>,
+    near_by: &'a Grid<u16>,
+    pa_grid: &'a Grid<u16>,
+    pb_grid: &'a Grid<u16>,
+    major_grid: &'a Grid<u8>,
+}
+
+/// Phases 1 and 2 (boundary profiles, smoothed), returning phase 3
+/// elevation (before the shelf) as a row filler `f(y, x0, row)` for cells
+/// `x0..x0 + row.len()` of row `y`, and the scaled shelf width.
+fn elevation_field<'a>(
+    inputs: &ElevationInputs<'a>,
+    seed: u64,
+    params: &Params,
+) -> (impl Fn(usize, usize, &mut [f32]) + Sync + 'a, f32) {
+    let &ElevationInputs {
+        plate_id,
+        plates,
+        btype_grid,
+        dist_grid,
+        near_bx,
+        near_by,
+        pa_grid,
+        pb_grid,
+        major_grid,
+    } = inputs;
     let w = plate_id.w;
     let h = plate_id.h;
     let n = w * h;
 
-    // Resolution scale: all pixel-based params are authored for 2048-wide.
-    let scale = w as f32 / 2048.0;
-
-    let detail_seed = seed_u32(seed, SALT_DETAIL);
-    let ridge_seed = seed_u32(seed, SALT_RIDGE);
-    let coast_seed = seed_u32(seed, SALT_COAST);
-    let warp_seed = seed_u32(seed, SALT_WARP);
-    let interior_seed = seed_u32(seed, SALT_INTERIOR);
-    let chain_seed = seed_u32(seed, SALT_CHAIN);
-    let base_seed = seed_u32(seed, SALT_BASE);
-
-    // Scale pixel-based params
-    let mw = params.mountain_width * scale;
-    let blur_sigma = params.blur_sigma * scale;
-    let shelf_width = params.shelf_width * scale;
+    // Built-in profile distances are in reference cells; params are in km.
+    let world = params.world;
+    let scale = world.reference_scale(w);
+
+    let seeds = SeedTree::new(seed).child("elevation");
+    let detail_seed = seeds.child("detail").seed_u32();
+    let ridge_seed = seeds.child("ridge").seed_u32();
+    let coast_seed = seeds.child("coast").seed_u32();
+    let warp_seed = seeds.child("warp").seed_u32();
+    let interior_seed = seeds.child("interior").seed_u32();
+    let chain_seed = seeds.child("chain").seed_u32();
+    let base_seed = seeds.child("base").seed_u32();
+
+    // Terrain noise takes normalized coords and repeats once per map width,
+    // so it meets itself at the E-W seam.
+    let basis = params.elevation_noise;
+    let fbm = move |u, v, seed, octaves, freq0, lac, gain| {
+        Fbm::new(octaves, freq0, lac, gain).with_basis(basis).with_period(1.0, None).sample(u, v, seed)
+    };
+    let ridged_fbm = move |u, v, seed, octaves, freq0, lac, gain| {
+        Fbm::new(octaves, freq0, lac, gain).with_basis(basis).with_period(1.0, None).ridged(u, v, seed)
+    };
+
+    // Distances in cells
+    let mw = world.cells(params.mountain_width, w);
+    let blur_sigma = world.cells(params.blur_sigma, w);
+    let shelf_width = world.cells(params.shelf_width, w);
     let interior_dist = 80.0 * scale;
     let coast_dist_max = 100.0 * scale;
     let ridge_dist_max = 120.0 * scale;
@@ -84,10 +143,12 @@ pub fn build_elevation(
                     let ty = dx / len;
                     let along = (x as f32 * tx + y as f32 * ty) / w as f32;
                     let across = (x as f32 * ty + y as f32 * (-tx)) / w as f32;
-                    let chain = ridged_fbm(
-                        along * 6.0, across * 18.0,
-                        chain_seed, 3, 1.0, 2.0, 0.5,
-                    ).clamp(0.0, 1.0);
+                    // Along/across-boundary coords are not periodic; the
+                    // profile blur below hides the seam
+                    let chain = Fbm::new(3, 1.0, 2.0, 0.5)
+                        .with_basis(basis)
+                        .ridged(along * 6.0, across * 18.0, chain_seed)
+                        .clamp(0.0, 1.0);
                     let m = 0.25 + 0.75 * chain;
                     [po * m, ma * m]
                 } else {
@@ -99,142 +160,105 @@ pub fn build_elevation(
         })
         .collect();
 
-    let mut profile_off: Vec<f32> = profiles.iter().map(|p| p[0]).collect();
-    let mut mt_amp: Vec<f32> = profiles.iter().map(|p| p[1]).collect();
+    let smoothed = |channel: usize| {
+        let data = profiles.iter().map(|p| p[channel]).collect();
+        let mut grid = Grid::from_vec(w, h, data)
+            .with_topology(dist_grid.topology)
+            .with_metric(dist_grid.metric);
+        grid.gaussian_blur(blur_sigma, blur_sigma);
+        grid.data
+    };
 
     // Phase 2: Smooth profiles to eliminate Voronoi ridge discontinuities.
-    blur_grid(&mut profile_off, w, h, blur_sigma);
-    blur_grid(&mut mt_amp, w, h, blur_sigma);
+    let profile_off = smoothed(0);
+    let mt_amp = smoothed(1);
 
     // Phase 3: Final elevation = base + smoothed profile + noise (parallel).
     let coast_amp = params.coast_amp;
     let interior_amp = params.interior_amp;
     let detail_amp = params.detail_amp;
+    let sea_level = params.sea_level;
+
+    // Noises taken every cell are evaluated a row at a time
+    let row_fbm = move |octaves, freq0, lac, gain| Fbm::new(octaves, freq0, lac, gain).with_basis(basis).with_period(1.0, None);
+    let terrain_warp = [Warp::new(row_fbm(3, 4.0, 2.0, 0.5), 0.06)];
+    let ridge_warp = [Warp::new(row_fbm(3, 6.0, 2.0, 0.5), 0.10)];
+    let base_fbm = row_fbm(4, 2.5, 2.0, 0.5);
+    let detail_fbm = row_fbm(4, 10.0, 2.0, 0.5);
+
+    let field = move |y: usize, x0: usize, row: &mut [f32]| {
+        let len = row.len();
+        let batch = |fbm: Fbm, us: &[f32], vs: &[f32], seed: u32| {
+            let mut out = vec![0.0; len];
+            fbm.sample_batch(us, vs, seed, &mut out);
+            out
+        };
+
+        // Normalized coords for noise, domain-warped
+        let mut wus: Vec<f32> = (x0..x0 + len).map(|x| x as f32 / w as f32).collect();
+        let mut wvs = vec![y as f32 / h as f32; len];
+        warped_batch(&mut wus, &mut wvs, warp_seed, &terrain_warp);
+
+        let base_noise = batch(base_fbm, &wus, &wvs, base_seed);
+        let detail_noise = batch(detail_fbm, &wus, &wvs, detail_seed);
+
+        for (i, out) in row.iter_mut().enumerate() {
+            let x = x0 + i;
+            let idx = y * w + x;
+            let pid = plate_id.get(x, y) as usize;
+            let dist = dist_grid.get(x, y);
+            let is_continental = plates.is_continental[pid];
+            let profile_offset = profile_off[idx];
+            let mountain_amp = mt_amp[idx];
+            let (wu, wv) = (wus[i], wvs[i]);
+
+            // Per-pixel base elevation: noise field + coastal taper.
+            let base_center = plates.base_elevation[pid];
+            let base = if is_continental {
+                let taper = smoothstep((dist / shelf_width).min(1.0));
+                (base_center + base_noise[i] * 500.0) * taper
+            } else {
+                base_center + base_noise[i] * 200.0
+            };
+
+            // Interior terrain variation
+            let interior_noise = if is_continental {
+                let interior_weight = smoothstep((dist / interior_dist).min(1.0));
+                let terrain = fbm(wu, wv, interior_seed, 5, 4.0, 2.1, 0.5);
+                terrain * 1000.0 * interior_amp * interior_weight
+            } else {
+                fbm(wu, wv, interior_seed, 3, 3.0, 2.0, 0.5) * 400.0 * interior_amp
+            };
+
+            // Coastline perturbation
+            let coast_perturb = if dist < coast_dist_max {
+                let weight = smoothstep(1.0 - (dist / coast_dist_max).min(1.0));
+                let large = fbm(wu, wv, coast_seed, 3, 3.0, 2.0, 0.5) * 800.0;
+                let small = fbm(wu, wv, coast_seed.wrapping_add(100), 4, 15.0, 2.0, 0.5) * 300.0;
+                (large + small) * weight * coast_amp
+            } else {
+                0.0
+            };
+
+            // Fine detail noise
+            let detail = detail_noise[i] * detail_amp;
+
+            // Ridge noise near convergent boundaries
+            let ridge = if mountain_amp > 0.0 && dist < ridge_dist_max {
+                let (ru, rv) = warped(wu, wv, ridge_seed, &ridge_warp);
+                let r = ridged_fbm(ru, rv, ridge_seed, 4, 6.0, 2.1, 0.45)
+                    .clamp(0.0, 1.0);
+                let falloff = smoothstep(1.0 - (dist / ridge_dist_max).min(1.0));
+                r * mountain_amp * falloff
+            } else {
+                0.0
+            };
 
-    let mut height = Grid::<f32>::new(w, h);
-    height
-        .data
-        .par_chunks_mut(w)
-        .enumerate()
-        .for_each(|(y, row)| {
-            for x in 0..w {
-                let i = y * w + x;
-                let pid = plate_id.get(x, y) as usize;
-                let dist = dist_grid.get(x, y);
-                let is_continental = plates.is_continental[pid];
-                let profile_offset = profile_off[i];
-                let mountain_amp = mt_amp[i];
-
-                // Normalized coords for noise
-                let u = x as f32 / w as f32;
-                let v = y as f32 / h as f32;
-
-                // Domain warping
-                let warp_x = fbm(u * 2.0, v * 2.0, warp_seed, 3, 2.0, 2.0, 0.5) * 0.06;
-                let warp_y =
-                    fbm(u * 2.0 + 17.0, v * 2.0 + 31.0, warp_seed, 3, 2.0, 2.0, 0.5) * 0.06;
-                let wu = u + warp_x;
-                let wv = v + warp_y;
-
-                // Per-pixel base elevation: noise field + coastal taper.
-                let base_center = plates.base_elevation[pid];
-                let base_noise = fbm(wu, wv, base_seed, 4, 2.5, 2.0, 0.5);
-                let base = if is_continental {
-                    let taper = smoothstep((dist / shelf_width).min(1.0));
-                    (base_center + base_noise * 500.0) * taper
-                } else {
-                    base_center + base_noise * 200.0
-                };
-
-                // Interior terrain variation
-                let interior_noise = if is_continental {
-                    let interior_weight = smoothstep((dist / interior_dist).min(1.0));
-                    let terrain = fbm(wu, wv, interior_seed, 5, 4.0, 2.1, 0.5);
-                    terrain * 1000.0 * interior_amp * interior_weight
-                } else {
-                    fbm(wu, wv, interior_seed, 3, 3.0, 2.0, 0.5) * 400.0 * interior_amp
-                };
-
-                // Coastline perturbation
-                let coast_perturb = if dist < coast_dist_max {
-                    let weight = smoothstep(1.0 - (dist / coast_dist_max).min(1.0));
-                    let large = fbm(wu, wv, coast_seed, 3, 3.0, 2.0, 0.5) * 800.0;
-                    let small = fbm(wu, wv, coast_seed.wrapping_add(100), 4, 15.0, 2.0, 0.5) * 300.0;
-                    (large + small) * weight * coast_amp
-                } else {
-                    0.0
-                };
-
-                // Fine detail noise
-                let detail = fbm(wu, wv, detail_seed, 4, 10.0, 2.0, 0.5) * detail_amp;
-
-                // Ridge noise near convergent boundaries
-                let ridge = if mountain_amp > 0.0 && dist < ridge_dist_max {
-                    let rw1 = fbm(
-                        wu * 3.0, wv * 3.0,
-                        ridge_seed.wrapping_add(50), 3, 2.0, 2.0, 0.5,
-                    ) * 0.10;
-                    let rw2 = fbm(
-                        wu * 3.0 + 7.3, wv * 3.0 + 2.9,
-                        ridge_seed.wrapping_add(51), 3, 2.0, 2.0, 0.5,
-                    ) * 0.10;
-                    let r = ridged_fbm(wu + rw1, wv + rw2, ridge_seed, 4, 6.0, 2.1, 0.45)
-                        .clamp(0.0, 1.0);
-                    let falloff = smoothstep(1.0 - (dist / ridge_dist_max).min(1.0));
-                    r * mountain_amp * falloff
-                } else {
-                    0.0
-                };
-
-                row[x] = base + profile_offset + coast_perturb + interior_noise + detail + ridge;
-            }
-        });
-
-    // Continental shelf: smooth transition from coast to deep ocean
-    add_continental_shelf(&mut height, shelf_width);
-
-    height
-}
-
-/// Separable Gaussian blur with E-W wrapping, clamped N-S.
-fn blur_grid(data: &mut Vec<f32>, w: usize, h: usize, sigma: f32) {
-    let radius = (sigma * 3.0).ceil() as usize;
-    if radius == 0 {
-        return;
-    }
-
-    let kernel: Vec<f32> = (0..=radius)
-        .map(|i| (-(i as f32 * i as f32) / (2.0 * sigma * sigma)).exp())
-        .collect();
-    let sum: f32 = kernel[0] + 2.0 * kernel[1..].iter().sum::<f32>();
-    let kernel: Vec<f32> = kernel.iter().map(|k| k / sum).collect();
-
-    // Horizontal pass (E-W wrap)
-    let mut tmp = vec![0.0f32; w * h];
-    for y in 0..h {
-        for x in 0..w {
-            let mut s = data[y * w + x] * kernel[0];
-            for r in 1..=radius {
-                s += data[y * w + (x + w - r) % w] * kernel[r];
-                s += data[y * w + (x + r) % w] * kernel[r];
-            }
-            tmp[y * w + x] = s;
+            *out = base + profile_offset + coast_perturb + interior_noise + detail + ridge - sea_level;
         }
-    }
+    };
 
-    // Vertical pass (clamp at edges)
-    for y in 0..h {
-        for x in 0..w {
-            let mut s = tmp[y * w + x] * kernel[0];
-            for r in 1..=radius {
-                let uy = y.saturating_sub(r);
-                let dy = (y + r).min(h - 1);
-                s += tmp[uy * w + x] * kernel[r];
-                s += tmp[dy * w + x] * kernel[r];
-            }
-            data[y * w + x] = s;
-        }
-    }
+    (field, shelf_width)
 }
 
 fn compute_rate(plates: &PlateSet, pid_a: usize, pid_b: usize) -> f32 {
@@ -246,7 +270,9 @@ fn compute_rate(plates: &PlateSet, pid_a: usize, pid_b: usize) -> f32 {
 }
 
 /// Returns (elevation_offset, mountain_noise_amplitude) based on boundary type.
-/// All pixel-based distances are multiplied by `scale` for resolution independence.
+/// Built-in distances are in reference cells, multiplied by `scale`
+/// (`WorldScale::reference_scale`) for resolution independence; relief
+/// scales with `WorldScale::relief`.
 fn boundary_profile(
     btype: u8,
     dist: f32,
@@ -262,13 +288,14 @@ fn boundary_profile(
     let rate_factor = rate.min(2.0);
     let ms = params.mountain_scale;
     let ts = params.trench_scale;
-    let mw = params.mountain_width * scale;
+    // km → cells: km / REFERENCE_CELL_KM reference cells, times scale
+    let mw = params.mountain_width / REFERENCE_CELL_KM * scale;
 
     // Orogenesis boost: 1.5x base mountain heights for more mountain coverage.
     let oro = 1.5f32;
     let strength = if is_major { 1.0 } else { 0.35 };
 
-    match btype {
+    let (offset, amp) = match btype {
         CONVERGENT => {
             let pa_cont = plates.is_continental[pa];
             let pb_cont = plates.is_continental[pb];
@@ -317,12 +344,14 @@ fn boundary_profile(
         }
         TRANSFORM => (0.0, 0.0),
         _ => (0.0, 0.0),
-    }
+    };
+    let relief = params.world.relief();
+    (offset * relief, amp * relief)
 }
 
 #[inline]
 fn gaussian(dist: f32, sigma: f32) -> f32 {
-    (-dist * dist / (2.0 * sigma * sigma)).exp()
+    fmath::exp(-dist * dist / (2.0 * sigma * sigma))
 }
 
 #[inline]
@@ -331,74 +360,22 @@ fn smoothstep(t: f32) -> f32 {
     t * t * (3.0 - 2.0 * t)
 }
 
-/// Continental shelf via distance-from-land chamfer.
+/// Continental shelf from the exact distance to land (measured under the
+/// height grid's topology and metric).
 fn add_continental_shelf(height: &mut Grid<f32>, shelf_width: f32) {
-    let w = height.w;
-    let h = height.h;
-
-    let land: Vec<bool> = height.data.iter().map(|&h| h > 0.0).collect();
-
-    let mut coast_dist = vec![f32::MAX; w * h];
-    for (i, &is_land) in land.iter().enumerate() {
-        if is_land {
-            coast_dist[i] = 0.0;
-        }
-    }
-
-    // Forward chamfer with E-W wrapping
-    for y in 0..h {
-        for x in 0..w {
-            let i = y * w + x;
-            for (dx, dy, cost) in [
-                (-1i32, 0, 1.0f32),
-                (0, -1, 1.0),
-                (-1, -1, 1.414),
-                (1, -1, 1.414),
-            ] {
-                let ny = y as i32 + dy;
-                if ny < 0 || ny >= h as i32 {
-                    continue;
-                }
-                let nx = ((x as i32 + dx) % w as i32 + w as i32) as usize % w;
-                let ni = ny as usize * w + nx;
-                let c = coast_dist[ni] + cost;
-                if c < coast_dist[i] {
-                    coast_dist[i] = c;
-                }
-            }
-        }
-    }
-    // Backward chamfer with E-W wrapping
-    for y in (0..h).rev() {
-        for x in (0..w).rev() {
-            let i = y * w + x;
-            for (dx, dy, cost) in [
-                (1i32, 0, 1.0f32),
-                (0, 1, 1.0),
-                (1, 1, 1.414),
-                (-1, 1, 1.414),
-            ] {
-                let ny = y as i32 + dy;
-                if ny < 0 || ny >= h as i32 {
-                    continue;
-                }
-                let nx = ((x as i32 + dx) % w as i32 + w as i32) as usize % w;
-                let ni = ny as usize * w + nx;
-                let c = coast_dist[ni] + cost;
-                if c < coast_dist[i] {
-                    coast_dist[i] = c;
-                }
-            }
-        }
+    let coast_dist = distance::edt(height, |h| h > 0.0);
+    for (elev, &d) in height.data.iter_mut().zip(&coast_dist.data) {
+        *elev = shelf(*elev, d, shelf_width);
     }
+}
 
-    // Apply shelf: near-coast ocean gets gentle slope
-    for i in 0..w * h {
-        if !land[i] && coast_dist[i] < shelf_width {
-            let t = coast_dist[i] / shelf_width;
-            let st = smoothstep(t);
-            let shelf_elev = -250.0 * st;
-            height.data[i] = height.data[i].max(shelf_elev);
-        }
+/// Near-coast ocean gets a gentle slope down to -250 m at `shelf_width`.
+#[inline]
+fn shelf(elev: f32, coast_dist: f32, shelf_width: f32) -> f32 {
+    if elev <= 0.0 && coast_dist < shelf_width {
+        let st = smoothstep(coast_dist / shelf_width);
+        elev.max(-250.0 * st)
+    } else {
+        elev
     }
 }
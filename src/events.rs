@@ -0,0 +1,281 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::Map;
+use crate::grid::{Grid, cell_latlon};
+use crate::history::{Site, SiteKind};
+use crate::plates::{CONVERGENT, DIVERGENT, TRANSFORM};
+use crate::regions::label_landmasses;
+use crate::rivers::{RiverSegment, extract_network};
+use crate::terrain::cell_size_m;
+
+/// Plate boundaries shorter than this (km) aren't logged.
+const MIN_BOUNDARY_KM: f32 = 1000.0;
+/// Landmasses smaller than this (km^2) aren't logged.
+const MIN_LANDMASS_KM2: f64 = 50_000.0;
+/// Rivers shorter than this from source to mouth (km) aren't logged.
+const MIN_RIVER_KM: f32 = 300.0;
+
+/// Something notable the generator produced, with where it happened.
+/// Serialized with an `event` tag; `Display` gives a one-line summary
+/// ("collision belt formed between plates 3 and 7 at 12.5N 40.3E ...").
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Convergent boundary that raised land: a mountain belt.
+    CollisionBelt { plates: [u16; 2], length_km: f32, peak_m: f32, lat: f32, lon: f32 },
+    /// Convergent boundary under the sea: a trench.
+    SubductionZone { plates: [u16; 2], length_km: f32, depth_m: f32, lat: f32, lon: f32 },
+    /// Divergent boundary: a mid-ocean ridge or continental rift.
+    Rift { plates: [u16; 2], length_km: f32, lat: f32, lon: f32 },
+    TransformFault { plates: [u16; 2], length_km: f32, lat: f32, lon: f32 },
+    /// Connected land (see `Map::label_landmasses`).
+    Landmass { id: u32, area_km2: f64, peak_m: f32, lat: f32, lon: f32 },
+    /// A river's main stem reaching the sea; the position is the mouth.
+    RiverMouth { length_km: f32, discharge: f32, order: u8, source_lat: f32, source_lon: f32, lat: f32, lon: f32 },
+    NationFounded { nation: u16, lat: f32, lon: f32 },
+    /// A site abandoned when its nation fell.
+    Ruin { nation: u16, lat: f32, lon: f32 },
+}
+
+impl Event {
+    /// Where the event happened.
+    pub fn latlon(&self) -> (f32, f32) {
+        match *self {
+            Event::CollisionBelt { lat, lon, .. }
+            | Event::SubductionZone { lat, lon, .. }
+            | Event::Rift { lat, lon, .. }
+            | Event::TransformFault { lat, lon, .. }
+            | Event::Landmass { lat, lon, .. }
+            | Event::RiverMouth { lat, lon, .. }
+            | Event::NationFounded { lat, lon, .. }
+            | Event::Ruin { lat, lon, .. } => (lat, lon),
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (lat, lon) = self.latlon();
+        let at = Place(lat, lon);
+        match self {
+            Event::CollisionBelt { plates: [a, b], peak_m, .. } => {
+                write!(f, "collision belt formed between plates {} and {} at {} (peak {:.0} m)", a, b, at, peak_m)
+            }
+            Event::SubductionZone { plates: [a, b], depth_m, .. } => {
+                write!(f, "plate {} subducts against plate {} at {} (trench {:.0} m)", a, b, at, depth_m)
+            }
+            Event::Rift { plates: [a, b], .. } => write!(f, "plates {} and {} rift apart at {}", a, b, at),
+            Event::TransformFault { plates: [a, b], .. } => {
+                write!(f, "plates {} and {} slide past each other at {}", a, b, at)
+            }
+            Event::Landmass { id, area_km2, .. } => {
+                write!(f, "landmass {} of {:.0} km^2 centered at {}", id, area_km2, at)
+            }
+            Event::RiverMouth { length_km, source_lat, source_lon, .. } => write!(
+                f,
+                "river of length {:.0} km from {} reached the ocean at {}",
+                length_km,
+                Place(*source_lat, *source_lon),
+                at
+            ),
+            Event::NationFounded { nation, .. } => write!(f, "nation {} founded its capital at {}", nation, at),
+            Event::Ruin { nation, .. } => write!(f, "a city of nation {} fell to ruin at {}", nation, at),
+        }
+    }
+}
+
+struct Place(f32, f32);
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ns = if self.0 >= 0.0 { 'N' } else { 'S' };
+        let ew = if self.1 >= 0.0 { 'E' } else { 'W' };
+        write!(f, "{:.1}{} {:.1}{}", self.0.abs(), ns, self.1.abs(), ew)
+    }
+}
+
+/// The event log of a finished map, in generation order: plate
+/// boundaries, landmasses, rivers, then history. Within each group the
+/// biggest come first. Layers that haven't been generated contribute
+/// nothing. `Pipeline::set_event_listener` gets the same events as the
+/// stages that form them finish.
+pub fn events(map: &Map) -> Vec<Event> {
+    let mut events = boundary_events(&map.plate_id, &map.boundary_type, &map.height);
+    events.extend(landmass_events(&map.height));
+    events.extend(river_events(&map.height, &map.river_flow));
+    events.extend(site_events(&map.sites, map.w, map.h));
+    events
+}
+
+/// Nations founded and sites abandoned, in history order.
+pub(crate) fn site_events(sites: &[Site], w: usize, h: usize) -> Vec<Event> {
+    sites
+        .iter()
+        .map(|site| {
+            let (lat, lon) = cell_latlon(site.x, site.y, w, h);
+            match site.kind {
+                SiteKind::Capital => Event::NationFounded { nation: site.nation, lat, lon },
+                SiteKind::Ruin => Event::Ruin { nation: site.nation, lat, lon },
+            }
+        })
+        .collect()
+}
+
+/// Boundary cells grouped by plate pair and boundary type.
+struct Boundary {
+    cells: usize,
+    /// Sum of x unwrapped to within half a map of the first cell, and of y.
+    sum_x: f64,
+    sum_y: f64,
+    first_x: usize,
+    max_h: f32,
+    min_h: f32,
+}
+
+/// Plate boundaries by plate pair and type, longest first.
+pub(crate) fn boundary_events(plate_id: &Grid<u16>, boundary_type: &Grid<u8>, height: &Grid<f32>) -> Vec<Event> {
+    let (w, h) = (height.w, height.h);
+    if boundary_type.data.len() != w * h || plate_id.data.len() != w * h {
+        return Vec::new();
+    }
+    let mut groups: BTreeMap<(u8, [u16; 2]), Boundary> = BTreeMap::new();
+    for y in 0..h {
+        for x in 0..w {
+            let btype = boundary_type.get(x, y);
+            if !matches!(btype, CONVERGENT | DIVERGENT | TRANSFORM) {
+                continue;
+            }
+            let p = plate_id.get(x, y);
+            let Some(q) = plate_id.neighbors4(x, y).map(|(nx, ny)| plate_id.get(nx, ny)).find(|&q| q != p)
+            else {
+                continue;
+            };
+            let e = height.get(x, y);
+            let g = groups.entry((btype, [p.min(q), p.max(q)])).or_insert(Boundary {
+                cells: 0,
+                sum_x: 0.0,
+                sum_y: 0.0,
+                first_x: x,
+                max_h: f32::NEG_INFINITY,
+                min_h: f32::INFINITY,
+            });
+            let dx = (x as i64 - g.first_x as i64 + w as i64 / 2).rem_euclid(w as i64) - w as i64 / 2;
+            g.cells += 1;
+            g.sum_x += (g.first_x as i64 + dx) as f64;
+            g.sum_y += y as f64;
+            g.max_h = g.max_h.max(e);
+            g.min_h = g.min_h.min(e);
+        }
+    }
+
+    // Both plates mark the cells along their side, so a boundary runs
+    // about one cell for every two of its cells
+    let cell_km = cell_size_m(0, w, h).1 / 1000.0 * 0.5;
+    let mut events: Vec<(f32, Event)> = groups
+        .into_iter()
+        .filter(|(_, g)| g.cells as f32 * cell_km >= MIN_BOUNDARY_KM)
+        .map(|((btype, plates), g)| {
+            let cx = (g.sum_x / g.cells as f64).rem_euclid(w as f64);
+            let cy = g.sum_y / g.cells as f64;
+            let (lat, lon) = cell_latlon(cx as usize, cy as usize, w, h);
+            let length_km = g.cells as f32 * cell_km;
+            let event = match btype {
+                CONVERGENT if g.max_h > 0.0 => Event::CollisionBelt { plates, length_km, peak_m: g.max_h, lat, lon },
+                CONVERGENT => Event::SubductionZone { plates, length_km, depth_m: g.min_h, lat, lon },
+                DIVERGENT => Event::Rift { plates, length_km, lat, lon },
+                _ => Event::TransformFault { plates, length_km, lat, lon },
+            };
+            (length_km, event)
+        })
+        .collect();
+    events.sort_by(|a, b| b.0.total_cmp(&a.0));
+    events.into_iter().map(|(_, e)| e).collect()
+}
+
+/// Landmasses, largest first.
+pub(crate) fn landmass_events(height: &Grid<f32>) -> Vec<Event> {
+    let (ids, regions) = label_landmasses(height);
+    let mut peak = vec![f32::NEG_INFINITY; regions.len()];
+    for (&id, &e) in ids.data.iter().zip(&height.data) {
+        if let Some(p) = peak.get_mut(id as usize) {
+            *p = p.max(e);
+        }
+    }
+    let mut events: Vec<(f64, Event)> = regions
+        .into_iter()
+        .filter(|r| r.area_km2 >= MIN_LANDMASS_KM2)
+        .map(|r| {
+            let event = Event::Landmass {
+                id: r.id,
+                area_km2: r.area_km2,
+                peak_m: peak[r.id as usize],
+                lat: r.centroid_lat,
+                lon: r.centroid_lon,
+            };
+            (r.area_km2, event)
+        })
+        .collect();
+    events.sort_by(|a, b| b.0.total_cmp(&a.0));
+    events.into_iter().map(|(_, e)| e).collect()
+}
+
+/// Rivers by main stem, longest first.
+pub(crate) fn river_events(height: &Grid<f32>, river_flow: &Grid<f32>) -> Vec<Event> {
+    let (w, h) = (height.w, height.h);
+    if river_flow.data.len() != w * h {
+        return Vec::new();
+    }
+    let segments = extract_network(height, river_flow);
+    let cell = |p: (f32, f32)| ((p.0.round() as i64).rem_euclid(w as i64) as usize, p.1.round() as usize);
+
+    // Segments draining into each cell, to walk upstream from a mouth
+    let mut into: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (i, s) in segments.iter().enumerate() {
+        if let Some(&last) = s.points.last() {
+            into.entry(cell(last)).or_default().push(i);
+        }
+    }
+    let length_km = |s: &RiverSegment| -> f32 {
+        s.points
+            .windows(2)
+            .map(|p| {
+                let (dx, dy) = cell_size_m(cell(p[0]).1.min(h - 1), w, h);
+                ((p[1].0 - p[0].0) * dx).hypot((p[1].1 - p[0].1) * dy) / 1000.0
+            })
+            .sum()
+    };
+
+    let mut events: Vec<(f32, Event)> = Vec::new();
+    for mouth in segments.iter().filter(|s| s.mouth && !s.points.is_empty()) {
+        // Main stem: keep taking the tributary with the most discharge
+        let mut length = length_km(mouth);
+        let mut source = mouth.points[0];
+        let mut seg = mouth;
+        while let Some(up) = into.get(&cell(seg.points[0])).and_then(|ups| {
+            ups.iter().map(|&i| &segments[i]).filter(|s| !std::ptr::eq(*s, seg)).max_by(|a, b| {
+                let fa = a.flow.last().copied().unwrap_or(0.0);
+                let fb = b.flow.last().copied().unwrap_or(0.0);
+                fa.total_cmp(&fb)
+            })
+        }) {
+            length += length_km(up);
+            source = up.points[0];
+            seg = up;
+        }
+        if length < MIN_RIVER_KM {
+            continue;
+        }
+        let (mx, my) = cell(*mouth.points.last().unwrap());
+        let (sx, sy) = cell(source);
+        let (lat, lon) = cell_latlon(mx, my, w, h);
+        let (source_lat, source_lon) = cell_latlon(sx, sy, w, h);
+        let discharge = mouth.flow.iter().copied().fold(0.0, f32::max);
+        let event = Event::RiverMouth { length_km: length, discharge, order: mouth.order, source_lat, source_lon, lat, lon };
+        events.push((length, event));
+    }
+    events.sort_by(|a, b| b.0.total_cmp(&a.0));
+    events.into_iter().map(|(_, e)| e).collect()
+}
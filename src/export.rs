@@ -0,0 +1,211 @@
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+#[cfg(feature = "image")]
+use image::ImageEncoder;
+#[cfg(feature = "image")]
+use image::codecs::png::PngEncoder;
+use serde::Serialize;
+
+use crate::Map;
+use crate::grid::Grid;
+use crate::layers::LayerView;
+
+/// Sidecar describing a heightmap (or other f32 layer) export. 16-bit PNG
+/// values map linearly from 0 at `min` to 65535 at `max`; the raw file
+/// stores values directly.
+#[derive(Clone, Debug, Serialize)]
+pub struct HeightmapMeta {
+    pub width: usize,
+    pub height: usize,
+    /// Minimum elevation (meters).
+    pub min: f32,
+    /// Maximum elevation (meters).
+    pub max: f32,
+    /// Raw sample format: row-major little-endian f32, row 0 = north.
+    pub format: &'static str,
+    pub units: &'static str,
+}
+
+pub fn heightmap_meta(height: &Grid<f32>) -> HeightmapMeta {
+    layer_meta(height, "meters")
+}
+
+/// Sidecar for any f32 layer export, with the units of its values.
+pub fn layer_meta(grid: &Grid<f32>, units: &'static str) -> HeightmapMeta {
+    let (min, max) = min_max(grid);
+    HeightmapMeta {
+        width: grid.w,
+        height: grid.h,
+        min,
+        max,
+        format: "f32le",
+        units,
+    }
+}
+
+/// Units of a built-in layer's values (see `layers::BUILTIN_LAYERS`), for
+/// sidecars; empty for layers without any.
+pub fn layer_units(name: &str) -> &'static str {
+    match name {
+        "height" => "meters",
+        "boundary_dist" => "cells",
+        "temperature" => "celsius",
+        "precipitation" => "mm/year",
+        // Rainfall summed over the upstream (hydrology-resolution) cells
+        "river_flow" => "mm/year*cells",
+        "population" => "people",
+        "plate_id" | "nation_id" | "culture_id" => "id",
+        "boundary_type" | "boundary_major" | "ice" => "class",
+        _ => "",
+    }
+}
+
+/// Encode elevation (or any f32 layer) as a 16-bit grayscale PNG,
+/// normalized to the full min..max range (see `HeightmapMeta`).
+#[cfg(feature = "image")]
+pub fn heightmap_png16(height: &Grid<f32>) -> Vec<u8> {
+    let (min, max) = min_max(height);
+    let range = (max - min).max(1e-6);
+    let mut samples = Vec::with_capacity(height.data.len() * 2);
+    for &e in &height.data {
+        let v = (((e - min) / range) * 65535.0).round() as u16;
+        samples.extend_from_slice(&v.to_ne_bytes());
+    }
+
+    let mut buf = Vec::new();
+    PngEncoder::new(&mut buf)
+        .write_image(&samples, height.w as u32, height.h as u32, image::ExtendedColorType::L16)
+        .expect("PNG encode failed");
+    buf
+}
+
+/// Raw elevation in meters (or any f32 layer) as little-endian f32,
+/// row-major.
+pub fn heightmap_f32le(height: &Grid<f32>) -> Vec<u8> {
+    height.data.iter().flat_map(|e| e.to_le_bytes()).collect()
+}
+
+/// Write `<stem>16.png`, `<stem>.f32`, and the `<stem>.json` sidecar.
+#[cfg(feature = "image")]
+pub fn write_heightmap(dir: &Path, stem: &str, height: &Grid<f32>) -> std::io::Result<()> {
+    std::fs::write(dir.join(format!("{}16.png", stem)), heightmap_png16(height))?;
+    std::fs::write(dir.join(format!("{}.f32", stem)), heightmap_f32le(height))?;
+    let meta = serde_json::to_string_pretty(&heightmap_meta(height)).map_err(std::io::Error::other)?;
+    std::fs::write(dir.join(format!("{}.json", stem)), meta)
+}
+
+/// Encode a layer as a single-channel 32-bit float TIFF, row 0 = north,
+/// for GIS tools and image editors that read float rasters.
+#[cfg(feature = "image")]
+pub fn layer_tiff(grid: &Grid<f32>) -> std::io::Result<Vec<u8>> {
+    let mut buf = Cursor::new(Vec::new());
+    tiff::encoder::TiffEncoder::new(&mut buf)
+        .and_then(|mut tiff| {
+            tiff.write_image::<tiff::encoder::colortype::Gray32Float>(grid.w as u32, grid.h as u32, &grid.data)
+        })
+        .map_err(std::io::Error::other)?;
+    Ok(buf.into_inner())
+}
+
+/// Grid cell types with a NumPy dtype.
+pub trait NpyElement: Copy {
+    /// NumPy dtype string (`descr`).
+    const DESCR: &'static str;
+    fn write_le(self, out: &mut Vec<u8>);
+}
+
+impl NpyElement for f32 {
+    const DESCR: &'static str = "<f4";
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for u16 {
+    const DESCR: &'static str = "<u2";
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl NpyElement for u8 {
+    const DESCR: &'static str = "|u1";
+    fn write_le(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+}
+
+/// Encode a grid as a NumPy `.npy` (format 1.0) array of shape (h, w),
+/// row 0 = north, loadable with `numpy.load`.
+pub fn npy<T: NpyElement>(grid: &Grid<T>) -> Vec<u8> {
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {}), }}",
+        T::DESCR,
+        grid.h,
+        grid.w
+    );
+    // Magic (6) + version (2) + length (2) + header, padded to 64 bytes
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(10 + header.len() + grid.data.len() * 4);
+    out.extend_from_slice(b"\x93NUMPY\x01\x00");
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for &v in &grid.data {
+        v.write_le(&mut out);
+    }
+    out
+}
+
+/// Write one grid as `<path>` in `.npy` format.
+pub fn write_npy<T: NpyElement>(path: &Path, grid: &Grid<T>) -> std::io::Result<()> {
+    std::fs::write(path, npy(grid))
+}
+
+/// Every map layer (see `Map::layers`) as an uncompressed `.npz` archive
+/// (what `numpy.savez` writes), one array per grid named after the layer.
+pub fn map_npz(map: &Map) -> std::io::Result<Vec<u8>> {
+    let names: Vec<String> = map.layers().into_iter().map(|info| info.name).collect();
+    layers_npz(map, &names)
+}
+
+/// The named map layers as an `.npz` archive, like `map_npz`. Names that
+/// aren't layers are skipped.
+pub fn layers_npz(map: &Map, names: &[impl AsRef<str>]) -> std::io::Result<Vec<u8>> {
+    let arrays = names.iter().filter_map(|name| {
+        let data = match map.layer_view(name.as_ref())? {
+            LayerView::F32(g) => npy(g.as_ref()),
+            LayerView::U16(g) => npy(g),
+            LayerView::U8(g) => npy(g),
+        };
+        Some((name.as_ref(), data))
+    });
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .large_file(true);
+    for (name, data) in arrays {
+        zip.start_file(format!("{}.npy", name), options).map_err(std::io::Error::other)?;
+        zip.write_all(&data)?;
+    }
+    Ok(zip.finish().map_err(std::io::Error::other)?.into_inner())
+}
+
+/// Write the whole map as `<path>` in `.npz` format.
+pub fn write_npz(path: &Path, map: &Map) -> std::io::Result<()> {
+    std::fs::write(path, map_npz(map)?)
+}
+
+fn min_max(height: &Grid<f32>) -> (f32, f32) {
+    if height.data.is_empty() {
+        return (0.0, 0.0);
+    }
+    height
+        .data
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), &e| (lo.min(e), hi.max(e)))
+}
@@ -0,0 +1,71 @@
+//! Float math for the generation stages. std's transcendental functions
+//! (`powf`, `exp`, `sin`, ...) call the platform's libm, whose last-bit
+//! results differ between x86 and ARM, glibc and musl, and so would the
+//! worlds built from them. With the `strict-determinism` feature these go
+//! to the pure-Rust `libm` crate instead, which returns the same bits
+//! everywhere. `+ - * /` and `sqrt` are exactly rounded by IEEE 754 and
+//! Rust never fuses them, so they need no wrapper.
+
+#[cfg(feature = "strict-determinism")]
+macro_rules! pick {
+    ($strict:expr, $native:expr) => {
+        $strict
+    };
+}
+
+#[cfg(not(feature = "strict-determinism"))]
+macro_rules! pick {
+    ($strict:expr, $native:expr) => {
+        $native
+    };
+}
+
+#[inline]
+pub fn powf(x: f32, y: f32) -> f32 {
+    pick!(libm::powf(x, y), x.powf(y))
+}
+
+#[inline]
+pub fn exp(x: f32) -> f32 {
+    pick!(libm::expf(x), x.exp())
+}
+
+#[inline]
+pub fn ln(x: f32) -> f32 {
+    pick!(libm::logf(x), x.ln())
+}
+
+#[inline]
+pub fn sin(x: f32) -> f32 {
+    pick!(libm::sinf(x), x.sin())
+}
+
+#[inline]
+pub fn cos(x: f32) -> f32 {
+    pick!(libm::cosf(x), x.cos())
+}
+
+#[inline]
+pub fn tan(x: f32) -> f32 {
+    pick!(libm::tanf(x), x.tan())
+}
+
+#[inline]
+pub fn asin(x: f32) -> f32 {
+    pick!(libm::asinf(x), x.asin())
+}
+
+#[inline]
+pub fn atan(x: f32) -> f32 {
+    pick!(libm::atanf(x), x.atan())
+}
+
+#[inline]
+pub fn atan2(y: f32, x: f32) -> f32 {
+    pick!(libm::atan2f(y, x), y.atan2(x))
+}
+
+#[inline]
+pub fn hypot(x: f32, y: f32) -> f32 {
+    pick!(libm::hypotf(x, y), x.hypot(y))
+}
@@ -0,0 +1,123 @@
+//! Repeated generation at one size without reallocating. A `Generator`
+//! keeps the buffers of each run's temporaries (distance transform, blur
+//! copies, hi-res hydrology grids) and of the outputs a `Map` doesn't
+//! keep, and hands them to the next run.
+
+#[cfg(feature = "parallel")]
+use std::sync::Arc;
+
+use crate::cancel::CancelToken;
+use crate::clock::Instant;
+use crate::config::Params;
+use crate::scratch::{self, Recycle, Scratch};
+#[cfg(feature = "parallel")]
+use crate::threads::ThreadPool;
+use crate::{Map, Pipeline, Stage, Timing, WorldgenError, check_size, push_total};
+
+/// Generation context for maps of one size. Each call builds the same
+/// world the free functions (`generate`, `generate_base`) would.
+///
+/// ```ignore
+/// let mut generator = Generator::new(2048, 1024)?;
+/// for seed in 0..10 {
+///     let (map, _) = generator.generate_base(seed, &params)?;
+///     // ... use the map, then give its buffers back
+///     generator.recycle(map);
+/// }
+/// ```
+pub struct Generator {
+    w: usize,
+    h: usize,
+    scratch: Scratch,
+    #[cfg(feature = "parallel")]
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl Generator {
+    /// A context for `w x h` maps; fails if the size doesn't pass
+    /// `check_size`.
+    pub fn new(w: usize, h: usize) -> Result<Self, WorldgenError> {
+        check_size(w, h)?;
+        Ok(Self {
+            w,
+            h,
+            scratch: Scratch::default(),
+            #[cfg(feature = "parallel")]
+            pool: None,
+        })
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.w, self.h)
+    }
+
+    /// Run on `pool` instead of rayon's global pool (see
+    /// `Pipeline::set_thread_pool`).
+    #[cfg(feature = "parallel")]
+    pub fn set_thread_pool(&mut self, pool: Option<Arc<ThreadPool>>) {
+        self.pool = pool;
+    }
+
+    /// Every stage, as `generate`.
+    pub fn generate(&mut self, seed: u64, params: &Params) -> Result<(Map, Vec<Timing>), WorldgenError> {
+        self.run(seed, params, &Stage::ALL, |_, _| {}, &CancelToken::new())
+    }
+
+    /// Everything except hydrology and what follows it, as `generate_base`.
+    pub fn generate_base(&mut self, seed: u64, params: &Params) -> Result<(Map, Vec<Timing>), WorldgenError> {
+        self.run(seed, params, &Stage::BASE, |_, _| {}, &CancelToken::new())
+    }
+
+    /// `targets` and their inputs, reporting and cancelling as
+    /// `Pipeline::run`. Fails if `params` don't pass
+    /// `Params::validate_for_size`.
+    pub fn run(
+        &mut self,
+        seed: u64,
+        params: &Params,
+        targets: &[Stage],
+        progress: impl Fn(Stage, f32) + Sync,
+        cancel: &CancelToken,
+    ) -> Result<(Map, Vec<Timing>), WorldgenError> {
+        let total_start = Instant::now();
+        let mut pipeline = Pipeline::new(seed, self.w, self.h, params.clone())?.with_scratch(std::mem::take(&mut self.scratch));
+        #[cfg(feature = "parallel")]
+        pipeline.set_thread_pool(self.pool.clone());
+        let result = pipeline.run(targets, progress, cancel);
+        let (map, scratch) = pipeline.into_map_and_scratch();
+        self.scratch = scratch;
+        let mut timings = match result {
+            Ok(timings) => timings,
+            Err(e) => {
+                self.recycle(map);
+                return Err(e);
+            }
+        };
+        push_total(&mut timings, total_start);
+        Ok((map, timings))
+    }
+
+    /// Hand back the grids of a map that is no longer needed, for the
+    /// next run to reuse.
+    pub fn recycle(&mut self, map: Map) {
+        scratch::with(&mut self.scratch, || {
+            (map.height, map.river_flow).recycle();
+            (map.population, map.rgba).recycle();
+            (map.plate_id, map.nation_id).recycle();
+            (map.culture_id, map.ice).recycle();
+            (map.boundary_type, map.boundary_major).recycle();
+            (map.boundary_dist.codes, map.temperature.codes).recycle();
+            map.precipitation.codes.recycle();
+        });
+    }
+
+    /// Bytes held in buffers waiting for the next run.
+    pub fn retained_bytes(&self) -> usize {
+        self.scratch.bytes()
+    }
+
+    /// Free the retained buffers.
+    pub fn clear(&mut self) {
+        self.scratch = Scratch::default();
+    }
+}
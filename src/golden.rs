@@ -0,0 +1,165 @@
+//! Golden-world regression checks: fingerprint a generated map, store the
+//! fingerprint with the settings that built it, and later find out whether
+//! the same settings still build the same world (e.g. after upgrading the
+//! crate), and if not, which layers changed.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Params, parse_migrated};
+use crate::{Map, WorldgenError, generate};
+
+/// Compact hash of a map: each layer's digest and their fold (see
+/// `Map::layer_digests`), as hex strings so they survive TOML's signed
+/// integers.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub digest: String,
+    pub layers: Vec<LayerDigest>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerDigest {
+    pub name: String,
+    pub digest: String,
+}
+
+/// Fingerprint of a generated map.
+pub fn fingerprint(map: &Map) -> Fingerprint {
+    Fingerprint {
+        digest: format!("{:016x}", map.digest()),
+        layers: map
+            .layer_digests()
+            .into_iter()
+            .map(|(name, d)| LayerDigest { name, digest: format!("{:016x}", d) })
+            .collect(),
+    }
+}
+
+impl Fingerprint {
+    /// Names of the layers whose digest differs from `other`'s, including
+    /// layers only one of them has.
+    pub fn changed_layers(&self, other: &Fingerprint) -> Vec<String> {
+        let find = |f: &Fingerprint, name: &str| f.layers.iter().find(|l| l.name == name).map(|l| l.digest.clone());
+        let mut changed: Vec<String> = self
+            .layers
+            .iter()
+            .filter(|l| find(other, &l.name).as_ref() != Some(&l.digest))
+            .map(|l| l.name.clone())
+            .collect();
+        changed.extend(other.layers.iter().filter(|l| find(self, &l.name).is_none()).map(|l| l.name.clone()));
+        changed
+    }
+}
+
+/// Settings plus the fingerprint of the world they built: a `WorldFile`
+/// with a `[fingerprint]` table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GoldenWorld {
+    /// Crate version that recorded the fingerprint.
+    pub version: String,
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub params: Params,
+    pub fingerprint: Fingerprint,
+}
+
+/// Outcome of checking a world against its golden file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GoldenCheck {
+    /// No golden file existed; one was written.
+    Recorded,
+    /// Same world as recorded.
+    Matches,
+    /// The world differs in these layers from the one recorded by
+    /// `recorded_by` (a crate version).
+    Changed { layers: Vec<String>, recorded_by: String },
+}
+
+impl GoldenWorld {
+    /// Fingerprint `map`, built from these settings.
+    pub fn new(seed: u64, width: usize, height: usize, params: &Params, map: &Map) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            width,
+            height,
+            params: params.clone(),
+            fingerprint: fingerprint(map),
+        }
+    }
+
+    /// Generate the world and fingerprint it.
+    pub fn record(seed: u64, width: usize, height: usize, params: &Params) -> Result<Self, WorldgenError> {
+        let (map, _) = generate(seed, width, height, params)?;
+        Ok(Self::new(seed, width, height, params, &map))
+    }
+
+    /// Regenerate from the stored settings and compare.
+    pub fn verify(&self) -> Result<GoldenCheck, WorldgenError> {
+        let (map, _) = generate(self.seed, self.width, self.height, &self.params)?;
+        Ok(self.compare(&map))
+    }
+
+    /// Compare an already generated map against the stored fingerprint.
+    pub fn compare(&self, map: &Map) -> GoldenCheck {
+        let layers = self.fingerprint.changed_layers(&fingerprint(map));
+        if layers.is_empty() {
+            GoldenCheck::Matches
+        } else {
+            GoldenCheck::Changed { layers, recorded_by: self.version.clone() }
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        // As in `WorldFile::to_toml`: through JSON for shortest f32 output
+        let json = serde_json::to_string(self).expect("golden world serializes");
+        let value: toml::Value = serde_json::from_str(&json).expect("JSON is valid TOML data");
+        toml::to_string(&value).expect("golden world serializes")
+    }
+
+    /// Parse a golden file, upgrading older parameter versions.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        parse_migrated(text, true, Some("params"))
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_toml())
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_toml(&text).map_err(|e| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+        })
+    }
+}
+
+/// Check `map` (built from these settings) against the golden file at
+/// `path`, writing the file if it doesn't exist yet. A golden file for
+/// different settings counts as changed in every layer.
+pub fn check_golden(
+    path: &Path,
+    seed: u64,
+    width: usize,
+    height: usize,
+    params: &Params,
+    map: &Map,
+) -> std::io::Result<GoldenCheck> {
+    let current = GoldenWorld::new(seed, width, height, params, map);
+    if !path.exists() {
+        current.save(path)?;
+        return Ok(GoldenCheck::Recorded);
+    }
+    let stored = GoldenWorld::load(path)?;
+    let same_settings = (stored.seed, stored.width, stored.height) == (seed, width, height)
+        && serde_json::to_value(&stored.params).ok() == serde_json::to_value(params).ok();
+    if !same_settings {
+        let layers = current.fingerprint.layers.iter().map(|l| l.name.clone()).collect();
+        return Ok(GoldenCheck::Changed { layers, recorded_by: stored.version });
+    }
+    Ok(stored.compare(map))
+}
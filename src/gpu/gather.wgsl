@@ -0,0 +1,55 @@
+// out[y][x] = sum over y taps (j, wy) of wy * sum over x taps (i, wx) of
+// wx * src[j][i], or the largest src[j][i] under the taps when mode is 1.
+// Same summation order as the CPU `Grid::resample` and convolutions.
+// Taps are (source index, weight bits); `offsets` holds the x tap ranges
+// (w + 1 entries) followed by the y tap ranges (h + 1 entries).
+
+struct Params {
+    src_w: u32,
+    w: u32,
+    h: u32,
+    mode: u32,
+}
+
+@group(0) @binding(0) var<uniform> p: Params;
+@group(0) @binding(1) var<storage, read> src: array<f32>;
+@group(0) @binding(2) var<storage, read_write> dst: array<f32>;
+@group(0) @binding(3) var<storage, read> offsets: array<u32>;
+@group(0) @binding(4) var<storage, read> x_taps: array<vec2<u32>>;
+@group(0) @binding(5) var<storage, read> y_taps: array<vec2<u32>>;
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let x = id.x;
+    let y = id.y;
+    if x >= p.w || y >= p.h {
+        return;
+    }
+    let x0 = offsets[x];
+    let x1 = offsets[x + 1u];
+    let y0 = offsets[p.w + 1u + y];
+    let y1 = offsets[p.w + 2u + y];
+
+    var acc = 0.0;
+    if p.mode == 1u {
+        acc = bitcast<f32>(0xff800000u); // -inf
+    }
+    for (var j = y0; j < y1; j++) {
+        let ty = y_taps[j];
+        let row = ty.x * p.src_w;
+        var line = 0.0;
+        for (var i = x0; i < x1; i++) {
+            let tx = x_taps[i];
+            let v = src[row + tx.x];
+            if p.mode == 1u {
+                acc = max(acc, v);
+            } else {
+                line += v * bitcast<f32>(tx.y);
+            }
+        }
+        if p.mode == 0u {
+            acc += line * bitcast<f32>(ty.y);
+        }
+    }
+    dst[y * p.w + x] = acc;
+}
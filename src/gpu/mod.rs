@@ -0,0 +1,221 @@
+//! Optional wgpu compute backend (the `gpu` feature). The first call
+//! looks for an adapter; without one, or when a grid is too small to be
+//! worth the transfer or too large for the device's buffers, every
+//! entry point returns `None` and the caller runs its CPU path.
+//! Implemented so far: `Grid::resample` (all filters, so the hydrology
+//! upscale) and flat-metric separable convolutions and Gaussian blurs.
+//! Noise and elevation phase 3, the climate loops and the boundary
+//! distance field (where jump flooding would go) still run on the CPU,
+//! so base generation at 2048x1024 is not yet sub-second; they are left
+//! for a follow-up. The kernels sum in the CPU's order, but a driver
+//! may fuse multiply-adds, so worlds built on a GPU are not guaranteed
+//! digest-identical to CPU worlds; `set_enabled(false)` forces the CPU
+//! path.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use wgpu::util::DeviceExt;
+
+use crate::grid::{Edge, Grid};
+
+/// Below this many output cells the transfer costs more than the CPU.
+const MIN_CELLS: usize = 1 << 16;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static CONTEXT: OnceLock<Option<Context>> = OnceLock::new();
+
+/// Turn the GPU path on or off for the whole process (on by default).
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether GPU work will run: enabled and an adapter was found.
+pub fn available() -> bool {
+    context().is_some()
+}
+
+/// Name of the adapter in use, if any.
+pub fn adapter_name() -> Option<String> {
+    context().map(|c| c.adapter.get_info().name)
+}
+
+struct Context {
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    gather: wgpu::ComputePipeline,
+}
+
+fn context() -> Option<&'static Context> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    CONTEXT.get_or_init(Context::new).as_ref()
+}
+
+impl Context {
+    fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("worldgen"),
+                required_features: wgpu::Features::empty(),
+                required_limits: adapter.limits(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gather"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gather.wgsl").into()),
+        });
+        let gather = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("gather"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Some(Self { adapter, device, queue, gather })
+    }
+
+    /// Whether a buffer of `n` f32s fits the device limits.
+    fn fits(&self, n: usize) -> bool {
+        let limits = self.device.limits();
+        let bytes = (n * 4) as u64;
+        bytes <= limits.max_storage_buffer_binding_size as u64 && bytes <= limits.max_buffer_size
+    }
+}
+
+/// Gather `src` through per-axis tap lists (source index, weight) into a
+/// `x_taps.len() x y_taps.len()` grid: the weighted sum, or the largest
+/// source cell under the taps with `max`. Indices must already be folded
+/// onto the source. The result keeps the source topology and metric.
+pub(crate) fn gather(src: &Grid<f32>, x_taps: &[Vec<(usize, f32)>], y_taps: &[Vec<(usize, f32)>], max: bool) -> Option<Grid<f32>> {
+    let (w, h) = (x_taps.len(), y_taps.len());
+    if w * h < MIN_CELLS {
+        return None;
+    }
+    let ctx = context()?;
+    let n_taps = x_taps.iter().chain(y_taps).map(Vec::len).max().unwrap_or(0);
+    if !ctx.fits(src.data.len()) || !ctx.fits(w * h) || !ctx.fits(2 * (w + h) * n_taps) {
+        return None;
+    }
+
+    let mut offsets = Vec::with_capacity(w + h + 2);
+    let mut flatten = |taps: &[Vec<(usize, f32)>]| {
+        let mut flat: Vec<[u32; 2]> = Vec::new();
+        offsets.push(0u32);
+        for t in taps {
+            flat.extend(t.iter().map(|&(i, wt)| [i as u32, wt.to_bits()]));
+            offsets.push(flat.len() as u32);
+        }
+        flat
+    };
+    let xs = flatten(x_taps);
+    let ys = flatten(y_taps);
+
+    let device = &ctx.device;
+    let storage = |label, contents: &[u8]| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::STORAGE,
+        })
+    };
+    let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("params"),
+        contents: bytemuck::cast_slice(&[src.w as u32, w as u32, h as u32, max as u32]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let src_buf = storage("src", bytemuck::cast_slice(&src.data));
+    let offsets_buf = storage("offsets", bytemuck::cast_slice(&offsets));
+    // Empty bindings are invalid; pad with one unused tap.
+    let xs_buf = storage("x_taps", bytemuck::cast_slice(if xs.is_empty() { &[[0u32; 2]] } else { &xs }));
+    let ys_buf = storage("y_taps", bytemuck::cast_slice(if ys.is_empty() { &[[0u32; 2]] } else { &ys }));
+    let size = (w * h * 4) as u64;
+    let dst_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("dst"),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("gather"),
+        layout: &ctx.gather.get_bind_group_layout(0),
+        entries: &[&params, &src_buf, &dst_buf, &offsets_buf, &xs_buf, &ys_buf]
+            .iter()
+            .enumerate()
+            .map(|(i, b)| wgpu::BindGroupEntry { binding: i as u32, resource: b.as_entire_binding() })
+            .collect::<Vec<_>>(),
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("gather") });
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("gather"), timestamp_writes: None });
+        pass.set_pipeline(&ctx.gather);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(w.div_ceil(16) as u32, h.div_ceil(16) as u32, 1);
+    }
+    encoder.copy_buffer_to_buffer(&dst_buf, 0, &readback, 0, size);
+    ctx.queue.submit([encoder.finish()]);
+
+    let slice = readback.slice(..);
+    let (tx, rx) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |r| {
+        let _ = tx.send(r);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    rx.recv().ok()?.ok()?;
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    readback.unmap();
+    Some(Grid::from_vec(w, h, data).with_topology(src.topology).with_metric(src.metric))
+}
+
+/// Separable convolution of `grid` (see `Grid::convolve_separable_with`)
+/// on the GPU. Returns false, leaving the grid untouched, when the CPU
+/// should do it.
+pub(crate) fn convolve_separable(grid: &mut Grid<f32>, kernel_x: &[f32], kernel_y: &[f32], edge_x: Edge, edge_y: Edge) -> bool {
+    let (w, h) = (grid.w, grid.h);
+    if w * h < MIN_CELLS || context().is_none() {
+        return false;
+    }
+    let identity = |n: usize| (0..n).map(|i| vec![(i, 1.0)]).collect::<Vec<_>>();
+    let around = |n: usize, kernel: &[f32], edge: Edge| {
+        let r = (kernel.len() / 2) as i64;
+        (0..n)
+            .map(|i| kernel.iter().enumerate().map(|(k, &kw)| (edge.fold(i as i64 + k as i64 - r, n), kw)).collect())
+            .collect::<Vec<_>>()
+    };
+
+    let mut out: Option<Grid<f32>> = None;
+    if !kernel_x.is_empty() {
+        match gather(grid, &around(w, kernel_x, edge_x), &identity(h), false) {
+            Some(g) => out = Some(g),
+            None => return false,
+        }
+    }
+    if !kernel_y.is_empty() {
+        match gather(out.as_ref().unwrap_or(grid), &identity(w), &around(h, kernel_y, edge_y), false) {
+            Some(g) => out = Some(g),
+            None => return false,
+        }
+    }
+    if let Some(g) = out {
+        *grid = g;
+    }
+    true
+}
@@ -0,0 +1,53 @@
+use std::borrow::Cow;
+
+use crate::fmath;
+use super::{Grid, Metric};
+
+/// Normalized symmetric Gaussian kernel of `2 * radius + 1` taps.
+pub fn gaussian_kernel(sigma: f32, radius: usize) -> Vec<f32> {
+    let r = radius as i32;
+    let kernel: Vec<f32> = (-r..=r)
+        .map(|d| fmath::exp(-(d as f32 * d as f32) / (2.0 * sigma * sigma)))
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter().map(|k| k / sum).collect()
+}
+
+/// Kernel radius covering three sigmas.
+#[inline]
+fn radius_for(sigma: f32) -> usize {
+    (sigma * 3.0).ceil() as usize
+}
+
+impl Grid<f32> {
+    /// Separable Gaussian blur in place, parallel over rows. Sigmas are in
+    /// N-S cells; 0 skips that axis. Edges wrap or clamp per the grid's
+    /// topology. Under a spherical metric each row's E-W sigma widens by
+    /// 1 / cos(latitude) so the blur covers the same ground distance
+    /// everywhere (capped at half the map width).
+    pub fn gaussian_blur(&mut self, sigma_x: f32, sigma_y: f32) {
+        let (w, h) = (self.w, self.h);
+        if w == 0 || h == 0 {
+            return;
+        }
+        let (edge_x, edge_y) = self.edges();
+        let metric = self.metric;
+
+        let kernel = |sigma| if radius_for(sigma) > 0 { gaussian_kernel(sigma, radius_for(sigma)) } else { Vec::new() };
+        if metric == Metric::Flat {
+            self.convolve_separable_with(&kernel(sigma_x), &kernel(sigma_y), edge_x, edge_y);
+            return;
+        }
+
+        if radius_for(sigma_x) > 0 {
+            self.convolve_rows(
+                |y| {
+                    let sigma = sigma_x / metric.x_scale(y, h);
+                    Cow::Owned(gaussian_kernel(sigma, radius_for(sigma).min(w.saturating_sub(1) / 2)))
+                },
+                edge_x,
+            );
+        }
+        self.convolve_separable_with(&[], &kernel(sigma_y), edge_x, edge_y);
+    }
+}
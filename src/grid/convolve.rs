@@ -0,0 +1,147 @@
+use std::borrow::Cow;
+
+use super::Grid;
+use crate::par::*;
+use crate::scratch;
+
+/// How a convolution reads cells past an edge of one axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    /// Continue from the opposite edge.
+    Wrap,
+    /// Repeat the edge cell.
+    Clamp,
+    /// Reflect about the edge (`-1` reads `0`, `-2` reads `1`).
+    Mirror,
+}
+
+impl Edge {
+    /// Fold an out-of-range index back onto [0, n).
+    #[inline]
+    pub fn fold(self, i: i64, n: usize) -> usize {
+        let n = n as i64;
+        match self {
+            Edge::Wrap => i.rem_euclid(n) as usize,
+            Edge::Clamp => i.clamp(0, n - 1) as usize,
+            Edge::Mirror => {
+                let p = i.rem_euclid(2 * n);
+                (if p < n { p } else { 2 * n - 1 - p }) as usize
+            }
+        }
+    }
+}
+
+/// out[x] = sum_k kernel[k] * src[x + k] over a padded row. Written as
+/// whole-slice multiply-adds so the compiler vectorizes the inner loop.
+#[inline]
+fn convolve_padded(padded: &[f32], kernel: &[f32], out: &mut [f32]) {
+    out.fill(0.0);
+    let n = out.len();
+    for (k, &kw) in kernel.iter().enumerate() {
+        for (o, &p) in out.iter_mut().zip(&padded[k..k + n]) {
+            *o += kw * p;
+        }
+    }
+}
+
+impl Grid<f32> {
+    /// Edge policies implied by the topology (wrap where it wraps, clamp
+    /// elsewhere).
+    pub fn edges(&self) -> (Edge, Edge) {
+        let edge = |wraps| if wraps { Edge::Wrap } else { Edge::Clamp };
+        (edge(self.topology.wraps_x()), edge(self.topology.wraps_y()))
+    }
+
+    /// Separable convolution in place: `kernel_x` along rows, then
+    /// `kernel_y` along columns. Kernels have odd length and are centered;
+    /// an empty kernel skips that axis. Edges follow the topology.
+    pub fn convolve_separable(&mut self, kernel_x: &[f32], kernel_y: &[f32]) {
+        let (ex, ey) = self.edges();
+        self.convolve_separable_with(kernel_x, kernel_y, ex, ey);
+    }
+
+    /// `convolve_separable` with explicit edge policies per axis.
+    pub fn convolve_separable_with(&mut self, kernel_x: &[f32], kernel_y: &[f32], edge_x: Edge, edge_y: Edge) {
+        #[cfg(feature = "gpu")]
+        if crate::gpu::convolve_separable(self, kernel_x, kernel_y, edge_x, edge_y) {
+            return;
+        }
+        self.convolve_rows(|_| Cow::Borrowed(kernel_x), edge_x);
+        self.convolve_columns(kernel_y, edge_y);
+    }
+
+    /// Horizontal pass with a kernel chosen per row (e.g. widened toward
+    /// the poles), parallel over rows. Empty kernels leave a row as is.
+    pub(super) fn convolve_rows<'k>(&mut self, kernel_for_row: impl Fn(usize) -> Cow<'k, [f32]> + Sync, edge: Edge) {
+        let w = self.w;
+        if w == 0 {
+            return;
+        }
+        self.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+            let kernel = kernel_for_row(y);
+            if kernel.is_empty() {
+                return;
+            }
+            let r = (kernel.len() / 2) as i64;
+            let padded: Vec<f32> = (-r..w as i64 + r).map(|x| row[edge.fold(x, w)]).collect();
+            convolve_padded(&padded, &kernel, row);
+        });
+    }
+
+    /// Vertical pass: whole source rows scaled and summed into each output
+    /// row, parallel over rows.
+    fn convolve_columns(&mut self, kernel: &[f32], edge: Edge) {
+        let (w, h) = (self.w, self.h);
+        if kernel.is_empty() || w == 0 {
+            return;
+        }
+        let r = (kernel.len() / 2) as i64;
+        let src = scratch::copy_of(&self.data);
+        self.data.par_chunks_mut(w).enumerate().for_each(|(y, out)| {
+            out.fill(0.0);
+            for (k, &kw) in kernel.iter().enumerate() {
+                let sy = edge.fold(y as i64 + k as i64 - r, h);
+                for (o, &s) in out.iter_mut().zip(&src[sy * w..(sy + 1) * w]) {
+                    *o += kw * s;
+                }
+            }
+        });
+        scratch::give(src);
+    }
+
+    /// Convolve in place with a small dense `N x N` kernel (N odd, e.g. a
+    /// 3x3 Sobel or 5x5 sharpen), indexed `kernel[dy][dx]` and centered.
+    /// Edges follow the topology.
+    pub fn convolve<const N: usize>(&mut self, kernel: &[[f32; N]; N]) {
+        let (ex, ey) = self.edges();
+        self.convolve_with(kernel, ex, ey);
+    }
+
+    /// `convolve` with explicit edge policies per axis.
+    pub fn convolve_with<const N: usize>(&mut self, kernel: &[[f32; N]; N], edge_x: Edge, edge_y: Edge) {
+        assert!(N % 2 == 1, "kernel size must be odd");
+        let (w, h) = (self.w, self.h);
+        if w == 0 {
+            return;
+        }
+        let r = (N / 2) as i64;
+        let src = scratch::copy_of(&self.data);
+        self.data.par_chunks_mut(w).enumerate().for_each(|(y, out)| {
+            out.fill(0.0);
+            let mut padded = vec![0.0f32; w + N - 1];
+            for (dy, krow) in kernel.iter().enumerate() {
+                let sy = edge_y.fold(y as i64 + dy as i64 - r, h);
+                let line = &src[sy * w..(sy + 1) * w];
+                for (p, x) in padded.iter_mut().zip(-r..) {
+                    *p = line[edge_x.fold(x, w)];
+                }
+                for (dx, &kw) in krow.iter().enumerate() {
+                    for (o, &p) in out.iter_mut().zip(&padded[dx..dx + w]) {
+                        *o += kw * p;
+                    }
+                }
+            }
+        });
+        scratch::give(src);
+    }
+}
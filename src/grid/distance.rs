@@ -0,0 +1,163 @@
+use super::Grid;
+use crate::cancel::CancelToken;
+use crate::par::*;
+use crate::scratch;
+
+/// Marks "no feature reachable" in the nearest-feature coordinate grids.
+pub const NO_FEATURE: u16 = u16::MAX;
+
+/// 1D squared-distance transform (Felzenszwalb & Huttenlocher): for each
+/// p in [0, f.len()), the minimum over q of `scale_sq * (p - q)^2 + f[q]`
+/// and the q that attains it. Infinite entries of `f` contribute nothing.
+/// With `wraps`, q is also tried one period to either side, which covers
+/// every shortest path around the ring.
+fn envelope_1d(f: &[f64], scale_sq: f64, wraps: bool, out: &mut [f64], arg: &mut [usize]) {
+    let n = f.len() as i64;
+    let shifts: &[i64] = if wraps { &[-n, 0, n] } else { &[0] };
+
+    // Parabola roots (position, source index) in increasing position order
+    let mut sites: Vec<(i64, usize)> = Vec::new();
+    for &shift in shifts {
+        sites.extend((0..n as usize).filter(|&q| f[q].is_finite()).map(|q| (q as i64 + shift, q)));
+    }
+    if sites.is_empty() {
+        out.fill(f64::INFINITY);
+        arg.fill(usize::MAX);
+        return;
+    }
+
+    let height = |(p, q): (i64, usize)| f[q] + scale_sq * (p * p) as f64;
+    let cross = |a: (i64, usize), b: (i64, usize)| {
+        (height(b) - height(a)) / (2.0 * scale_sq * (b.0 - a.0) as f64)
+    };
+
+    // Lower envelope: hull[k] is lowest on [bounds[k], bounds[k + 1])
+    let mut hull: Vec<(i64, usize)> = Vec::with_capacity(sites.len());
+    let mut bounds: Vec<f64> = Vec::with_capacity(sites.len() + 1);
+    for &site in &sites {
+        let mut s = f64::NEG_INFINITY;
+        while let Some(&top) = hull.last() {
+            s = cross(top, site);
+            if s > bounds[hull.len() - 1] {
+                break;
+            }
+            hull.pop();
+            bounds.pop();
+            s = f64::NEG_INFINITY;
+        }
+        hull.push(site);
+        bounds.push(s);
+    }
+    bounds.push(f64::INFINITY);
+
+    let mut k = 0;
+    for p in 0..n {
+        while bounds[k + 1] < p as f64 {
+            k += 1;
+        }
+        let (q, src) = hull[k];
+        let d = (p - q) as f64;
+        out[p as usize] = scale_sq * d * d + f[src];
+        arg[p as usize] = src;
+    }
+}
+
+/// Exact Euclidean distance to the nearest cell where `is_feature` holds,
+/// plus that cell's coordinates. Distances follow the grid's topology
+/// (axes that wrap are measured the short way round; `Sphere` is treated
+/// as a cylinder here) and metric: under `Spherical`, E-W offsets are
+/// scaled at the query cell's latitude. Units are N-S cells.
+///
+/// Cells with no reachable feature get `f32::MAX` and `NO_FEATURE`.
+/// All three grids inherit the input's topology and metric.
+pub fn nearest_feature<T: Copy + Default + Sync>(
+    grid: &Grid<T>,
+    is_feature: impl Fn(T) -> bool + Sync,
+) -> (Grid<f32>, Grid<u16>, Grid<u16>) {
+    nearest_feature_with_progress(grid, is_feature, &|_| {}, &CancelToken::new())
+}
+
+/// `nearest_feature`, calling `progress` with the fraction done after
+/// each of the two passes. If `cancel` fires, the remaining rows or
+/// columns are skipped and keep `f32::MAX` and `NO_FEATURE`.
+pub fn nearest_feature_with_progress<T: Copy + Default + Sync>(
+    grid: &Grid<T>,
+    is_feature: impl Fn(T) -> bool + Sync,
+    progress: &dyn Fn(f32),
+    cancel: &CancelToken,
+) -> (Grid<f32>, Grid<u16>, Grid<u16>) {
+    let (w, h) = (grid.w, grid.h);
+    let (wrap_x, wrap_y) = (grid.topology.wraps_x(), grid.topology.wraps_y());
+    let mut dist = scratch::take(w * h, f32::MAX);
+    let mut near_x = scratch::take(w * h, NO_FEATURE);
+    let mut near_y = scratch::take(w * h, NO_FEATURE);
+    if w == 0 || h == 0 {
+        return (like(grid, dist), like(grid, near_x), like(grid, near_y));
+    }
+
+    // Pass 1, per column: nearest feature row (column-major)
+    let column_rows: Vec<Vec<u16>> = (0..w)
+        .into_par_iter()
+        .map(|x| {
+            if cancel.is_cancelled() {
+                return vec![NO_FEATURE; h];
+            }
+            let f: Vec<f64> = (0..h)
+                .map(|y| if is_feature(grid.get(x, y)) { 0.0 } else { f64::INFINITY })
+                .collect();
+            let mut out = vec![0.0; h];
+            let mut arg = vec![0; h];
+            envelope_1d(&f, 1.0, wrap_y, &mut out, &mut arg);
+            arg.iter().map(|&a| if a == usize::MAX { NO_FEATURE } else { a as u16 }).collect()
+        })
+        .collect();
+    progress(0.5);
+
+    // Pass 2, per row: squared N-S distances from pass 1, combined along
+    // the row with the row's E-W scale
+    dist.par_chunks_mut(w)
+        .zip(near_x.par_chunks_mut(w))
+        .zip(near_y.par_chunks_mut(w))
+        .enumerate()
+        .for_each(|(y, ((drow, nxrow), nyrow))| {
+            if cancel.is_cancelled() {
+                return;
+            }
+            let f: Vec<f64> = column_rows
+                .iter()
+                .map(|rows| match rows[y] {
+                    NO_FEATURE => f64::INFINITY,
+                    fy => {
+                        let dy = (y as i64 - fy as i64).abs();
+                        let dy = if wrap_y { dy.min(h as i64 - dy) } else { dy };
+                        (dy * dy) as f64
+                    }
+                })
+                .collect();
+            let sx = grid.metric.x_scale(y, h) as f64;
+            let mut out = vec![0.0; w];
+            let mut arg = vec![0; w];
+            envelope_1d(&f, sx * sx, wrap_x, &mut out, &mut arg);
+            for (x, (d, &a)) in out.iter().zip(&arg).enumerate() {
+                if d.is_finite() {
+                    drow[x] = d.sqrt() as f32;
+                    nxrow[x] = a as u16;
+                    nyrow[x] = column_rows[a][y];
+                }
+            }
+        });
+    progress(1.0);
+
+    (like(grid, dist), like(grid, near_x), like(grid, near_y))
+}
+
+/// Exact Euclidean distance to the nearest feature cell (see
+/// `nearest_feature`).
+pub fn edt<T: Copy + Default + Sync>(grid: &Grid<T>, is_feature: impl Fn(T) -> bool + Sync) -> Grid<f32> {
+    nearest_feature(grid, is_feature).0
+}
+
+/// Grid over `data` with the same shape, topology and metric as `grid`.
+fn like<T, U: Copy + Default>(grid: &Grid<T>, data: Vec<U>) -> Grid<U> {
+    Grid::from_vec(grid.w, grid.h, data).with_topology(grid.topology).with_metric(grid.metric)
+}
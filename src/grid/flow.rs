@@ -0,0 +1,226 @@
+use std::f32::consts::{FRAC_PI_4, TAU};
+
+use crate::cancel::CancelToken;
+use crate::fmath;
+use crate::par::*;
+use crate::scratch;
+use super::{Grid, Metric};
+
+/// D8 neighbor offsets, indexed by direction code.
+pub const D8_OFFSETS: [(i32, i32); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1, 0),           (1, 0),
+    (-1, 1),  (0, 1),  (1, 1),
+];
+
+/// D8 code for a cell with no lower neighbor (flat or sink).
+pub const NO_FLOW: u8 = 255;
+
+/// D-infinity angle for a cell with no downhill facet.
+pub const NO_ANGLE: f32 = -1.0;
+
+/// Up to two downstream cells of a cell and the fraction of its flow each
+/// receives.
+pub type Receivers = [Option<(usize, f32)>; 2];
+
+/// D8 flow direction (steepest descent to one of the 8 neighbors), with
+/// neighbors from the grid's topology and step lengths from its metric.
+/// Cells are codes into `D8_OFFSETS`, or `NO_FLOW`.
+pub fn d8(elev: &Grid<f32>) -> Grid<u8> {
+    let (w, h) = (elev.w, elev.h);
+    let mut dirs = Grid::from_vec(w, h, scratch::take(w * h, 0)).with_topology(elev.topology).with_metric(elev.metric);
+    if w == 0 {
+        return dirs;
+    }
+    let flat_dist: [f32; 8] = D8_OFFSETS.map(|(dx, dy)| if dx != 0 && dy != 0 { std::f32::consts::SQRT_2 } else { 1.0 });
+    let metric = elev.metric;
+
+    dirs.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+        // Step lengths shrink E-W toward the poles under a spherical metric
+        let dist = if metric == Metric::Flat {
+            flat_dist
+        } else {
+            D8_OFFSETS.map(|(dx, dy)| metric.step_len(dx, dy, y, h))
+        };
+        for (x, out) in row.iter_mut().enumerate() {
+            let e = elev.get(x, y);
+            let mut best_dir = NO_FLOW;
+            let mut best_slope = 0.0f32;
+            for (d, &(dx, dy)) in D8_OFFSETS.iter().enumerate() {
+                let Some((nx, ny)) = elev.wrap(x as i32 + dx, y as i32 + dy) else {
+                    continue;
+                };
+                let slope = (e - elev.get(nx, ny)) / dist[d];
+                if slope > best_slope {
+                    best_slope = slope;
+                    best_dir = d as u8;
+                }
+            }
+            *out = best_dir;
+        }
+    });
+
+    dirs
+}
+
+/// Receiver of each cell under D8 directions.
+pub fn d8_receivers(dirs: &Grid<u8>) -> impl Fn(usize) -> Receivers + Sync + '_ {
+    move |i| {
+        let d = dirs.data[i];
+        if d >= 8 {
+            return [None, None];
+        }
+        let (dx, dy) = D8_OFFSETS[d as usize];
+        let to = dirs.wrap((i % dirs.w) as i32 + dx, (i / dirs.w) as i32 + dy);
+        [to.map(|(x, y)| (y * dirs.w + x, 1.0)), None]
+    }
+}
+
+/// Neighbor offsets in counterclockwise angle order from east (north is
+/// up, so -y): E, NE, N, NW, W, SW, S, SE.
+const ANGLE_OFFSETS: [(i32, i32); 8] = [(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// D-infinity flow angle (Tarboton 1997): steepest descent over the eight
+/// triangular facets around each cell, as radians counterclockwise from
+/// east, or `NO_ANGLE`. Flow then splits between the two neighbors that
+/// bracket the angle, which avoids D8's grid-aligned parallel channels.
+pub fn dinf(elev: &Grid<f32>) -> Grid<f32> {
+    let (w, h) = (elev.w, elev.h);
+    let mut angles = Grid::new(w, h).with_topology(elev.topology).with_metric(elev.metric);
+    if w == 0 {
+        return angles;
+    }
+    angles.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+        let sx = elev.metric.x_scale(y, h);
+        for (x, out) in row.iter_mut().enumerate() {
+            let e0 = elev.get(x, y);
+            let at = |k: usize| {
+                let (dx, dy) = ANGLE_OFFSETS[k % 8];
+                elev.wrap(x as i32 + dx, y as i32 + dy).map(|(nx, ny)| elev.get(nx, ny))
+            };
+            let mut best = (0.0f32, NO_ANGLE);
+            for facet in 0..8 {
+                // Facet between a cardinal neighbor and the adjacent diagonal
+                let (card, diag, sign) = if facet % 2 == 0 { (facet, facet + 1, 1.0) } else { (facet + 1, facet, -1.0) };
+                let (Some(e1), Some(e2)) = (at(card), at(diag)) else {
+                    continue;
+                };
+                // Cardinal step and the step across from it to the diagonal
+                let (d1, d2) = if card % 4 == 0 { (sx, 1.0) } else { (1.0, sx) };
+                let (s1, s2) = ((e0 - e1) / d1, (e1 - e2) / d2);
+                let max_r = fmath::atan(d2 / d1);
+                let (mut r, mut s) = (fmath::atan2(s2, s1), fmath::hypot(s1, s2));
+                if r < 0.0 {
+                    (r, s) = (0.0, s1);
+                } else if r > max_r {
+                    (r, s) = (max_r, (e0 - e2) / fmath::hypot(d1, d2));
+                }
+                if s > best.0 {
+                    let base = (card % 8) as f32 * FRAC_PI_4;
+                    best = (s, (base + sign * r).rem_euclid(TAU));
+                }
+            }
+            *out = best.1;
+        }
+    });
+    angles
+}
+
+/// Receivers of each cell under D-infinity angles: the two neighbors
+/// bracketing the angle, weighted by angular proximity.
+pub fn dinf_receivers(angles: &Grid<f32>) -> impl Fn(usize) -> Receivers + Sync + '_ {
+    move |i| {
+        let a = angles.data[i];
+        if a < 0.0 {
+            return [None, None];
+        }
+        let sector = ((a / FRAC_PI_4) as usize).min(7);
+        let frac = (a - sector as f32 * FRAC_PI_4) / FRAC_PI_4;
+        let (x, y) = ((i % angles.w) as i32, (i / angles.w) as i32);
+        let to = |k: usize, share: f32| {
+            let (dx, dy) = ANGLE_OFFSETS[k % 8];
+            let cell = angles.wrap(x + dx, y + dy)?;
+            (share > 0.0).then_some((cell.1 * angles.w + cell.0, share))
+        };
+        [to(sector, 1.0 - frac), to(sector + 1, frac)]
+    }
+}
+
+/// Cells ordered so every cell comes before all of its receivers
+/// (upstream first), by repeatedly taking cells with no unprocessed
+/// donors. Cells caught in a cycle are left out.
+pub fn topological_order(n: usize, receivers: impl Fn(usize) -> Receivers + Sync) -> Vec<u32> {
+    let mut donors = scratch::take(n, 0u8);
+    for i in 0..n {
+        for (r, _) in receivers(i).into_iter().flatten() {
+            donors[r] = donors[r].saturating_add(1);
+        }
+    }
+    let mut ready: Vec<u32> = (0..n as u32).filter(|&i| donors[i as usize] == 0).collect();
+    let mut order = Vec::with_capacity(n);
+    while let Some(i) = ready.pop() {
+        order.push(i);
+        for (r, _) in receivers(i as usize).into_iter().flatten() {
+            donors[r] -= 1;
+            if donors[r] == 0 {
+                ready.push(r as u32);
+            }
+        }
+    }
+    scratch::give(donors);
+    order
+}
+
+/// Accumulate `weight(i)` over `n` cells downstream: each cell's total
+/// (its own weight plus everything received) is passed to its receivers
+/// in proportion. `order` must be topological (see `topological_order`).
+/// With a weight of 1 this is drainage area in cells; with rainfall it is
+/// discharge. `progress` receives the fraction of `order` processed; if
+/// `cancel` fires, the pass stops and totals are left incomplete.
+pub fn accumulate(
+    n: usize,
+    order: &[u32],
+    receivers: impl Fn(usize) -> Receivers,
+    weight: impl Fn(usize) -> f32 + Sync,
+    progress: &dyn Fn(f32),
+    cancel: &CancelToken,
+) -> Vec<f32> {
+    let mut acc = scratch::take(n, 0.0f32);
+    acc.par_iter_mut().enumerate().for_each(|(i, a)| *a = weight(i));
+    let report_every = (order.len() / 100).max(1);
+    for (k, &i) in order.iter().enumerate() {
+        if k % report_every == 0 {
+            if cancel.is_cancelled() {
+                break;
+            }
+            progress(k as f32 / order.len() as f32);
+        }
+        let total = acc[i as usize];
+        for (r, share) in receivers(i as usize).into_iter().flatten() {
+            acc[r] += total * share;
+        }
+    }
+    acc
+}
+
+/// D8 flow accumulation of a per-cell weight (e.g. precipitation),
+/// reporting through `progress` and stopping on `cancel` as in `accumulate`.
+pub fn accumulate_d8(
+    dirs: &Grid<u8>,
+    weight: impl Fn(usize) -> f32 + Sync,
+    progress: &dyn Fn(f32),
+    cancel: &CancelToken,
+) -> Grid<f32> {
+    let receivers = d8_receivers(dirs);
+    let order = topological_order(dirs.data.len(), &receivers);
+    let acc = accumulate(dirs.data.len(), &order, &receivers, weight, progress, cancel);
+    Grid::from_vec(dirs.w, dirs.h, acc).with_topology(dirs.topology).with_metric(dirs.metric)
+}
+
+/// D-infinity flow accumulation of a per-cell weight.
+pub fn accumulate_dinf(angles: &Grid<f32>, weight: impl Fn(usize) -> f32 + Sync) -> Grid<f32> {
+    let receivers = dinf_receivers(angles);
+    let order = topological_order(angles.data.len(), &receivers);
+    let acc = accumulate(angles.data.len(), &order, &receivers, weight, &|_| {}, &CancelToken::new());
+    Grid::from_vec(angles.w, angles.h, acc).with_topology(angles.topology).with_metric(angles.metric)
+}
@@ -0,0 +1,95 @@
+use super::{Grid, Rect};
+
+/// Marks cells outside every component in a label grid.
+pub const NO_COMPONENT: u32 = u32::MAX;
+
+/// One connected region found by `label_components`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Component {
+    /// Number of cells.
+    pub cells: usize,
+    /// Smallest window holding every cell. On grids that wrap in x it
+    /// may run past the E-W seam (see `Rect`).
+    pub bounds: Rect,
+    /// Mean cell position, with cell `(i, j)` centered on `(i, j)`. Taken
+    /// inside `bounds`, so components across the seam average correctly;
+    /// x is folded back into `0..w`.
+    pub centroid: (f32, f32),
+}
+
+/// Label the connected regions of cells where `include` holds, following
+/// the grid's topology: 8-connected with `diagonal`, else 4-connected.
+/// Returns each cell's component index (`NO_COMPONENT` where `include`
+/// is false) and the components, numbered in row-major order of their
+/// first cell.
+pub fn label_components<T: Copy + Default>(
+    grid: &Grid<T>,
+    diagonal: bool,
+    include: impl Fn(T) -> bool,
+) -> (Grid<u32>, Vec<Component>) {
+    let (w, h) = (grid.w, grid.h);
+    let mut labels = Grid::from_vec(w, h, vec![NO_COMPONENT; w * h])
+        .with_topology(grid.topology)
+        .with_metric(grid.metric);
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+    let mut cells = Vec::new();
+
+    for start in 0..w * h {
+        if labels.data[start] != NO_COMPONENT || !include(grid.data[start]) {
+            continue;
+        }
+        let id = components.len() as u32;
+        labels.data[start] = id;
+        stack.push((start % w, start / w));
+        cells.clear();
+        while let Some((x, y)) = stack.pop() {
+            cells.push((x, y));
+            let mut visit = |(nx, ny): (usize, usize)| {
+                let i = ny * w + nx;
+                if labels.data[i] == NO_COMPONENT && include(grid.data[i]) {
+                    labels.data[i] = id;
+                    stack.push((nx, ny));
+                }
+            };
+            if diagonal {
+                grid.neighbors8(x, y).for_each(&mut visit);
+            } else {
+                grid.neighbors4(x, y).for_each(&mut visit);
+            }
+        }
+        components.push(summarize(&cells, w, grid.topology.wraps_x()));
+    }
+    (labels, components)
+}
+
+fn summarize(cells: &[(usize, usize)], w: usize, wraps_x: bool) -> Component {
+    let y0 = cells.iter().map(|c| c.1).min().unwrap();
+    let y1 = cells.iter().map(|c| c.1).max().unwrap();
+
+    // Columns in use; on a wrapping grid the window starts just after the
+    // widest run of empty columns, so it never spans that gap
+    let mut cols: Vec<usize> = cells.iter().map(|c| c.0).collect();
+    cols.sort_unstable();
+    cols.dedup();
+    let (mut x0, mut x1) = (cols[0], cols[cols.len() - 1]);
+    if wraps_x {
+        let mut widest = w - 1 - x1 + x0; // the gap across the seam
+        for pair in cols.windows(2) {
+            let gap = pair[1] - pair[0] - 1;
+            if gap > widest {
+                widest = gap;
+                (x0, x1) = (pair[1], pair[0] + w);
+            }
+        }
+    }
+
+    let n = cells.len() as f64;
+    let cx = cells.iter().map(|c| ((c.0 + w - x0) % w) as f64).sum::<f64>() / n + x0 as f64;
+    let cy = cells.iter().map(|c| c.1 as f64).sum::<f64>() / n;
+    Component {
+        cells: cells.len(),
+        bounds: Rect { x: x0, y: y0, w: x1 - x0 + 1, h: y1 - y0 + 1 },
+        centroid: ((cx % w as f64) as f32, cy as f32),
+    }
+}
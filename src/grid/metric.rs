@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fmath;
+
+/// How far apart cells are. `Flat` treats every cell as a unit square;
+/// `Spherical` reads the grid as an equirectangular globe, where a cell's
+/// E-W extent shrinks with the cosine of its latitude.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Metric {
+    #[default]
+    Flat,
+    Spherical,
+}
+
+/// Floor on the E-W scale so polar rows keep a usable (if very wide)
+/// footprint instead of collapsing to zero width.
+const MIN_X_SCALE: f32 = 1e-3;
+
+impl Metric {
+    /// E-W size of a cell in row `y` relative to its N-S size.
+    #[inline]
+    pub fn x_scale(self, y: usize, h: usize) -> f32 {
+        match self {
+            Metric::Flat => 1.0,
+            Metric::Spherical => {
+                let lat = (0.5 - (y as f32 + 0.5) / h as f32) * std::f32::consts::PI;
+                fmath::cos(lat).max(MIN_X_SCALE)
+            }
+        }
+    }
+
+    /// Length of a step of (dx, dy) cells taken from row `y`, in N-S cell
+    /// units.
+    #[inline]
+    pub fn step_len(self, dx: i32, dy: i32, y: usize, h: usize) -> f32 {
+        let sx = dx as f32 * self.x_scale(y, h);
+        (sx * sx + (dy * dy) as f32).sqrt()
+    }
+}
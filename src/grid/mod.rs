@@ -0,0 +1,259 @@
+mod blur;
+mod convolve;
+mod label;
+pub mod distance;
+pub mod flow;
+mod metric;
+mod quant;
+mod resample;
+mod stats;
+mod topology;
+
+use serde::{Deserialize, Serialize};
+
+use crate::par::*;
+
+pub use blur::gaussian_kernel;
+pub use convolve::Edge;
+pub use label::{Component, NO_COMPONENT, label_components};
+pub use metric::Metric;
+pub use quant::QuantGrid;
+pub use resample::Filter;
+pub use stats::{Histogram, Stats};
+pub use topology::Topology;
+
+/// Axis-aligned window in cell coordinates. `x` may start anywhere (and
+/// the window may run past the E-W seam) on grids that wrap in x.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+}
+
+/// Row-major flat grid. No per-cell objects, f32 friendly.
+/// Edge connectivity comes from `topology` (cylindrical by default),
+/// cell spacing from `metric` (flat by default).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Grid<T> {
+    pub data: Vec<T>,
+    pub w: usize,
+    pub h: usize,
+    pub topology: Topology,
+    pub metric: Metric,
+}
+
+impl<T: Copy + Default> Grid<T> {
+    pub fn new(w: usize, h: usize) -> Self {
+        Self {
+            data: vec![T::default(); w * h],
+            w,
+            h,
+            topology: Topology::default(),
+            metric: Metric::default(),
+        }
+    }
+
+    /// Wrap existing row-major data (length must be `w * h`).
+    pub fn from_vec(w: usize, h: usize, data: Vec<T>) -> Self {
+        assert_eq!(data.len(), w * h, "grid data length mismatch");
+        Self { data, w, h, topology: Topology::default(), metric: Metric::default() }
+    }
+
+    /// Same grid with a different topology.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Same grid with a different metric.
+    pub fn with_metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Copy out a window. Columns wrap when the topology wraps in x; the
+    /// window is otherwise clipped to the grid. The result has hard edges
+    /// (`Topology::Plane`) and a flat metric, since it no longer spans the
+    /// globe.
+    pub fn crop(&self, rect: Rect) -> Grid<T> {
+        let h = rect.h.min(self.h.saturating_sub(rect.y));
+        let w = if self.topology.wraps_x() {
+            rect.w.min(self.w)
+        } else {
+            rect.w.min(self.w.saturating_sub(rect.x))
+        };
+        let mut data = Vec::with_capacity(w * h);
+        for y in rect.y..rect.y + h {
+            let row = &self.data[y * self.w..(y + 1) * self.w];
+            data.extend((rect.x..rect.x + w).map(|x| row[x % self.w]));
+        }
+        Grid::from_vec(w, h, data).with_topology(Topology::Plane)
+    }
+
+    /// Squared distance between two cell positions, following the
+    /// topology's wraps and scaling E-W offsets by the metric at their
+    /// mean latitude.
+    #[inline]
+    pub fn dist_sq(&self, ax: f32, ay: f32, bx: f32, by: f32) -> f32 {
+        let (dx, dy) = self.topology.delta(ax, ay, bx, by, self.w, self.h);
+        if self.metric == Metric::Flat {
+            return dx * dx + dy * dy;
+        }
+        let row = ((ay + dy * 0.5).round().max(0.0) as usize).min(self.h.saturating_sub(1));
+        let dx = dx * self.metric.x_scale(row, self.h);
+        dx * dx + dy * dy
+    }
+
+    /// Map a possibly out-of-range cell onto the grid (see `Topology::wrap`).
+    #[inline]
+    pub fn wrap(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        self.topology.wrap(x, y, self.w, self.h)
+    }
+
+    /// 4-connected neighbors under the grid's topology.
+    #[inline]
+    pub fn neighbors4(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + use<T> {
+        self.topology.neighbors4(x, y, self.w, self.h)
+    }
+
+    /// 8-connected neighbors under the grid's topology.
+    #[inline]
+    pub fn neighbors8(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + use<T> {
+        self.topology.neighbors8(x, y, self.w, self.h)
+    }
+
+    #[inline]
+    pub fn idx(&self, x: usize, y: usize) -> usize {
+        debug_assert!(x < self.w && y < self.h);
+        y * self.w + x
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> T {
+        self.data[self.idx(x, y)]
+    }
+
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, v: T) {
+        let i = self.idx(x, y);
+        self.data[i] = v;
+    }
+
+    /// Overwrite every cell with `f(x, y)`.
+    pub fn fill_with(&mut self, f: impl Fn(usize, usize) -> T) {
+        let w = self.w;
+        for (i, v) in self.data.iter_mut().enumerate() {
+            *v = f(i % w, i / w);
+        }
+    }
+
+    /// New grid of the same size with `f` applied to every cell.
+    pub fn map<U: Copy + Default>(&self, f: impl Fn(T) -> U) -> Grid<U> {
+        Grid { data: self.data.iter().map(|&v| f(v)).collect(), w: self.w, h: self.h, topology: self.topology, metric: self.metric }
+    }
+
+    /// Cellwise combination of two same-sized grids.
+    pub fn zip_with<U: Copy + Default, V: Copy + Default>(
+        &self,
+        other: &Grid<U>,
+        f: impl Fn(T, U) -> V,
+    ) -> Grid<V> {
+        assert!(self.w == other.w && self.h == other.h, "grid size mismatch");
+        let data = self.data.iter().zip(&other.data).map(|(&a, &b)| f(a, b)).collect();
+        Grid { data, w: self.w, h: self.h, topology: self.topology, metric: self.metric }
+    }
+}
+
+/// Row-parallel versions of the combinators (rayon, or sequential without
+/// the `parallel` feature).
+impl<T: Copy + Default + Send + Sync> Grid<T> {
+    /// New `w x h` grid with `f(x, y)` in every cell, filled in parallel.
+    pub fn par_from_fn(w: usize, h: usize, f: impl Fn(usize, usize) -> T + Sync) -> Self {
+        let mut grid = Self::new(w, h);
+        grid.par_fill_with(f);
+        grid
+    }
+
+    /// Overwrite every cell with `f(x, y)`, one row per task.
+    pub fn par_fill_with(&mut self, f: impl Fn(usize, usize) -> T + Sync) {
+        let w = self.w;
+        if w == 0 {
+            return;
+        }
+        self.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+            for (x, v) in row.iter_mut().enumerate() {
+                *v = f(x, y);
+            }
+        });
+    }
+
+    /// Overwrite each row with `f(y, row)`, one row per task; for stages
+    /// that evaluate a whole row at once.
+    pub fn par_fill_rows(&mut self, f: impl Fn(usize, &mut [T]) + Sync) {
+        let w = self.w;
+        if w == 0 {
+            return;
+        }
+        self.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| f(y, row));
+    }
+
+    /// Parallel `map`.
+    pub fn par_map<U: Copy + Default + Send>(&self, f: impl Fn(T) -> U + Sync) -> Grid<U> {
+        Grid { data: self.data.par_iter().map(|&v| f(v)).collect(), w: self.w, h: self.h, topology: self.topology, metric: self.metric }
+    }
+
+    /// Parallel `zip_with`.
+    pub fn par_zip_with<U: Copy + Default + Sync, V: Copy + Default + Send>(
+        &self,
+        other: &Grid<U>,
+        f: impl Fn(T, U) -> V + Sync,
+    ) -> Grid<V> {
+        assert!(self.w == other.w && self.h == other.h, "grid size mismatch");
+        let data = self.data.par_iter().zip(&other.data).map(|(&a, &b)| f(a, b)).collect();
+        Grid { data, w: self.w, h: self.h, topology: self.topology, metric: self.metric }
+    }
+}
+
+/// Wrap x-coordinate for E-W wrapping. y is clamped (polar boundary).
+/// Returns None if y is out of bounds.
+#[inline]
+pub fn wrap_xy(x: i32, y: i32, w: usize, h: usize) -> Option<(usize, usize)> {
+    Topology::Cylinder.wrap(x, y, w, h)
+}
+
+/// 4-connected neighbors with E-W wrapping.
+pub fn neighbors4_wrap(x: usize, y: usize, w: usize, h: usize) -> impl Iterator<Item = (usize, usize)> {
+    Topology::Cylinder.neighbors4(x, y, w, h)
+}
+
+/// 8-connected neighbors with E-W wrapping.
+pub fn neighbors8_wrap(x: usize, y: usize, w: usize, h: usize) -> impl Iterator<Item = (usize, usize)> {
+    Topology::Cylinder.neighbors8(x, y, w, h)
+}
+
+/// Geographic coordinates (lat, lon in degrees) of a cell center.
+/// Equirectangular: row 0 is the north pole edge, column 0 is lon -180.
+#[inline]
+pub fn cell_latlon(x: usize, y: usize, w: usize, h: usize) -> (f32, f32) {
+    let lat = 90.0 - (y as f32 + 0.5) / h as f32 * 180.0;
+    let lon = (x as f32 + 0.5) / w as f32 * 360.0 - 180.0;
+    (lat, lon)
+}
+
+/// Fractional cell position of a geographic position, with cell centers
+/// on whole numbers (the coordinates `Grid::sample` takes).
+#[inline]
+pub fn latlon_pos(lat: f32, lon: f32, w: usize, h: usize) -> (f32, f32) {
+    ((lon + 180.0) / 360.0 * w as f32 - 0.5, (90.0 - lat) / 180.0 * h as f32 - 0.5)
+}
+
+/// Cell containing a geographic position (inverse of `cell_latlon`).
+/// Longitude wraps; latitude clamps to the first/last row.
+#[inline]
+pub fn latlon_cell(lat: f32, lon: f32, w: usize, h: usize) -> (usize, usize) {
+    let x = (((lon + 180.0) / 360.0 * w as f32).floor() as i64).rem_euclid(w as i64) as usize;
+    let y = (((90.0 - lat) / 180.0 * h as f32).floor() as i64).clamp(0, h as i64 - 1) as usize;
+    (x, y)
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Grid, Rect};
+
+/// f32 grid stored as u16 codes with a linear scale and offset, at half
+/// the memory of `Grid<f32>`. Codes span the source's own min..max, so the
+/// round-trip error is at most half a step: `(max - min) / 65535 / 2`
+/// (about 0.001 C for temperature, 0.05 mm for precipitation). Meant for
+/// finished layers that are only read back, not for stage intermediates.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuantGrid {
+    pub codes: Grid<u16>,
+    pub scale: f32,
+    pub offset: f32,
+}
+
+impl QuantGrid {
+    /// Quantize a grid over its finite value range. Topology and metric are
+    /// kept on the codes.
+    pub fn encode(grid: &Grid<f32>) -> Self {
+        let (lo, hi) = grid
+            .data
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        let (offset, scale) = if lo <= hi { (lo, (hi - lo) / u16::MAX as f32) } else { (0.0, 0.0) };
+        let codes = grid.par_map(|v| {
+            if scale > 0.0 { ((v - offset) / scale).round().clamp(0.0, u16::MAX as f32) as u16 } else { 0 }
+        });
+        Self { codes, scale, offset }
+    }
+
+    #[inline]
+    pub fn w(&self) -> usize {
+        self.codes.w
+    }
+
+    #[inline]
+    pub fn h(&self) -> usize {
+        self.codes.h
+    }
+
+    /// Largest difference between a decoded value and the original.
+    pub fn max_error(&self) -> f32 {
+        self.scale * 0.5
+    }
+
+    /// Decoded value at a cell.
+    #[inline]
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.codes.get(x, y) as f32 * self.scale + self.offset
+    }
+
+    /// Bilinear sample at a fractional cell position (see `Grid::sample`).
+    pub fn sample(&self, fx: f32, fy: f32) -> f32 {
+        self.codes.sample(fx, fy) * self.scale + self.offset
+    }
+
+    /// Decode to a full f32 grid (same topology and metric).
+    pub fn decode(&self) -> Grid<f32> {
+        let (scale, offset) = (self.scale, self.offset);
+        self.codes.par_map(|c| c as f32 * scale + offset)
+    }
+
+    /// Window of the codes with the same scale and offset (see `Grid::crop`).
+    pub fn crop(&self, rect: Rect) -> Self {
+        Self { codes: self.codes.crop(rect), scale: self.scale, offset: self.offset }
+    }
+
+    /// Overwrite the cells of `rect` (columns wrap) with `src`, a window
+    /// the size of `rect` as `crop` returns. Cells outside keep their
+    /// codes unless a new value falls outside the current range, in which
+    /// case the whole grid is requantized.
+    pub fn write_window(&mut self, src: &Grid<f32>, rect: Rect) {
+        let (w, h) = (self.w(), self.h());
+        let hi = self.offset + self.scale * u16::MAX as f32;
+        let rows = rect.y..(rect.y + rect.h).min(h);
+        let cells = || {
+            rows.clone()
+                .flat_map(|y| (rect.x..rect.x + rect.w.min(w)).map(move |x| (x % w, y, src.get(x - rect.x, y - rect.y))))
+        };
+        if cells().all(|(_, _, v)| (self.offset..=hi).contains(&v)) {
+            for (x, y, v) in cells() {
+                let code = if self.scale > 0.0 { ((v - self.offset) / self.scale).round() } else { 0.0 };
+                self.codes.set(x, y, code.clamp(0.0, u16::MAX as f32) as u16);
+            }
+            return;
+        }
+        let mut values = self.decode();
+        for (x, y, v) in cells() {
+            values.set(x, y, v);
+        }
+        *self = Self::encode(&values);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(w: usize, h: usize) -> Grid<f32> {
+        Grid::par_from_fn(w, h, |x, y| (x as f32 * 0.37).sin() * 120.0 + y as f32 * 3.5 - 40.0)
+    }
+
+    #[test]
+    fn round_trip_is_within_half_a_step() {
+        let grid = ramp(64, 32);
+        let quant = QuantGrid::encode(&grid);
+        let decoded = quant.decode();
+        for (a, b) in grid.data.iter().zip(&decoded.data) {
+            // Plus f32 rounding in the scale and offset
+            assert!((a - b).abs() <= quant.max_error() * 1.01, "{a} vs {b}");
+        }
+        assert_eq!(quant.get(5, 7), decoded.get(5, 7));
+    }
+
+    #[test]
+    fn constant_grid_round_trips_exactly() {
+        let quant = QuantGrid::encode(&Grid::from_vec(4, 4, vec![12.5; 16]));
+        assert!(quant.decode().data.iter().all(|&v| v == 12.5));
+    }
+
+    #[test]
+    fn write_window_only_touches_the_window() {
+        let grid = ramp(32, 16);
+        let mut quant = QuantGrid::encode(&grid);
+        let before = quant.decode();
+        // Wraps past the east edge
+        let rect = Rect { x: 28, y: 3, w: 8, h: 4 };
+        let window = Grid::from_vec(8, 4, vec![0.0; 32]);
+        quant.write_window(&window, rect);
+        let after = quant.decode();
+        for y in 0..16 {
+            for x in 0..32 {
+                let inside = (3..7).contains(&y) && !(4..28).contains(&x);
+                if inside {
+                    assert!(after.get(x, y).abs() <= quant.max_error(), "({x}, {y}) = {}", after.get(x, y));
+                } else {
+                    assert_eq!(after.get(x, y), before.get(x, y), "({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn write_window_requantizes_past_the_range() {
+        let mut quant = QuantGrid::encode(&ramp(16, 8));
+        let before = quant.decode();
+        quant.write_window(&Grid::from_vec(1, 1, vec![1.0e4]), Rect { x: 2, y: 2, w: 1, h: 1 });
+        assert!((quant.get(2, 2) - 1.0e4).abs() <= quant.max_error());
+        assert!((quant.get(9, 5) - before.get(9, 5)).abs() <= quant.max_error() + 1e-3);
+    }
+}
@@ -0,0 +1,200 @@
+use super::Grid;
+use crate::par::*;
+use crate::scratch;
+
+/// Reconstruction kernel for `Grid::resample`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    /// Source cell under the target cell center.
+    Nearest,
+    /// Linear between the four surrounding cell centers.
+    Bilinear,
+    /// Catmull-Rom over the surrounding 4x4 cells (may overshoot).
+    Bicubic,
+    /// Coverage-weighted mean of the source cells under the target cell.
+    Area,
+    /// Largest source cell under the target cell (keeps thin features such
+    /// as river flow when shrinking).
+    Max,
+}
+
+/// Catmull-Rom weights for the taps at -1, 0, 1, 2 around a sample `t`
+/// (0..1) past tap 0.
+#[inline]
+fn catmull_rom(t: f32) -> [f32; 4] {
+    let (t2, t3) = (t * t, t * t * t);
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+/// Fold an out-of-range index back onto [0, n): wrap or clamp.
+#[inline]
+fn fold(i: i64, n: usize, wraps: bool) -> usize {
+    if wraps { i.rem_euclid(n as i64) as usize } else { i.clamp(0, n as i64 - 1) as usize }
+}
+
+/// Source taps (unwrapped index, weight) for each target index along one
+/// axis, mapping cell centers: `s = (d + 0.5) * src / dst - 0.5`.
+fn axis_taps(src: usize, dst: usize, filter: Filter) -> Vec<Vec<(i64, f32)>> {
+    let ratio = src as f64 / dst as f64;
+    (0..dst)
+        .map(|d| {
+            let center = (d as f64 + 0.5) * ratio - 0.5;
+            match filter {
+                Filter::Nearest => vec![((center + 0.5).floor() as i64, 1.0)],
+                Filter::Bilinear => {
+                    let i = center.floor();
+                    let f = (center - i) as f32;
+                    vec![(i as i64, 1.0 - f), (i as i64 + 1, f)]
+                }
+                Filter::Bicubic => {
+                    let i = center.floor();
+                    let w = catmull_rom((center - i) as f32);
+                    (0..4).map(|k| (i as i64 - 1 + k, w[k as usize])).collect()
+                }
+                Filter::Area | Filter::Max => {
+                    let (lo, hi) = (d as f64 * ratio, (d + 1) as f64 * ratio);
+                    (lo.floor() as i64..hi.ceil() as i64)
+                        .map(|i| {
+                            let overlap = (hi.min(i as f64 + 1.0) - lo.max(i as f64)).max(0.0);
+                            (i, (overlap / (hi - lo)) as f32)
+                        })
+                        .filter(|&(_, w)| w > 0.0)
+                        .collect()
+                }
+            }
+        })
+        .collect()
+}
+
+impl Grid<f32> {
+    /// Resample to `w x h` with the given filter. Taps past an edge wrap
+    /// or clamp according to the grid's topology; the result keeps the
+    /// topology and metric.
+    pub fn resample(&self, w: usize, h: usize, filter: Filter) -> Grid<f32> {
+        let mut dst = Grid::from_vec(w, h, scratch::take(w * h, 0.0)).with_topology(self.topology).with_metric(self.metric);
+        if self.w == 0 || self.h == 0 || w == 0 {
+            return dst;
+        }
+        let (wrap_x, wrap_y) = (self.topology.wraps_x(), self.topology.wraps_y());
+        let x_taps: Vec<Vec<(usize, f32)>> = axis_taps(self.w, w, filter)
+            .into_iter()
+            .map(|t| t.into_iter().map(|(i, wt)| (fold(i, self.w, wrap_x), wt)).collect())
+            .collect();
+        let y_taps: Vec<Vec<(usize, f32)>> = axis_taps(self.h, h, filter)
+            .into_iter()
+            .map(|t| t.into_iter().map(|(i, wt)| (fold(i, self.h, wrap_y), wt)).collect())
+            .collect();
+
+        #[cfg(feature = "gpu")]
+        if let Some(gpu) = crate::gpu::gather(self, &x_taps, &y_taps, filter == Filter::Max) {
+            return gpu;
+        }
+
+        dst.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+            let ys = &y_taps[y];
+            for (out, xs) in row.iter_mut().zip(&x_taps) {
+                *out = if filter == Filter::Max {
+                    ys.iter()
+                        .flat_map(|&(sy, _)| xs.iter().map(move |&(sx, _)| (sx, sy)))
+                        .map(|(sx, sy)| self.get(sx, sy))
+                        .fold(f32::NEG_INFINITY, f32::max)
+                } else {
+                    let mut sum = 0.0f32;
+                    for &(sy, wy) in ys {
+                        let mut line = 0.0f32;
+                        for &(sx, wx) in xs {
+                            line += self.get(sx, sy) * wx;
+                        }
+                        sum += line * wy;
+                    }
+                    sum
+                };
+            }
+        });
+
+        dst
+    }
+}
+
+/// Point sampling between cell centers. Positions are in cells with cell
+/// `(i, j)` centered on `(i as f32, j as f32)`; samples past an edge wrap
+/// or clamp per the grid's topology.
+impl<T: Copy + Default + Into<f32>> Grid<T> {
+    /// Bilinear sample at fractional cell position (fx, fy).
+    pub fn sample(&self, fx: f32, fy: f32) -> f32 {
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - x0, fy - y0);
+        let xs = [0, 1].map(|k| fold(x0 as i64 + k, self.w, self.topology.wraps_x()));
+        let ys = [0, 1].map(|k| fold(y0 as i64 + k, self.h, self.topology.wraps_y()));
+        let at = |x: usize, y: usize| -> f32 { self.get(x, y).into() };
+        let top = at(xs[0], ys[0]) + (at(xs[1], ys[0]) - at(xs[0], ys[0])) * tx;
+        let bot = at(xs[0], ys[1]) + (at(xs[1], ys[1]) - at(xs[0], ys[1])) * tx;
+        top + (bot - top) * ty
+    }
+
+    /// Catmull-Rom bicubic sample at (fx, fy): smoother than `sample`, but
+    /// may overshoot the surrounding values.
+    pub fn sample_bicubic(&self, fx: f32, fy: f32) -> f32 {
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (wx, wy) = (catmull_rom(fx - x0), catmull_rom(fy - y0));
+        let xs = [-1, 0, 1, 2].map(|k| fold(x0 as i64 + k, self.w, self.topology.wraps_x()));
+        let ys = [-1, 0, 1, 2].map(|k| fold(y0 as i64 + k, self.h, self.topology.wraps_y()));
+        let mut sum = 0.0;
+        for (&y, &wy) in ys.iter().zip(&wy) {
+            let line: f32 = xs.iter().zip(&wx).map(|(&x, &wx)| self.get(x, y).into() * wx).sum();
+            sum += line * wy;
+        }
+        sum
+    }
+}
+
+impl<T: Copy + Default + PartialEq + Send + Sync> Grid<T> {
+    /// Resample class or id data to `w x h`: each target cell takes the
+    /// value covering most of it (the `Area` footprint), ties going to the
+    /// value met first in row-major order. Keeps topology and metric.
+    pub fn resample_majority(&self, w: usize, h: usize) -> Grid<T> {
+        let mut dst = Grid::new(w, h).with_topology(self.topology).with_metric(self.metric);
+        if self.w == 0 || self.h == 0 || w == 0 {
+            return dst;
+        }
+        let (wrap_x, wrap_y) = (self.topology.wraps_x(), self.topology.wraps_y());
+        let x_taps: Vec<Vec<(usize, f32)>> = axis_taps(self.w, w, Filter::Area)
+            .into_iter()
+            .map(|t| t.into_iter().map(|(i, wt)| (fold(i, self.w, wrap_x), wt)).collect())
+            .collect();
+        let y_taps: Vec<Vec<(usize, f32)>> = axis_taps(self.h, h, Filter::Area)
+            .into_iter()
+            .map(|t| t.into_iter().map(|(i, wt)| (fold(i, self.h, wrap_y), wt)).collect())
+            .collect();
+
+        dst.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+            let mut votes: Vec<(T, f32)> = Vec::new();
+            for (out, xs) in row.iter_mut().zip(&x_taps) {
+                votes.clear();
+                for &(sy, wy) in &y_taps[y] {
+                    for &(sx, wx) in xs {
+                        let v = self.get(sx, sy);
+                        match votes.iter_mut().find(|(u, _)| *u == v) {
+                            Some((_, n)) => *n += wx * wy,
+                            None => votes.push((v, wx * wy)),
+                        }
+                    }
+                }
+                let mut best = votes[0];
+                for &vote in &votes[1..] {
+                    if vote.1 > best.1 {
+                        best = vote;
+                    }
+                }
+                *out = best.0;
+            }
+        });
+
+        dst
+    }
+}
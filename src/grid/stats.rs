@@ -0,0 +1,136 @@
+use super::Grid;
+use crate::par::*;
+
+/// Summary of a grid's values. NaNs are skipped; an empty selection has
+/// `count == 0` and NaN min, max and mean.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+/// Value counts over `bins` equal-width bins spanning `min..=max`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub min: f32,
+    pub max: f32,
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn bin_width(&self) -> f32 {
+        (self.max - self.min) / self.counts.len().max(1) as f32
+    }
+
+    /// Bin holding `v` (values outside the range land in the end bins).
+    #[inline]
+    pub fn bin_of(&self, v: f32) -> usize {
+        let n = self.counts.len();
+        let width = self.bin_width();
+        if width <= 0.0 {
+            return 0;
+        }
+        (((v - self.min) / width) as usize).min(n.saturating_sub(1))
+    }
+
+    /// Approximate `q`-quantile (0..=1): the upper edge of the bin where the
+    /// running count reaches `q` of the total.
+    pub fn quantile(&self, q: f32) -> f32 {
+        let total: u64 = self.counts.iter().sum();
+        let target = (q.clamp(0.0, 1.0) as f64 * total as f64).ceil() as u64;
+        let mut seen = 0;
+        for (i, &c) in self.counts.iter().enumerate() {
+            seen += c;
+            if seen >= target.max(1) {
+                return self.min + (i + 1) as f32 * self.bin_width();
+            }
+        }
+        self.max
+    }
+}
+
+impl<T: Copy + Default + Into<f32> + Sync> Grid<T> {
+    /// Count, min, max and mean in one parallel pass.
+    pub fn stats(&self) -> Stats {
+        self.stats_where(|_| true)
+    }
+
+    /// `stats` over the cells whose flat index passes `keep` (e.g. land
+    /// cells of another grid: `|i| height.data[i] > 0.0`). Partial sums
+    /// cover fixed chunks and are added in order, so the mean does not
+    /// depend on how rayon splits the work.
+    pub fn stats_where(&self, keep: impl Fn(usize) -> bool + Sync) -> Stats {
+        const CHUNK: usize = 1 << 14;
+        let partials: Vec<(usize, f32, f32, f64)> = self
+            .data
+            .par_chunks(CHUNK)
+            .enumerate()
+            .map(|(c, chunk)| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| keep(c * CHUNK + i))
+                    .map(|(_, &v)| v.into())
+                    .filter(|v: &f32| !v.is_nan())
+                    .fold((0usize, f32::INFINITY, f32::NEG_INFINITY, 0.0f64), |(n, lo, hi, s), v| {
+                        (n + 1, lo.min(v), hi.max(v), s + v as f64)
+                    })
+            })
+            .collect();
+        let (count, min, max, sum) = partials
+            .into_iter()
+            .fold((0, f32::INFINITY, f32::NEG_INFINITY, 0.0), |a, b| (a.0 + b.0, a.1.min(b.1), a.2.max(b.2), a.3 + b.3));
+        if count == 0 {
+            return Stats { count, min: f32::NAN, max: f32::NAN, mean: f32::NAN };
+        }
+        Stats { count, min, max, mean: (sum / count as f64) as f32 }
+    }
+
+    /// Exact `q`-quantile (0..=1) of the values, by nearest rank: the value
+    /// at index `floor(q * n)` in sorted order. Selects rather than sorts.
+    /// None when there are no (non-NaN) values.
+    pub fn percentile(&self, q: f32) -> Option<f32> {
+        self.percentile_where(q, |_| true)
+    }
+
+    /// `percentile` over the values that pass `keep`.
+    pub fn percentile_where(&self, q: f32, keep: impl Fn(f32) -> bool + Sync) -> Option<f32> {
+        let mut v: Vec<f32> = self.data.par_iter().map(|&v| v.into()).filter(|&v| !v.is_nan() && keep(v)).collect();
+        if v.is_empty() {
+            return None;
+        }
+        let k = ((q.clamp(0.0, 1.0) as f64 * v.len() as f64) as usize).min(v.len() - 1);
+        let (_, p, _) = v.select_nth_unstable_by(k, f32::total_cmp);
+        Some(*p)
+    }
+
+    /// Histogram of the values over their own min..max.
+    pub fn histogram(&self, bins: usize) -> Histogram {
+        let s = self.stats();
+        let (min, max) = if s.count > 0 { (s.min, s.max) } else { (0.0, 0.0) };
+        let mut hist = Histogram { min, max, counts: vec![0; bins] };
+        if bins == 0 || s.count == 0 {
+            return hist;
+        }
+        const CHUNK: usize = 1 << 14;
+        let partials: Vec<Vec<u64>> = self
+            .data
+            .par_chunks(CHUNK)
+            .map(|chunk| {
+                let mut acc = vec![0u64; bins];
+                for v in chunk.iter().map(|&v| v.into()).filter(|v: &f32| !v.is_nan()) {
+                    acc[hist.bin_of(v)] += 1;
+                }
+                acc
+            })
+            .collect();
+        let counts = partials.into_iter().fold(vec![0u64; bins], |mut a, b| {
+            a.iter_mut().zip(&b).for_each(|(x, y)| *x += y);
+            a
+        });
+        hist.counts = counts;
+        hist
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+/// How a grid's edges connect. Neighbor, wrap and distance helpers consult
+/// this, so algorithms run unchanged on any of the shapes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    /// E-W wrap, N-S edges are hard (the equirectangular world map).
+    #[default]
+    Cylinder,
+    /// Both axes wrap.
+    Torus,
+    /// No wrapping; every edge is hard.
+    Plane,
+    /// Equirectangular sphere: E-W wrap, and stepping past a pole row
+    /// lands on the same row half a world away.
+    Sphere,
+}
+
+impl Topology {
+    /// Whether x wraps around.
+    #[inline]
+    pub fn wraps_x(self) -> bool {
+        !matches!(self, Topology::Plane)
+    }
+
+    /// Whether y wraps around.
+    #[inline]
+    pub fn wraps_y(self) -> bool {
+        matches!(self, Topology::Torus)
+    }
+
+    /// Map a possibly out-of-range cell onto the grid, or None if it falls
+    /// off a hard edge.
+    #[inline]
+    pub fn wrap(self, x: i32, y: i32, w: usize, h: usize) -> Option<(usize, usize)> {
+        let (wi, hi) = (w as i32, h as i32);
+        let (x, y) = match self {
+            Topology::Cylinder if y < 0 || y >= hi => return None,
+            Topology::Plane if x < 0 || x >= wi || y < 0 || y >= hi => return None,
+            Topology::Torus => (x, y.rem_euclid(hi)),
+            Topology::Sphere if y < 0 => (x + wi / 2, -y - 1),
+            Topology::Sphere if y >= hi => (x + wi / 2, 2 * hi - y - 1),
+            _ => (x, y),
+        };
+        if y < 0 || y >= hi {
+            return None; // stepped past a pole by more than a full row span
+        }
+        Some((x.rem_euclid(wi) as usize, y as usize))
+    }
+
+    /// 4-connected neighbors.
+    pub fn neighbors4(self, x: usize, y: usize, w: usize, h: usize) -> impl Iterator<Item = (usize, usize)> {
+        self.offsets([(-1, 0), (1, 0), (0, -1), (0, 1)], x, y, w, h)
+    }
+
+    /// 8-connected neighbors.
+    pub fn neighbors8(self, x: usize, y: usize, w: usize, h: usize) -> impl Iterator<Item = (usize, usize)> {
+        let offsets: [(i32, i32); 8] = [
+            (-1, -1), (0, -1), (1, -1),
+            (-1, 0),           (1, 0),
+            (-1, 1),  (0, 1),  (1, 1),
+        ];
+        self.offsets(offsets, x, y, w, h)
+    }
+
+    fn offsets<const N: usize>(
+        self,
+        offsets: [(i32, i32); N],
+        x: usize,
+        y: usize,
+        w: usize,
+        h: usize,
+    ) -> impl Iterator<Item = (usize, usize)> {
+        let mut out = [(0usize, 0usize); N];
+        let mut n = 0;
+        for (dx, dy) in offsets {
+            if let Some(pos) = self.wrap(x as i32 + dx, y as i32 + dy, w, h) {
+                out[n] = pos;
+                n += 1;
+            }
+        }
+        out.into_iter().take(n)
+    }
+
+    /// Shortest offset from (ax, ay) to (bx, by) in cells, following wraps.
+    /// On the sphere the over-the-pole path (via the mirrored image of b)
+    /// is considered as well.
+    #[inline]
+    pub fn delta(self, ax: f32, ay: f32, bx: f32, by: f32, w: usize, h: usize) -> (f32, f32) {
+        let (wf, hf) = (w as f32, h as f32);
+        let wrap_d = |d: f32, span: f32| d - span * (d / span).round();
+        let dx = if self.wraps_x() { wrap_d(bx - ax, wf) } else { bx - ax };
+        let dy = if self.wraps_y() { wrap_d(by - ay, hf) } else { by - ay };
+        if self != Topology::Sphere {
+            return (dx, dy);
+        }
+        // Mirror b across whichever pole is nearer to it
+        let my = if by < hf * 0.5 { -by - 1.0 } else { 2.0 * hf - by - 1.0 };
+        let mdx = wrap_d(bx + wf * 0.5 - ax, wf);
+        let mdy = my - ay;
+        if mdx * mdx + mdy * mdy < dx * dx + dy * dy { (mdx, mdy) } else { (dx, dy) }
+    }
+
+    /// Squared length of `delta`.
+    #[inline]
+    pub fn dist_sq(self, ax: f32, ay: f32, bx: f32, by: f32, w: usize, h: usize) -> f32 {
+        let (dx, dy) = self.delta(ax, ay, bx, by, w, h);
+        dx * dx + dy * dy
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::BinaryHeap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::grid::Grid;
+use crate::noise::gradient_noise_periodic;
+use crate::par::*;
+use crate::rng::{SeedTree, hash2};
+
+/// Nation id for unclaimed cells (ocean, uninhabited islands).
+pub const NO_NATION: u16 = u16::MAX;
+
+/// Fraction of contested border cells that can change hands per epoch.
+const CONFLICT_INTENSITY: f32 = 0.2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SiteKind {
+    Capital,
+    /// Former capital captured during the border simulation.
+    Ruin,
+}
+
+/// A discrete settlement site produced by the history simulation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Site {
+    pub x: usize,
+    pub y: usize,
+    pub kind: SiteKind,
+    pub nation: u16,
+}
+
+/// Priority queue entry for cost-based territorial growth (min-heap on cost).
+#[derive(PartialEq)]
+struct Entry {
+    cost: f32,
+    idx: usize,
+    nation: u16,
+}
+
+impl Eq for Entry {}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Found nations and simulate their borders.
+///
+/// Capitals are placed on the most populous land, spaced apart. Territory first
+/// grows by terrain cost (mountains and rivers are barriers), giving purely
+/// cost-based borders. If `epochs > 0`, a conflict simulation then runs: each
+/// epoch, border cells can flip to a stronger neighbor (weighted by population
+/// strength and terrain defense), producing irregular borders and enclaves.
+/// Captured capitals become ruins and the nation relocates its capital.
+pub fn simulate_history(
+    height: &Grid<f32>,
+    population: &Grid<f32>,
+    river_flow: &Grid<f32>,
+    num_nations: usize,
+    epochs: usize,
+    seed: u64,
+) -> (Grid<u16>, Vec<Site>) {
+    let w = height.w;
+
+    let capitals = place_capitals(height, population, num_nations);
+    let mut nation = grow_territory(height, river_flow, &capitals);
+    let mut sites: Vec<Site> = capitals
+        .iter()
+        .enumerate()
+        .map(|(n, &i)| Site { x: i % w, y: i / w, kind: SiteKind::Capital, nation: n as u16 })
+        .collect();
+
+    let epoch_seeds = SeedTree::new(seed).child("history").child("epochs");
+    let num = capitals.len();
+
+    for epoch in 0..epochs {
+        // Nation strength = population held
+        let mut strength = vec![0.0f32; num];
+        for (i, &n) in nation.data.iter().enumerate() {
+            if n != NO_NATION {
+                strength[n as usize] += population.data[i];
+            }
+        }
+
+        let epoch_seed = epoch_seeds.index(epoch as u64).seed_u32();
+        let prev = nation.clone();
+        nation.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let owner = prev.get(x, y);
+                if owner == NO_NATION {
+                    continue;
+                }
+
+                // Strongest foreign neighbor contests this cell
+                let mut attacker = NO_NATION;
+                let mut attacker_strength = 0.0f32;
+                for (nx, ny) in prev.neighbors4(x, y) {
+                    let n = prev.get(nx, ny);
+                    if n != NO_NATION && n != owner && strength[n as usize] > attacker_strength {
+                        attacker = n;
+                        attacker_strength = strength[n as usize];
+                    }
+                }
+                if attacker == NO_NATION {
+                    continue;
+                }
+
+                let defender_strength = strength[owner as usize];
+                let odds = attacker_strength / (attacker_strength + defender_strength).max(1e-6);
+                let defense = 1.0 / (1.0 + local_relief(height, x, y) / 200.0);
+                // Campaign noise: fronts advance in coherent regions, with a little per-cell jitter
+                let (u, v) = (x as f32 / w as f32 * 100.0, y as f32 / w as f32 * 100.0);
+                let front = gradient_noise_periodic(u, v, epoch_seed, 100, None) * 0.5 + 0.5;
+                let jitter = (hash2(x as i32, y as i32, epoch_seed) >> 8) as f32 / 16777216.0;
+                let roll = 0.8 * front + 0.2 * jitter;
+                if roll < CONFLICT_INTENSITY * 2.0 * odds * defense {
+                    *out = attacker;
+                }
+            }
+        });
+
+        // Cells mostly surrounded by one foreign nation are absorbed (removes speckle,
+        // keeps larger enclaves).
+        let prev = nation.clone();
+        nation.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let owner = prev.get(x, y);
+                if owner == NO_NATION {
+                    continue;
+                }
+                let mut counts = [(NO_NATION, 0u8); 4];
+                for (nx, ny) in prev.neighbors4(x, y) {
+                    let n = prev.get(nx, ny);
+                    if n == NO_NATION || n == owner {
+                        continue;
+                    }
+                    if let Some(c) = counts.iter_mut().find(|c| c.0 == n || c.1 == 0) {
+                        c.0 = n;
+                        c.1 += 1;
+                    }
+                }
+                if let Some(&(n, _)) = counts.iter().find(|c| c.1 >= 3) {
+                    *out = n;
+                }
+            }
+        });
+
+        // Captured capitals become ruins; survivors relocate to their most populous cell.
+        for n in 0..num {
+            let Some(cap) = sites
+                .iter()
+                .position(|s| s.kind == SiteKind::Capital && s.nation == n as u16)
+            else {
+                continue;
+            };
+            let (cx, cy) = (sites[cap].x, sites[cap].y);
+            if nation.get(cx, cy) == n as u16 {
+                continue;
+            }
+            sites[cap].kind = SiteKind::Ruin;
+
+            let best = nation
+                .data
+                .iter()
+                .enumerate()
+                .filter(|&(_, &o)| o == n as u16)
+                .max_by(|a, b| population.data[a.0].total_cmp(&population.data[b.0]))
+                .map(|(i, _)| i);
+            if let Some(i) = best {
+                sites.push(Site { x: i % w, y: i / w, kind: SiteKind::Capital, nation: n as u16 });
+            }
+        }
+    }
+
+    (nation, sites)
+}
+
+/// Pick up to `count` capital cells: most populous land first, with a
+/// minimum E-W-wrapped separation so nations don't cluster.
+fn place_capitals(height: &Grid<f32>, population: &Grid<f32>, count: usize) -> Vec<usize> {
+    let w = height.w;
+    let h = height.h;
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<usize> = (0..w * h)
+        .filter(|&i| height.data[i] > 0.0 && population.data[i] > 0.0)
+        .collect();
+    candidates.sort_by(|&a, &b| population.data[b].total_cmp(&population.data[a]).then(a.cmp(&b)));
+
+    let land = candidates.len().max(1);
+    let min_dist = ((land as f32 / count as f32).sqrt() * 0.8).max(2.0);
+
+    let mut capitals: Vec<usize> = Vec::with_capacity(count);
+    for &i in &candidates {
+        if capitals.len() >= count {
+            break;
+        }
+        let (x, y) = ((i % w) as f32, (i / w) as f32);
+        let ok = capitals.iter().all(|&c| {
+            let dx_raw = (x - (c % w) as f32).abs();
+            let dx = dx_raw.min(w as f32 - dx_raw);
+            let dy = y - (c / w) as f32;
+            (dx * dx + dy * dy).sqrt() >= min_dist
+        });
+        if ok {
+            capitals.push(i);
+        }
+    }
+
+    capitals
+}
+
+/// Multi-source Dijkstra over land from the capitals. Steep terrain, high
+/// altitude, and river crossings are expensive, so borders follow natural barriers.
+fn grow_territory(height: &Grid<f32>, river_flow: &Grid<f32>, capitals: &[usize]) -> Grid<u16> {
+    let w = height.w;
+    let h = height.h;
+    let mut nation = Grid::<u16>::new(w, h);
+    for v in &mut nation.data {
+        *v = NO_NATION;
+    }
+
+    let mut heap = BinaryHeap::new();
+    for (n, &idx) in capitals.iter().enumerate() {
+        heap.push(Entry { cost: 0.0, idx, nation: n as u16 });
+    }
+
+    while let Some(Entry { cost, idx, nation: n }) = heap.pop() {
+        if nation.data[idx] != NO_NATION {
+            continue;
+        }
+        nation.data[idx] = n;
+
+        let (x, y) = (idx % w, idx / w);
+        for (nx, ny) in nation.neighbors8(x, y) {
+            let ni = ny * w + nx;
+            if nation.data[ni] != NO_NATION || height.data[ni] <= 0.0 {
+                continue;
+            }
+            let step = if nx != x && ny != y { 1.414 } else { 1.0 };
+            let climb = (height.data[ni] - height.data[idx]).abs() / 100.0;
+            let altitude = height.data[ni].max(0.0) / 1000.0;
+            let river = if river_flow.data[ni] > 0.0 { 3.0 } else { 0.0 };
+            heap.push(Entry {
+                cost: cost + step * (1.0 + climb + altitude) + river,
+                idx: ni,
+                nation: n,
+            });
+        }
+    }
+
+    nation
+}
+
+/// Max absolute elevation difference to 4-neighbors (meters).
+#[inline]
+fn local_relief(height: &Grid<f32>, x: usize, y: usize) -> f32 {
+    let e = height.get(x, y);
+    height.neighbors4(x, y)
+        .map(|(nx, ny)| (height.get(nx, ny) - e).abs())
+        .fold(0.0, f32::max)
+}
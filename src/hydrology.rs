@@ -1,22 +1,23 @@
 use std::collections::BinaryHeap;
 use std::cmp::Ordering;
 
-use rayon::prelude::*;
-
+use crate::cancel::CancelToken;
 use crate::config::Params;
-use crate::grid::Grid;
-use crate::noise::fbm;
-use crate::rng::seed_u32;
-
-const SALT_MEANDER: u64 = 0xD1A_CAFE_0001;
+use crate::fmath;
+use crate::grid::{Filter, Grid, Rect, flow};
+use crate::noise::Fbm;
+use crate::par::*;
+use crate::rng::SeedTree;
+use crate::scratch;
 
 /// Max cells allowed for hydro grid (256M).
 const MAX_HYDRO_CELLS: usize = 256_000_000;
 
-/// Determine upscale factor: target 8x, but auto-reduce if base res is too large.
-pub fn hydro_scale(w: usize, h: usize) -> usize {
+/// Determine upscale factor: target `max` (8x by default), but auto-reduce
+/// if base res is too large.
+pub fn hydro_scale(w: usize, h: usize, max: usize) -> usize {
     let base = w * h;
-    for s in (1..=8).rev() {
+    for s in (1..=max).rev() {
         if base * s * s <= MAX_HYDRO_CELLS {
             return s;
         }
@@ -48,65 +49,15 @@ impl Ord for FloodEntry {
     }
 }
 
-/// Bilinear upscale of elevation grid.
-fn upscale_bilinear(src: &Grid<f32>, scale: usize) -> Grid<f32> {
-    let sw = src.w;
-    let sh = src.h;
-    let dw = sw * scale;
-    let dh = sh * scale;
-    let mut dst = Grid::new(dw, dh);
-
-    dst.data.par_chunks_mut(dw).enumerate().for_each(|(dy, row)| {
-        let sy_f = (dy as f32 + 0.5) / scale as f32 - 0.5;
-        let sy0 = (sy_f.floor() as i32).clamp(0, sh as i32 - 1) as usize;
-        let sy1 = (sy0 + 1).min(sh - 1);
-        let fy = sy_f - sy0 as f32;
-
-        for dx in 0..dw {
-            let sx_f = (dx as f32 + 0.5) / scale as f32 - 0.5;
-            let sx0_raw = sx_f.floor() as i32;
-            let sx0 = ((sx0_raw % sw as i32) + sw as i32) as usize % sw; // E-W wrap
-            let sx1 = (sx0 + 1) % sw; // E-W wrap
-            let fx = sx_f - sx0_raw as f32;
-
-            let v00 = src.get(sx0, sy0);
-            let v10 = src.get(sx1, sy0);
-            let v01 = src.get(sx0, sy1);
-            let v11 = src.get(sx1, sy1);
-
-            let top = v00 + (v10 - v00) * fx;
-            let bot = v01 + (v11 - v01) * fx;
-            row[dx] = top + (bot - top) * fy;
-        }
-    });
-
-    dst
-}
-
-/// Nearest-neighbor upscale for precipitation.
-fn upscale_nearest(src: &Grid<f32>, scale: usize) -> Grid<f32> {
-    let dw = src.w * scale;
-    let dh = src.h * scale;
-    let mut dst = Grid::new(dw, dh);
-
-    dst.data.par_chunks_mut(dw).enumerate().for_each(|(dy, row)| {
-        let sy = dy / scale;
-        for dx in 0..dw {
-            let sx = dx / scale;
-            row[dx] = src.get(sx, sy);
-        }
-    });
-
-    dst
-}
-
 /// Barnes et al. priority-flood depression filling (in-place).
 /// Seeds from ocean cells + top/bottom rows so every land cell drains to the nearest coast.
-fn priority_flood(elev: &mut Grid<f32>) {
+/// `progress` receives the fraction of cells visited; `cancel` stops the
+/// fill part way.
+fn priority_flood(elev: &mut Grid<f32>, progress: &dyn Fn(f32), cancel: &CancelToken) {
     let w = elev.w;
     let h = elev.h;
     let n = w * h;
-    let mut visited = vec![false; n];
+    let mut visited = scratch::take(n, false);
     let mut heap = BinaryHeap::new();
 
     let offsets: [(i32, i32); 8] = [
@@ -157,6 +108,8 @@ fn priority_flood(elev: &mut Grid<f32>) {
         }
     }
 
+    let mut visited_count = visited.iter().filter(|&&v| v).count();
+    let report_every = (n / 100).max(1);
     while let Some(cell) = heap.pop() {
         let ci = cell.idx as usize;
         let cx = ci % w;
@@ -171,6 +124,14 @@ fn priority_flood(elev: &mut Grid<f32>) {
 
             if visited[ni] { continue; }
             visited[ni] = true;
+            visited_count += 1;
+            if visited_count % report_every == 0 {
+                if cancel.is_cancelled() {
+                    scratch::give(visited);
+                    return;
+                }
+                progress(visited_count as f32 / n as f32);
+            }
 
             // Fill depression: raise neighbor to at least current cell's elevation.
             // Add tiny epsilon so filled areas slope toward their outlet —
@@ -182,34 +143,43 @@ fn priority_flood(elev: &mut Grid<f32>) {
             heap.push(FloodEntry { elev: elev.data[ni], idx: ni as u32 });
         }
     }
+    scratch::give(visited);
 }
 
 /// Add noise to elevation to create river meanders.
 /// Applied BEFORE priority flood so drainage paths curve around noise features
 /// while still reaching the coast. Amplitude scales inversely with elevation
 /// (more meander on flat plains, less in mountains — matching real physics).
-fn add_meander_noise(elev: &mut Grid<f32>, seed: u64) {
+/// `cell_km` is the cell size of `elev`.
+fn add_meander_noise(elev: &mut Grid<f32>, seed: u64, cell_km: f32, params: &Params) {
     let w = elev.w;
-    let noise_seed = seed_u32(seed, SALT_MEANDER);
+    let seeds = SeedTree::new(seed).child("hydrology").child("meander");
+    let (large_seed, small_seed) = (seeds.child("large").seed_u32(), seeds.child("small").seed_u32());
+    let large_wl = params.meander_wavelength / cell_km;
+    let small_wl = params.meander_detail_wavelength / cell_km;
+    // Periods of one grid width keep the meanders seamless E-W
+    let large_noise = Fbm::new(3, 1.0, 2.0, 0.5).with_period(w as f32 / large_wl, None);
+    let small_noise = Fbm::new(2, 1.0, 2.0, 0.5).with_period(w as f32 / small_wl, None);
+    let meander_amp = params.meander_amp;
 
     elev.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
         for x in 0..w {
             let e = row[x];
             if e > 0.0 {
                 // Amplitude fades with elevation: full on plains, weak in mountains.
-                // Plains (<200m): 15m noise. Mountains (>2000m): ~2m noise.
-                let amp = 15.0 / (1.0 + e / 400.0);
+                // Plains (<200m): full amplitude (15m). Mountains (>2000m): ~1/6.
+                let amp = meander_amp / (1.0 + e / 400.0);
 
                 // Two scales of noise for natural-looking curves:
-                // Large sweeps (wavelength ~200 hi-res px ≈ 25 base px ≈ 500km)
-                let nx = x as f32 / 200.0;
-                let ny = y as f32 / 200.0;
-                let large = fbm(nx, ny, noise_seed, 3, 1.0, 2.0, 0.5);
+                // Large sweeps (~500km by default)
+                let nx = x as f32 / large_wl;
+                let ny = y as f32 / large_wl;
+                let large = large_noise.sample(nx, ny, large_seed);
 
-                // Smaller wiggles (wavelength ~60 hi-res px ≈ 8 base px ≈ 150km)
-                let nx2 = x as f32 / 60.0;
-                let ny2 = y as f32 / 60.0;
-                let small = fbm(nx2, ny2, noise_seed ^ 0xFF, 2, 1.0, 2.0, 0.5);
+                // Smaller wiggles (~150km)
+                let nx2 = x as f32 / small_wl;
+                let ny2 = y as f32 / small_wl;
+                let small = small_noise.sample(nx2, ny2, small_seed);
 
                 row[x] += amp * (0.7 * large + 0.3 * small);
 
@@ -223,174 +193,59 @@ fn add_meander_noise(elev: &mut Grid<f32>, seed: u64) {
     });
 }
 
-/// Compute D8 flow direction for each cell (steepest descent).
-/// Returns direction as index 0-7 into the 8-neighbor offset array, or 255 for no-flow (flat/sink).
-fn compute_flow_direction(elev: &Grid<f32>) -> Grid<u8> {
-    let w = elev.w;
-    let h = elev.h;
-    let mut flow_dir = Grid::new(w, h);
-
-    let offsets: [(i32, i32); 8] = [
-        (-1, -1), (0, -1), (1, -1),
-        (-1, 0),           (1, 0),
-        (-1, 1),  (0, 1),  (1, 1),
-    ];
-    let dist: [f32; 8] = [
-        std::f32::consts::SQRT_2, 1.0, std::f32::consts::SQRT_2,
-        1.0,                           1.0,
-        std::f32::consts::SQRT_2, 1.0, std::f32::consts::SQRT_2,
-    ];
-
-    flow_dir.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
-        for x in 0..w {
-            let e = elev.get(x, y);
-            let mut best_dir: u8 = 255;
-            let mut best_slope = 0.0f32;
-
-            for (d, &(dx, dy)) in offsets.iter().enumerate() {
-                let ny = y as i32 + dy;
-                if ny < 0 || ny >= h as i32 { continue; }
-                let ny = ny as usize;
-                let nx = ((x as i32 + dx) % w as i32 + w as i32) as usize % w;
-                let ne = elev.get(nx, ny);
-                let slope = (e - ne) / dist[d];
-                if slope > best_slope {
-                    best_slope = slope;
-                    best_dir = d as u8;
-                }
-            }
-
-            row[x] = best_dir;
-        }
-    });
-
-    flow_dir
-}
-
-/// Argsort indices by elevation (descending — highest first).
-fn argsort_descending(elev: &Grid<f32>) -> Vec<u32> {
-    let n = elev.data.len();
-    let mut indices: Vec<u32> = (0..n as u32).collect();
-    indices.par_sort_unstable_by(|&a, &b| {
-        elev.data[b as usize]
-            .partial_cmp(&elev.data[a as usize])
-            .unwrap_or(Ordering::Equal)
-    });
-    indices
-}
-
-/// Flow accumulation: traverse highest-to-lowest, each cell adds its
-/// precipitation + upstream flow to its D8 downstream neighbor.
-fn flow_accumulation(
-    flow_dir: &Grid<u8>,
-    hi_precip: &Grid<f32>,
-    sorted: &[u32],
-) -> Vec<f32> {
-    let w = flow_dir.w;
-    let h = flow_dir.h;
-    let n = w * h;
-
-    let offsets: [(i32, i32); 8] = [
-        (-1, -1), (0, -1), (1, -1),
-        (-1, 0),           (1, 0),
-        (-1, 1),  (0, 1),  (1, 1),
-    ];
-
-    let downstream_of = |i: usize, dir: u8| -> Option<usize> {
-        if dir >= 8 { return None; }
-        let x = i % w;
-        let y = i / w;
-        let (dx, dy) = offsets[dir as usize];
-        let ny = y as i32 + dy;
-        if ny < 0 || ny >= h as i32 { return None; }
-        let ny = ny as usize;
-        let nx = ((x as i32 + dx) % w as i32 + w as i32) as usize % w;
-        Some(ny * w + nx)
-    };
-
-    let mut flow = vec![0.0f32; n];
-    for i in 0..n {
-        flow[i] = hi_precip.data[i];
-    }
-
-    for &idx in sorted {
-        let i = idx as usize;
-        if let Some(ni) = downstream_of(i, flow_dir.data[i]) {
-            flow[ni] += flow[i];
-        }
-    }
-
-    flow
-}
-
-/// Downsample flow accumulation: for each base-res cell, take MAX from its scale×scale block.
-fn downsample_max(flow: &[f32], hi_w: usize, hi_h: usize, scale: usize) -> Grid<f32> {
-    let base_w = hi_w / scale;
-    let base_h = hi_h / scale;
-    let mut out = Grid::new(base_w, base_h);
-
-    out.data.par_chunks_mut(base_w).enumerate().for_each(|(by, row)| {
-        for bx in 0..base_w {
-            let mut max_val = 0.0f32;
-            for dy in 0..scale {
-                let hy = by * scale + dy;
-                if hy >= hi_h { continue; }
-                for dx in 0..scale {
-                    let hx = bx * scale + dx;
-                    if hx >= hi_w { continue; }
-                    let v = flow[hy * hi_w + hx];
-                    if v > max_val { max_val = v; }
-                }
-            }
-            row[bx] = max_val;
-        }
-    });
-
-    out
-}
-
 /// Main hydrology pipeline. Returns base-resolution river_flow grid.
 /// Also carves valleys into the provided heightmap along river paths.
+/// `progress` receives the overall fraction done; priority flood and flow
+/// accumulation, the slow steps, report as they go. Returns `None`, with
+/// `height` untouched, if `cancel` fires first.
 pub fn compute_hydrology(
     height: &mut Grid<f32>,
     precipitation: &Grid<f32>,
-    _seed: u64,
+    seed: u64,
     params: &Params,
-) -> Grid<f32> {
+    progress: &dyn Fn(f32),
+    cancel: &CancelToken,
+) -> Option<Grid<f32>> {
     let w = height.w;
     let h = height.h;
-    let scale = hydro_scale(w, h);
+    let scale = hydro_scale(w, h, params.hydro_upscale);
 
     // 1. Upscale elevation (bilinear)
-    let mut hi_elev = upscale_bilinear(height, scale);
-    let hi_w = hi_elev.w;
-    let hi_h = hi_elev.h;
+    let mut hi_elev = height.resample(w * scale, h * scale, Filter::Bilinear);
 
     // 3. Meander noise: small-scale perturbation BEFORE priority flood.
-    add_meander_noise(&mut hi_elev, _seed);
+    let hi_cell_km = params.world.cell_km(w) / scale as f32;
+    add_meander_noise(&mut hi_elev, seed, hi_cell_km, params);
+    progress(0.1);
 
     // 4. Priority flood — fill depressions in-place
-    priority_flood(&mut hi_elev);
+    priority_flood(&mut hi_elev, &|f| progress(0.1 + 0.4 * f), cancel);
+    if cancel.is_cancelled() {
+        scratch::give(hi_elev.data);
+        return None;
+    }
 
     // 5. D8 flow direction
-    let flow_dir = compute_flow_direction(&hi_elev);
-
-    // 6. Argsort by elevation (descending)
-    let sorted = argsort_descending(&hi_elev);
-    drop(hi_elev);
-
-    // 7. Upscale precipitation (nearest-neighbor)
-    let hi_precip = upscale_nearest(precipitation, scale);
-
-    // 8. Flow accumulation
-    let flow = flow_accumulation(&flow_dir, &hi_precip, &sorted);
-    drop(flow_dir);
-    drop(hi_precip);
-    drop(sorted);
+    let flow_dir = flow::d8(&hi_elev);
+    scratch::give(hi_elev.data);
+    progress(0.55);
+
+    // 6. Upscale precipitation (nearest-neighbor)
+    let hi_precip = precipitation.resample(w * scale, h * scale, Filter::Nearest);
+
+    // 7. Flow accumulation (upstream first)
+    let flow = flow::accumulate_d8(&flow_dir, |i| hi_precip.data[i], &|f| progress(0.55 + 0.35 * f), cancel);
+    scratch::give(flow_dir.data);
+    scratch::give(hi_precip.data);
+    if cancel.is_cancelled() {
+        scratch::give(flow.data);
+        return None;
+    }
+    progress(0.9);
 
     // 9. Downsample to base resolution (max in each block)
-    let mut river_flow = downsample_max(&flow, hi_w, hi_h, scale);
-    drop(flow);
+    let mut river_flow = flow.resample(w, h, Filter::Max);
+    scratch::give(flow.data);
 
     // Zero out ocean cells
     for i in 0..w * h {
@@ -402,18 +257,15 @@ pub fn compute_hydrology(
     // 10. Percentile threshold on raw flow (unchanged from what worked).
     // This preserves river-to-ocean continuity since flow increases monotonically
     // downstream — if a cell passes, every cell downstream of it also passes.
-    let mut land_flows: Vec<f32> = river_flow.data.iter().copied().filter(|&v| v > 0.0).collect();
-    let flow_threshold = if land_flows.len() > 100 {
-        land_flows.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
-        let idx = ((1.0 - params.river_threshold as f64) * land_flows.len() as f64) as usize;
-        let idx = idx.min(land_flows.len() - 1);
-        land_flows[idx]
+    let land_cells = river_flow.data.iter().filter(|&&v| v > 0.0).count();
+    let flow_threshold = if land_cells > 100 {
+        river_flow.percentile_where(1.0 - params.river_threshold, |v| v > 0.0).unwrap_or(f32::MAX)
     } else {
         f32::MAX
     };
 
     // Save raw flow before zeroing (needed for upstream extension)
-    let raw_flow: Vec<f32> = river_flow.data.clone();
+    let raw_flow = scratch::copy_of(&river_flow.data);
 
     for i in 0..w * h {
         if river_flow.data[i] < flow_threshold {
@@ -431,7 +283,7 @@ pub fn compute_hydrology(
         ];
 
         // Label connected components of the thresholded river network.
-        let mut labels = vec![0u32; w * h];
+        let mut labels = scratch::take(w * h, 0u32);
         let mut next_label = 1u32;
         let mut comp_sizes: Vec<u32> = vec![0]; // index 0 unused
         for start in 0..w * h {
@@ -459,14 +311,14 @@ pub fn compute_hydrology(
             }
         }
 
-        // Each component can grow by up to 50% of its original size.
+        // Each component can grow by up to `basin_growth` (50%) of its original size.
         let mut added = vec![0u32; next_label as usize];
         let max_add: Vec<u32> = comp_sizes.iter()
-            .map(|&s| (s as f32 * 0.5).ceil() as u32)
+            .map(|&s| (s as f32 * params.basin_growth).ceil() as u32)
             .collect();
 
         // Must have meaningful flow to extend (not just noise-level drainage)
-        let min_extend_flow = flow_threshold * 0.05;
+        let min_extend_flow = flow_threshold * params.basin_min_flow;
 
         for _pass in 0..20 {
             let mut changed = false;
@@ -500,66 +352,149 @@ pub fn compute_hydrology(
             }
             if !changed { break; }
         }
+        scratch::give(labels);
     }
+    scratch::give(raw_flow);
 
     // 12. Carve valleys into the heightmap along river paths.
-    carve_valleys(height, &river_flow, flow_threshold);
+    carve_valleys(height, &river_flow, flow_threshold, params.carve_depth, params.carve_max);
+
+    Some(river_flow)
+}
+
+/// Re-route the rivers of a finished map inside `window` (columns wrap)
+/// after its terrain changed from `before` to `height`, at base
+/// resolution. Flow is routed over the window both ways and the
+/// difference applied to `river_flow`, so cells the edit doesn't reach
+/// keep their rivers exactly. Rivers entering the window keep their flow;
+/// its edges act as outlets, so a river that now leaves somewhere else
+/// isn't followed outside. Flow below the map's weakest existing river is
+/// dropped, and new river cells are carved as in `compute_hydrology`
+/// (without widening). Does nothing on a map without rivers.
+pub fn reroute_window(
+    height: &mut Grid<f32>,
+    before: &Grid<f32>,
+    river_flow: &mut Grid<f32>,
+    precipitation: &Grid<f32>,
+    window: Rect,
+    params: &Params,
+) {
+    let (w, h) = (height.w, height.h);
+    let Some(threshold) = river_flow.data.iter().copied().filter(|&f| f > 0.0).min_by(f32::total_cmp) else {
+        return;
+    };
+    let rows = window.y..(window.y + window.h).min(h);
+    let ww = window.w.min(w);
+    if ww == 0 || rows.is_empty() {
+        return;
+    }
+    // A hi-res cell's rain, in base cells
+    let scale = hydro_scale(w, h, params.hydro_upscale);
+    let cell_weight = (scale * scale) as f32;
+
+    // Window with a sea column on either side, so its edges drain like
+    // coasts; a full-width window wraps as the map does
+    let pad = usize::from(ww < w);
+    let lw = ww + 2 * pad;
+    let lh = rows.len();
+    let at = |lx: usize, ly: usize| ((window.x + lx - pad) % w, window.y + ly);
+    let inside = |lx: usize| lx >= pad && lx < pad + ww;
+    let local = |grid: &Grid<f32>, f: &(dyn Fn(f32) -> f32 + Sync)| {
+        Grid::par_from_fn(lw, lh, |lx, ly| {
+            if inside(lx) {
+                let (x, y) = at(lx, ly);
+                f(grid.get(x, y))
+            } else {
+                0.0
+            }
+        })
+    };
+    let mut weight = local(precipitation, &|p| p * cell_weight);
 
-    river_flow
+    // Rivers just outside pour into their lowest neighbor inside
+    let local_of = |x: usize, y: usize| -> Option<(usize, usize)> {
+        let lx = (x + w - window.x % w) % w + pad;
+        (rows.contains(&y) && inside(lx)).then(|| (lx, y - window.y))
+    };
+    let edge = (0..lh).flat_map(|ly| {
+        let cols: Vec<usize> = if ly == 0 || ly == lh - 1 { (pad..pad + ww).collect() } else { vec![pad, pad + ww - 1] };
+        cols.into_iter().map(move |lx| (lx, ly))
+    });
+    let mut ring: Vec<(usize, usize)> = edge
+        .flat_map(|(lx, ly)| {
+            let (x, y) = at(lx, ly);
+            before.neighbors8(x, y)
+        })
+        .filter(|&(x, y)| local_of(x, y).is_none())
+        .collect();
+    ring.sort_unstable();
+    ring.dedup();
+    for (x, y) in ring {
+        let flow = river_flow.get(x, y);
+        if flow <= 0.0 {
+            continue;
+        }
+        let lowest = before
+            .neighbors8(x, y)
+            .min_by(|&(ax, ay), &(bx, by)| before.get(ax, ay).total_cmp(&before.get(bx, by)));
+        if let Some((lx, ly)) = lowest.and_then(|(nx, ny)| local_of(nx, ny)) {
+            weight.data[ly * lw + lx] += flow;
+        }
+    }
+
+    let cancel = CancelToken::new();
+    let route = |grid: &Grid<f32>| {
+        let mut elev = local(grid, &|e| e);
+        priority_flood(&mut elev, &|_| {}, &cancel);
+        flow::accumulate_d8(&flow::d8(&elev), |i| weight.data[i], &|_| {}, &cancel)
+    };
+    let (old_acc, new_acc) = (route(before), route(height));
+
+    for ly in 0..lh {
+        for lx in pad..pad + ww {
+            let (a0, a1) = (old_acc.get(lx, ly), new_acc.get(lx, ly));
+            if a0 == a1 {
+                continue;
+            }
+            let (x, y) = at(lx, ly);
+            let (e, old) = (height.get(x, y), river_flow.get(x, y));
+            let flow = if old > 0.0 { old + a1 - a0 } else { a1 };
+            let new = if e > 0.0 && flow >= threshold { flow } else { 0.0 };
+            if new > 0.0 && old <= 0.0 {
+                let depth = (params.carve_depth * fmath::ln(1.0 + new / threshold)).min(params.carve_max);
+                height.set(x, y, (e - depth).max(1.0));
+            }
+            river_flow.set(x, y, new);
+        }
+    }
 }
 
 /// Carve river valleys into the heightmap.
-/// Erosion depth = K * ln(1 + flow/threshold), capped, then blurred to widen valleys.
-fn carve_valleys(height: &mut Grid<f32>, river_flow: &Grid<f32>, threshold: f32) {
+/// Erosion depth = K * ln(1 + flow/threshold), capped at `max_depth`, then
+/// blurred to widen valleys.
+fn carve_valleys(height: &mut Grid<f32>, river_flow: &Grid<f32>, threshold: f32, k: f32, max_depth: f32) {
     let w = height.w;
     let h = height.h;
     let n = w * h;
     let threshold = threshold.max(1.0);
 
     // Compute raw carving depth per cell
-    let mut carve = vec![0.0f32; n];
+    let mut carve = scratch::take(n, 0.0f32);
     for i in 0..n {
         let flow = river_flow.data[i];
         if flow > 0.0 {
-            let depth = 25.0 * (1.0 + flow / threshold).ln();
-            carve[i] = depth.min(150.0);
+            let depth = k * fmath::ln(1.0 + flow / threshold);
+            carve[i] = depth.min(max_depth);
         }
     }
 
-    // Widen valleys with separable Gaussian blur (sigma ~1.5 cells)
-    let sigma: f32 = 1.5;
-    let radius = (sigma * 3.0).ceil() as i32;
-    let kernel: Vec<f32> = (-radius..=radius)
-        .map(|d| (-(d as f32).powi(2) / (2.0 * sigma * sigma)).exp())
-        .collect();
-    let ksum: f32 = kernel.iter().sum();
-    let kernel: Vec<f32> = kernel.iter().map(|k| k / ksum).collect();
-
-    // Blur X (with E-W wrapping)
-    let mut temp = vec![0.0f32; n];
-    temp.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
-        for x in 0..w {
-            let mut sum = 0.0f32;
-            for (ki, dx) in (-radius..=radius).enumerate() {
-                let sx = ((x as i32 + dx) % w as i32 + w as i32) as usize % w;
-                sum += carve[y * w + sx] * kernel[ki];
-            }
-            row[x] = sum;
-        }
-    });
-
-    // Blur Y (clamp at poles)
-    let mut blurred = vec![0.0f32; n];
-    blurred.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
-        for x in 0..w {
-            let mut sum = 0.0f32;
-            for (ki, dy) in (-radius..=radius).enumerate() {
-                let sy = (y as i32 + dy).clamp(0, h as i32 - 1) as usize;
-                sum += temp[sy * w + x] * kernel[ki];
-            }
-            row[x] = sum;
-        }
-    });
+    // Widen valleys with separable Gaussian blur (sigma ~1.5 cells); the
+    // height grid's topology and metric keep valley widths even on the globe
+    let mut blurred = Grid::from_vec(w, h, carve)
+        .with_topology(height.topology)
+        .with_metric(height.metric);
+    blurred.gaussian_blur(1.5, 1.5);
+    let blurred = blurred.data;
 
     // Apply carving: subtract from heightmap, don't go below sea level
     for i in 0..n {
@@ -567,4 +502,5 @@ fn carve_valleys(height: &mut Grid<f32>, river_flow: &Grid<f32>, threshold: f32)
             height.data[i] = (height.data[i] - blurred[i]).max(1.0);
         }
     }
+    scratch::give(blurred);
 }
@@ -0,0 +1,95 @@
+//! Reading user-provided heightmaps, in the formats `export` writes, for
+//! `generate_from_heightmap`.
+
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::grid::Grid;
+
+/// The fields of an export sidecar (`export::HeightmapMeta`) an import
+/// needs.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct HeightmapSidecar {
+    pub width: usize,
+    pub height: usize,
+    /// Elevation (meters) of PNG value 0.
+    pub min: f32,
+    /// Elevation (meters) of PNG value 65535.
+    pub max: f32,
+}
+
+/// Raw little-endian f32 meters, row-major, row 0 = north.
+pub fn heightmap_from_f32le(bytes: &[u8], w: usize, h: usize) -> io::Result<Grid<f32>> {
+    if Some(bytes.len()) != w.checked_mul(h).and_then(|n| n.checked_mul(4)) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} bytes is not a {}x{} f32 heightmap", bytes.len(), w, h),
+        ));
+    }
+    let data = bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+    Ok(Grid::from_vec(w, h, data))
+}
+
+/// Grayscale PNG (16-bit, or 8-bit scaled up) with values mapped linearly
+/// from `min` meters at black to `max` at white.
+#[cfg(feature = "image")]
+pub fn heightmap_from_png(bytes: &[u8], min: f32, max: f32) -> io::Result<Grid<f32>> {
+    let image = image::load_from_memory(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let gray = image.into_luma16();
+    let (w, h) = (gray.width() as usize, gray.height() as usize);
+    let data = gray.into_raw().into_iter().map(|v| min + (max - min) * v as f32 / 65535.0).collect();
+    Ok(Grid::from_vec(w, h, data))
+}
+
+/// Load a heightmap file: `.f32` (raw meters) or `.png`. A raw file's
+/// size comes from the JSON sidecar written next to an export
+/// (`<stem>.json` for `<stem>.f32` or `<stem>16.png`). A PNG's range is
+/// `range` (meters at black and white) if given, else the sidecar's.
+pub fn load_heightmap(path: &Path, range: Option<(f32, f32)>) -> io::Result<Grid<f32>> {
+    #[cfg(not(feature = "image"))]
+    let _ = range;
+    let bytes = std::fs::read(path)?;
+    let sidecar = sidecar_for(path)?;
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "f32" | "raw" => {
+            let meta = sidecar.ok_or_else(|| missing(path, "a JSON sidecar with its width and height"))?;
+            heightmap_from_f32le(&bytes, meta.width, meta.height)
+        }
+        #[cfg(feature = "image")]
+        "png" => {
+            let (min, max) = range
+                .or(sidecar.map(|m| (m.min, m.max)))
+                .ok_or_else(|| missing(path, "a JSON sidecar or an elevation range"))?;
+            heightmap_from_png(&bytes, min, max)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: unsupported heightmap format (expected .f32 or .png)", path.display()),
+        )),
+    }
+}
+
+/// `<stem>.json` next to `path`, with a `16` suffix on the stem dropped.
+fn sidecar_for(path: &Path) -> io::Result<Option<HeightmapSidecar>> {
+    let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+        return Ok(None);
+    };
+    let candidates = [Some(stem), stem.strip_suffix("16")];
+    for stem in candidates.into_iter().flatten() {
+        let json = path.with_file_name(format!("{}.json", stem));
+        if json.exists() {
+            let text = std::fs::read_to_string(&json)?;
+            let meta = serde_json::from_str(&text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", json.display(), e)))?;
+            return Ok(Some(meta));
+        }
+    }
+    Ok(None)
+}
+
+fn missing(path: &Path, what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{}: needs {}", path.display(), what))
+}
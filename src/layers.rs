@@ -0,0 +1,253 @@
+//! Name-based access to a map's grid layers, so code that handles layers
+//! generically (export, diffs, digests, the server) doesn't list `Map`
+//! fields, and so later subsystems (biomes, soil, hazards) can attach
+//! layers of their own without changing `Map`.
+//!
+//! ```ignore
+//! let rain = map.layer::<f32>("precipitation").unwrap();
+//! map.insert_layer("soil_depth", soil);
+//! for info in map.layers() {
+//!     println!("{} ({})", info.name, info.ty.name());
+//! }
+//! ```
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Map;
+use crate::grid::Grid;
+
+/// Cell type of a layer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LayerType {
+    F32,
+    U16,
+    U8,
+}
+
+impl LayerType {
+    pub fn name(self) -> &'static str {
+        match self {
+            LayerType::F32 => "f32",
+            LayerType::U16 => "u16",
+            LayerType::U8 => "u8",
+        }
+    }
+}
+
+/// One entry of `Map::layers`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct LayerInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: LayerType,
+    /// A `Map` field rather than an added layer.
+    pub builtin: bool,
+}
+
+/// Grid layers that are `Map` fields, in field order. `boundary_dist`,
+/// `temperature` and `precipitation` are stored 16-bit quantized and read
+/// back as f32.
+pub const BUILTIN_LAYERS: [(&str, LayerType); 12] = [
+    ("height", LayerType::F32),
+    ("plate_id", LayerType::U16),
+    ("boundary_type", LayerType::U8),
+    ("boundary_major", LayerType::U8),
+    ("boundary_dist", LayerType::F32),
+    ("temperature", LayerType::F32),
+    ("precipitation", LayerType::F32),
+    ("ice", LayerType::U8),
+    ("river_flow", LayerType::F32),
+    ("population", LayerType::F32),
+    ("nation_id", LayerType::U16),
+    ("culture_id", LayerType::U16),
+];
+
+/// An added layer's grid.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum LayerData {
+    F32(Grid<f32>),
+    U16(Grid<u16>),
+    U8(Grid<u8>),
+}
+
+impl LayerData {
+    pub fn view(&self) -> LayerView<'_> {
+        match self {
+            LayerData::F32(g) => LayerView::F32(Cow::Borrowed(g)),
+            LayerData::U16(g) => LayerView::U16(g),
+            LayerData::U8(g) => LayerView::U8(g),
+        }
+    }
+
+    /// Same layer through `f32`, `u16` and `u8` grid functions.
+    pub fn map_grid(
+        &self,
+        f32s: impl FnOnce(&Grid<f32>) -> Grid<f32>,
+        u16s: impl FnOnce(&Grid<u16>) -> Grid<u16>,
+        u8s: impl FnOnce(&Grid<u8>) -> Grid<u8>,
+    ) -> LayerData {
+        match self {
+            LayerData::F32(g) => LayerData::F32(f32s(g)),
+            LayerData::U16(g) => LayerData::U16(u16s(g)),
+            LayerData::U8(g) => LayerData::U8(u8s(g)),
+        }
+    }
+}
+
+/// A layer read from a map: borrowed, except quantized layers, which are
+/// decoded.
+#[derive(Clone, Debug)]
+pub enum LayerView<'a> {
+    F32(Cow<'a, Grid<f32>>),
+    U16(&'a Grid<u16>),
+    U8(&'a Grid<u8>),
+}
+
+impl LayerView<'_> {
+    pub fn ty(&self) -> LayerType {
+        match self {
+            LayerView::F32(_) => LayerType::F32,
+            LayerView::U16(_) => LayerType::U16,
+            LayerView::U8(_) => LayerType::U8,
+        }
+    }
+
+    /// `(w, h)` of the grid.
+    pub fn size(&self) -> (usize, usize) {
+        match self {
+            LayerView::F32(g) => (g.w, g.h),
+            LayerView::U16(g) => (g.w, g.h),
+            LayerView::U8(g) => (g.w, g.h),
+        }
+    }
+
+    /// One cell as f32.
+    pub fn value(&self, x: usize, y: usize) -> f32 {
+        match self {
+            LayerView::F32(g) => g.get(x, y),
+            LayerView::U16(g) => g.get(x, y) as f32,
+            LayerView::U8(g) => g.get(x, y) as f32,
+        }
+    }
+
+    /// Every cell as f32 (ids and classes converted exactly).
+    pub fn to_f32(&self) -> Cow<'_, Grid<f32>> {
+        match self {
+            LayerView::F32(g) => Cow::Borrowed(g.as_ref()),
+            LayerView::U16(g) => Cow::Owned(g.map(|v| v as f32)),
+            LayerView::U8(g) => Cow::Owned(g.map(|v| v as f32)),
+        }
+    }
+}
+
+/// Cell types a layer can have, for `Map::layer` and `Map::insert_layer`.
+pub trait LayerValue: Copy + Default + Send + Sync + 'static {
+    const TYPE: LayerType;
+    fn wrap(grid: Grid<Self>) -> LayerData;
+    fn unwrap(view: LayerView<'_>) -> Option<Cow<'_, Grid<Self>>>;
+}
+
+impl LayerValue for f32 {
+    const TYPE: LayerType = LayerType::F32;
+
+    fn wrap(grid: Grid<f32>) -> LayerData {
+        LayerData::F32(grid)
+    }
+
+    fn unwrap(view: LayerView<'_>) -> Option<Cow<'_, Grid<f32>>> {
+        match view {
+            LayerView::F32(g) => Some(g),
+            _ => None,
+        }
+    }
+}
+
+impl LayerValue for u16 {
+    const TYPE: LayerType = LayerType::U16;
+
+    fn wrap(grid: Grid<u16>) -> LayerData {
+        LayerData::U16(grid)
+    }
+
+    fn unwrap(view: LayerView<'_>) -> Option<Cow<'_, Grid<u16>>> {
+        match view {
+            LayerView::U16(g) => Some(Cow::Borrowed(g)),
+            _ => None,
+        }
+    }
+}
+
+impl LayerValue for u8 {
+    const TYPE: LayerType = LayerType::U8;
+
+    fn wrap(grid: Grid<u8>) -> LayerData {
+        LayerData::U8(grid)
+    }
+
+    fn unwrap(view: LayerView<'_>) -> Option<Cow<'_, Grid<u8>>> {
+        match view {
+            LayerView::U8(g) => Some(Cow::Borrowed(g)),
+            _ => None,
+        }
+    }
+}
+
+impl Map {
+    /// Name and cell type of every grid layer: the built-in ones in field
+    /// order, then added layers by name.
+    pub fn layers(&self) -> Vec<LayerInfo> {
+        let builtin = BUILTIN_LAYERS.iter().map(|&(name, ty)| LayerInfo { name: name.into(), ty, builtin: true });
+        let added = self.extra_layers.iter().map(|(name, data)| LayerInfo {
+            name: name.clone(),
+            ty: data.view().ty(),
+            builtin: false,
+        });
+        builtin.chain(added).collect()
+    }
+
+    /// The layer called `name`, whatever its type.
+    pub fn layer_view(&self, name: &str) -> Option<LayerView<'_>> {
+        let view = match name {
+            "height" => LayerView::F32(Cow::Borrowed(&self.height)),
+            "plate_id" => LayerView::U16(&self.plate_id),
+            "boundary_type" => LayerView::U8(&self.boundary_type),
+            "boundary_major" => LayerView::U8(&self.boundary_major),
+            "boundary_dist" => LayerView::F32(Cow::Owned(self.boundary_dist.decode())),
+            "temperature" => LayerView::F32(Cow::Owned(self.temperature.decode())),
+            "precipitation" => LayerView::F32(Cow::Owned(self.precipitation.decode())),
+            "ice" => LayerView::U8(&self.ice),
+            "river_flow" => LayerView::F32(Cow::Borrowed(&self.river_flow)),
+            "population" => LayerView::F32(Cow::Borrowed(&self.population)),
+            "nation_id" => LayerView::U16(&self.nation_id),
+            "culture_id" => LayerView::U16(&self.culture_id),
+            _ => return self.extra_layers.get(name).map(LayerData::view),
+        };
+        Some(view)
+    }
+
+    /// The layer called `name` if its cells are `T`s.
+    pub fn layer<T: LayerValue>(&self, name: &str) -> Option<Cow<'_, Grid<T>>> {
+        T::unwrap(self.layer_view(name)?)
+    }
+
+    /// Add (or replace) a layer, returning the one it replaced. Panics if
+    /// `name` is a built-in layer or `grid` isn't the map's size.
+    pub fn insert_layer<T: LayerValue>(&mut self, name: impl Into<String>, grid: Grid<T>) -> Option<LayerData> {
+        let name = name.into();
+        assert!(
+            !BUILTIN_LAYERS.iter().any(|&(n, _)| n == name),
+            "`{}` is a built-in layer",
+            name
+        );
+        assert_eq!((grid.w, grid.h), (self.w, self.h), "layer `{}` is not the map's size", name);
+        self.extra_layers.insert(name, T::wrap(grid))
+    }
+
+    /// Remove an added layer.
+    pub fn remove_layer(&mut self, name: &str) -> Option<LayerData> {
+        self.extra_layers.remove(name)
+    }
+}
@@ -1,18 +1,62 @@
+#[cfg(feature = "parallel")]
+pub mod batch;
+pub mod cancel;
 pub mod climate;
 pub mod config;
+pub mod culture;
+pub mod diff;
+pub mod edit;
 pub mod elevation;
+pub mod events;
+pub mod export;
+pub mod fmath;
+pub mod generator;
+pub mod golden;
+#[cfg(feature = "gpu")]
+pub mod gpu;
 pub mod grid;
+pub mod history;
 pub mod hydrology;
+pub mod import;
+pub mod layers;
+pub mod mapfile;
 pub mod noise;
+pub mod pipeline;
 pub mod plates;
+pub mod poi;
+pub mod population;
+pub mod profile;
+pub mod pyramid;
+pub mod query;
+pub mod regions;
 pub mod render;
+pub mod rivers;
 pub mod rng;
+pub mod statistics;
+pub mod terrain;
+#[cfg(feature = "parallel")]
+pub mod threads;
 
-use std::time::Instant;
+mod clock;
+mod par;
+mod scratch;
 
-use config::Params;
-use grid::Grid;
+use std::collections::BTreeMap;
 
+use serde::{Deserialize, Serialize};
+
+use cancel::CancelToken;
+use clock::Instant;
+use config::{InvalidParams, Params};
+use grid::{Grid, QuantGrid, Rect};
+use layers::LayerData;
+#[cfg(feature = "parallel")]
+pub use batch::{generate_batch, generate_batch_with};
+pub use generator::Generator;
+pub use golden::fingerprint;
+pub use pipeline::{Pipeline, Stage};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Map {
     pub w: usize,
     pub h: usize,
@@ -20,13 +64,121 @@ pub struct Map {
     pub plate_id: Grid<u16>,
     pub boundary_type: Grid<u8>,
     pub boundary_major: Grid<u8>,
-    pub boundary_dist: Grid<f32>,
+    /// Distance to the nearest plate boundary, in cells (16-bit quantized).
+    pub boundary_dist: QuantGrid,
     pub macro_id: Vec<usize>,
     pub num_macro: usize,
     pub rgba: Vec<u8>,
-    pub temperature: Grid<f32>,
-    pub precipitation: Grid<f32>,
+    /// Degrees C (16-bit quantized).
+    pub temperature: QuantGrid,
+    /// mm/year (16-bit quantized).
+    pub precipitation: QuantGrid,
+    /// Ice class per cell (`climate::ICE_*`).
+    pub ice: Grid<u8>,
     pub river_flow: Grid<f32>,
+    pub population: Grid<f32>,
+    pub nation_id: Grid<u16>,
+    pub sites: Vec<history::Site>,
+    pub culture_id: Grid<u16>,
+    pub cultures: Vec<culture::Culture>,
+    /// Layers added after generation by name (see `layers`), e.g. by
+    /// subsystems that post-process a map.
+    pub extra_layers: BTreeMap<String, LayerData>,
+}
+
+impl Map {
+    /// Hash of each layer's serialized bytes (FNV-1a, 64-bit). Two runs
+    /// of a seed built the same world exactly when these match; compare
+    /// them across machines to check `strict-determinism`.
+    /// Added layers come after the built-in ones, so a map without any
+    /// digests as before they existed.
+    pub fn layer_digests(&self) -> Vec<(String, u64)> {
+        fn digest(value: &impl Serialize) -> u64 {
+            let bytes = bincode::serialize(value).expect("map layers serialize");
+            bytes.iter().fold(0xCBF2_9CE4_8422_2325u64, |h, &b| (h ^ b as u64).wrapping_mul(0x0100_0000_01B3))
+        }
+        let builtin = [
+            ("height", digest(&self.height)),
+            ("plate_id", digest(&self.plate_id)),
+            ("boundary_type", digest(&self.boundary_type)),
+            ("boundary_major", digest(&self.boundary_major)),
+            ("boundary_dist", digest(&self.boundary_dist)),
+            ("macro_id", digest(&self.macro_id)),
+            ("rgba", digest(&self.rgba)),
+            ("temperature", digest(&self.temperature)),
+            ("precipitation", digest(&self.precipitation)),
+            ("ice", digest(&self.ice)),
+            ("river_flow", digest(&self.river_flow)),
+            ("population", digest(&self.population)),
+            ("nation_id", digest(&self.nation_id)),
+            ("sites", digest(&self.sites)),
+            ("culture_id", digest(&self.culture_id)),
+            ("cultures", digest(&self.cultures)),
+        ];
+        let added = self.extra_layers.iter().map(|(name, data)| (name.clone(), digest(data)));
+        builtin.into_iter().map(|(name, d)| (name.to_string(), d)).chain(added).collect()
+    }
+
+    /// All `layer_digests` folded into one value.
+    pub fn digest(&self) -> u64 {
+        self.layer_digests().iter().fold(0, |h, &(_, d)| rng::splitmix64(h ^ d))
+    }
+
+    /// Extract a sub-window as a smaller map (e.g. one continent at higher
+    /// zoom). The window may cross the E-W seam. Every grid and the color
+    /// image are cropped, sites outside the window are dropped, and all
+    /// cell coordinates become window-relative. Plate and culture tables
+    /// are kept whole, so ids stay valid. Added layers are cropped too.
+    pub fn crop(&self, rect: Rect) -> Map {
+        let height = self.height.crop(rect);
+        let (w, h) = (height.w, height.h);
+
+        let mut rgba = Vec::with_capacity(w * h * 4);
+        for y in rect.y..rect.y + h {
+            let row = &self.rgba[y * self.w * 4..(y + 1) * self.w * 4];
+            for x in rect.x..rect.x + w {
+                let i = (x % self.w) * 4;
+                rgba.extend_from_slice(&row[i..i + 4]);
+            }
+        }
+
+        let sites = self
+            .sites
+            .iter()
+            .filter_map(|s| {
+                let dx = (s.x + self.w - rect.x % self.w) % self.w;
+                let dy = s.y.checked_sub(rect.y)?;
+                (dx < w && dy < h).then(|| history::Site { x: dx, y: dy, ..s.clone() })
+            })
+            .collect();
+
+        Map {
+            w,
+            h,
+            height,
+            plate_id: self.plate_id.crop(rect),
+            boundary_type: self.boundary_type.crop(rect),
+            boundary_major: self.boundary_major.crop(rect),
+            boundary_dist: self.boundary_dist.crop(rect),
+            macro_id: self.macro_id.clone(),
+            num_macro: self.num_macro,
+            rgba,
+            temperature: self.temperature.crop(rect),
+            precipitation: self.precipitation.crop(rect),
+            ice: self.ice.crop(rect),
+            river_flow: self.river_flow.crop(rect),
+            population: self.population.crop(rect),
+            nation_id: self.nation_id.crop(rect),
+            sites,
+            culture_id: self.culture_id.crop(rect),
+            cultures: self.cultures.clone(),
+            extra_layers: self
+                .extra_layers
+                .iter()
+                .map(|(name, data)| (name.clone(), data.map_grid(|g| g.crop(rect), |g| g.crop(rect), |g| g.crop(rect))))
+                .collect(),
+        }
+    }
 }
 
 pub struct Timing {
@@ -34,163 +186,245 @@ pub struct Timing {
     pub ms: f64,
 }
 
+/// Longest side a map can have: nearest-boundary coordinates are stored
+/// as u16, with `u16::MAX` reserved for "none".
+pub const MAX_SIDE: usize = u16::MAX as usize;
+
+/// Most cells a map can have. Generation keeps roughly 50 bytes of
+/// intermediates per cell, so this is already past 10 GB.
+pub const MAX_CELLS: usize = 1 << 28;
+
+/// Why generation didn't produce a map.
+#[derive(Debug)]
+pub enum WorldgenError {
+    InvalidParams(InvalidParams),
+    /// A side is longer than `MAX_SIDE`.
+    TooLarge { w: usize, h: usize },
+    /// More than `MAX_CELLS` cells; generation would run out of memory.
+    TooManyCells { w: usize, h: usize },
+    /// The `CancelToken` fired before generation finished.
+    Cancelled,
+}
+
+impl std::fmt::Display for WorldgenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorldgenError::InvalidParams(e) => e.fmt(f),
+            WorldgenError::TooLarge { w, h } => {
+                write!(f, "a {}x{} map is too large (at most {} cells per side)", w, h, MAX_SIDE)
+            }
+            WorldgenError::TooManyCells { w, h } => {
+                write!(f, "a {}x{} map has too many cells (at most {})", w, h, MAX_CELLS)
+            }
+            WorldgenError::Cancelled => write!(f, "generation cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for WorldgenError {}
+
+impl From<InvalidParams> for WorldgenError {
+    fn from(e: InvalidParams) -> Self {
+        WorldgenError::InvalidParams(e)
+    }
+}
+
+/// Check that a `w`x`h` map is within `MAX_SIDE` and `MAX_CELLS`. Empty
+/// or too-small maps are caught by `Params::validate_for_size`.
+pub fn check_size(w: usize, h: usize) -> Result<(), WorldgenError> {
+    if w > MAX_SIDE || h > MAX_SIDE {
+        return Err(WorldgenError::TooLarge { w, h });
+    }
+    if w.checked_mul(h).is_none_or(|cells| cells > MAX_CELLS) {
+        return Err(WorldgenError::TooManyCells { w, h });
+    }
+    Ok(())
+}
+
 /// Generate everything except hydrology (fast: ~2s at 2048x1024).
-pub fn generate_base(seed: u64, w: usize, h: usize, params: &Params) -> (Map, Vec<Timing>) {
-    let mut timings = Vec::new();
+/// Fails if the size doesn't pass `check_size` or `params` don't pass
+/// `Params::validate_for_size`.
+pub fn generate_base(seed: u64, w: usize, h: usize, params: &Params) -> Result<(Map, Vec<Timing>), WorldgenError> {
+    let plates_seed = params.seeds.plates.unwrap_or(seed);
+    let mut rng = rng::SeedTree::new(plates_seed).child("plates").rng();
+    generate_base_with_rng(seed, w, h, params, &mut rng)
+}
+
+/// `generate_base`, calling `progress(stage, fraction)` as it goes. Each
+/// stage reports 0.0 when it starts and 1.0 when it ends; plate growth
+/// and the distance field also report in between. Stops with
+/// `WorldgenError::Cancelled` soon after `cancel` fires.
+pub fn generate_base_with_progress(
+    seed: u64,
+    w: usize,
+    h: usize,
+    params: &Params,
+    progress: impl Fn(Stage, f32) + Sync,
+    cancel: &CancelToken,
+) -> Result<(Map, Vec<Timing>), WorldgenError> {
     let total_start = Instant::now();
+    let mut pipeline = Pipeline::new(seed, w, h, params.clone())?;
+    let mut timings = pipeline.run(&Stage::BASE, progress, cancel)?;
+    push_total(&mut timings, total_start);
+    Ok((pipeline.into_map(), timings))
+}
 
-    // 1. Seed macroplates first (needed for density-guided microplate seeding)
-    let t = Instant::now();
-    let macro_seeds = plates::seed::poisson_plate_seeds(
-        w, h, params.num_macroplates, seed ^ plates::properties::SALT_MACRO,
-    );
-    // 2. Seed microplates with variable density: denser near macroplate boundaries
-    let seeds = plates::seed::poisson_variable_seeds(
-        w, h, params.num_microplates, seed, &macro_seeds,
-    );
-    timings.push(Timing {
-        name: "plate_seed",
-        ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
+/// `generate_base` with plate seeding and plate properties drawn from
+/// `rng` instead of the default generator; the noise stages still follow
+/// `seed` (or their `params.seeds` overrides).
+pub fn generate_base_with_rng(
+    seed: u64,
+    w: usize,
+    h: usize,
+    params: &Params,
+    rng: &mut impl rng::RngCore,
+) -> Result<(Map, Vec<Timing>), WorldgenError> {
+    let total_start = Instant::now();
+    let mut pipeline = Pipeline::new(seed, w, h, params.clone())?;
+    let mut timings = pipeline.run_with_rng(&Stage::BASE, rng);
+    push_total(&mut timings, total_start);
+    Ok((pipeline.into_map(), timings))
+}
 
-    // 3. Grow microplates (noise-weighted Dijkstra)
-    let t = Instant::now();
-    let plate_id = plates::grow::grow_plates(w, h, &seeds, seed, params.boundary_noise);
+fn push_total(timings: &mut Vec<Timing>, start: Instant) {
     timings.push(Timing {
-        name: "plate_grow",
-        ms: t.elapsed().as_secs_f64() * 1000.0,
+        name: "TOTAL",
+        ms: start.elapsed().as_secs_f64() * 1000.0,
     });
+}
 
-    // 4. Assign plate properties (macro grouping + velocities)
-    let t = Instant::now();
-    let plate_set = plates::properties::assign_plate_properties(
-        params.num_microplates,
-        params.num_macroplates,
-        &seeds,
-        &macro_seeds,
-        &plate_id,
-        params.continental_fraction,
-        params.boundary_noise,
-        seed,
-    );
-    timings.push(Timing {
-        name: "plate_properties",
-        ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
+/// Compute hydrology (slow: ~8s at 2048x1024). Carves valleys into map.height.
+pub fn generate_rivers(map: &mut Map, seed: u64, params: &Params) -> (Grid<f32>, Timing) {
+    generate_rivers_with_progress(map, seed, params, |_, _| {}, &CancelToken::new()).expect("never cancelled")
+}
 
-    // 4. Extract + classify boundaries (major/minor)
+/// `generate_rivers`, calling `progress(Stage::Hydrology, fraction)` as
+/// it goes, including through priority flood and flow accumulation. If
+/// `cancel` fires first, returns `WorldgenError::Cancelled` and leaves
+/// `map.height` uncarved.
+pub fn generate_rivers_with_progress(
+    map: &mut Map,
+    seed: u64,
+    params: &Params,
+    progress: impl Fn(Stage, f32),
+    cancel: &CancelToken,
+) -> Result<(Grid<f32>, Timing), WorldgenError> {
     let t = Instant::now();
-    let (btype_grid, pa_grid, pb_grid, major_grid) =
-        plates::boundary::extract_boundaries(&plate_id, &plate_set);
-    timings.push(Timing {
-        name: "boundaries",
+    progress(Stage::Hydrology, 0.0);
+    let seed = params.seeds.hydrology.unwrap_or(seed);
+    let precipitation = map.precipitation.decode();
+    let report = |f| progress(Stage::Hydrology, f);
+    let river_flow = hydrology::compute_hydrology(&mut map.height, &precipitation, seed, params, &report, cancel)
+        .ok_or(WorldgenError::Cancelled)?;
+    progress(Stage::Hydrology, 1.0);
+    let timing = Timing {
+        name: "hydrology",
         ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
+    };
+    Ok((river_flow, timing))
+}
 
-    // 5. Distance field with nearest-boundary propagation
+/// Compute population density. Requires rivers (water access).
+pub fn generate_population(map: &Map, params: &Params) -> (Grid<f32>, Timing) {
     let t = Instant::now();
-    let (dist_grid, near_bx, near_by) =
-        plates::distance::boundary_distance_field(&btype_grid);
-    timings.push(Timing {
-        name: "distance_field",
+    let population = population::compute_population(
+        &map.height,
+        &map.temperature.decode(),
+        &map.precipitation.decode(),
+        &map.river_flow,
+        params.total_population,
+        params.world.cell_km(map.w),
+    );
+    let timing = Timing {
+        name: "population",
         ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
+    };
+    (population, timing)
+}
 
-    // 6. Build elevation from boundary profiles
+/// Found nations and simulate their borders. Requires population.
+pub fn generate_history(map: &Map, seed: u64, params: &Params) -> (Grid<u16>, Vec<history::Site>, Timing) {
     let t = Instant::now();
-    let height = elevation::build_elevation(
-        &plate_id,
-        &plate_set,
-        &btype_grid,
-        &dist_grid,
-        &near_bx,
-        &near_by,
-        &pa_grid,
-        &pb_grid,
-        &major_grid,
+    let (nation_id, sites) = history::simulate_history(
+        &map.height,
+        &map.population,
+        &map.river_flow,
+        params.num_nations,
+        params.history_epochs,
         seed,
-        params,
     );
-    timings.push(Timing {
-        name: "elevation",
-        ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
-
-    // 7. Render
-    let t = Instant::now();
-    let rgba = render::render_map(&height);
-    timings.push(Timing {
-        name: "render",
-        ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
-
-    // 8. Temperature
-    let t = Instant::now();
-    let temperature = climate::compute_temperature(&height, seed);
-    timings.push(Timing {
-        name: "temperature",
-        ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
-
-    // 9. Precipitation
-    let t = Instant::now();
-    let precipitation = climate::compute_precipitation(&height, &temperature, seed, params);
-    timings.push(Timing {
-        name: "precipitation",
+    let timing = Timing {
+        name: "history",
         ms: t.elapsed().as_secs_f64() * 1000.0,
-    });
-
-    let total_ms = total_start.elapsed().as_secs_f64() * 1000.0;
-    timings.push(Timing {
-        name: "TOTAL",
-        ms: total_ms,
-    });
-
-    let map = Map {
-        w,
-        h,
-        height,
-        plate_id,
-        boundary_type: btype_grid,
-        boundary_major: major_grid,
-        boundary_dist: dist_grid,
-        macro_id: plate_set.macro_id,
-        num_macro: plate_set.num_macro,
-        rgba,
-        temperature,
-        precipitation,
-        river_flow: Grid::new(w, h), // empty — computed separately
     };
-
-    (map, timings)
+    (nation_id, sites, timing)
 }
 
-/// Compute hydrology (slow: ~8s at 2048x1024). Carves valleys into map.height.
-pub fn generate_rivers(map: &mut Map, seed: u64, params: &Params) -> (Grid<f32>, Timing) {
+/// Partition land into culture areas with regional naming styles.
+pub fn generate_cultures(map: &Map, seed: u64, params: &Params) -> (Grid<u16>, Vec<culture::Culture>, Timing) {
     let t = Instant::now();
-    let river_flow = hydrology::compute_hydrology(&mut map.height, &map.precipitation, seed, params);
+    let (culture_id, cultures) = culture::compute_cultures(&map.height, params.num_cultures, seed);
     let timing = Timing {
-        name: "hydrology",
+        name: "cultures",
         ms: t.elapsed().as_secs_f64() * 1000.0,
     };
-    (river_flow, timing)
+    (culture_id, cultures, timing)
 }
 
-/// Full generate (used by CLI). Calls generate_base + generate_rivers.
-pub fn generate(seed: u64, w: usize, h: usize, params: &Params) -> (Map, Vec<Timing>) {
-    let (mut map, mut timings) = generate_base(seed, w, h, params);
+/// Full generate (used by CLI): every stage, from plates through rivers,
+/// population, history, and cultures.
+pub fn generate(seed: u64, w: usize, h: usize, params: &Params) -> Result<(Map, Vec<Timing>), WorldgenError> {
+    Generator::new(w, h)?.generate(seed, params)
+}
 
-    let (river_flow, hydro_timing) = generate_rivers(&mut map, seed, params);
-    map.river_flow = river_flow;
+/// Every stage downstream of elevation (climate, ice, rendering, rivers,
+/// population, history, cultures) over given terrain: meters, 0 = sea
+/// level, row 0 = north (see `import::load_heightmap`). Plate layers are
+/// left empty. Fails if the size doesn't pass `check_size` or `params`
+/// don't pass `Params::validate_for_size`.
+pub fn generate_from_heightmap(height: Grid<f32>, seed: u64, params: &Params) -> Result<(Map, Vec<Timing>), WorldgenError> {
+    let total_start = Instant::now();
+    let mut pipeline = Pipeline::from_heightmap(seed, height, params.clone())?;
+    let mut timings = pipeline
+        .run(&Stage::ALL, |_, _| {}, &CancelToken::new())
+        .expect("never cancelled");
+    push_total(&mut timings, total_start);
+    Ok((pipeline.into_map(), timings))
+}
 
-    // Recalculate total to include hydrology
-    let base_total = timings.pop().unwrap(); // remove base TOTAL
-    let total_ms = base_total.ms + hydro_timing.ms;
-    timings.push(hydro_timing);
-    timings.push(Timing {
-        name: "TOTAL",
-        ms: total_ms,
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 7;
+    const W: usize = 128;
+    const H: usize = 64;
+
+    #[test]
+    fn same_seed_builds_the_same_world() {
+        let params = Params::default();
+        let (a, _) = generate(SEED, W, H, &params).unwrap();
+        let (b, _) = generate(SEED, W, H, &params).unwrap();
+        assert_eq!(a.layer_digests(), b.layer_digests());
+
+        // Parallel reductions must not depend on how work is split
+        #[cfg(feature = "parallel")]
+        {
+            let pool = threads::pool(1).unwrap();
+            let (c, _) = pool.install(|| generate(SEED, W, H, &params)).unwrap();
+            assert_eq!(a.layer_digests(), c.layer_digests());
+        }
+    }
+
+    /// Digest of the test world under `strict-determinism`, as built on
+    /// x86-64 Linux. Every platform must reproduce it.
+    #[cfg(feature = "strict-determinism")]
+    const STRICT_DIGEST: u64 = 0xc732_49cf_7c79_34d8;
 
-    (map, timings)
+    #[cfg(feature = "strict-determinism")]
+    #[test]
+    fn strict_world_matches_reference() {
+        let (map, _) = generate(SEED, W, H, &Params::default()).unwrap();
+        assert_eq!(map.digest(), STRICT_DIGEST, "layer digests: {:016x?}", map.layer_digests());
+    }
 }
@@ -1,79 +1,558 @@
-use std::path::PathBuf;
-use worldgen::config::Params;
-use worldgen::render;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::time::Instant;
 
-fn main() {
-    let args: Vec<String> = std::env::args().collect();
+use clap::builder::PossibleValuesParser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use worldgen::cancel::CancelToken;
+use worldgen::config::{Params, Preset, RandomizeConstraints, WorldFile};
+use worldgen::events::Event;
+use worldgen::grid::Filter;
+use worldgen::render::{LayerKind, Palette, PalettePreset, RenderStyle};
+use worldgen::{Map, Pipeline, Stage, Timing, export, import, poi, profile, render};
 
-    let seed: u64 = args.get(1).and_then(|s| s.parse().ok()).unwrap_or(42);
-    let width: usize = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(2048);
-    let height: usize = args.get(3).and_then(|s| s.parse().ok()).unwrap_or(1024);
-    let out_dir: PathBuf = args
-        .get(4)
-        .map(PathBuf::from)
-        .unwrap_or_else(|| PathBuf::from("artifacts"));
+/// Procedural worlds from plate tectonics up through climate, rivers and
+/// history.
+#[derive(Parser)]
+#[command(name = "worldgen", version)]
+struct Cli {
+    /// Worker threads (default: one per core)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a world and write its layers, exports and previews
+    Generate(GenerateArgs),
+    /// Render one layer of a world as a PNG
+    Render(RenderArgs),
+    /// Print a world's statistics as JSON
+    Stats {
+        /// A world.toml (regenerated) or a map saved with `Map::save`
+        world: PathBuf,
+    },
+    /// Write a world's full-precision heightmap and its layers for NumPy
+    Export {
+        /// A world.toml (regenerated) or a map saved with `Map::save`
+        world: PathBuf,
+        #[arg(long, default_value = "export")]
+        out: PathBuf,
+    },
+    /// Run the web server (the `server` binary) with the remaining arguments
+    Serve {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// Map size in cells
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "2048x1024", value_parser = parse_size)]
+    size: (usize, usize),
+    #[arg(long, default_value = "artifacts")]
+    out: PathBuf,
+    /// Outputs to write (default: all): layers as in `render --layer`, and
+    /// composite, map_boundaries, profile, globe, pois, events and export
+    #[arg(long, value_name = "NAMES", value_delimiter = ',', value_parser = output_names())]
+    layers: Vec<String>,
+    /// How layers are written: rendered, or as their values (at the
+    /// generated size under --supersample)
+    #[arg(long, value_enum, default_value_t = Format::Png)]
+    format: Format,
+    /// Also write slippy-map tiles (z/x/y.png) of the final map up to this
+    /// zoom (at most 20)
+    #[arg(long, value_name = "MAX_ZOOM", value_parser = clap::value_parser!(u32).range(..=render::tiles::MAX_ZOOM as i64))]
+    tiles: Option<u32>,
+    /// Generate at N times the size and filter map-sized images back down.
+    /// Generation depends on resolution, so this builds a different world
+    /// from the same seed, not a smoother render of the N=1 one
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    supersample: u32,
+    /// Built-in world type, or `random` for parameters drawn from the seed
+    #[arg(long, value_parser = preset_names(), conflicts_with_all = ["config", "world"])]
+    preset: Option<String>,
+    /// TOML or JSON parameter file, or a world.toml for its parameters
+    /// alone; missing fields keep their defaults
+    #[arg(long, visible_alias = "params", value_name = "FILE", conflicts_with = "world")]
+    config: Option<PathBuf>,
+    /// Set one parameter over the preset, config or world (repeatable);
+    /// dotted keys reach into tables, as in `world.gravity=5`
+    #[arg(long = "param", value_name = "KEY=VALUE", value_parser = parse_override)]
+    overrides: Vec<(String, String)>,
+    /// A world.toml from an earlier run, for its seed, size, supersampling
+    /// and parameters
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["seed", "size", "supersample"])]
+    world: Option<PathBuf>,
+    /// Build climate, rivers and history over this terrain (raw f32 or
+    /// PNG) instead of generating it; its size replaces --size
+    #[arg(long, value_name = "FILE", conflicts_with = "size")]
+    heightmap: Option<PathBuf>,
+    /// Meters the darkest and brightest pixels of a PNG heightmap stand
+    /// for (an exported heightmap's JSON sidecar gives them otherwise)
+    #[arg(long, value_name = "MIN,MAX", requires = "heightmap", allow_hyphen_values = true, value_parser = parse_range)]
+    height_range: Option<(f32, f32)>,
+}
+
+/// File format of `generate`'s layers.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Rendered colors
+    Png,
+    /// Values as 16-bit grayscale, scaled min..max, with a JSON sidecar
+    Png16,
+    /// Values as 32-bit float grayscale
+    Tiff,
+    /// Values as little-endian f32, with a JSON sidecar
+    Raw,
+    /// Values as arrays in one layers.npz for NumPy
+    Npz,
+}
+
+/// `generate` outputs besides the layers, selectable with --layers.
+const EXTRA_OUTPUTS: [&str; 7] = ["composite", "map_boundaries", "profile", "globe", "pois", "events", "export"];
+
+#[derive(Args)]
+struct RenderArgs {
+    /// A world.toml (regenerated) or a map saved with `Map::save`
+    world: PathBuf,
+    #[arg(long, default_value = "map", value_parser = layer_names())]
+    layer: String,
+    #[arg(long, default_value = "map.png")]
+    out: PathBuf,
+    #[arg(long, value_parser = palette_names())]
+    palette: Option<String>,
+    /// Hillshade strength, 0 to 1
+    #[arg(long, default_value_t = 0.0)]
+    hillshade: f32,
+    /// Graticule spacing in degrees
+    #[arg(long, value_name = "DEGREES")]
+    graticule: Option<f32>,
+    /// Draw rivers over the layer
+    #[arg(long)]
+    rivers: bool,
+    /// Draw plate boundaries over the layer
+    #[arg(long)]
+    boundaries: bool,
+}
+
+fn preset_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(Preset::ALL.iter().map(|p| p.name()).chain(["random"]))
+}
+
+fn layer_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(LayerKind::ALL.iter().map(|k| k.name()))
+}
+
+fn output_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(LayerKind::ALL.iter().map(|k| k.name()).chain(EXTRA_OUTPUTS))
+}
+
+fn palette_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(PalettePreset::ALL.iter().map(|p| p.name()))
+}
+
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    s.split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .ok_or_else(|| format!("`{}` is not WIDTHxHEIGHT", s))
+}
+
+fn parse_range(s: &str) -> Result<(f32, f32), String> {
+    s.split_once(',')
+        .and_then(|(lo, hi)| Some((lo.trim().parse().ok()?, hi.trim().parse().ok()?)))
+        .ok_or_else(|| format!("`{}` is not MIN,MAX in meters", s))
+}
+
+fn parse_override(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("`{}` is not KEY=VALUE", s))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if let Some(threads) = cli.threads {
+        #[cfg(feature = "parallel")]
+        worldgen::threads::set_global_threads(threads).expect("thread pool is set up first");
+        #[cfg(not(feature = "parallel"))]
+        let _ = threads;
+    }
+    let result = match cli.command {
+        Command::Generate(args) => generate(args),
+        Command::Render(args) => render_layer(args),
+        Command::Stats { world } => stats(&world),
+        Command::Export { world, out } => export_world(&world, &out),
+        Command::Serve { args } => return serve(cli.threads, args),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    std::fs::create_dir_all(&out_dir).expect("failed to create output directory");
+/// A world.toml, warning if another version of worldgen wrote it.
+fn load_world_file(path: &Path) -> Result<WorldFile, String> {
+    let file = WorldFile::load(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !file.is_current() {
+        eprintln!(
+            "warning: {} was written by worldgen {}; this is {}",
+            path.display(),
+            file.version,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+    Ok(file)
+}
 
-    let params = Params::default();
+/// Parameters from a parameter file, or from the `[params]` table of a
+/// world.toml (its seed and size are left out).
+fn load_config(path: &Path) -> Result<Params, String> {
+    let is_world = path.extension().is_some_and(|e| e == "toml")
+        && std::fs::read_to_string(path).is_ok_and(|text| {
+            text.parse::<toml::Table>().is_ok_and(|table| table.contains_key("params"))
+        });
+    if is_world {
+        Ok(load_world_file(path)?.params)
+    } else {
+        Params::from_file(path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+/// `params` with `--param KEY=VALUE` overrides applied in order. Values
+/// read as JSON (numbers, `true`, `null`) and otherwise as strings, so
+/// names like `simplex` need no quotes.
+fn apply_overrides(params: Params, overrides: &[(String, String)]) -> Result<Params, String> {
+    let mut params = params;
+    for (key, value) in overrides {
+        let mut root = serde_json::to_value(&params).expect("params serialize");
+        let (tables, field) = key.rsplit_once('.').unwrap_or(("", key));
+        let mut table = &mut root;
+        for name in tables.split('.').filter(|name| !name.is_empty()) {
+            table = table
+                .get_mut(name)
+                .filter(|t| t.is_object())
+                .ok_or_else(|| format!("--param {}: `{}` is not a parameter table", key, name))?;
+        }
+        let value = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.clone()));
+        table.as_object_mut().expect("tables are objects").insert(field.to_string(), value);
+        params = serde_json::from_value(root).map_err(|e| format!("--param {}: {}", key, e))?;
+    }
+    Ok(params)
+}
+
+/// The world at `path`: a map saved with `Map::save`, or a world.toml,
+/// regenerated.
+fn load_world(path: &Path) -> Result<Map, String> {
+    if path.extension().is_some_and(|e| e == "toml") {
+        let file = load_world_file(path)?;
+        let (width, height) = file.generated_size();
+        eprintln!("Generating {}x{} map with seed={}", width, height, file.seed);
+        let (map, _) = worldgen::generate(file.seed, width, height, &file.params).map_err(|e| e.to_string())?;
+        Ok(map)
+    } else {
+        Map::load(path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+fn save_png(path: &Path, rgba: &[u8], w: usize, h: usize) {
+    image::save_buffer(path, rgba, w as u32, h as u32, image::ColorType::Rgba8).expect("failed to save image");
+    eprintln!("Saved {}", path.display());
+}
+
+fn generate(args: GenerateArgs) -> Result<(), String> {
+    // Everything unless --layers names some; layers without values of
+    // their own can only be rendered
+    let wants = |name: &str| args.layers.is_empty() || args.layers.iter().any(|n| n == name);
+    if args.format != Format::Png {
+        let named = args.layers.iter().filter_map(|name| LayerKind::from_name(name));
+        if let Some(kind) = named.into_iter().find(|kind| kind.grid().is_none()) {
+            let format = args.format.to_possible_value().expect("formats have names");
+            return Err(format!(
+                "layer `{}` has no values to write as {}; use --format png",
+                kind.name(),
+                format.get_name()
+            ));
+        }
+    }
+    let kinds: Vec<LayerKind> = LayerKind::ALL
+        .into_iter()
+        .filter(|kind| wants(kind.name()) && (args.format == Format::Png || kind.grid().is_some()))
+        .collect();
+
+    let heightmap = match &args.heightmap {
+        Some(path) => {
+            let grid = import::load_heightmap(path, args.height_range);
+            Some(grid.map_err(|e| format!("{}: {}", path.display(), e))?)
+        }
+        None => None,
+    };
+
+    // A world.toml from an earlier run stands in for seed, size,
+    // supersampling and parameters
+    let world_file = args.world.as_deref().map(load_world_file).transpose()?;
+    let (seed, (width, height), supersample) = match &world_file {
+        Some(file) => (file.seed, (file.width, file.height), file.supersample),
+        None => (args.seed, args.size, args.supersample as usize),
+    };
+    let (width, height) = heightmap.as_ref().map_or((width, height), |grid| (grid.w, grid.h));
+    let out_dir = args.out;
+
+    std::fs::create_dir_all(&out_dir).map_err(|e| format!("{}: {}", out_dir.display(), e))?;
+
+    let params = match (world_file, args.preset.as_deref(), &args.config) {
+        (Some(file), _, _) => file.params,
+        (None, Some("random"), _) => {
+            Params::randomize(seed, &RandomizeConstraints::default()).expect("default constraints are valid")
+        }
+        (None, Some(name), _) => Params::preset(Preset::from_name(name).expect("clap checked the name")),
+        (None, None, Some(path)) => load_config(path)?,
+        (None, None, None) => Params::default(),
+    };
+    let params = apply_overrides(params, &args.overrides)?;
+
+    if let Ok(warnings) = params.validate() {
+        for w in warnings {
+            eprintln!("warning: {}", w);
+        }
+    }
 
     eprintln!(
-        "Generating {}x{} map with seed={}, macro={}, micro={}",
-        width, height, seed, params.num_macroplates, params.num_microplates
+        "Generating {}x{} map with seed={}, macro={}, micro={}, supersample={}",
+        width, height, seed, params.num_macroplates, params.num_microplates, supersample
     );
 
-    let (map, timings) = worldgen::generate(seed, width, height, &params);
+    // Supersampling generates at N times the output size; map-sized images
+    // are filtered back down when saved. Plate counts, noise scales and
+    // hydrology are per map, not per km, so this is a different world
+    let (gen_w, gen_h) = (width.saturating_mul(supersample), height.saturating_mul(supersample));
+    let imported = heightmap.is_some();
+    let start = Instant::now();
+    let pipeline = match heightmap {
+        Some(grid) if supersample > 1 => {
+            Pipeline::from_heightmap(seed, grid.resample(gen_w, gen_h, Filter::Bicubic), params.clone())
+        }
+        Some(grid) => Pipeline::from_heightmap(seed, grid, params.clone()),
+        None => Pipeline::new(seed, gen_w, gen_h, params.clone()),
+    };
+    let mut pipeline = pipeline.map_err(|e| e.to_string())?;
+    // Event log: what formed where, for lore and debugging, collected as
+    // the stages that form it finish
+    let (event_tx, event_rx) = mpsc::channel();
+    if wants("events") {
+        pipeline.set_event_listener(Some(Box::new(move |event| {
+            let _ = event_tx.send(event);
+        })));
+    }
+    let mut timings = pipeline.run(&Stage::ALL, |_, _| {}, &CancelToken::new()).map_err(|e| e.to_string())?;
+    timings.push(Timing { name: "TOTAL", ms: start.elapsed().as_secs_f64() * 1000.0 });
+    let map = pipeline.into_map();
 
     // Print timings
     eprintln!("\nTimings:");
     for t in &timings {
         eprintln!("  {:20} {:8.1} ms", t.name, t.ms);
     }
+    eprintln!("World digest: {:016x}", map.digest());
+
+    // Settings to regenerate this world: pass the file back with --world
+    // (not for imported terrain, which the settings can't rebuild)
+    if !imported {
+        let world_path = out_dir.join("world.toml");
+        WorldFile::new(seed, width, height, &params)
+            .with_supersample(supersample)
+            .save(&world_path)
+            .expect("failed to save world file");
+        eprintln!("Saved {}", world_path.display());
+    }
 
     // Save diagnostic PNGs
     let save = |name: &str, rgba: &[u8], w: usize, h: usize| {
-        let path = out_dir.join(name);
-        image::save_buffer(&path, rgba, w as u32, h as u32, image::ColorType::Rgba8)
-            .expect("failed to save image");
-        eprintln!("Saved {}", path.display());
+        if supersample > 1 && (w, h) == (gen_w, gen_h) {
+            let rgba = render::resample::downsample(rgba, w, h, supersample);
+            save_png(&out_dir.join(name), &rgba, width, height);
+        } else {
+            save_png(&out_dir.join(name), rgba, w, h);
+        }
     };
 
-    // 1. Plate map
-    let plate_rgba = render::render_plates(
-        &map.plate_id,
-        &map.boundary_type,
-        &map.boundary_major,
-        &map.macro_id,
-        map.num_macro,
-    );
-    save("plates.png", &plate_rgba, width, height);
+    // The selected layers, re-rendered from the finished map or as values
+    if args.format == Format::Png {
+        let style = RenderStyle::default();
+        for &kind in &kinds {
+            let rgba = render::render_layer(&map, kind, &style);
+            save(&format!("{}.png", kind.name()), &rgba, gen_w, gen_h);
+        }
+    } else {
+        write_layer_values(&map, &kinds, args.format, &out_dir);
+    }
 
-    // 2. Boundary types
-    let bound_rgba = render::render_boundaries(&map.boundary_type, &map.boundary_major);
-    save("boundaries.png", &bound_rgba, width, height);
+    if wants("export") {
+        write_exports(&map, &out_dir);
+    }
 
-    // 3. Distance field
-    let dist_rgba = render::render_distance(&map.boundary_dist);
-    save("distance.png", &dist_rgba, width, height);
+    // Cross-section once around the equator (three legs; 180-degree
+    // legs would be ambiguous great circles)
+    if wants("profile") {
+        let waypoints = [(0.0, -180.0), (0.0, -60.0), (0.0, 60.0), (0.0, 180.0)];
+        let samples = profile::extract_profile(&map, &waypoints, 1024);
+        save("profile.png", &render::render_profile(&samples, 1024, 256), 1024, 256);
+    }
 
-    // 4. Grayscale heightmap
-    let hmap_rgba = render::render_heightmap(&map.height);
-    save("heightmap.png", &hmap_rgba, width, height);
+    // Composite: final map with hillshade, rivers, and graticule overlays
+    if wants("composite") {
+        let composite_style = RenderStyle {
+            hillshade: 0.35,
+            river_overlay: true,
+            graticule: Some(30.0),
+            ..RenderStyle::default()
+        };
+        let composite = render::render_layer(&map, LayerKind::Map, &composite_style);
+        save("composite.png", &composite, gen_w, gen_h);
+    }
 
-    // 5. Final rendered map
-    save("map.png", &map.rgba, width, height);
+    if wants("map_boundaries") {
+        let tectonic_style = RenderStyle { boundaries: true, ..RenderStyle::default() };
+        let tectonic = render::render_layer(&map, LayerKind::Map, &tectonic_style);
+        save("map_boundaries.png", &tectonic, gen_w, gen_h);
+    }
 
-    // 6. Temperature
-    let temp_rgba = render::render_temperature(&map.temperature);
-    save("temperature.png", &temp_rgba, width, height);
+    // Rotating globe
+    if wants("globe") {
+        let frames = render::animation::globe_frames(&map.rgba, gen_w, gen_h, 256, 36, 20.0);
+        let path = out_dir.join("globe.gif");
+        std::fs::write(&path, render::animation::encode_gif(&frames, 256, 80)).expect("failed to write GIF");
+        eprintln!("Saved {}", path.display());
+    }
 
-    // 7. Precipitation
-    let precip_rgba = render::render_precipitation(&map.precipitation);
-    save("precipitation.png", &precip_rgba, width, height);
+    // Points of interest (GeoJSON)
+    if wants("pois") {
+        let pois = poi::extract_pois(&map);
+        let path = out_dir.join("pois.json");
+        let json = serde_json::to_string_pretty(&poi::to_geojson(&pois)).expect("failed to encode POIs");
+        std::fs::write(&path, json).expect("failed to write POIs");
+        eprintln!("Saved {} ({} features)", path.display(), pois.len());
+    }
 
-    // 8. Rivers
-    let river_rgba = render::render_rivers(&map.height, &map.river_flow, &map.precipitation, &map.temperature);
-    save("rivers.png", &river_rgba, width, height);
+    if wants("events") {
+        let events: Vec<Event> = event_rx.try_iter().collect();
+        let path = out_dir.join("events.json");
+        let json = serde_json::to_string_pretty(&events).expect("failed to encode events");
+        std::fs::write(&path, json).expect("failed to write events");
+        eprintln!("Saved {} ({} events)", path.display(), events.len());
+    }
+
+    // Slippy-map tiles (z/x/y.png) of the final map
+    if let Some(max_zoom) = args.tiles {
+        let tile_dir = out_dir.join("tiles");
+        let n = render::tiles::write_pyramid(&tile_dir, &map.rgba, gen_w, gen_h, max_zoom)
+            .expect("failed to write tiles");
+        eprintln!("Saved {} ({} tiles, zoom 0-{})", tile_dir.display(), n, max_zoom);
+    }
 
     eprintln!("\nDone.");
+    Ok(())
+}
+
+/// The values behind `kinds` (see `LayerKind::grid`), one file per layer
+/// named after it, or one layers.npz of their grids.
+fn write_layer_values(map: &Map, kinds: &[LayerKind], format: Format, out_dir: &Path) {
+    let write = |name: String, bytes: &[u8]| {
+        let path = out_dir.join(name);
+        std::fs::write(&path, bytes).expect("failed to write layer");
+        eprintln!("Saved {}", path.display());
+    };
+    let grids: Vec<&str> = kinds.iter().filter_map(|kind| kind.grid()).collect();
+    if format == Format::Npz {
+        write("layers.npz".into(), &export::layers_npz(map, &grids).expect("failed to export npz"));
+        return;
+    }
+    for kind in kinds {
+        let Some(grid) = kind.grid() else { continue };
+        let view = map.layer_view(grid).expect("built-in layers exist");
+        let values = view.to_f32();
+        let name = kind.name();
+        match format {
+            Format::Png16 => write(format!("{}16.png", name), &export::heightmap_png16(&values)),
+            Format::Tiff => {
+                write(format!("{}.tif", name), &export::layer_tiff(&values).expect("failed to encode TIFF"))
+            }
+            Format::Raw => write(format!("{}.f32", name), &export::heightmap_f32le(&values)),
+            Format::Png | Format::Npz => unreachable!("written above"),
+        }
+        if matches!(format, Format::Png16 | Format::Raw) {
+            let meta = export::layer_meta(&values, export::layer_units(grid));
+            let json = serde_json::to_string_pretty(&meta).expect("sidecars serialize");
+            write(format!("{}.json", name), json.as_bytes());
+        }
+    }
+}
+
+/// Full-precision heightmap (16-bit PNG, raw f32, JSON sidecar) and every
+/// grid layer for NumPy (`numpy.load("map.npz")`).
+fn write_exports(map: &Map, out_dir: &Path) {
+    export::write_heightmap(out_dir, "heightmap", &map.height).expect("failed to export heightmap");
+    for name in ["heightmap16.png", "heightmap.f32", "heightmap.json"] {
+        eprintln!("Saved {}", out_dir.join(name).display());
+    }
+    let path = out_dir.join("map.npz");
+    export::write_npz(&path, map).expect("failed to export npz");
+    eprintln!("Saved {}", path.display());
+}
+
+fn render_layer(args: RenderArgs) -> Result<(), String> {
+    let map = load_world(&args.world)?;
+    let kind = LayerKind::from_name(&args.layer).expect("clap checked the name");
+    let palette = args.palette.as_deref().and_then(PalettePreset::from_name).map(Palette::preset);
+    let style = RenderStyle {
+        palette: palette.unwrap_or_default(),
+        hillshade: args.hillshade.clamp(0.0, 1.0),
+        river_overlay: args.rivers,
+        boundaries: args.boundaries,
+        graticule: args.graticule.filter(|&g| g > 0.0),
+        ..RenderStyle::default()
+    };
+    save_png(&args.out, &render::render_layer(&map, kind, &style), map.w, map.h);
+    Ok(())
+}
+
+fn stats(world: &Path) -> Result<(), String> {
+    let map = load_world(world)?;
+    println!("{}", serde_json::to_string_pretty(&map.statistics()).expect("statistics serialize"));
+    Ok(())
+}
+
+fn export_world(world: &Path, out_dir: &Path) -> Result<(), String> {
+    let map = load_world(world)?;
+    std::fs::create_dir_all(out_dir).map_err(|e| format!("{}: {}", out_dir.display(), e))?;
+    write_exports(&map, out_dir);
+    Ok(())
+}
+
+/// Run the `server` binary built beside this one, passing `--threads` on.
+fn serve(threads: Option<usize>, args: Vec<String>) -> ExitCode {
+    let exe = std::env::current_exe().expect("the running executable has a path");
+    let server = exe.with_file_name(format!("server{}", std::env::consts::EXE_SUFFIX));
+    let mut command = std::process::Command::new(&server);
+    if let Some(threads) = threads {
+        command.args(["--threads", &threads.to_string()]);
+    }
+    match command.args(args).status() {
+        Ok(status) if status.success() => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}: {} (build it with `cargo build --release --bin server`)", server.display(), e);
+            ExitCode::FAILURE
+        }
+    }
 }
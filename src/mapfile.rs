@@ -0,0 +1,54 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::Map;
+
+/// Leading bytes of every saved map.
+const MAGIC: &[u8; 8] = b"WGMAP\0\0\0";
+
+/// Layout version written after the magic. Bump whenever `Map` (or any
+/// type inside it) changes shape; older files are then rejected instead
+/// of decoding into garbage.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Write a map: magic, little-endian `FORMAT_VERSION`, then the bincode
+/// encoding of every field.
+pub fn write_map<W: Write>(mut out: W, map: &Map) -> io::Result<()> {
+    out.write_all(MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut out, map).map_err(io::Error::other)?;
+    out.flush()
+}
+
+/// Read a map written by `write_map`.
+pub fn read_map<R: Read>(mut input: R) -> io::Result<Map> {
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a worldgen map file"));
+    }
+    let mut version = [0u8; 4];
+    input.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("map file version {} (expected {})", version, FORMAT_VERSION),
+        ));
+    }
+    bincode::deserialize_from(input).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+impl Map {
+    /// Persist the whole map so an expensive generation can be reloaded
+    /// later instead of regenerated.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_map(BufWriter::new(File::create(path)?), self)
+    }
+
+    /// Load a map written by `Map::save`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Map> {
+        read_map(BufReader::new(File::open(path)?))
+    }
+}
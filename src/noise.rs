@@ -1,19 +1,126 @@
-use crate::rng::hash2;
+use serde::{Deserialize, Serialize};
+
+use crate::fmath;
+use crate::grid::Grid;
+use crate::rng::{Rng, RngCore, hash2, splitmix32};
 
 #[inline]
 fn smootherstep(t: f32) -> f32 {
     t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
 }
 
+/// d/dt of `smootherstep`.
+#[inline]
+fn smootherstep_d(t: f32) -> f32 {
+    30.0 * t * t * (t - 1.0) * (t - 1.0)
+}
+
 #[inline]
 fn lerp(a: f32, b: f32, t: f32) -> f32 {
     a + (b - a) * t
 }
 
+#[inline]
+fn grad(hash: u32, dx: f32, dy: f32) -> f32 {
+    // 16 evenly-spaced unit gradients (every 22.5°).
+    // Eliminates the directional bias of 4-gradient Perlin.
+    match hash & 15 {
+        0  =>  dx,
+        1  =>  0.924 * dx + 0.383 * dy,
+        2  =>  0.707 * (dx + dy),
+        3  =>  0.383 * dx + 0.924 * dy,
+        4  =>  dy,
+        5  => -0.383 * dx + 0.924 * dy,
+        6  =>  0.707 * (-dx + dy),
+        7  => -0.924 * dx + 0.383 * dy,
+        8  => -dx,
+        9  => -0.924 * dx - 0.383 * dy,
+        10 =>  0.707 * (-dx - dy),
+        11 => -0.383 * dx - 0.924 * dy,
+        12 => -dy,
+        13 =>  0.383 * dx - 0.924 * dy,
+        14 =>  0.707 * (dx - dy),
+        _  =>  0.924 * dx - 0.383 * dy,
+    }
+}
+
+/// Direction of the gradient `grad` dots with, as (gx, gy).
+#[inline]
+fn grad_dir(hash: u32) -> (f32, f32) {
+    match hash & 15 {
+        0  => ( 1.0,    0.0),
+        1  => ( 0.924,  0.383),
+        2  => ( 0.707,  0.707),
+        3  => ( 0.383,  0.924),
+        4  => ( 0.0,    1.0),
+        5  => (-0.383,  0.924),
+        6  => (-0.707,  0.707),
+        7  => (-0.924,  0.383),
+        8  => (-1.0,    0.0),
+        9  => (-0.924, -0.383),
+        10 => (-0.707, -0.707),
+        11 => (-0.383, -0.924),
+        12 => ( 0.0,   -1.0),
+        13 => ( 0.383, -0.924),
+        14 => ( 0.707, -0.707),
+        _  => ( 0.924, -0.383),
+    }
+}
+
+/// A noise value with its analytical partial derivatives in the input
+/// coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NoiseGrad {
+    pub value: f32,
+    /// dN/dx.
+    pub dx: f32,
+    /// dN/dy.
+    pub dy: f32,
+}
+
+impl NoiseGrad {
+    /// Length of the gradient: steepness of the field.
+    #[inline]
+    pub fn slope(&self) -> f32 {
+        (self.dx * self.dx + self.dy * self.dy).sqrt()
+    }
+
+    /// Curl of the field taken as a stream function: (dN/dy, -dN/dx),
+    /// which runs along the contours and has zero divergence.
+    #[inline]
+    pub fn curl(&self) -> [f32; 2] {
+        [self.dy, -self.dx]
+    }
+
+    #[inline]
+    fn scale(self, k: f32) -> Self {
+        Self { value: self.value * k, dx: self.dx * k, dy: self.dy * k }
+    }
+}
+
 /// 2D gradient noise (Perlin-style). Better isotropy than value noise --
 /// no grid-aligned diagonal artifacts.
 #[inline]
 pub fn gradient_noise(x: f32, y: f32, seed: u32) -> f32 {
+    gradient_lattice(x, y, |ix, iy| hash2(ix, iy, seed))
+}
+
+/// `gradient_noise` repeating every `period_x` units in x (and
+/// `period_y` in y if given): lattice corners wrap, so the field is exactly
+/// continuous across the seam, not just close.
+#[inline]
+pub fn gradient_noise_periodic(x: f32, y: f32, seed: u32, period_x: u32, period_y: Option<u32>) -> f32 {
+    let px = period_x.max(1) as i32;
+    let py = period_y.map(|p| p.max(1) as i32);
+    gradient_lattice(x, y, |ix, iy| {
+        let iy = py.map_or(iy, |p| iy.rem_euclid(p));
+        hash2(ix.rem_euclid(px), iy, seed)
+    })
+}
+
+/// Gradient noise with corner hashes from `corner(ix, iy)`.
+#[inline(always)]
+fn gradient_lattice(x: f32, y: f32, corner: impl Fn(i32, i32) -> u32) -> f32 {
     let ix = x.floor() as i32;
     let iy = y.floor() as i32;
     let fx = x - ix as f32;
@@ -21,34 +128,10 @@ pub fn gradient_noise(x: f32, y: f32, seed: u32) -> f32 {
     let sx = smootherstep(fx);
     let sy = smootherstep(fy);
 
-    #[inline]
-    fn grad(hash: u32, dx: f32, dy: f32) -> f32 {
-        // 16 evenly-spaced unit gradients (every 22.5°).
-        // Eliminates the directional bias of 4-gradient Perlin.
-        match hash & 15 {
-            0  =>  dx,
-            1  =>  0.924 * dx + 0.383 * dy,
-            2  =>  0.707 * (dx + dy),
-            3  =>  0.383 * dx + 0.924 * dy,
-            4  =>  dy,
-            5  => -0.383 * dx + 0.924 * dy,
-            6  =>  0.707 * (-dx + dy),
-            7  => -0.924 * dx + 0.383 * dy,
-            8  => -dx,
-            9  => -0.924 * dx - 0.383 * dy,
-            10 =>  0.707 * (-dx - dy),
-            11 => -0.383 * dx - 0.924 * dy,
-            12 => -dy,
-            13 =>  0.383 * dx - 0.924 * dy,
-            14 =>  0.707 * (dx - dy),
-            _  =>  0.924 * dx - 0.383 * dy,
-        }
-    }
-
-    let v00 = grad(hash2(ix, iy, seed), fx, fy);
-    let v10 = grad(hash2(ix + 1, iy, seed), fx - 1.0, fy);
-    let v01 = grad(hash2(ix, iy + 1, seed), fx, fy - 1.0);
-    let v11 = grad(hash2(ix + 1, iy + 1, seed), fx - 1.0, fy - 1.0);
+    let v00 = grad(corner(ix, iy), fx, fy);
+    let v10 = grad(corner(ix + 1, iy), fx - 1.0, fy);
+    let v01 = grad(corner(ix, iy + 1), fx, fy - 1.0);
+    let v11 = grad(corner(ix + 1, iy + 1), fx - 1.0, fy - 1.0);
 
     let a = lerp(v00, v10, sx);
     let b = lerp(v01, v11, sx);
@@ -56,55 +139,890 @@ pub fn gradient_noise(x: f32, y: f32, seed: u32) -> f32 {
     lerp(a, b, sy) * 1.414
 }
 
+/// `gradient_noise` with its derivatives.
+#[inline]
+pub fn gradient_noise_d(x: f32, y: f32, seed: u32) -> NoiseGrad {
+    gradient_lattice_d(x, y, |ix, iy| hash2(ix, iy, seed))
+}
+
+/// `gradient_noise_periodic` with its derivatives.
+#[inline]
+pub fn gradient_noise_periodic_d(x: f32, y: f32, seed: u32, period_x: u32, period_y: Option<u32>) -> NoiseGrad {
+    let px = period_x.max(1) as i32;
+    let py = period_y.map(|p| p.max(1) as i32);
+    gradient_lattice_d(x, y, |ix, iy| {
+        let iy = py.map_or(iy, |p| iy.rem_euclid(p));
+        hash2(ix.rem_euclid(px), iy, seed)
+    })
+}
+
+/// `gradient_lattice` differentiated: the bilinear blend of the corner
+/// gradients plus the fade curves' slope times the corner differences.
+#[inline(always)]
+fn gradient_lattice_d(x: f32, y: f32, corner: impl Fn(i32, i32) -> u32) -> NoiseGrad {
+    let ix = x.floor() as i32;
+    let iy = y.floor() as i32;
+    let fx = x - ix as f32;
+    let fy = y - iy as f32;
+    let sx = smootherstep(fx);
+    let sy = smootherstep(fy);
+    let dsx = smootherstep_d(fx);
+    let dsy = smootherstep_d(fy);
+
+    let (h00, h10, h01, h11) = (corner(ix, iy), corner(ix + 1, iy), corner(ix, iy + 1), corner(ix + 1, iy + 1));
+    let v00 = grad(h00, fx, fy);
+    let v10 = grad(h10, fx - 1.0, fy);
+    let v01 = grad(h01, fx, fy - 1.0);
+    let v11 = grad(h11, fx - 1.0, fy - 1.0);
+    let (g00, g10, g01, g11) = (grad_dir(h00), grad_dir(h10), grad_dir(h01), grad_dir(h11));
+
+    // n = v00 + sx k1 + sy k2 + sx sy k3
+    let k1 = v10 - v00;
+    let k2 = v01 - v00;
+    let k3 = v00 - v10 - v01 + v11;
+    let value = lerp(lerp(v00, v10, sx), lerp(v01, v11, sx), sy);
+    let dx = lerp(lerp(g00.0, g10.0, sx), lerp(g01.0, g11.0, sx), sy) + dsx * (k1 + sy * k3);
+    let dy = lerp(lerp(g00.1, g10.1, sx), lerp(g01.1, g11.1, sx), sy) + dsy * (k2 + sx * k3);
+    NoiseGrad { value, dx, dy }.scale(1.414)
+}
+
 /// Alias for gradient_noise.
 pub fn value_noise(x: f32, y: f32, seed: u32) -> f32 {
     gradient_noise(x, y, seed)
 }
 
+/// 2D simplex noise: gradient noise on a triangular lattice, summing
+/// radial falloffs from the three corners of the containing triangle. No
+/// axis-aligned structure, so large smooth areas stay isotropic where
+/// `gradient_noise` shows faint grid lines. Uses the same 16 gradients.
+#[inline]
+pub fn simplex_noise(x: f32, y: f32, seed: u32) -> f32 {
+    // Skew to the square lattice and back: (sqrt(3) - 1) / 2, (3 - sqrt(3)) / 6
+    const F2: f32 = 0.366_025_4;
+    const G2: f32 = 0.211_324_87;
+
+    let s = (x + y) * F2;
+    let i = (x + s).floor() as i32;
+    let j = (y + s).floor() as i32;
+    let t = (i + j) as f32 * G2;
+    let x0 = x - (i as f32 - t);
+    let y0 = y - (j as f32 - t);
+    // Lower or upper triangle of the skewed cell
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+    let corner = |ci: i32, cj: i32, dx: f32, dy: f32| {
+        let falloff = 0.5 - dx * dx - dy * dy;
+        if falloff <= 0.0 {
+            return 0.0;
+        }
+        let f2 = falloff * falloff;
+        f2 * f2 * grad(hash2(ci, cj, seed), dx, dy)
+    };
+
+    let n0 = corner(i, j, x0, y0);
+    let n1 = corner(i + i1, j + j1, x0 - i1 as f32 + G2, y0 - j1 as f32 + G2);
+    let n2 = corner(i + 1, j + 1, x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+    // Scale to the spread of `gradient_noise` (RMS ~0.3) so amplitudes tuned
+    // for one basis suit the other; peaks stay within ~[-0.6, 0.6]
+    (n0 + n1 + n2) * 56.0
+}
+
+/// `simplex_noise` with its derivatives.
+#[inline]
+pub fn simplex_noise_d(x: f32, y: f32, seed: u32) -> NoiseGrad {
+    const F2: f32 = 0.366_025_4;
+    const G2: f32 = 0.211_324_87;
+
+    let s = (x + y) * F2;
+    let i = (x + s).floor() as i32;
+    let j = (y + s).floor() as i32;
+    let t = (i + j) as f32 * G2;
+    let x0 = x - (i as f32 - t);
+    let y0 = y - (j as f32 - t);
+    let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+    // d/dd of f^4 (g . d) with f = 0.5 - |d|^2 is f^4 g - 8 f^3 (g . d) d
+    let corner = |ci: i32, cj: i32, dx: f32, dy: f32| {
+        let falloff = 0.5 - dx * dx - dy * dy;
+        if falloff <= 0.0 {
+            return NoiseGrad::default();
+        }
+        let h = hash2(ci, cj, seed);
+        let (gx, gy) = grad_dir(h);
+        let dot = grad(h, dx, dy);
+        let f3 = falloff * falloff * falloff;
+        let f4 = f3 * falloff;
+        NoiseGrad {
+            value: f4 * dot,
+            dx: f4 * gx - 8.0 * f3 * dot * dx,
+            dy: f4 * gy - 8.0 * f3 * dot * dy,
+        }
+    };
+
+    let n0 = corner(i, j, x0, y0);
+    let n1 = corner(i + i1, j + j1, x0 - i1 as f32 + G2, y0 - j1 as f32 + G2);
+    let n2 = corner(i + 1, j + 1, x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+    NoiseGrad {
+        value: n0.value + n1.value + n2.value,
+        dx: n0.dx + n1.dx + n2.dx,
+        dy: n0.dy + n1.dy + n2.dy,
+    }
+    .scale(56.0)
+}
+
+/// `simplex_noise` repeating every `period_x` units in x (and `period_y`
+/// in y if given). The triangular lattice has no horizontal period, so
+/// this crossfades each point with its copy one period back; the blend is
+/// renormalized so the spread stays even across the period.
+pub fn simplex_noise_periodic(x: f32, y: f32, seed: u32, period_x: u32, period_y: Option<u32>) -> f32 {
+    crossfade(x, period_x, |x| match period_y {
+        Some(py) => crossfade(y, py, |y| simplex_noise(x, y, seed)),
+        None => simplex_noise(x, y, seed),
+    })
+}
+
+/// Make `f` periodic in one coordinate: blend f(t) into f(t - period)
+/// linearly across each period, dividing by the RMS of the weights.
+#[inline]
+fn crossfade(t: f32, period: u32, f: impl Fn(f32) -> f32) -> f32 {
+    let p = period.max(1) as f32;
+    let t = t.rem_euclid(p);
+    let a = t / p;
+    (f(t) * (1.0 - a) + f(t - p) * a) / ((1.0 - a) * (1.0 - a) + a * a).sqrt()
+}
+
+/// `simplex_noise_periodic` with its derivatives.
+pub fn simplex_noise_periodic_d(x: f32, y: f32, seed: u32, period_x: u32, period_y: Option<u32>) -> NoiseGrad {
+    crossfade_d(x, period_x, false, |x| match period_y {
+        Some(py) => crossfade_d(y, py, true, |y| simplex_noise_d(x, y, seed)),
+        None => simplex_noise_d(x, y, seed),
+    })
+}
+
+/// `crossfade` differentiated along `t`, which is y if `along_y` else x.
+/// The other derivative is blended like the value.
+#[inline]
+fn crossfade_d(t: f32, period: u32, along_y: bool, f: impl Fn(f32) -> NoiseGrad) -> NoiseGrad {
+    let p = period.max(1) as f32;
+    let t = t.rem_euclid(p);
+    let a = t / p;
+    let (na, nb) = (f(t), f(t - p));
+    let r = ((1.0 - a) * (1.0 - a) + a * a).sqrt();
+    let blend = |u: f32, v: f32| (u * (1.0 - a) + v * a) / r;
+    let value = blend(na.value, nb.value);
+    // Quotient rule on the weights: da/dt = 1/p, dr/da = (2a - 1) / r
+    let weight_d = ((nb.value - na.value) / r - value * (2.0 * a - 1.0) / (r * r)) / p;
+    let (dx, dy) = if along_y {
+        (blend(na.dx, nb.dx), blend(na.dy, nb.dy) + weight_d)
+    } else {
+        (blend(na.dx, nb.dx) + weight_d, blend(na.dy, nb.dy))
+    };
+    NoiseGrad { value, dx, dy }
+}
+
+/// Lattice noise summed by `Fbm`, selectable per stage. Serializes by
+/// `name`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoiseBasis {
+    /// `gradient_noise` (square lattice).
+    #[default]
+    Gradient,
+    /// `simplex_noise` (triangular lattice).
+    Simplex,
+}
+
+impl NoiseBasis {
+    pub const ALL: [NoiseBasis; 2] = [NoiseBasis::Gradient, NoiseBasis::Simplex];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NoiseBasis::Gradient => "gradient",
+            NoiseBasis::Simplex => "simplex",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    /// One octave of this noise, in approximately [-1, 1].
+    #[inline]
+    pub fn sample(self, x: f32, y: f32, seed: u32) -> f32 {
+        match self {
+            NoiseBasis::Gradient => gradient_noise(x, y, seed),
+            NoiseBasis::Simplex => simplex_noise(x, y, seed),
+        }
+    }
+
+    /// `sample` repeating every `period_x` (and `period_y`) units.
+    #[inline]
+    pub fn sample_periodic(self, x: f32, y: f32, seed: u32, period_x: u32, period_y: Option<u32>) -> f32 {
+        match self {
+            NoiseBasis::Gradient => gradient_noise_periodic(x, y, seed, period_x, period_y),
+            NoiseBasis::Simplex => simplex_noise_periodic(x, y, seed, period_x, period_y),
+        }
+    }
+
+    /// `sample` with its derivatives.
+    #[inline]
+    pub fn sample_d(self, x: f32, y: f32, seed: u32) -> NoiseGrad {
+        match self {
+            NoiseBasis::Gradient => gradient_noise_d(x, y, seed),
+            NoiseBasis::Simplex => simplex_noise_d(x, y, seed),
+        }
+    }
+
+    /// `sample_periodic` with its derivatives.
+    #[inline]
+    pub fn sample_periodic_d(self, x: f32, y: f32, seed: u32, period_x: u32, period_y: Option<u32>) -> NoiseGrad {
+        match self {
+            NoiseBasis::Gradient => gradient_noise_periodic_d(x, y, seed, period_x, period_y),
+            NoiseBasis::Simplex => simplex_noise_periodic_d(x, y, seed, period_x, period_y),
+        }
+    }
+}
+
+/// Octave settings for fractal noise: `octaves` layers starting at
+/// `freq0`, each `lacunarity` times the frequency and `gain` times the
+/// amplitude of the last, over a chosen basis. With a period the sum
+/// repeats exactly every `period_x` input units in x (and `period_y` in
+/// y); `period_y` only applies together with `period_x`.
+#[derive(Clone, Copy, Debug)]
+pub struct Fbm {
+    pub basis: NoiseBasis,
+    pub octaves: u32,
+    pub freq0: f32,
+    pub lacunarity: f32,
+    pub gain: f32,
+    pub period_x: Option<f32>,
+    pub period_y: Option<f32>,
+}
+
+impl Fbm {
+    pub fn new(octaves: u32, freq0: f32, lacunarity: f32, gain: f32) -> Self {
+        Self { basis: NoiseBasis::default(), octaves, freq0, lacunarity, gain, period_x: None, period_y: None }
+    }
+
+    /// Same settings over a different basis.
+    pub fn with_basis(mut self, basis: NoiseBasis) -> Self {
+        self.basis = basis;
+        self
+    }
+
+    /// Same settings, tiling every `period_x` input units in x and
+    /// optionally `period_y` in y. For a map sampled at u = x / w this is
+    /// a period of 1 in u, which keeps the field seamless at the E-W edge.
+    pub fn with_period(mut self, period_x: f32, period_y: Option<f32>) -> Self {
+        self.period_x = Some(period_x);
+        self.period_y = period_y;
+        self
+    }
+
+    /// Fractal Brownian Motion, normalized to the basis range.
+    #[inline]
+    pub fn sample(&self, x: f32, y: f32, seed: u32) -> f32 {
+        self.sum(x, y, seed, |n| n)
+    }
+
+    /// Ridged fBm: each octave folded to `1 - |n|`, so zero crossings
+    /// become sharp crests.
+    #[inline]
+    pub fn ridged(&self, x: f32, y: f32, seed: u32) -> f32 {
+        self.sum(x, y, seed, |n| 1.0 - n.abs())
+    }
+
+    /// `sample` with its derivatives in (x, y).
+    #[inline]
+    pub fn sample_d(&self, x: f32, y: f32, seed: u32) -> NoiseGrad {
+        self.sum_d(x, y, seed, |n| (n, 1.0))
+    }
+
+    /// `ridged` with its derivatives in (x, y). The crests are creases, so
+    /// the derivative flips sign across them.
+    #[inline]
+    pub fn ridged_d(&self, x: f32, y: f32, seed: u32) -> NoiseGrad {
+        self.sum_d(x, y, seed, |n| (1.0 - n.abs(), -n.signum()))
+    }
+
+    /// Curl noise: a swirling, divergence-free vector field from the
+    /// derivatives of `sample_d`, so advecting along it neither piles up
+    /// nor drains anything.
+    #[inline]
+    pub fn curl(&self, x: f32, y: f32, seed: u32) -> [f32; 2] {
+        self.sample_d(x, y, seed).curl()
+    }
+
+    /// Billow: each octave folded to `|n|`, in [0, 1]. Rounded, puffy
+    /// lumps with creased valleys; the inverse of `ridged`.
+    #[inline]
+    pub fn billow(&self, x: f32, y: f32, seed: u32) -> f32 {
+        self.sum(x, y, seed, |n| n.abs())
+    }
+
+    /// Hybrid multifractal (after Musgrave): each octave is weighted by
+    /// the product of `n + offset` over the octaves before it, clamped to [0, 1],
+    /// so low ground stays smooth while high ground gathers detail. `offset`
+    /// ~0.7; where the weights saturate this equals `sample`.
+    #[inline]
+    pub fn hybrid(&self, x: f32, y: f32, seed: u32, offset: f32) -> f32 {
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        let mut weight = 1.0f32;
+        self.octaves(x, y, seed, |n, amp| {
+            let signal = n + offset;
+            sum += weight * signal * amp;
+            norm += amp;
+            weight = (weight * signal).clamp(0.0, 1.0);
+        });
+        if norm > 0.0 { sum / norm - offset } else { 0.0 }
+    }
+
+    /// `sample` at many points: `out[i] = sample(xs[i], ys[i])`, bit for
+    /// bit. Runs octave by octave over the whole batch, so per-octave setup
+    /// (frequency, period snapping, seed, normalization) is paid once per
+    /// batch instead of once per point.
+    pub fn sample_batch(&self, xs: &[f32], ys: &[f32], seed: u32, out: &mut [f32]) {
+        self.sum_batch(xs, ys, seed, out, |n| n)
+    }
+
+    /// `ridged` at many points, as `sample_batch`.
+    pub fn ridged_batch(&self, xs: &[f32], ys: &[f32], seed: u32, out: &mut [f32]) {
+        self.sum_batch(xs, ys, seed, out, |n| 1.0 - n.abs())
+    }
+
+    /// `sample_batch` along a row: `out[i] = sample(x0 + i * dx, y)`.
+    pub fn sample_row(&self, out: &mut [f32], x0: f32, dx: f32, y: f32, seed: u32) {
+        let xs: Vec<f32> = (0..out.len()).map(|i| x0 + i as f32 * dx).collect();
+        let ys = vec![y; out.len()];
+        self.sample_batch(&xs, &ys, seed, out)
+    }
+
+    /// `sum` over a batch, in the same order of operations per point.
+    fn sum_batch(&self, xs: &[f32], ys: &[f32], seed: u32, out: &mut [f32], shape: impl Fn(f32) -> f32) {
+        assert!(xs.len() == out.len() && ys.len() == out.len(), "noise batch length mismatch");
+        out.fill(0.0);
+        let mut norm = 0.0;
+        let mut amp = 1.0;
+        for _ in 0..self.octaves {
+            norm += amp;
+            amp *= self.gain;
+        }
+        self.octaves_batch(xs, ys, seed, |i, n, amp| out[i] += shape(n) * amp);
+        for v in out.iter_mut() {
+            *v = if norm > 0.0 { *v / norm } else { 0.0 };
+        }
+    }
+
+    /// `octaves` over a batch: `visit(point, noise, amplitude)`, octave by
+    /// octave.
+    fn octaves_batch(&self, xs: &[f32], ys: &[f32], seed: u32, mut visit: impl FnMut(usize, f32, f32)) {
+        const COS30: f32 = 0.866025;
+        const SIN30: f32 = 0.5;
+        const SHIFT_X: f32 = 0.618034;
+        const SHIFT_Y: f32 = 0.381966;
+        let snap = |period: f32, freq: f32| {
+            let cells = (period * freq).round().max(1.0);
+            (cells as u32, cells / period)
+        };
+        let mut amp = 1.0;
+        let mut freq = self.freq0;
+        match self.period_x {
+            Some(period_x) => {
+                for o in 0..self.octaves {
+                    let (cells_x, fx) = snap(period_x, freq);
+                    let (cells_y, fy) = match self.period_y {
+                        Some(py) => {
+                            let (cells, f) = snap(py, freq);
+                            (Some(cells), f)
+                        }
+                        None => (None, fx),
+                    };
+                    let shift = o as f32;
+                    let (ox, oy) = ((shift * SHIFT_X).fract(), (shift * SHIFT_Y).fract());
+                    let s = seed.wrapping_add(o);
+                    for (i, (&x, &y)) in xs.iter().zip(ys).enumerate() {
+                        visit(i, self.basis.sample_periodic(x * fx + ox, y * fy + oy, s, cells_x, cells_y), amp);
+                    }
+                    amp *= self.gain;
+                    freq *= self.lacunarity;
+                }
+            }
+            None => {
+                let mut px = xs.to_vec();
+                let mut py = ys.to_vec();
+                for o in 0..self.octaves {
+                    let s = seed.wrapping_add(o);
+                    for (i, (x, y)) in px.iter_mut().zip(py.iter_mut()).enumerate() {
+                        visit(i, self.basis.sample(*x * freq, *y * freq, s), amp);
+                        let (rx, ry) = (*x * COS30 - *y * SIN30, *x * SIN30 + *y * COS30);
+                        *x = rx;
+                        *y = ry;
+                    }
+                    amp *= self.gain;
+                    freq *= self.lacunarity;
+                }
+            }
+        }
+    }
+
+    /// Weighted octave sum of `shape(noise)`, normalized by the weights.
+    #[inline]
+    fn sum(&self, x: f32, y: f32, seed: u32, shape: impl Fn(f32) -> f32) -> f32 {
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        self.octaves(x, y, seed, |n, amp| {
+            sum += shape(n) * amp;
+            norm += amp;
+        });
+        if norm > 0.0 { sum / norm } else { 0.0 }
+    }
+
+    /// Call `visit(noise, amplitude)` for each octave, with ~30° rotation
+    /// per octave to decorrelate the lattices, and seed + i per octave.
+    #[inline]
+    fn octaves(&self, x: f32, y: f32, seed: u32, mut visit: impl FnMut(f32, f32)) {
+        if let Some(period_x) = self.period_x {
+            return self.octaves_periodic(x, y, seed, period_x, visit);
+        }
+        let mut amp = 1.0;
+        let mut freq = self.freq0;
+        const COS30: f32 = 0.866025;
+        const SIN30: f32 = 0.5;
+        let mut px = x;
+        let mut py = y;
+        for i in 0..self.octaves {
+            visit(self.basis.sample(px * freq, py * freq, seed.wrapping_add(i)), amp);
+            amp *= self.gain;
+            freq *= self.lacunarity;
+            let (rx, ry) = (px * COS30 - py * SIN30, px * SIN30 + py * COS30);
+            px = rx;
+            py = ry;
+        }
+    }
+
+    /// `octaves` for a periodic field. Each octave's frequency is snapped
+    /// so a whole number of lattice cells fits the period, and rotation
+    /// (which would break the period) is replaced by a per-octave shift of
+    /// the lattice so cell edges of successive octaves don't line up.
+    #[inline]
+    fn octaves_periodic(&self, x: f32, y: f32, seed: u32, period_x: f32, mut visit: impl FnMut(f32, f32)) {
+        // Lattice shift per octave: golden-ratio steps never repeat
+        const SHIFT_X: f32 = 0.618034;
+        const SHIFT_Y: f32 = 0.381966;
+        let snap = |period: f32, freq: f32| {
+            let cells = (period * freq).round().max(1.0);
+            (cells as u32, cells / period)
+        };
+        let mut amp = 1.0;
+        let mut freq = self.freq0;
+        for i in 0..self.octaves {
+            let (cells_x, fx) = snap(period_x, freq);
+            let (cells_y, fy) = match self.period_y {
+                Some(py) => {
+                    let (cells, f) = snap(py, freq);
+                    (Some(cells), f)
+                }
+                None => (None, fx),
+            };
+            let shift = i as f32;
+            let (sx, sy) = (x * fx + (shift * SHIFT_X).fract(), y * fy + (shift * SHIFT_Y).fract());
+            visit(self.basis.sample_periodic(sx, sy, seed.wrapping_add(i), cells_x, cells_y), amp);
+            amp *= self.gain;
+            freq *= self.lacunarity;
+        }
+    }
+
+    /// `sum` carrying derivatives: `shape` returns the
+    /// shaped value and its slope, and each octave's derivatives are
+    /// mapped back through that octave's scale and rotation.
+    fn sum_d(&self, x: f32, y: f32, seed: u32, shape: impl Fn(f32) -> (f32, f32)) -> NoiseGrad {
+        const COS30: f32 = 0.866025;
+        const SIN30: f32 = 0.5;
+        const SHIFT_X: f32 = 0.618034;
+        const SHIFT_Y: f32 = 0.381966;
+        let snap = |period: f32, freq: f32| {
+            let cells = (period * freq).round().max(1.0);
+            (cells as u32, cells / period)
+        };
+        let mut acc = NoiseGrad::default();
+        let mut amp = 1.0;
+        let mut freq = self.freq0;
+        let mut norm = 0.0;
+        // Accumulated rotation (cos, sin) of the non-periodic octaves
+        let (mut c, mut s) = (1.0f32, 0.0f32);
+        let (mut px, mut py) = (x, y);
+        for i in 0..self.octaves {
+            let (n, dx, dy) = match self.period_x {
+                Some(period_x) => {
+                    let (cells_x, fx) = snap(period_x, freq);
+                    let (cells_y, fy) = match self.period_y {
+                        Some(p) => {
+                            let (cells, f) = snap(p, freq);
+                            (Some(cells), f)
+                        }
+                        None => (None, fx),
+                    };
+                    let shift = i as f32;
+                    let (sx, sy) = (x * fx + (shift * SHIFT_X).fract(), y * fy + (shift * SHIFT_Y).fract());
+                    let n = self.basis.sample_periodic_d(sx, sy, seed.wrapping_add(i), cells_x, cells_y);
+                    (n.value, n.dx * fx, n.dy * fy)
+                }
+                None => {
+                    let n = self.basis.sample_d(px * freq, py * freq, seed.wrapping_add(i));
+                    // (px, py) = R (x, y), so d/dx = R^T d/d(px, py)
+                    let (gx, gy) = (n.dx * freq, n.dy * freq);
+                    (n.value, gx * c + gy * s, -gx * s + gy * c)
+                }
+            };
+            let (v, slope) = shape(n);
+            acc.value += v * amp;
+            acc.dx += slope * dx * amp;
+            acc.dy += slope * dy * amp;
+            norm += amp;
+            amp *= self.gain;
+            freq *= self.lacunarity;
+            (px, py) = (px * COS30 - py * SIN30, px * SIN30 + py * COS30);
+            (c, s) = (c * COS30 - s * SIN30, c * SIN30 + s * COS30);
+        }
+        if norm > 0.0 { acc.scale(1.0 / norm) } else { NoiseGrad::default() }
+    }
+}
+
 /// Fractal Brownian Motion with per-octave rotation to break grid alignment.
 pub fn fbm(x: f32, y: f32, seed: u32, octaves: u32, freq0: f32, lac: f32, gain: f32) -> f32 {
-    let mut sum = 0.0;
-    let mut amp = 1.0;
-    let mut freq = freq0;
-    let mut norm = 0.0;
-    // Rotate ~30° per octave to decorrelate
-    const COS30: f32 = 0.866025;
-    const SIN30: f32 = 0.5;
-    let mut px = x;
-    let mut py = y;
-    for i in 0..octaves {
-        sum += gradient_noise(px * freq, py * freq, seed.wrapping_add(i)) * amp;
-        norm += amp;
-        amp *= gain;
-        freq *= lac;
-        let (rx, ry) = (px * COS30 - py * SIN30, px * SIN30 + py * COS30);
-        px = rx;
-        py = ry;
-    }
-    if norm > 0.0 { sum / norm } else { 0.0 }
+    Fbm::new(octaves, freq0, lac, gain).sample(x, y, seed)
 }
 
 /// Ridged FBM with per-octave rotation.
 pub fn ridged_fbm(
     x: f32, y: f32, seed: u32, octaves: u32, freq0: f32, lac: f32, gain: f32,
 ) -> f32 {
-    let mut sum = 0.0;
-    let mut amp = 1.0;
-    let mut freq = freq0;
-    let mut norm = 0.0;
-    const COS30: f32 = 0.866025;
-    const SIN30: f32 = 0.5;
-    let mut px = x;
-    let mut py = y;
-    for i in 0..octaves {
-        let n = gradient_noise(px * freq, py * freq, seed.wrapping_add(i));
-        sum += (1.0 - n.abs()) * amp;
-        norm += amp;
-        amp *= gain;
-        freq *= lac;
-        let (rx, ry) = (px * COS30 - py * SIN30, px * SIN30 + py * COS30);
-        px = rx;
-        py = ry;
-    }
-    if norm > 0.0 { sum / norm } else { 0.0 }
+    Fbm::new(octaves, freq0, lac, gain).ridged(x, y, seed)
+}
+
+/// One stage of a domain warp: (x, y) moves by `fbm` times `amp` per
+/// axis, the y displacement sampled at `offset` from the point so the two
+/// axes don't move in lockstep.
+#[derive(Clone, Copy, Debug)]
+pub struct Warp {
+    pub fbm: Fbm,
+    pub amp: [f32; 2],
+    pub offset: [f32; 2],
+}
+
+impl Warp {
+    pub fn new(fbm: Fbm, amp: f32) -> Self {
+        Self { fbm, amp: [amp, amp], offset: [5.2, 1.3] }
+    }
+
+    /// Same warp with separate x and y amplitudes.
+    pub fn with_amp(mut self, amp_x: f32, amp_y: f32) -> Self {
+        self.amp = [amp_x, amp_y];
+        self
+    }
+
+    /// Seeds of stage `k` of a chain: one per axis, all mixed from `seed`.
+    #[inline]
+    fn seeds(seed: u32, k: usize) -> (u32, u32) {
+        let sx = splitmix32(seed ^ k as u32);
+        (sx, splitmix32(sx))
+    }
+}
+
+/// (x, y) displaced by each warp of `chain` in turn, each stage sampling
+/// at the point the one before produced (nested warping). Stage seeds are
+/// derived from `seed`, so one salt per chain is enough.
+pub fn warped(x: f32, y: f32, seed: u32, chain: &[Warp]) -> (f32, f32) {
+    chain.iter().enumerate().fold((x, y), |(x, y), (k, warp)| {
+        let (sx, sy) = Warp::seeds(seed, k);
+        let dx = warp.fbm.sample(x, y, sx) * warp.amp[0];
+        let dy = warp.fbm.sample(x + warp.offset[0], y + warp.offset[1], sy) * warp.amp[1];
+        (x + dx, y + dy)
+    })
+}
+
+/// `warped` over a batch in place, with `Fbm::sample_batch`.
+pub fn warped_batch(xs: &mut [f32], ys: &mut [f32], seed: u32, chain: &[Warp]) {
+    assert_eq!(xs.len(), ys.len(), "warp batch length mismatch");
+    let mut dx = vec![0.0; xs.len()];
+    let mut dy = vec![0.0; xs.len()];
+    for (k, warp) in chain.iter().enumerate() {
+        let (sx, sy) = Warp::seeds(seed, k);
+        warp.fbm.sample_batch(xs, ys, sx, &mut dx);
+        let ox: Vec<f32> = xs.iter().map(|x| x + warp.offset[0]).collect();
+        let oy: Vec<f32> = ys.iter().map(|y| y + warp.offset[1]).collect();
+        warp.fbm.sample_batch(&ox, &oy, sy, &mut dy);
+        for i in 0..xs.len() {
+            xs[i] += dx[i] * warp.amp[0];
+            ys[i] += dy[i] * warp.amp[1];
+        }
+    }
+}
+
+/// `w x h` grid of `fbm.curl` sampled at (x / w, y / w), one map width
+/// per unit in both axes so the swirls stay round. Give `fbm` a period of
+/// 1.0 in x for a field that is seamless E-W. Vectors are in stream-function
+/// units per map width; divide by `w` for units per cell.
+pub fn curl_field(w: usize, h: usize, fbm: &Fbm, seed: u32) -> Grid<[f32; 2]> {
+    Grid::par_from_fn(w, h, |x, y| fbm.curl(x as f32 / w as f32, y as f32 / w as f32, seed))
+}
+
+/// How `worley` measures distance to feature points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CellMetric {
+    /// Round cells.
+    #[default]
+    Euclidean,
+    /// Diamond-shaped cells with straight, diagonal-leaning walls.
+    Manhattan,
+    /// Square cells with axis-aligned walls.
+    Chebyshev,
+}
+
+impl CellMetric {
+    #[inline]
+    fn dist(self, dx: f32, dy: f32) -> f32 {
+        match self {
+            CellMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+            CellMetric::Manhattan => dx.abs() + dy.abs(),
+            CellMetric::Chebyshev => dx.abs().max(dy.abs()),
+        }
+    }
+}
+
+/// Result of `worley` at one point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cellular {
+    /// Distance to the nearest feature point.
+    pub f1: f32,
+    /// Distance to the second nearest.
+    pub f2: f32,
+    /// Hash of the nearest feature's lattice cell: constant across a cell,
+    /// for coloring or seeding per-cell values.
+    pub id: u32,
+}
+
+impl Cellular {
+    /// `f2 - f1`: zero on cell walls, growing toward cell centers. The
+    /// usual crack / crater-rim pattern.
+    #[inline]
+    pub fn edge(&self) -> f32 {
+        self.f2 - self.f1
+    }
+}
+
+/// Feature point of lattice cell (cx, cy): uniformly jittered inside it.
+#[inline]
+fn feature_point(cx: i32, cy: i32, seed: u32) -> (f32, f32, u32) {
+    let h = hash2(cx, cy, seed);
+    let jx = (h >> 8) as f32 / 16777216.0;
+    let jy = (splitmix32(h) >> 8) as f32 / 16777216.0;
+    (cx as f32 + jx, cy as f32 + jy, h)
+}
+
+/// 2D cellular (Worley) noise: distances from (x, y) to the nearest and
+/// second-nearest of one randomly placed feature point per unit cell.
+/// Distances are in lattice units (F1 is at most ~1.5 Euclidean); scale
+/// the input to set the cell size.
+pub fn worley(x: f32, y: f32, seed: u32, metric: CellMetric) -> Cellular {
+    let ix = x.floor() as i32;
+    let iy = y.floor() as i32;
+    let visit = |best: &mut Cellular, cx: i32, cy: i32| {
+        let (px, py, id) = feature_point(cx, cy, seed);
+        let d = metric.dist(px - x, py - y);
+        if d < best.f1 {
+            *best = Cellular { f1: d, f2: best.f1, id };
+        } else if d < best.f2 {
+            best.f2 = d;
+        }
+    };
+    let mut best = Cellular { f1: f32::INFINITY, f2: f32::INFINITY, id: 0 };
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            visit(&mut best, ix + dx, iy + dy);
+        }
+    }
+    // Points two cells away are at least 1 away in every metric, so the
+    // outer ring only matters when F2 is still farther than that.
+    if best.f2 > 1.0 {
+        for dy in -2..=2i32 {
+            for dx in -2..=2i32 {
+                if dx.abs() == 2 || dy.abs() == 2 {
+                    visit(&mut best, ix + dx, iy + dy);
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Blue-noise (Poisson-disk) points over a `w x h` map that wraps E-W,
+/// by Bridson's algorithm: every point is at least `radius(x, y)` from
+/// those placed before it (measured at the new point), and sampling stops
+/// only when no gap is left that could take another. A varying `radius`
+/// gives density gradients; a constant one, even spacing without clumps.
+pub fn blue_noise_samples(w: usize, h: usize, seed: u64, radius: impl Fn(f32, f32) -> f32) -> Vec<[f32; 2]> {
+    // Candidates tried around an active point before it is retired
+    const CANDIDATES: usize = 30;
+    // Radius probe lattice for sizing the buckets
+    const PROBE: usize = 16;
+    if w == 0 || h == 0 {
+        return Vec::new();
+    }
+    let (wf, hf) = (w as f32, h as f32);
+    let mut rng = Rng::new(seed);
+
+    // Buckets sized so the smallest probed radius spans about one. They hold
+    // any number of points, so a missed minimum costs speed, not correctness.
+    let r_min = (0..PROBE * PROBE)
+        .map(|i| radius(((i % PROBE) as f32 + 0.5) * wf / PROBE as f32, ((i / PROBE) as f32 + 0.5) * hf / PROBE as f32))
+        .fold(f32::INFINITY, f32::min)
+        .max(1.0);
+    let gw = (wf / r_min).ceil() as usize;
+    let gh = (hf / r_min).ceil() as usize;
+    let (cell_w, cell_h) = (wf / gw as f32, hf / gh as f32);
+    let bucket = |x: f32, y: f32| {
+        let bx = ((x / cell_w) as usize).min(gw - 1);
+        let by = ((y / cell_h) as usize).min(gh - 1);
+        by * gw + bx
+    };
+    let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); gw * gh];
+    let mut points: Vec<[f32; 2]> = Vec::new();
+
+    let fits = |points: &[[f32; 2]], buckets: &[Vec<u32>], x: f32, y: f32| {
+        let r = radius(x, y);
+        let span_x = (r / cell_w).ceil() as i32;
+        let span_y = (r / cell_h).ceil() as i32;
+        let (bx, by) = ((x / cell_w) as i32, (y / cell_h) as i32);
+        // Every column once, even when the span laps the map
+        let cols = if 2 * span_x + 1 >= gw as i32 { 0..gw as i32 } else { bx - span_x..bx + span_x + 1 };
+        for ny in (by - span_y).max(0)..(by + span_y + 1).min(gh as i32) {
+            for nx in cols.clone() {
+                let b = ny as usize * gw + nx.rem_euclid(gw as i32) as usize;
+                for &i in &buckets[b] {
+                    let [px, py] = points[i as usize];
+                    let dx_raw = (px - x).abs();
+                    let dx = dx_raw.min(wf - dx_raw);
+                    let dy = py - y;
+                    if dx * dx + dy * dy < r * r {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    };
+
+    let first = [rng.range_f32(0.0, wf), rng.range_f32(0.0, hf)];
+    buckets[bucket(first[0], first[1])].push(0);
+    points.push(first);
+    let mut active = vec![0usize];
+
+    while !active.is_empty() {
+        let slot = rng.range_usize(active.len());
+        let [px, py] = points[active[slot]];
+        let r = radius(px, py);
+        let mut placed = false;
+        for _ in 0..CANDIDATES {
+            // Uniform in the annulus [r, 2r]
+            let angle = rng.next_f32() * std::f32::consts::TAU;
+            let dist = r * (1.0 + rng.next_f32());
+            let x = (px + dist * fmath::cos(angle)).rem_euclid(wf);
+            let y = py + dist * fmath::sin(angle);
+            if !(0.0..hf).contains(&y) || !fits(&points, &buckets, x, y) {
+                continue;
+            }
+            buckets[bucket(x, y)].push(points.len() as u32);
+            active.push(points.len());
+            points.push([x, y]);
+            placed = true;
+            break;
+        }
+        if !placed {
+            active.swap_remove(slot);
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u32 = 0x5eed;
+    const STEP: f32 = 1e-3;
+
+    /// Points off the lattice lines, where every basis is smooth.
+    fn points() -> impl Iterator<Item = (f32, f32)> {
+        (0..64).map(|i| (i as f32 * 0.731 + 0.13, i as f32 * 0.377 - 5.29))
+    }
+
+    /// Central differences of `f` at (x, y).
+    fn numeric(f: impl Fn(f32, f32) -> f32, x: f32, y: f32) -> (f32, f32) {
+        ((f(x + STEP, y) - f(x - STEP, y)) / (2.0 * STEP), (f(x, y + STEP) - f(x, y - STEP)) / (2.0 * STEP))
+    }
+
+    fn assert_derivatives(name: &str, f: impl Fn(f32, f32) -> f32, d: impl Fn(f32, f32) -> NoiseGrad) {
+        for (x, y) in points() {
+            let g = d(x, y);
+            let (dx, dy) = numeric(&f, x, y);
+            let tol = 0.02 * (1.0 + g.slope());
+            assert!((g.value - f(x, y)).abs() < 1e-5, "{name} value at ({x}, {y})");
+            assert!((g.dx - dx).abs() < tol && (g.dy - dy).abs() < tol, "{name} at ({x}, {y}): {g:?} vs ({dx}, {dy})");
+        }
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        assert_derivatives("gradient", |x, y| gradient_noise(x, y, SEED), |x, y| gradient_noise_d(x, y, SEED));
+        assert_derivatives("simplex", |x, y| simplex_noise(x, y, SEED), |x, y| simplex_noise_d(x, y, SEED));
+        assert_derivatives(
+            "periodic gradient",
+            |x, y| gradient_noise_periodic(x, y, SEED, 8, Some(4)),
+            |x, y| gradient_noise_periodic_d(x, y, SEED, 8, Some(4)),
+        );
+        assert_derivatives(
+            "periodic simplex",
+            |x, y| simplex_noise_periodic(x, y, SEED, 8, None),
+            |x, y| simplex_noise_periodic_d(x, y, SEED, 8, None),
+        );
+        for basis in NoiseBasis::ALL {
+            let fbm = Fbm::new(4, 0.5, 2.0, 0.5).with_basis(basis);
+            assert_derivatives(basis.name(), |x, y| fbm.sample(x, y, SEED), |x, y| fbm.sample_d(x, y, SEED));
+        }
+    }
+
+    #[test]
+    fn curl_is_divergence_free() {
+        let fbm = Fbm::new(3, 0.5, 2.0, 0.5);
+        for (x, y) in points() {
+            let du = (fbm.curl(x + STEP, y, SEED)[0] - fbm.curl(x - STEP, y, SEED)[0]) / (2.0 * STEP);
+            let dv = (fbm.curl(x, y + STEP, SEED)[1] - fbm.curl(x, y - STEP, SEED)[1]) / (2.0 * STEP);
+            assert!((du + dv).abs() < 0.05, "divergence {} at ({x}, {y})", du + dv);
+        }
+    }
+
+    #[test]
+    fn periodic_noise_is_continuous_across_the_seam() {
+        for basis in NoiseBasis::ALL {
+            for y in [-3.7f32, 0.0, 0.4, 2.25] {
+                // The same point one period over
+                for x in [0.0f32, 0.25, 3.5] {
+                    let (a, b) = (basis.sample_periodic(x, y, SEED, 8, None), basis.sample_periodic(x + 8.0, y, SEED, 8, None));
+                    assert!((a - b).abs() < 1e-4, "{} at x = {x}: {a} vs {b}", basis.name());
+                }
+                // No step between the two sides of the seam
+                let (a, b) = (basis.sample_periodic(8.0 - STEP, y, SEED, 8, None), basis.sample_periodic(STEP, y, SEED, 8, None));
+                assert!((a - b).abs() < 0.02, "{} seam at y = {y}: {a} vs {b}", basis.name());
+            }
+
+            // A map's fBm at u = x / w
+            let fbm = Fbm::new(5, 3.0, 2.0, 0.5).with_basis(basis).with_period(1.0, None);
+            for y in [0.1f32, 0.5, 0.9] {
+                let (a, b) = (fbm.sample(1.0 - STEP, y, SEED), fbm.sample(STEP, y, SEED));
+                assert!((a - b).abs() < 0.05, "{} fBm seam at y = {y}: {a} vs {b}", basis.name());
+            }
+        }
+    }
 }
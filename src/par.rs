@@ -0,0 +1,58 @@
+//! Data parallelism. With the `parallel` feature (on by default) this is
+//! rayon's prelude; without it the same `par_*` methods hand back plain
+//! sequential iterators, for targets without threads such as
+//! `wasm32-unknown-unknown`. Stages only use the adaptors both share
+//! (`map`, `zip`, `enumerate`, `for_each`, `collect`, ...), so they build
+//! either way and give the same results.
+
+#[cfg(feature = "parallel")]
+pub use rayon::prelude::*;
+
+#[cfg(not(feature = "parallel"))]
+pub use seq::*;
+
+#[cfg(not(feature = "parallel"))]
+mod seq {
+    use std::ops::Range;
+    use std::slice::{Chunks, ChunksMut, Iter, IterMut};
+
+    pub trait SeqSlice<T> {
+        fn par_iter(&self) -> Iter<'_, T>;
+        fn par_chunks(&self, size: usize) -> Chunks<'_, T>;
+    }
+
+    impl<T> SeqSlice<T> for [T] {
+        fn par_iter(&self) -> Iter<'_, T> {
+            self.iter()
+        }
+
+        fn par_chunks(&self, size: usize) -> Chunks<'_, T> {
+            self.chunks(size)
+        }
+    }
+
+    pub trait SeqSliceMut<T> {
+        fn par_iter_mut(&mut self) -> IterMut<'_, T>;
+        fn par_chunks_mut(&mut self, size: usize) -> ChunksMut<'_, T>;
+    }
+
+    impl<T> SeqSliceMut<T> for [T] {
+        fn par_iter_mut(&mut self) -> IterMut<'_, T> {
+            self.iter_mut()
+        }
+
+        fn par_chunks_mut(&mut self, size: usize) -> ChunksMut<'_, T> {
+            self.chunks_mut(size)
+        }
+    }
+
+    pub trait SeqRange {
+        fn into_par_iter(self) -> Range<usize>;
+    }
+
+    impl SeqRange for Range<usize> {
+        fn into_par_iter(self) -> Range<usize> {
+            self
+        }
+    }
+}
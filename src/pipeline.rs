@@ -0,0 +1,854 @@
+//! Staged generation with cached intermediates. Each stage declares the
+//! stages it reads and the `Params` fields it uses, so changing a
+//! parameter recomputes only the stages downstream of it.
+
+#[cfg(feature = "parallel")]
+use std::sync::Arc;
+
+use crate::cancel::CancelToken;
+use crate::clock::Instant;
+use crate::config::{InvalidParams, Params};
+use crate::grid::{Grid, Metric, QuantGrid};
+use crate::plates::properties::PlateSet;
+use crate::rng::{self, Rng, RngCore};
+use crate::scratch::{self, Recycle, Scratch};
+#[cfg(feature = "parallel")]
+use crate::threads::ThreadPool;
+use crate::events::{self, Event};
+use crate::{Map, Timing, WorldgenError, check_size};
+use crate::{climate, culture, elevation, history, hydrology, plates, population, render, terrain};
+
+/// A generation stage, as reported to progress callbacks and cached by
+/// `Pipeline`. Names match the `Timing` entries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    PlateSeed,
+    PlateGrow,
+    PlateProperties,
+    Boundaries,
+    DistanceField,
+    Elevation,
+    Temperature,
+    Precipitation,
+    Ice,
+    Render,
+    Hydrology,
+    Population,
+    History,
+    Cultures,
+}
+
+impl Stage {
+    /// Every stage, in an order where each comes after its inputs.
+    pub const ALL: [Stage; 14] = [
+        Stage::PlateSeed,
+        Stage::PlateGrow,
+        Stage::PlateProperties,
+        Stage::Boundaries,
+        Stage::DistanceField,
+        Stage::Elevation,
+        Stage::Temperature,
+        Stage::Precipitation,
+        Stage::Ice,
+        Stage::Render,
+        Stage::Hydrology,
+        Stage::Population,
+        Stage::History,
+        Stage::Cultures,
+    ];
+
+    /// Stages of `generate_base`, in the order they run.
+    pub const BASE: [Stage; 10] = [
+        Stage::PlateSeed,
+        Stage::PlateGrow,
+        Stage::PlateProperties,
+        Stage::Boundaries,
+        Stage::DistanceField,
+        Stage::Elevation,
+        Stage::Temperature,
+        Stage::Precipitation,
+        Stage::Ice,
+        Stage::Render,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::PlateSeed => "plate_seed",
+            Stage::PlateGrow => "plate_grow",
+            Stage::PlateProperties => "plate_properties",
+            Stage::Boundaries => "boundaries",
+            Stage::DistanceField => "distance_field",
+            Stage::Elevation => "elevation",
+            Stage::Temperature => "temperature",
+            Stage::Precipitation => "precipitation",
+            Stage::Ice => "ice",
+            Stage::Render => "render",
+            Stage::Hydrology => "hydrology",
+            Stage::Population => "population",
+            Stage::History => "history",
+            Stage::Cultures => "cultures",
+        }
+    }
+
+    /// Stages whose outputs this one reads.
+    pub fn inputs(self) -> &'static [Stage] {
+        use Stage::*;
+        match self {
+            PlateSeed => &[],
+            PlateGrow => &[PlateSeed],
+            PlateProperties => &[PlateSeed, PlateGrow],
+            Boundaries => &[PlateGrow, PlateProperties],
+            DistanceField => &[Boundaries],
+            Elevation => &[PlateGrow, PlateProperties, Boundaries, DistanceField],
+            Temperature => &[Elevation],
+            Precipitation => &[Elevation, Temperature],
+            Ice => &[Elevation, Temperature, Precipitation],
+            Render => &[Elevation, Ice],
+            Hydrology => &[Elevation, Precipitation],
+            Population => &[Hydrology, Temperature, Precipitation],
+            History => &[Hydrology, Population],
+            Cultures => &[Hydrology],
+        }
+    }
+
+    /// `Params` fields this stage reads. A nested field is named by its
+    /// path (`seeds.plates`); naming a struct (`world`) covers all of it.
+    pub fn params(self) -> &'static [&'static str] {
+        use Stage::*;
+        match self {
+            PlateSeed => &["num_macroplates", "num_microplates", "seeds.plates"],
+            PlateGrow => &["boundary_noise", "seeds.plates"],
+            PlateProperties => &["num_macroplates", "num_microplates", "continental_fraction", "boundary_noise"],
+            Boundaries => &["spherical_metric"],
+            DistanceField => &[],
+            Elevation => &[
+                "blur_sigma",
+                "mountain_scale",
+                "trench_scale",
+                "mountain_width",
+                "coast_amp",
+                "interior_amp",
+                "detail_amp",
+                "elevation_noise",
+                "shelf_width",
+                "ridge_height",
+                "rift_depth",
+                "sea_level",
+                "world",
+                "seeds.elevation",
+            ],
+            Temperature => &["climate_noise", "equator_temperature", "pole_temperature", "seeds.climate"],
+            Precipitation => &["climate_noise", "rainfall_scale", "seeds.climate"],
+            Ice => &["world"],
+            Render => &[],
+            Hydrology => &[
+                "river_threshold",
+                "carve_depth",
+                "carve_max",
+                "meander_amp",
+                "meander_wavelength",
+                "meander_detail_wavelength",
+                "hydro_upscale",
+                "basin_growth",
+                "basin_min_flow",
+                "world",
+                "seeds.hydrology",
+            ],
+            Population => &["total_population", "world"],
+            History => &["num_nations", "history_epochs"],
+            Cultures => &["num_cultures"],
+        }
+    }
+
+    fn index(self) -> usize {
+        Stage::ALL.iter().position(|&s| s == self).expect("every stage is in ALL")
+    }
+}
+
+/// Seeding output, plus the plate generator's state after seeding so
+/// plate properties can be redrawn without reseeding.
+#[derive(Clone)]
+struct Seeds {
+    macro_seeds: Vec<[f32; 2]>,
+    seeds: Vec<[f32; 2]>,
+    rng: Option<Rng>,
+}
+
+#[derive(Clone)]
+struct Boundaries {
+    btype: Grid<u8>,
+    pa: Grid<u16>,
+    pb: Grid<u16>,
+    major: Grid<u8>,
+}
+
+impl Recycle for Boundaries {
+    fn recycle(self) {
+        (self.btype, self.major).recycle();
+        (self.pa, self.pb).recycle();
+    }
+}
+
+#[derive(Clone)]
+struct DistanceField {
+    dist: Grid<f32>,
+    near_x: Grid<u16>,
+    near_y: Grid<u16>,
+}
+
+impl Recycle for DistanceField {
+    fn recycle(self) {
+        self.dist.recycle();
+        (self.near_x, self.near_y).recycle();
+    }
+}
+
+/// Outputs of every stage that has run and is still current.
+#[derive(Clone, Default)]
+struct Cache {
+    seeds: Option<Seeds>,
+    plate_id: Option<Grid<u16>>,
+    plate_set: Option<PlateSet>,
+    boundaries: Option<Boundaries>,
+    distance: Option<DistanceField>,
+    height: Option<Grid<f32>>,
+    temperature: Option<Grid<f32>>,
+    precipitation: Option<Grid<f32>>,
+    ice: Option<Grid<u8>>,
+    rgba: Option<Vec<u8>>,
+    /// Carved heightmap and river flow.
+    hydrology: Option<(Grid<f32>, Grid<f32>)>,
+    population: Option<Grid<f32>>,
+    history: Option<(Grid<u16>, Vec<history::Site>)>,
+    cultures: Option<(Grid<u16>, Vec<culture::Culture>)>,
+}
+
+impl Cache {
+    fn has(&self, stage: Stage) -> bool {
+        match stage {
+            Stage::PlateSeed => self.seeds.is_some(),
+            Stage::PlateGrow => self.plate_id.is_some(),
+            Stage::PlateProperties => self.plate_set.is_some(),
+            Stage::Boundaries => self.boundaries.is_some(),
+            Stage::DistanceField => self.distance.is_some(),
+            Stage::Elevation => self.height.is_some(),
+            Stage::Temperature => self.temperature.is_some(),
+            Stage::Precipitation => self.precipitation.is_some(),
+            Stage::Ice => self.ice.is_some(),
+            Stage::Render => self.rgba.is_some(),
+            Stage::Hydrology => self.hydrology.is_some(),
+            Stage::Population => self.population.is_some(),
+            Stage::History => self.history.is_some(),
+            Stage::Cultures => self.cultures.is_some(),
+        }
+    }
+
+    /// Drop a stage's output, handing its buffers to the active pool.
+    fn clear(&mut self, stage: Stage) {
+        match stage {
+            Stage::PlateSeed => self.seeds = None,
+            Stage::PlateGrow => self.plate_id.take().recycle(),
+            Stage::PlateProperties => self.plate_set = None,
+            Stage::Boundaries => self.boundaries.take().recycle(),
+            Stage::DistanceField => self.distance.take().recycle(),
+            Stage::Elevation => self.height.take().recycle(),
+            Stage::Temperature => self.temperature.take().recycle(),
+            Stage::Precipitation => self.precipitation.take().recycle(),
+            Stage::Ice => self.ice.take().recycle(),
+            Stage::Render => self.rgba.take().recycle(),
+            Stage::Hydrology => self.hydrology.take().recycle(),
+            Stage::Population => self.population.take().recycle(),
+            Stage::History => self.history.take().map(|(ids, _)| ids).recycle(),
+            Stage::Cultures => self.cultures.take().map(|(ids, _)| ids).recycle(),
+        }
+    }
+}
+
+/// A world being generated stage by stage. Outputs stay cached, so after
+/// `set_params` only the stages that read a changed field (and those
+/// downstream of them) run again.
+///
+/// ```ignore
+/// let mut pipeline = Pipeline::new(42, 2048, 1024, Params::default())?;
+/// pipeline.run(&Stage::BASE, |_, _| {}, &CancelToken::new())?;
+/// pipeline.set_params(Params { rainfall_scale: 1.5, ..Params::default() })?;
+/// // Reruns precipitation, ice and render only
+/// pipeline.run(&Stage::BASE, |_, _| {}, &CancelToken::new())?;
+/// ```
+pub struct Pipeline {
+    seed: u64,
+    w: usize,
+    h: usize,
+    params: Params,
+    cache: Cache,
+    /// Buffers of dropped outputs and finished temporaries, for later runs.
+    scratch: Scratch,
+    /// Elevation came from `from_heightmap` and is never recomputed.
+    imported: bool,
+    on_event: Option<Box<dyn FnMut(Event) + Send>>,
+    #[cfg(feature = "parallel")]
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl Pipeline {
+    /// An empty pipeline; fails if the size doesn't pass `check_size` or
+    /// `params` don't pass `Params::validate_for_size`.
+    pub fn new(seed: u64, w: usize, h: usize, params: Params) -> Result<Self, WorldgenError> {
+        check_size(w, h)?;
+        params.validate_for_size(w, h)?;
+        Ok(Self {
+            seed,
+            w,
+            h,
+            params,
+            cache: Cache::default(),
+            scratch: Scratch::default(),
+            imported: false,
+            on_event: None,
+            #[cfg(feature = "parallel")]
+            pool: None,
+        })
+    }
+
+    /// A pipeline over given terrain (meters, 0 = sea level, row 0 =
+    /// north) instead of generated tectonics and elevation: only the
+    /// stages after `Stage::Elevation` run, and the tectonic and elevation
+    /// parameters don't apply. Fails as `new` does for the grid's size.
+    pub fn from_heightmap(seed: u64, height: Grid<f32>, params: Params) -> Result<Self, WorldgenError> {
+        let mut pipeline = Self::new(seed, height.w, height.h, params)?;
+        let metric = if pipeline.params.spherical_metric { Metric::Spherical } else { Metric::Flat };
+        pipeline.cache.height = Some(height.with_metric(metric));
+        pipeline.imported = true;
+        Ok(pipeline)
+    }
+
+    /// Whether `stage` is fixed by an imported heightmap: elevation and
+    /// everything it is built from.
+    fn pinned(&self, stage: Stage) -> bool {
+        self.imported && stage.index() <= Stage::Elevation.index()
+    }
+
+    /// Draw buffers from `scratch` (see `Generator`).
+    pub(crate) fn with_scratch(mut self, scratch: Scratch) -> Self {
+        self.scratch = scratch;
+        self
+    }
+
+    /// Switch to another seed, dropping every cached output but keeping
+    /// their buffers for the next run (cheaper than a new `Pipeline` of
+    /// the same size).
+    pub fn reseed(&mut self, seed: u64) {
+        if seed != self.seed {
+            self.seed = seed;
+            for stage in Stage::ALL {
+                self.invalidate(stage);
+            }
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.w, self.h)
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+
+    /// Replace the parameters, dropping the cached output of every stage
+    /// that reads a changed field and of everything downstream. Fails,
+    /// leaving the pipeline as it was, if `params` don't validate.
+    pub fn set_params(&mut self, params: Params) -> Result<(), InvalidParams> {
+        params.validate_for_size(self.w, self.h)?;
+        let changed = changed_fields(&self.params, &params);
+        self.params = params;
+        for stage in Stage::ALL {
+            let reads_changed = stage
+                .params()
+                .iter()
+                .any(|p| changed.iter().any(|c| c == p || c.strip_prefix(p).is_some_and(|rest| rest.starts_with('.'))));
+            if reads_changed {
+                self.invalidate(stage);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the cached output of `stage` and of every stage downstream.
+    /// An imported heightmap (see `from_heightmap`) stays.
+    pub fn invalidate(&mut self, stage: Stage) {
+        let mut dirty = [false; Stage::ALL.len()];
+        let pinned = Stage::ALL.map(|s| self.pinned(s));
+        dirty[stage.index()] = !pinned[stage.index()];
+        let cache = &mut self.cache;
+        scratch::with(&mut self.scratch, || {
+            for s in Stage::ALL {
+                if pinned[s.index()] {
+                    continue;
+                }
+                if s.inputs().iter().any(|i| dirty[i.index()]) {
+                    dirty[s.index()] = true;
+                }
+                if dirty[s.index()] {
+                    cache.clear(s);
+                }
+            }
+        });
+    }
+
+    /// Run the stages on `pool` instead of rayon's global pool (None goes
+    /// back to the global one). See `threads`.
+    #[cfg(feature = "parallel")]
+    pub fn set_thread_pool(&mut self, pool: Option<Arc<ThreadPool>>) {
+        self.pool = pool;
+    }
+
+    /// Call `on_event` with the events (see `events`) of each stage as it
+    /// finishes: plate boundaries and landmasses after elevation, rivers
+    /// after hydrology, nations and ruins after history (an imported
+    /// heightmap's landmasses aren't reported). None stops.
+    pub fn set_event_listener(&mut self, on_event: Option<Box<dyn FnMut(Event) + Send>>) {
+        self.on_event = on_event;
+    }
+
+    /// Whether `stage` has a current cached output.
+    pub fn is_done(&self, stage: Stage) -> bool {
+        self.cache.has(stage)
+    }
+
+    /// Run `targets` and any of their inputs that aren't cached, in
+    /// dependency order. `progress` gets `(stage, fraction)` for each
+    /// stage that runs, as in `generate_base_with_progress`. Returns the
+    /// timings of the stages that ran. If `cancel` fires, stops with
+    /// `WorldgenError::Cancelled`; finished stages stay cached.
+    pub fn run(
+        &mut self,
+        targets: &[Stage],
+        progress: impl Fn(Stage, f32) + Sync,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Timing>, WorldgenError> {
+        #[cfg(feature = "parallel")]
+        if let Some(pool) = self.pool.clone() {
+            return pool.install(|| self.run_inner(targets, None, &progress, cancel));
+        }
+        self.run_inner(targets, None, &progress, cancel)
+    }
+
+    /// `run`, drawing plate seeding and plate properties from `rng`.
+    /// The plate generator's state isn't kept, so later runs can't redraw
+    /// plate properties alone; `invalidate(Stage::PlateSeed)` first.
+    pub(crate) fn run_with_rng(&mut self, targets: &[Stage], rng: &mut dyn RngCore) -> Vec<Timing> {
+        self.run_inner(targets, Some(rng), &|_, _| {}, &CancelToken::new()).expect("never cancelled")
+    }
+
+    fn run_inner(
+        &mut self,
+        targets: &[Stage],
+        rng: Option<&mut dyn RngCore>,
+        progress: &dyn Fn(Stage, f32),
+        cancel: &CancelToken,
+    ) -> Result<Vec<Timing>, WorldgenError> {
+        // Temporaries come from and go back to this pipeline's pool
+        let mut scratch = std::mem::take(&mut self.scratch);
+        let result = scratch::with(&mut scratch, || self.run_stages(targets, rng, progress, cancel));
+        self.scratch = scratch;
+        result
+    }
+
+    fn run_stages(
+        &mut self,
+        targets: &[Stage],
+        mut rng: Option<&mut dyn RngCore>,
+        progress: &dyn Fn(Stage, f32),
+        cancel: &CancelToken,
+    ) -> Result<Vec<Timing>, WorldgenError> {
+        let mut needed = [false; Stage::ALL.len()];
+        for &t in targets {
+            needed[t.index()] = true;
+        }
+        // A cached stage needs nothing more (its inputs may never have
+        // run, as under an imported heightmap)
+        for s in Stage::ALL.iter().rev() {
+            if needed[s.index()] && !self.cache.has(*s) {
+                for i in s.inputs() {
+                    needed[i.index()] = true;
+                }
+            }
+        }
+
+        let mut timings = Vec::new();
+        for stage in Stage::ALL {
+            if !needed[stage.index()] || self.cache.has(stage) || self.pinned(stage) {
+                continue;
+            }
+            let t = Instant::now();
+            progress(stage, 0.0);
+            self.compute(stage, rng.as_mut().map(|r| &mut **r as &mut dyn RngCore), &|f| progress(stage, f), cancel);
+            if cancel.is_cancelled() {
+                self.cache.clear(stage);
+                return Err(WorldgenError::Cancelled);
+            }
+            progress(stage, 1.0);
+            if let Some(mut on_event) = self.on_event.take() {
+                self.stage_events(stage).into_iter().for_each(&mut on_event);
+                self.on_event = Some(on_event);
+            }
+            timings.push(Timing {
+                name: stage.name(),
+                ms: t.elapsed().as_secs_f64() * 1000.0,
+            });
+        }
+        Ok(timings)
+    }
+
+    /// Compute one stage whose inputs are all cached.
+    fn compute(&mut self, stage: Stage, rng: Option<&mut dyn RngCore>, progress: &dyn Fn(f32), cancel: &CancelToken) {
+        let (seed, w, h) = (self.seed, self.w, self.h);
+        let params = &self.params;
+        let c = &mut self.cache;
+        let missing = "stage inputs are computed first";
+        match stage {
+            Stage::PlateSeed => {
+                let plates_seed = params.seeds.plates.unwrap_or(seed);
+                let mut own = rng::SeedTree::new(plates_seed).child("plates").rng();
+                let external = rng.is_some();
+                let mut rng: &mut dyn RngCore = match rng {
+                    Some(rng) => rng,
+                    None => &mut own,
+                };
+                // Macroplates first (needed for density-guided microplate
+                // seeding), then microplates, denser near macroplate boundaries
+                let macro_seeds = plates::seed::poisson_plate_seeds(w, h, params.num_macroplates, &mut rng);
+                let seeds = plates::seed::poisson_variable_seeds(w, h, params.num_microplates, &mut rng, &macro_seeds);
+                let rng = (!external).then(|| own.clone());
+                c.seeds = Some(Seeds { macro_seeds, seeds, rng });
+            }
+            Stage::PlateGrow => {
+                let seeds = c.seeds.as_ref().expect(missing);
+                c.plate_id = Some(plates::grow::grow_plates(
+                    w,
+                    h,
+                    &seeds.seeds,
+                    params.seeds.plates.unwrap_or(seed),
+                    params.boundary_noise,
+                    progress,
+                    cancel,
+                ));
+            }
+            Stage::PlateProperties => {
+                let seeds = c.seeds.as_ref().expect(missing);
+                let mut own;
+                let mut rng: &mut dyn RngCore = match rng {
+                    Some(rng) => rng,
+                    None => {
+                        own = seeds.rng.clone().expect("plate seeding kept its generator");
+                        &mut own
+                    }
+                };
+                c.plate_set = Some(plates::properties::assign_plate_properties(
+                    params.num_microplates,
+                    params.num_macroplates,
+                    &seeds.seeds,
+                    &seeds.macro_seeds,
+                    c.plate_id.as_ref().expect(missing),
+                    params.continental_fraction,
+                    params.boundary_noise,
+                    &mut rng,
+                ));
+            }
+            Stage::Boundaries => {
+                let (btype, pa, pb, major) = plates::boundary::extract_boundaries(
+                    c.plate_id.as_ref().expect(missing),
+                    c.plate_set.as_ref().expect(missing),
+                );
+                let metric = if params.spherical_metric { Metric::Spherical } else { Metric::Flat };
+                c.boundaries = Some(Boundaries { btype: btype.with_metric(metric), pa, pb, major });
+            }
+            Stage::DistanceField => {
+                let btype = &c.boundaries.as_ref().expect(missing).btype;
+                let (dist, near_x, near_y) = plates::distance::boundary_distance_field(btype, progress, cancel);
+                c.distance = Some(DistanceField { dist, near_x, near_y });
+            }
+            Stage::Elevation => {
+                let b = c.boundaries.as_ref().expect(missing);
+                let d = c.distance.as_ref().expect(missing);
+                c.height = Some(elevation::build_elevation(
+                    c.plate_id.as_ref().expect(missing),
+                    c.plate_set.as_ref().expect(missing),
+                    &b.btype,
+                    &d.dist,
+                    &d.near_x,
+                    &d.near_y,
+                    &b.pa,
+                    &b.pb,
+                    &b.major,
+                    params.seeds.elevation.unwrap_or(seed),
+                    params,
+                ));
+            }
+            Stage::Temperature => {
+                let height = c.height.as_ref().expect(missing);
+                c.temperature = Some(climate::compute_temperature(height, params.seeds.climate.unwrap_or(seed), params));
+            }
+            Stage::Precipitation => {
+                c.precipitation = Some(climate::compute_precipitation(
+                    c.height.as_ref().expect(missing),
+                    c.temperature.as_ref().expect(missing),
+                    params.seeds.climate.unwrap_or(seed),
+                    params,
+                ));
+            }
+            Stage::Ice => {
+                c.ice = Some(climate::compute_ice(
+                    c.height.as_ref().expect(missing),
+                    c.temperature.as_ref().expect(missing),
+                    c.precipitation.as_ref().expect(missing),
+                    params.world.cell_km(w),
+                ));
+            }
+            Stage::Render => {
+                let height = c.height.as_ref().expect(missing);
+                let ao = terrain::compute_ambient_occlusion(height, terrain::default_ao_radius(w));
+                let mut rgba = render::render_map(height, c.ice.as_ref().expect(missing), &render::Palette::default());
+                render::apply_ambient_occlusion(&mut rgba, &ao, 0.5);
+                c.rgba = Some(rgba);
+            }
+            Stage::Hydrology => {
+                let mut height = scratch::copy_grid(c.height.as_ref().expect(missing));
+                // Climate as stored in a `Map`, so this matches
+                // `generate_rivers` on the finished base map
+                let precipitation = stored(c.precipitation.as_ref().expect(missing));
+                let river_flow = hydrology::compute_hydrology(
+                    &mut height,
+                    &precipitation,
+                    params.seeds.hydrology.unwrap_or(seed),
+                    params,
+                    progress,
+                    cancel,
+                );
+                precipitation.recycle();
+                c.hydrology = match river_flow {
+                    Some(flow) => Some((height, flow)),
+                    None => {
+                        height.recycle();
+                        None
+                    }
+                };
+            }
+            Stage::Population => {
+                let (height, river_flow) = c.hydrology.as_ref().expect(missing);
+                c.population = Some(population::compute_population(
+                    height,
+                    &stored(c.temperature.as_ref().expect(missing)),
+                    &stored(c.precipitation.as_ref().expect(missing)),
+                    river_flow,
+                    params.total_population,
+                    params.world.cell_km(w),
+                ));
+            }
+            Stage::History => {
+                let (height, river_flow) = c.hydrology.as_ref().expect(missing);
+                c.history = Some(history::simulate_history(
+                    height,
+                    c.population.as_ref().expect(missing),
+                    river_flow,
+                    params.num_nations,
+                    params.history_epochs,
+                    seed,
+                ));
+            }
+            Stage::Cultures => {
+                let (height, _) = c.hydrology.as_ref().expect(missing);
+                c.cultures = Some(culture::compute_cultures(height, params.num_cultures, seed));
+            }
+        }
+    }
+
+    /// Events formed by `stage`, which has just run.
+    fn stage_events(&self, stage: Stage) -> Vec<Event> {
+        let c = &self.cache;
+        match stage {
+            Stage::Elevation => {
+                let height = c.height.as_ref().expect("elevation ran");
+                let mut formed = match (&c.plate_id, &c.boundaries) {
+                    (Some(plate_id), Some(b)) => events::boundary_events(plate_id, &b.btype, height),
+                    _ => Vec::new(),
+                };
+                formed.extend(events::landmass_events(height));
+                formed
+            }
+            Stage::Hydrology => {
+                let (height, river_flow) = c.hydrology.as_ref().expect("hydrology ran");
+                events::river_events(height, river_flow)
+            }
+            Stage::History => {
+                let (_, sites) = c.history.as_ref().expect("history ran");
+                events::site_events(sites, self.w, self.h)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Assemble a `Map` from copies of the cached outputs. Layers of
+    /// stages that haven't run are left empty, as in `generate_base`.
+    pub fn map(&self) -> Map {
+        Pipeline {
+            seed: self.seed,
+            w: self.w,
+            h: self.h,
+            params: self.params.clone(),
+            cache: self.cache.clone(),
+            scratch: Scratch::default(),
+            imported: self.imported,
+            on_event: None,
+            #[cfg(feature = "parallel")]
+            pool: None,
+        }
+        .into_map()
+    }
+
+    /// `map`, moving the cached outputs instead of copying them.
+    pub fn into_map(self) -> Map {
+        self.into_map_and_scratch().0
+    }
+
+    /// `into_map`, also returning the buffer pool with the buffers of the
+    /// outputs a `Map` doesn't keep.
+    pub(crate) fn into_map_and_scratch(mut self) -> (Map, Scratch) {
+        let map = scratch::with(&mut self.scratch, || Self::assemble(self.w, self.h, self.cache));
+        (map, self.scratch)
+    }
+
+    fn assemble(w: usize, h: usize, c: Cache) -> Map {
+        let (height, river_flow) = match c.hydrology {
+            Some((height, flow)) => {
+                c.height.recycle();
+                (height, flow)
+            }
+            None => (c.height.unwrap_or_else(|| Grid::new(w, h)), Grid::new(w, h)),
+        };
+        let (boundary_type, boundary_major) = match c.boundaries {
+            Some(b) => {
+                (b.pa, b.pb).recycle();
+                (b.btype, b.major)
+            }
+            None => (Grid::new(w, h), Grid::new(w, h)),
+        };
+        let (macro_id, num_macro) = c.plate_set.map_or((Vec::new(), 0), |p| (p.macro_id, p.num_macro));
+        let (nation_id, sites) =
+            c.history.unwrap_or_else(|| (Grid::from_vec(w, h, vec![history::NO_NATION; w * h]), Vec::new()));
+        let (culture_id, cultures) =
+            c.cultures.unwrap_or_else(|| (Grid::from_vec(w, h, vec![culture::NO_CULTURE; w * h]), Vec::new()));
+        let encode = |grid: Option<Grid<f32>>| {
+            let grid = grid.unwrap_or_else(|| Grid::new(w, h));
+            let quantized = QuantGrid::encode(&grid);
+            grid.recycle();
+            quantized
+        };
+        let boundary_dist = encode(c.distance.map(|d| {
+            (d.near_x, d.near_y).recycle();
+            d.dist
+        }));
+
+        Map {
+            w,
+            h,
+            height,
+            plate_id: c.plate_id.unwrap_or_else(|| Grid::new(w, h)),
+            boundary_type,
+            boundary_major,
+            boundary_dist,
+            macro_id,
+            num_macro,
+            rgba: c.rgba.unwrap_or_else(|| vec![0; w * h * 4]),
+            temperature: encode(c.temperature),
+            precipitation: encode(c.precipitation),
+            ice: c.ice.unwrap_or_else(|| Grid::new(w, h)),
+            river_flow,
+            population: c.population.unwrap_or_else(|| Grid::new(w, h)),
+            nation_id,
+            sites,
+            culture_id,
+            cultures,
+            extra_layers: Default::default(),
+        }
+    }
+}
+
+/// A climate layer as it reads back from a `Map` (16-bit quantized).
+fn stored(grid: &Grid<f32>) -> Grid<f32> {
+    QuantGrid::encode(grid).decode()
+}
+
+/// Dotted paths of the fields that differ between `a` and `b`, one level
+/// into nested structs (`world.gravity`).
+fn changed_fields(a: &Params, b: &Params) -> Vec<String> {
+    let (serde_json::Value::Object(a), serde_json::Value::Object(b)) =
+        (serde_json::to_value(a).expect("params serialize"), serde_json::to_value(b).expect("params serialize"))
+    else {
+        unreachable!("Params serializes as an object")
+    };
+    let mut changed = Vec::new();
+    for (key, va) in &a {
+        let vb = b.get(key).unwrap_or(&serde_json::Value::Null);
+        match (va, vb) {
+            (serde_json::Value::Object(fa), serde_json::Value::Object(fb)) => {
+                let keys = fa.keys().chain(fb.keys().filter(|k| !fa.contains_key(*k)));
+                for sub in keys {
+                    if fa.get(sub) != fb.get(sub) {
+                        changed.push(format!("{key}.{sub}"));
+                    }
+                }
+            }
+            _ if va != vb => changed.push(key.clone()),
+            _ => {}
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_fields_name_nested_fields_by_path() {
+        let a = Params::default();
+        let mut b = a.clone();
+        b.rainfall_scale *= 2.0;
+        b.world.gravity += 1.0;
+        b.seeds.climate = Some(3);
+        let mut changed = changed_fields(&a, &b);
+        changed.sort();
+        assert_eq!(changed, ["rainfall_scale", "seeds.climate", "world.gravity"]);
+        assert!(changed_fields(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn set_params_drops_only_stages_reading_changed_fields() {
+        let mut pipeline = Pipeline::new(3, 64, 32, Params::default()).unwrap();
+        pipeline.run(&Stage::BASE, |_, _| {}, &CancelToken::new()).unwrap();
+        assert!(Stage::BASE.iter().all(|&s| pipeline.is_done(s)));
+
+        let mut params = pipeline.params().clone();
+        params.rainfall_scale *= 2.0;
+        pipeline.set_params(params).unwrap();
+        // Precipitation and what reads it go; its inputs stay
+        for stage in [Stage::Elevation, Stage::Temperature] {
+            assert!(pipeline.is_done(stage), "{stage:?} was dropped");
+        }
+        for stage in [Stage::Precipitation, Stage::Ice, Stage::Render] {
+            assert!(!pipeline.is_done(stage), "{stage:?} was kept");
+        }
+
+        // A nested field invalidates the stages that read its struct
+        let mut params = pipeline.params().clone();
+        params.world.planet_radius_km *= 2.0;
+        pipeline.set_params(params).unwrap();
+        assert!(pipeline.is_done(Stage::DistanceField));
+        assert!(!pipeline.is_done(Stage::Elevation));
+    }
+}
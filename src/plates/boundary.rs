@@ -1,6 +1,6 @@
-use rayon::prelude::*;
-
-use crate::grid::{Grid, wrap_xy};
+use crate::fmath;
+use crate::grid::Grid;
+use crate::par::*;
 
 use super::properties::PlateSet;
 
@@ -41,7 +41,7 @@ pub fn extract_boundaries(
                 for (dx, dy) in neighbors {
                     let nx = x as i32 + dx;
                     let ny = y as i32 + dy;
-                    let Some((wnx, wny)) = wrap_xy(nx, ny, w, h) else {
+                    let Some((wnx, wny)) = plate_id.wrap(nx, ny) else {
                         continue;
                     };
                     let npid = plate_id.get(wnx, wny) as usize;
@@ -49,7 +49,7 @@ pub fn extract_boundaries(
                         continue;
                     }
 
-                    let nl = (dx as f32).hypot(dy as f32);
+                    let nl = fmath::hypot(dx as f32, dy as f32);
                     let normal = [dx as f32 / nl, dy as f32 / nl];
 
                     let va = plates.velocity[pid];
@@ -1,10 +1,9 @@
 use std::collections::BinaryHeap;
 
-use crate::grid::{Grid, neighbors8_wrap};
-use crate::noise::fbm;
-use crate::rng::seed_u32;
-
-const SALT_GROW: u64 = 0x6120_7700_CAFE_0002;
+use crate::cancel::CancelToken;
+use crate::grid::Grid;
+use crate::noise::Fbm;
+use crate::rng::SeedTree;
 
 /// Priority queue entry for noise-weighted Voronoi growth.
 /// Implements Ord with reversed cost for min-heap behavior.
@@ -36,20 +35,27 @@ impl PartialOrd for Entry {
 /// Each cell's growth cost is modulated by multi-octave Perlin noise,
 /// so plate boundaries follow noise contours instead of straight Voronoi edges.
 /// `boundary_noise` controls how much boundaries deviate: 0 = straight, higher = more organic.
+/// `progress` receives the fraction of cells claimed so far. If `cancel`
+/// fires, growth stops and the unclaimed cells keep `u16::MAX`.
 pub fn grow_plates(
     w: usize,
     h: usize,
     seeds: &[[f32; 2]],
     seed: u64,
     boundary_noise: f32,
+    progress: &dyn Fn(f32),
+    cancel: &CancelToken,
 ) -> Grid<u16> {
     let mut plate_id = Grid::<u16>::new(w, h);
     for v in &mut plate_id.data {
         *v = u16::MAX;
     }
 
-    let noise_seed = seed_u32(seed, SALT_GROW);
+    let noise_seed = SeedTree::new(seed).child("plates").child("grow").seed_u32();
     let mut heap = BinaryHeap::new();
+    let n = w * h;
+    let report_every = (n / 100).max(1);
+    let mut claimed = 0;
 
     // Seed each plate at cost 0 — don't claim yet, claim on pop
     for (i, s) in seeds.iter().enumerate() {
@@ -72,8 +78,15 @@ pub fn grow_plates(
             continue;
         }
         plate_id.set(x, y, pid); // Claim on pop = lowest cost wins
+        claimed += 1;
+        if claimed % report_every == 0 {
+            if cancel.is_cancelled() {
+                break;
+            }
+            progress(claimed as f32 / n as f32);
+        }
 
-        for (nx, ny) in neighbors8_wrap(x, y, w, h) {
+        for (nx, ny) in plate_id.neighbors8(x, y) {
             if plate_id.get(nx, ny) != u16::MAX {
                 continue;
             }
@@ -88,7 +101,7 @@ pub fn grow_plates(
             // that speed it up, so boundaries follow noise contours.
             let u = nx as f32 / w as f32;
             let v = ny as f32 / h as f32;
-            let noise = fbm(u, v, noise_seed, 4, 6.0, 2.0, 0.5);
+            let noise = Fbm::new(4, 6.0, 2.0, 0.5).with_period(1.0, None).sample(u, v, noise_seed);
             let cost_mult = (1.0 + noise * boundary_noise).max(0.05);
 
             let new_cost = cost + step * cost_mult;
@@ -1,13 +1,12 @@
+use crate::fmath;
 use crate::grid::Grid;
-use crate::noise::fbm;
-use crate::rng::{Rng, seed_u32};
-
-pub const SALT_MACRO: u64 = 0xAC20_F1A7_E000_0001;
-const SALT_CONTINENT: u64 = 0xC017_1E17_FACE_0001;
+use crate::noise::Fbm;
+use crate::rng::RngCore;
 
 /// Properties for the hierarchical plate system.
 /// Microplates are the actual grid-level plates (~50).
 /// Macroplates are groups of microplates (~8) representing tectonic plates.
+#[derive(Clone)]
 pub struct PlateSet {
     pub num_micro: usize,
     pub num_macro: usize,
@@ -25,16 +24,16 @@ pub fn assign_plate_properties(
     plate_id: &Grid<u16>,
     continental_fraction: f32,
     boundary_noise: f32,
-    seed: u64,
+    rng: &mut impl RngCore,
 ) -> PlateSet {
     let w = plate_id.w;
     let h = plate_id.h;
-    let mut rng = Rng::new(seed ^ 0xC1A5_51F0_0000_0001);
 
     // Assign each microplate to nearest macroplate center (noise-weighted).
     // Per-macroplate noise fields distort the Voronoi tessellation, creating
     // organic macroplate territories instead of geometric circles.
-    let macro_noise_seed = seed_u32(seed, 0xBA0B_AB0B_CAFE_0042);
+    let macro_noise_seed = rng.next_u32();
+    let macro_noise = Fbm::new(3, 3.0, 2.0, 0.5).with_period(1.0, None);
     let mut macro_id = vec![0usize; num_micro];
     for (i, ms) in micro_seeds.iter().enumerate() {
         let u = ms[0] / w as f32;
@@ -48,7 +47,7 @@ pub fn assign_plate_properties(
             let dy = ms[1] - mc[1];
             let base_d = dx * dx + dy * dy;
             // Unique noise per macroplate for organic grouping
-            let n = fbm(u, v, macro_noise_seed.wrapping_add(j as u32), 3, 3.0, 2.0, 0.5);
+            let n = macro_noise.sample(u, v, macro_noise_seed.wrapping_add(j as u32));
             let d = base_d * (1.0 + n * boundary_noise).max(0.1);
             if d < best_d {
                 best_d = d;
@@ -77,13 +76,13 @@ pub fn assign_plate_properties(
     // This decouples "what's land" from macroplate grouping, producing organic
     // continent shapes with irregular coastlines at microplate resolution.
     // Macroplates still control velocities and major/minor boundary classification.
-    let continent_seed = seed_u32(seed, SALT_CONTINENT);
+    let continent_seed = rng.next_u32();
     let mut noise_vals: Vec<(usize, f32)> = (0..num_micro)
         .map(|i| {
             let u = micro_seeds[i][0] / w as f32;
             let v = micro_seeds[i][1] / h as f32;
             // Low-frequency noise creates coherent continent blobs
-            let n = fbm(u, v, continent_seed, 3, 2.5, 2.0, 0.5);
+            let n = Fbm::new(3, 2.5, 2.0, 0.5).with_period(1.0, None).sample(u, v, continent_seed);
             (i, n)
         })
         .collect();
@@ -91,7 +90,7 @@ pub fn assign_plate_properties(
     // Sort by noise value (highest first) and assign continental
     // until we hit the target fraction. This creates continents where
     // the noise field is high, oceans where it's low.
-    noise_vals.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    noise_vals.sort_by(|a, b| b.1.total_cmp(&a.1));
 
     let total: usize = micro_counts.iter().sum();
     let mut is_continental = vec![false; num_micro];
@@ -109,7 +108,7 @@ pub fn assign_plate_properties(
     for v in &mut macro_velocity {
         let angle = rng.range_f32(0.0, std::f32::consts::TAU);
         let mag = rng.range_f32(0.3, 1.0);
-        *v = [angle.cos() * mag, angle.sin() * mag];
+        *v = [fmath::cos(angle) * mag, fmath::sin(angle) * mag];
     }
     // Subtract area-weighted mean for net-zero
     let (mut sx, mut sy, mut sw) = (0.0f32, 0.0f32, 0.0f32);
@@ -136,8 +135,8 @@ pub fn assign_plate_properties(
         let angle = rng.range_f32(0.0, std::f32::consts::TAU);
         let mag = rng.range_f32(0.0, 0.15);
         velocity[i] = [
-            mv[0] + angle.cos() * mag,
-            mv[1] + angle.sin() * mag,
+            mv[0] + fmath::cos(angle) * mag,
+            mv[1] + fmath::sin(angle) * mag,
         ];
     }
 
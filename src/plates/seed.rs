@@ -1,41 +1,10 @@
-use crate::rng::Rng;
+use crate::noise::blue_noise_samples;
+use crate::rng::RngCore;
 
 /// Poisson disk sampling for plate centers (uniform density).
-/// Attempts to place `count` points with minimum separation.
-/// Relaxes distance constraint if stuck.
-pub fn poisson_plate_seeds(w: usize, h: usize, count: usize, seed: u64) -> Vec<[f32; 2]> {
-    let mut rng = Rng::new(seed ^ 0xA1B2C3D4E5F60789);
-    let mut min_dist = ((w * h) as f32 / count as f32).sqrt() * 0.6;
-    let mut seeds: Vec<[f32; 2]> = Vec::with_capacity(count);
-    let mut attempts = 0usize;
-    let relax_interval = count * 200;
-
-    while seeds.len() < count && attempts < count * 2000 {
-        let x = rng.range_f32(0.0, w as f32);
-        let y = rng.range_f32(0.0, h as f32);
-
-        let ok = seeds.iter().all(|s| {
-            let dx_raw = (s[0] - x).abs();
-            let dx = dx_raw.min(w as f32 - dx_raw);
-            let dy = s[1] - y;
-            (dx * dx + dy * dy).sqrt() >= min_dist
-        });
-
-        if ok {
-            seeds.push([x, y]);
-        }
-        attempts += 1;
-        if attempts % relax_interval == 0 {
-            min_dist *= 0.85;
-        }
-    }
-
-    // Fallback: fill remaining randomly
-    while seeds.len() < count {
-        seeds.push([rng.range_f32(0.0, w as f32), rng.range_f32(0.0, h as f32)]);
-    }
-
-    seeds
+/// Places exactly `count` blue-noise points.
+pub fn poisson_plate_seeds(w: usize, h: usize, count: usize, rng: &mut impl RngCore) -> Vec<[f32; 2]> {
+    sample_count(w, h, count, rng, |_, _| 1.0)
 }
 
 /// Variable-density Poisson disk sampling for microplate centers.
@@ -45,51 +14,45 @@ pub fn poisson_variable_seeds(
     w: usize,
     h: usize,
     count: usize,
-    seed: u64,
+    rng: &mut impl RngCore,
     macro_centers: &[[f32; 2]],
 ) -> Vec<[f32; 2]> {
-    let mut rng = Rng::new(seed ^ 0xA1B2C3D4E5F60789);
-    let base_dist = ((w * h) as f32 / count as f32).sqrt() * 0.6;
-    let mut seeds: Vec<[f32; 2]> = Vec::with_capacity(count);
-    let mut attempts = 0usize;
-    let relax_interval = count * 200;
-    let mut relax_factor = 1.0f32;
-
-    while seeds.len() < count && attempts < count * 2000 {
-        let x = rng.range_f32(0.0, w as f32);
-        let y = rng.range_f32(0.0, h as f32);
-
+    sample_count(w, h, count, rng, |x, y| {
         // Compute boundary proximity (0 = at macro center, ~1 = on macro boundary)
         let proximity = macro_boundary_proximity(x, y, macro_centers, w);
-        // Near boundaries: smaller min_dist → denser packing.
-        // min_scale=0.35 means boundary plates ~8x smaller in area than interior plates.
+        // Near boundaries: smaller spacing → denser packing.
+        // min_scale=0.2 means boundary plates ~25x smaller in area than interior plates.
         let min_scale = 0.2;
-        let local_dist = base_dist
-            * (min_scale + (1.0 - min_scale) * (1.0 - proximity * proximity))
-            * relax_factor;
-
-        let ok = seeds.iter().all(|s| {
-            let dx_raw = (s[0] - x).abs();
-            let dx = dx_raw.min(w as f32 - dx_raw);
-            let dy = s[1] - y;
-            (dx * dx + dy * dy).sqrt() >= local_dist
-        });
+        min_scale + (1.0 - min_scale) * (1.0 - proximity * proximity)
+    })
+}
 
-        if ok {
-            seeds.push([x, y]);
-        }
-        attempts += 1;
-        if attempts % relax_interval == 0 {
-            relax_factor *= 0.85;
-        }
+/// Exactly `count` blue-noise points spaced in proportion to
+/// `spacing(x, y)`. Starts near the scale at which a maximal sampling
+/// holds `count` points at full spacing, shrinks it until the sampler yields
+/// enough, then drops a random surplus, which thins every region alike.
+fn sample_count(
+    w: usize,
+    h: usize,
+    count: usize,
+    rng: &mut impl RngCore,
+    spacing: impl Fn(f32, f32) -> f32,
+) -> Vec<[f32; 2]> {
+    if count == 0 || w == 0 || h == 0 {
+        return Vec::new();
     }
-
-    // Fallback
-    while seeds.len() < count {
-        seeds.push([rng.range_f32(0.0, w as f32), rng.range_f32(0.0, h as f32)]);
+    // A maximal Poisson-disk set of radius r holds ~0.65 / r^2 points per
+    // unit area; aim a little tight so the first pass usually suffices
+    let mut scale = ((w * h) as f32 * 0.6 / count as f32).sqrt();
+    loop {
+        let mut seeds = blue_noise_samples(w, h, rng.next_u64(), |x, y| scale * spacing(x, y));
+        if seeds.len() >= count {
+            rng.shuffle(&mut seeds);
+            seeds.truncate(count);
+            return seeds;
+        }
+        scale *= 0.9;
     }
-
-    seeds
 }
 
 /// How close a point is to a macroplate Voronoi boundary.
@@ -0,0 +1,347 @@
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::Map;
+use crate::culture::NO_CULTURE;
+use crate::grid::{Grid, cell_latlon};
+use crate::history::{NO_NATION, SiteKind};
+use crate::plates::CONVERGENT;
+use crate::population;
+
+/// Minimum elevation for a peak (meters).
+const PEAK_MIN_ELEV: f32 = 1500.0;
+/// Minimum elevation drop along a river step to count as a waterfall (meters).
+const WATERFALL_DROP: f32 = 150.0;
+/// Minimum suitability (0..1) for a deposit.
+const DEPOSIT_MIN_SCORE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoiKind {
+    Peak,
+    Volcano,
+    Waterfall,
+    Delta,
+    Harbor,
+    Settlement,
+    Capital,
+    Ruin,
+    Deposit,
+}
+
+/// What a `PoiKind::Deposit` holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resource {
+    /// Metal ores in uplifted rock near plate boundaries.
+    Ore,
+    /// Coal from wet, warm lowlands.
+    Coal,
+    /// Evaporite salt in arid lowlands.
+    Salt,
+    /// Oil under continental shelves away from active boundaries.
+    Oil,
+}
+
+/// A discrete point of interest, keyed by grid cell and geographic position.
+#[derive(Clone, Debug, Serialize)]
+pub struct Poi {
+    pub kind: PoiKind,
+    pub x: usize,
+    pub y: usize,
+    pub lat: f32,
+    pub lon: f32,
+    pub elevation: f32,
+    /// River discharge for river features, 0 otherwise.
+    pub flow: f32,
+    /// People around a settlement, 0 otherwise.
+    pub population: f32,
+    /// Resource of a deposit.
+    pub resource: Option<Resource>,
+    pub nation: Option<u16>,
+    /// Name in the local culture's style, if the cell has a culture.
+    pub name: Option<String>,
+    pub culture: Option<u16>,
+}
+
+/// Extract all discrete features from a generated map.
+/// Rivers, population, and history must already be computed for the
+/// river, harbor, and settlement features to appear.
+pub fn extract_pois(map: &Map) -> Vec<Poi> {
+    let mut pois = Vec::new();
+    find_peaks(map, &mut pois);
+    find_rivers(map, &mut pois);
+    find_harbors(map, &mut pois);
+
+    for s in population::find_settlements(&map.population) {
+        pois.push(Poi { population: s.population, ..make_poi(map, PoiKind::Settlement, s.x, s.y, 0.0) });
+    }
+
+    for site in &map.sites {
+        let kind = match site.kind {
+            SiteKind::Capital => PoiKind::Capital,
+            SiteKind::Ruin => PoiKind::Ruin,
+        };
+        pois.push(make_poi(map, kind, site.x, site.y, 0.0));
+    }
+
+    find_deposits(map, &mut pois);
+    pois
+}
+
+/// Encode POIs as a GeoJSON FeatureCollection (Point geometry, [lon, lat]).
+pub fn to_geojson(pois: &[Poi]) -> Value {
+    let features: Vec<Value> = pois
+        .iter()
+        .map(|p| {
+            json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [p.lon, p.lat] },
+                "properties": {
+                    "kind": p.kind,
+                    "x": p.x,
+                    "y": p.y,
+                    "elevation": p.elevation,
+                    "flow": p.flow,
+                    "population": p.population,
+                    "resource": p.resource,
+                    "nation": p.nation,
+                    "name": p.name,
+                    "culture": p.culture,
+                },
+            })
+        })
+        .collect();
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+fn make_poi(map: &Map, kind: PoiKind, x: usize, y: usize, flow: f32) -> Poi {
+    let (lat, lon) = cell_latlon(x, y, map.w, map.h);
+    let n = map.nation_id.get(x, y);
+    let c = map.culture_id.get(x, y);
+    let culture = map.cultures.get(c as usize).filter(|_| c != NO_CULTURE);
+    Poi {
+        kind,
+        x,
+        y,
+        lat,
+        lon,
+        elevation: map.height.get(x, y),
+        flow,
+        population: 0.0,
+        resource: None,
+        nation: if n == NO_NATION { None } else { Some(n) },
+        name: culture.map(|cu| cu.name_feature(x, y)),
+        culture: culture.map(|_| c),
+    }
+}
+
+/// Peaks: local maxima within a resolution-scaled window. Peaks near a
+/// convergent boundary and close to the coast are subduction-arc volcanoes.
+fn find_peaks(map: &Map, pois: &mut Vec<Poi>) {
+    let (w, h) = (map.w, map.h);
+    let scale = w as f32 / 2048.0;
+    let r = (6.0 * scale).ceil().max(2.0) as i32;
+    let arc_r = (10.0 * scale).ceil().max(2.0) as i32;
+
+    for y in 0..h {
+        for x in 0..w {
+            let e = map.height.get(x, y);
+            if e < PEAK_MIN_ELEV {
+                continue;
+            }
+            let mut is_max = true;
+            'win: for dy in -r..=r {
+                for dx in -r..=r {
+                    if let Some((nx, ny)) = map.height.wrap(x as i32 + dx, y as i32 + dy) {
+                        let ne = map.height.get(nx, ny);
+                        // Tie-break on index so plateaus yield one peak
+                        if ne > e || (ne == e && ny * w + nx < y * w + x) {
+                            is_max = false;
+                            break 'win;
+                        }
+                    }
+                }
+            }
+            if !is_max {
+                continue;
+            }
+
+            let mut near_convergent = false;
+            let mut near_ocean = false;
+            for dy in -arc_r..=arc_r {
+                for dx in -arc_r..=arc_r {
+                    if let Some((nx, ny)) = map.height.wrap(x as i32 + dx, y as i32 + dy) {
+                        near_convergent |= map.boundary_type.get(nx, ny) == CONVERGENT;
+                        near_ocean |= map.height.get(nx, ny) <= 0.0;
+                    }
+                }
+            }
+            let kind = if near_convergent && near_ocean { PoiKind::Volcano } else { PoiKind::Peak };
+            pois.push(make_poi(map, kind, x, y, 0.0));
+        }
+    }
+}
+
+/// Waterfalls (large drops to the downstream river cell) and deltas
+/// (river mouths in the top quartile of discharge).
+fn find_rivers(map: &Map, pois: &mut Vec<Poi>) {
+    let (w, h) = (map.w, map.h);
+    let mut mouths: Vec<(usize, usize, f32)> = Vec::new();
+
+    for y in 0..h {
+        for x in 0..w {
+            let flow = map.river_flow.get(x, y);
+            if flow <= 0.0 {
+                continue;
+            }
+            let e = map.height.get(x, y);
+
+            // Downstream = neighboring river cell with the largest discharge above ours
+            let mut down: Option<(usize, usize)> = None;
+            let mut down_flow = flow;
+            let mut at_coast = false;
+            for (nx, ny) in map.height.neighbors8(x, y) {
+                if map.height.get(nx, ny) <= 0.0 {
+                    at_coast = true;
+                }
+                let nf = map.river_flow.get(nx, ny);
+                if nf > down_flow {
+                    down_flow = nf;
+                    down = Some((nx, ny));
+                }
+            }
+
+            if let Some((dx, dy)) = down
+                && e - map.height.get(dx, dy) > WATERFALL_DROP
+            {
+                pois.push(make_poi(map, PoiKind::Waterfall, x, y, flow));
+            }
+            if at_coast && down.is_none() {
+                mouths.push((x, y, flow));
+            }
+        }
+    }
+
+    if mouths.is_empty() {
+        return;
+    }
+    let mut flows: Vec<f32> = mouths.iter().map(|m| m.2).collect();
+    flows.sort_unstable_by(f32::total_cmp);
+    let cutoff = flows[flows.len() * 3 / 4];
+    for (x, y, flow) in mouths {
+        if flow >= cutoff {
+            pois.push(make_poi(map, PoiKind::Delta, x, y, flow));
+        }
+    }
+}
+
+/// One harbor per nation: the coastal cell maximizing population times
+/// shelter (partially enclosed bays score highest).
+fn find_harbors(map: &Map, pois: &mut Vec<Poi>) {
+    let (w, h) = (map.w, map.h);
+    let num = map.sites.iter().map(|s| s.nation as usize + 1).max().unwrap_or(0);
+    let mut best: Vec<Option<(usize, usize, f32)>> = vec![None; num];
+    let r = 4i32;
+
+    for y in 0..h {
+        for x in 0..w {
+            let n = map.nation_id.get(x, y);
+            if n == NO_NATION || map.height.get(x, y) <= 0.0 {
+                continue;
+            }
+            if !map.height.neighbors8(x, y).any(|(nx, ny)| map.height.get(nx, ny) <= 0.0) {
+                continue;
+            }
+
+            let (mut water, mut total) = (0u32, 0u32);
+            for dy in -r..=r {
+                for dx in -r..=r {
+                    if let Some((nx, ny)) = map.height.wrap(x as i32 + dx, y as i32 + dy) {
+                        total += 1;
+                        if map.height.get(nx, ny) <= 0.0 {
+                            water += 1;
+                        }
+                    }
+                }
+            }
+            // Best shelter around 30% water: a bay, not a headland
+            let frac = water as f32 / total.max(1) as f32;
+            let shelter = (1.0 - ((frac - 0.3) / 0.3).abs()).max(0.0);
+            let score = map.population.get(x, y) * shelter;
+
+            let slot = &mut best[n as usize];
+            if score > 0.0 && slot.is_none_or(|b| score > b.2) {
+                *slot = Some((x, y, score));
+            }
+        }
+    }
+
+    for (x, y, _) in best.into_iter().flatten() {
+        pois.push(make_poi(map, PoiKind::Harbor, x, y, 0.0));
+    }
+}
+
+/// Resource deposits: the most suitable cell within a resolution-scaled
+/// window, per resource, where suitability reaches `DEPOSIT_MIN_SCORE`.
+fn find_deposits(map: &Map, pois: &mut Vec<Poi>) {
+    let scale = map.w as f32 / 2048.0;
+    let r = (24.0 * scale).ceil().max(4.0) as i32;
+    let temperature = map.temperature.decode();
+    let precipitation = map.precipitation.decode();
+    let boundary_dist = map.boundary_dist.decode();
+    let ramp = |lo: f32, hi: f32, v: f32| ((v - lo) / (hi - lo)).clamp(0.0, 1.0);
+
+    for resource in [Resource::Ore, Resource::Coal, Resource::Salt, Resource::Oil] {
+        let score = Grid::from_vec(
+            map.w,
+            map.h,
+            (0..map.w * map.h)
+                .map(|i| {
+                    let e = map.height.data[i];
+                    let (t, p) = (temperature.data[i], precipitation.data[i]);
+                    let d = boundary_dist.data[i] / scale.max(0.25);
+                    match resource {
+                        Resource::Ore => ramp(800.0, 4000.0, e) * (1.0 - ramp(0.0, 24.0, d)),
+                        Resource::Coal if e > 0.0 => {
+                            ramp(800.0, 2500.0, p) * (1.0 - ramp(0.0, 600.0, e)) * ramp(5.0, 15.0, t)
+                        }
+                        Resource::Salt if e > 0.0 => (1.0 - ramp(0.0, 400.0, p)) * (1.0 - ramp(0.0, 500.0, e)),
+                        Resource::Oil if e <= 0.0 => (1.0 - ramp(0.0, 200.0, -e)) * ramp(10.0, 40.0, d),
+                        _ => 0.0,
+                    }
+                })
+                .collect(),
+        );
+        for (x, y) in local_maxima(&score, r, DEPOSIT_MIN_SCORE) {
+            pois.push(Poi { resource: Some(resource), ..make_poi(map, PoiKind::Deposit, x, y, 0.0) });
+        }
+    }
+}
+
+/// Cells at least `min` that are the highest within `r` (ties go to the
+/// lowest index, so plateaus yield one cell).
+fn local_maxima(grid: &Grid<f32>, r: i32, min: f32) -> Vec<(usize, usize)> {
+    let w = grid.w;
+    let mut maxima = Vec::new();
+    for y in 0..grid.h {
+        for x in 0..w {
+            let v = grid.get(x, y);
+            if v < min {
+                continue;
+            }
+            let is_max = (-r..=r).all(|dy| {
+                (-r..=r).all(|dx| {
+                    grid.wrap(x as i32 + dx, y as i32 + dy).is_none_or(|(nx, ny)| {
+                        let nv = grid.get(nx, ny);
+                        nv < v || (nv == v && ny * w + nx >= y * w + x)
+                    })
+                })
+            });
+            if is_max {
+                maxima.push((x, y));
+            }
+        }
+    }
+    maxima
+}
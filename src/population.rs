@@ -0,0 +1,150 @@
+use crate::config::REFERENCE_CELL_KM;
+use crate::fmath;
+use crate::grid::{Grid, distance};
+use crate::par::*;
+
+/// Water-access falloff distance in reference cells (`REFERENCE_CELL_KM`).
+const WATER_FALLOFF: f32 = 6.0;
+/// Settlement search window radius at 2048 cells wide.
+const SETTLEMENT_RADIUS: f32 = 8.0;
+/// A settlement's cell must be this many times denser than the average
+/// inhabited cell.
+const SETTLEMENT_MIN_DENSITY: f32 = 4.0;
+
+/// A town or city: a local maximum of population density.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settlement {
+    pub x: usize,
+    pub y: usize,
+    /// People living within the search window around the center.
+    pub population: f32,
+}
+
+/// Compute population density (people per cell) from habitability.
+///
+/// Suitability per land cell is the product of:
+/// - arable land: moderate rainfall, low elevation, gentle slope
+/// - climate comfort: peaks around 18C, falls off toward ice and heat
+/// - water access: proximity to rivers and coast
+///
+/// Suitability is squared to concentrate people into the best land (urban clustering),
+/// then normalized so the grid sums to `total_population`. `cell_km` is
+/// the cell size.
+pub fn compute_population(
+    height: &Grid<f32>,
+    temperature: &Grid<f32>,
+    precipitation: &Grid<f32>,
+    river_flow: &Grid<f32>,
+    total_population: f32,
+    cell_km: f32,
+) -> Grid<f32> {
+    let w = height.w;
+    let h = height.h;
+    let scale = REFERENCE_CELL_KM / cell_km;
+
+    let water_dist = water_distance(height, river_flow);
+    let falloff = WATER_FALLOFF * scale.max(0.25);
+
+    let mut pop = Grid::<f32>::new(w, h);
+    pop.data.par_chunks_mut(w).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.iter_mut().enumerate() {
+            let elev = height.get(x, y);
+            if elev <= 0.0 {
+                *out = 0.0;
+                continue;
+            }
+
+            // Arable: rainfall window, elevation and slope penalties
+            let p = precipitation.get(x, y);
+            let rain = smoothstep(150.0, 600.0, p) * (1.0 - 0.5 * smoothstep(2000.0, 4000.0, p));
+            let altitude = 1.0 - smoothstep(1500.0, 4000.0, elev);
+            let xe = height.get((x + 1) % w, y);
+            let xw = height.get((x + w - 1) % w, y);
+            let yn = height.get(x, y.saturating_sub(1));
+            let ys = height.get(x, (y + 1).min(h - 1));
+            let grad = ((xe - xw).abs() + (ys - yn).abs()) * 0.5 * scale;
+            let flatness = 1.0 / (1.0 + grad / 150.0);
+            let arable = rain * altitude * flatness;
+
+            // Climate comfort
+            let t = temperature.get(x, y);
+            let dt = (t - 18.0) / 12.0;
+            let comfort = fmath::exp(-dt * dt);
+
+            // Water access
+            let d = water_dist[y * w + x];
+            let water = 0.3 + 0.7 * fmath::exp(-d / falloff);
+
+            let s = arable * comfort * water;
+            *out = s * s;
+        }
+    });
+
+    let sum: f64 = pop.data.iter().map(|&v| v as f64).sum();
+    if sum > 0.0 {
+        let k = (total_population as f64 / sum) as f32;
+        for v in pop.data.iter_mut() {
+            *v *= k;
+        }
+    }
+
+    pop
+}
+
+/// Settlements: cells that are the densest within a resolution-scaled
+/// window and well above the average inhabited cell, largest first.
+pub fn find_settlements(population: &Grid<f32>) -> Vec<Settlement> {
+    let (w, h) = (population.w, population.h);
+    let r = (SETTLEMENT_RADIUS * w as f32 / 2048.0).ceil().max(2.0) as i32;
+    let (sum, inhabited) = population
+        .data
+        .iter()
+        .filter(|&&v| v > 0.0)
+        .fold((0.0f64, 0usize), |(s, n), &v| (s + v as f64, n + 1));
+    if inhabited == 0 {
+        return Vec::new();
+    }
+    let min_density = (sum / inhabited as f64) as f32 * SETTLEMENT_MIN_DENSITY;
+
+    let mut settlements = Vec::new();
+    for y in 0..h {
+        for x in 0..w {
+            let p = population.get(x, y);
+            if p < min_density {
+                continue;
+            }
+            let mut is_max = true;
+            let mut total = 0.0;
+            'win: for dy in -r..=r {
+                for dx in -r..=r {
+                    if let Some((nx, ny)) = population.wrap(x as i32 + dx, y as i32 + dy) {
+                        let np = population.get(nx, ny);
+                        // Tie-break on index so flat maxima yield one settlement
+                        if np > p || (np == p && ny * w + nx < y * w + x) {
+                            is_max = false;
+                            break 'win;
+                        }
+                        total += np;
+                    }
+                }
+            }
+            if is_max {
+                settlements.push(Settlement { x, y, population: total });
+            }
+        }
+    }
+    settlements.sort_by(|a, b| b.population.total_cmp(&a.population));
+    settlements
+}
+
+/// Distance (cells) to the nearest river or ocean cell, E-W wrapped.
+fn water_distance(height: &Grid<f32>, river_flow: &Grid<f32>) -> Vec<f32> {
+    let water = height.zip_with(river_flow, |elev, flow| elev <= 0.0 || flow > 0.0);
+    distance::edt(&water, |is_water| is_water).data
+}
+
+#[inline]
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
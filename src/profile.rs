@@ -0,0 +1,84 @@
+use serde::Serialize;
+
+use crate::Map;
+use crate::fmath;
+use crate::grid::latlon_pos;
+use crate::terrain::PLANET_CIRCUMFERENCE_M;
+
+/// One point along a cross-section.
+#[derive(Clone, Debug, Serialize)]
+pub struct ProfileSample {
+    /// Great-circle distance from the first waypoint (km).
+    pub distance_km: f32,
+    pub lat: f32,
+    pub lon: f32,
+    pub elevation: f32,
+    pub temperature: f32,
+    pub precipitation: f32,
+}
+
+/// Sample `samples` evenly spaced points (bilinear between cells) along
+/// the great-circle legs joining `waypoints` (lat, lon in degrees).
+/// Antipodal legs are ambiguous; split them with an intermediate waypoint.
+pub fn extract_profile(map: &Map, waypoints: &[(f32, f32)], samples: usize) -> Vec<ProfileSample> {
+    if waypoints.len() < 2 || samples < 2 {
+        return Vec::new();
+    }
+    let radius_km = PLANET_CIRCUMFERENCE_M / 1000.0 / std::f32::consts::TAU;
+    let pts: Vec<[f32; 3]> = waypoints.iter().map(|&(lat, lon)| unit_vector(lat, lon)).collect();
+    let legs: Vec<f32> = pts.windows(2).map(|p| angle_between(p[0], p[1])).collect();
+    let total: f32 = legs.iter().sum();
+
+    let mut out = Vec::with_capacity(samples);
+    let mut leg = 0;
+    let mut leg_start = 0.0;
+    for i in 0..samples {
+        let s = total * i as f32 / (samples - 1) as f32;
+        while leg + 1 < legs.len() && s > leg_start + legs[leg] {
+            leg_start += legs[leg];
+            leg += 1;
+        }
+        let t = if legs[leg] > 0.0 { ((s - leg_start) / legs[leg]).clamp(0.0, 1.0) } else { 0.0 };
+        let v = slerp(pts[leg], pts[leg + 1], legs[leg], t);
+        let lat = fmath::asin(v[2].clamp(-1.0, 1.0)).to_degrees();
+        let lon = fmath::atan2(v[1], v[0]).to_degrees();
+        let (fx, fy) = latlon_pos(lat, lon, map.w, map.h);
+        out.push(ProfileSample {
+            distance_km: s * radius_km,
+            lat,
+            lon,
+            elevation: map.height.sample(fx, fy),
+            temperature: map.temperature.sample(fx, fy),
+            precipitation: map.precipitation.sample(fx, fy),
+        });
+    }
+    out
+}
+
+#[inline]
+fn unit_vector(lat: f32, lon: f32) -> [f32; 3] {
+    let (sl, cl) = lat.to_radians().sin_cos();
+    let (so, co) = lon.to_radians().sin_cos();
+    [cl * co, cl * so, sl]
+}
+
+#[inline]
+fn angle_between(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let dot = a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    let cx = a[1] * b[2] - a[2] * b[1];
+    let cy = a[2] * b[0] - a[0] * b[2];
+    let cz = a[0] * b[1] - a[1] * b[0];
+    fmath::atan2((cx * cx + cy * cy + cz * cz).sqrt(), dot)
+}
+
+/// Spherical interpolation between unit vectors `angle` radians apart.
+#[inline]
+fn slerp(a: [f32; 3], b: [f32; 3], angle: f32, t: f32) -> [f32; 3] {
+    let sin = fmath::sin(angle);
+    if sin.abs() < 1e-6 {
+        return a;
+    }
+    let wa = fmath::sin((1.0 - t) * angle) / sin;
+    let wb = fmath::sin(t * angle) / sin;
+    [a[0] * wa + b[0] * wb, a[1] * wa + b[1] * wb, a[2] * wa + b[2] * wb]
+}
@@ -0,0 +1,78 @@
+use crate::grid::{Filter, Grid, QuantGrid};
+use crate::render::resample::downsample;
+use crate::{Map, history};
+
+impl Map {
+    /// Level-of-detail pyramid: up to `levels` maps, each half the size of
+    /// the one before (odd sides round down), stopping before a side would
+    /// drop below 1. Scalar layers are area-averaged, except population,
+    /// which counts people per cell and so is summed; river flow keeps the
+    /// largest value so rivers survive; plate, nation and culture ids and
+    /// the boundary and ice classes take the majority, as do added u16 and
+    /// u8 layers (added f32 layers are averaged). Boundary distance is
+    /// rescaled to the level's cells, the color image is downsampled in
+    /// linear light, and sites move to the cell they fall in.
+    pub fn build_pyramid(&self, levels: usize) -> Vec<Map> {
+        let mut pyramid: Vec<Map> = Vec::with_capacity(levels);
+        for _ in 0..levels {
+            let prev = pyramid.last().unwrap_or(self);
+            if prev.w < 2 || prev.h < 2 {
+                break;
+            }
+            let next = prev.halve();
+            pyramid.push(next);
+        }
+        pyramid
+    }
+
+    /// One pyramid step (see `build_pyramid`).
+    fn halve(&self) -> Map {
+        let (w, h) = (self.w / 2, self.h / 2);
+        let cells_per_cell = (self.w * self.h) as f32 / (w * h) as f32;
+        let area = |g: &Grid<f32>| g.resample(w, h, Filter::Area);
+        let quant = |q: &QuantGrid, scale: f32| {
+            let mut g = area(&q.decode());
+            g.data.iter_mut().for_each(|v| *v *= scale);
+            QuantGrid::encode(&g)
+        };
+
+        let mut population = area(&self.population);
+        population.data.iter_mut().for_each(|v| *v *= cells_per_cell);
+        let rgba = if self.rgba.is_empty() { Vec::new() } else { downsample(&self.rgba, self.w, self.h, 2) };
+        let sites = self
+            .sites
+            .iter()
+            .map(|s| history::Site { x: (s.x / 2).min(w - 1), y: (s.y / 2).min(h - 1), ..s.clone() })
+            .collect();
+
+        Map {
+            w,
+            h,
+            height: area(&self.height),
+            plate_id: self.plate_id.resample_majority(w, h),
+            boundary_type: self.boundary_type.resample_majority(w, h),
+            boundary_major: self.boundary_major.resample_majority(w, h),
+            boundary_dist: quant(&self.boundary_dist, w as f32 / self.w as f32),
+            macro_id: self.macro_id.clone(),
+            num_macro: self.num_macro,
+            rgba,
+            temperature: quant(&self.temperature, 1.0),
+            precipitation: quant(&self.precipitation, 1.0),
+            ice: self.ice.resample_majority(w, h),
+            river_flow: self.river_flow.resample(w, h, Filter::Max),
+            population,
+            nation_id: self.nation_id.resample_majority(w, h),
+            sites,
+            culture_id: self.culture_id.resample_majority(w, h),
+            cultures: self.cultures.clone(),
+            extra_layers: self
+                .extra_layers
+                .iter()
+                .map(|(name, data)| {
+                    let data = data.map_grid(area, |g| g.resample_majority(w, h), |g| g.resample_majority(w, h));
+                    (name.clone(), data)
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use serde::Serialize;
+
+use crate::Map;
+use crate::grid::{latlon_cell, latlon_pos};
+
+/// Everything the map knows about one geographic point. Continuous
+/// layers are interpolated (bilinear between cell centers); ids and
+/// classes come from the cell containing the point.
+#[derive(Clone, Debug, Serialize)]
+pub struct CellInfo {
+    pub lat: f32,
+    pub lon: f32,
+    /// Cell containing the point.
+    pub x: usize,
+    pub y: usize,
+    /// Meters above sea level (negative under the sea).
+    pub elevation: f32,
+    /// Degrees C.
+    pub temperature: f32,
+    /// mm/year.
+    pub precipitation: f32,
+    pub river_flow: f32,
+    pub population: f32,
+    /// Distance to the nearest plate boundary, in cells.
+    pub boundary_distance: f32,
+    pub plate_id: u16,
+    /// Macroplate the point's plate belongs to.
+    pub macro_id: Option<usize>,
+    /// Ice class (`climate::ICE_*`).
+    pub ice: u8,
+    /// `history::NO_NATION` outside any nation.
+    pub nation_id: u16,
+    /// `culture::NO_CULTURE` at sea.
+    pub culture_id: u16,
+}
+
+impl CellInfo {
+    pub fn is_land(&self) -> bool {
+        self.elevation > 0.0
+    }
+}
+
+impl Map {
+    /// Query the map at a geographic coordinate (degrees; longitude wraps,
+    /// latitude clamps to the poles). Layers that haven't been generated
+    /// read as zero.
+    pub fn sample(&self, lat: f32, lon: f32) -> CellInfo {
+        let (w, h) = (self.w, self.h);
+        let (fx, fy) = latlon_pos(lat, lon, w, h);
+        let (x, y) = latlon_cell(lat, lon, w, h);
+        let plate_id = self.plate_id.get(x, y);
+        CellInfo {
+            lat,
+            lon,
+            x,
+            y,
+            elevation: self.height.sample(fx, fy),
+            temperature: self.temperature.sample(fx, fy),
+            precipitation: self.precipitation.sample(fx, fy),
+            river_flow: self.river_flow.sample(fx, fy),
+            population: self.population.sample(fx, fy),
+            boundary_distance: self.boundary_dist.sample(fx, fy),
+            plate_id,
+            macro_id: self.macro_id.get(plate_id as usize).copied(),
+            ice: self.ice.get(x, y),
+            nation_id: self.nation_id.get(x, y),
+            culture_id: self.culture_id.get(x, y),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+
+use crate::Map;
+use crate::grid::{Component, Grid, Rect, label_components};
+use crate::terrain::cell_size_m;
+
+/// One landmass or ocean basin.
+#[derive(Clone, Debug, Serialize)]
+pub struct Region {
+    /// Index into the region list and value in the id grid.
+    pub id: u32,
+    pub cells: usize,
+    /// Surface area in km^2 (cell areas shrink toward the poles).
+    pub area_km2: f64,
+    /// Smallest cell window holding the region; may run past the E-W seam.
+    pub bounds: Rect,
+    /// Mean position of the region's cells, in degrees.
+    pub centroid_lat: f32,
+    pub centroid_lon: f32,
+}
+
+impl Map {
+    /// Label connected landmasses (height > 0, 8-connected). Returns each
+    /// cell's region id (`grid::NO_COMPONENT` at sea) and the regions.
+    pub fn label_landmasses(&self) -> (Grid<u32>, Vec<Region>) {
+        label_landmasses(&self.height)
+    }
+
+    /// Label connected oceans and seas (height <= 0, 4-connected, so water
+    /// never slips between diagonally touching land cells). Returns each
+    /// cell's region id (`grid::NO_COMPONENT` on land) and the regions.
+    pub fn label_oceans(&self) -> (Grid<u32>, Vec<Region>) {
+        label_regions(&self.height, false, |h| h <= 0.0)
+    }
+}
+
+/// `Map::label_landmasses` over a bare height grid.
+pub(crate) fn label_landmasses(height: &Grid<f32>) -> (Grid<u32>, Vec<Region>) {
+    label_regions(height, true, |h| h > 0.0)
+}
+
+fn label_regions(height: &Grid<f32>, diagonal: bool, include: impl Fn(f32) -> bool) -> (Grid<u32>, Vec<Region>) {
+    let (w, h) = (height.w, height.h);
+    let (ids, components) = label_components(height, diagonal, include);
+
+    let row_km2: Vec<f64> = (0..h)
+        .map(|y| {
+            let (dx, dy) = cell_size_m(y, w, h);
+            dx as f64 * dy as f64 * 1e-6
+        })
+        .collect();
+    let mut area = vec![0.0f64; components.len()];
+    for (i, &id) in ids.data.iter().enumerate() {
+        if let Some(a) = area.get_mut(id as usize) {
+            *a += row_km2[i / w];
+        }
+    }
+
+    let regions = components
+        .into_iter()
+        .zip(area)
+        .enumerate()
+        .map(|(id, (c, area_km2))| region(id as u32, c, area_km2, w, h))
+        .collect();
+    (ids, regions)
+}
+
+fn region(id: u32, c: Component, area_km2: f64, w: usize, h: usize) -> Region {
+    let (cx, cy) = c.centroid;
+    Region {
+        id,
+        cells: c.cells,
+        area_km2,
+        bounds: c.bounds,
+        centroid_lat: 90.0 - (cy + 0.5) / h as f32 * 180.0,
+        centroid_lon: (cx + 0.5) / w as f32 * 360.0 - 180.0,
+    }
+}
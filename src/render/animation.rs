@@ -0,0 +1,83 @@
+#[cfg(feature = "image")]
+use image::codecs::gif::{GifEncoder, Repeat};
+#[cfg(feature = "image")]
+use image::{Delay, Frame, RgbaImage};
+
+use super::sample_rgba;
+use crate::par::*;
+
+const SPACE: [u8; 4] = [8, 10, 20, 255];
+
+/// Orthographic view of an equirectangular RGBA map as a `size` x `size`
+/// image, centered on (`center_lat`, `center_lon`) in degrees, with limb
+/// darkening toward the globe's edge.
+pub fn render_globe(
+    rgba: &[u8],
+    w: usize,
+    h: usize,
+    size: usize,
+    center_lat: f32,
+    center_lon: f32,
+) -> Vec<u8> {
+    let mut out = vec![0u8; size * size * 4];
+    let (sin0, cos0) = center_lat.to_radians().sin_cos();
+
+    out.par_chunks_mut(size * 4).enumerate().for_each(|(py, row)| {
+        let ny = 1.0 - (py as f32 + 0.5) / size as f32 * 2.0;
+        for px in 0..size {
+            let nx = (px as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let r2 = nx * nx + ny * ny;
+            let c = if r2 > 1.0 {
+                SPACE
+            } else {
+                // Inverse orthographic projection
+                let z = (1.0 - r2).sqrt();
+                let lat = (ny * cos0 + z * sin0).clamp(-1.0, 1.0).asin().to_degrees();
+                let lon = center_lon + nx.atan2(z * cos0 - ny * sin0).to_degrees();
+                let fx = (lon + 180.0) / 360.0 * w as f32 - 0.5;
+                let fy = (90.0 - lat) / 180.0 * h as f32 - 0.5;
+                let s = sample_rgba(rgba, w, h, fx, fy);
+                let shade = 0.35 + 0.65 * z.sqrt();
+                [
+                    (s[0] as f32 * shade) as u8,
+                    (s[1] as f32 * shade) as u8,
+                    (s[2] as f32 * shade) as u8,
+                    255,
+                ]
+            };
+            row[px * 4..px * 4 + 4].copy_from_slice(&c);
+        }
+    });
+
+    out
+}
+
+/// One full rotation (west to east, like a real planet) in `frames` steps, viewed from `tilt` degrees
+/// latitude. (Continental drift sequences need plate time-stepping, which
+/// the tectonics stage doesn't have yet.)
+pub fn globe_frames(rgba: &[u8], w: usize, h: usize, size: usize, frames: usize, tilt: f32) -> Vec<Vec<u8>> {
+    (0..frames)
+        .map(|i| {
+            let lon = 180.0 - 360.0 * i as f32 / frames as f32;
+            render_globe(rgba, w, h, size, tilt, lon)
+        })
+        .collect()
+}
+
+/// Encode square RGBA frames as a looping GIF. `delay_ms` is per frame.
+#[cfg(feature = "image")]
+pub fn encode_gif(frames: &[Vec<u8>], size: usize, delay_ms: u32) -> Vec<u8> {
+    let mut buf = Vec::new();
+    {
+        let mut encoder = GifEncoder::new_with_speed(&mut buf, 10);
+        encoder.set_repeat(Repeat::Infinite).expect("GIF encode failed");
+        let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+        for f in frames {
+            let img = RgbaImage::from_raw(size as u32, size as u32, f.clone()).expect("frame size mismatch");
+            encoder
+                .encode_frame(Frame::from_parts(img, 0, 0, delay))
+                .expect("GIF encode failed");
+        }
+    }
+    buf
+}
@@ -0,0 +1,120 @@
+use super::font::{GLYPH_ADVANCE, draw_text, fill_rect};
+use super::lerp_color;
+use crate::grid::{Grid, neighbors4_wrap};
+use crate::par::*;
+
+/// Upper depth (meters) of each bathymetric class; the last class is
+/// everything deeper.
+const DEPTH_BANDS: [f32; 7] = [200.0, 1000.0, 2000.0, 3000.0, 4000.0, 5000.0, 6000.0];
+
+/// Band tints from shelf to abyss (one more than `DEPTH_BANDS`).
+const BAND_COLORS: [[u8; 4]; 8] = [
+    [190, 228, 240, 255],
+    [150, 205, 230, 255],
+    [112, 178, 216, 255],
+    [82, 150, 200, 255],
+    [58, 120, 180, 255],
+    [40, 92, 156, 255],
+    [28, 66, 128, 255],
+    [18, 42, 96, 255],
+];
+
+const LAND: [u8; 4] = [214, 208, 192, 255];
+const COAST: [u8; 4] = [70, 70, 70, 255];
+
+const CHART_WATER: [u8; 4] = [248, 250, 252, 255];
+const CHART_SHALLOW: [u8; 4] = [190, 222, 242, 255];
+const CHART_LAND: [u8; 4] = [242, 226, 166, 255];
+const CHART_INK: [u8; 4] = [40, 60, 90, 255];
+
+/// Depth class of a cell, or None for land.
+#[inline]
+fn band(elev: f32) -> Option<usize> {
+    if elev > 0.0 {
+        return None;
+    }
+    let depth = -elev;
+    Some(DEPTH_BANDS.iter().position(|&d| depth < d).unwrap_or(DEPTH_BANDS.len()))
+}
+
+/// Depth-tinted bathymetry: ocean in discrete hypsometric classes, land
+/// flat gray, with a legend in the lower-left corner.
+pub fn render_bathymetry(height: &Grid<f32>) -> Vec<u8> {
+    let w = height.w;
+    let h = height.h;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let color = match band(height.get(x, y)) {
+                None if neighbors4_wrap(x, y, w, h).any(|(nx, ny)| height.get(nx, ny) <= 0.0) => COAST,
+                None => LAND,
+                Some(b) => BAND_COLORS[b],
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&color);
+        }
+    });
+
+    draw_legend(&mut rgba, w, h);
+    rgba
+}
+
+/// Nautical chart: white deep water, tinted and hatched shallows (< 200 m),
+/// thin depth contours at each class boundary, buff land, inked coastline.
+pub fn render_nautical(height: &Grid<f32>) -> Vec<u8> {
+    let w = height.w;
+    let h = height.h;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let b = band(height.get(x, y));
+            let color = match b {
+                None if neighbors4_wrap(x, y, w, h).any(|(nx, ny)| height.get(nx, ny) <= 0.0) => CHART_INK,
+                None => CHART_LAND,
+                Some(b) => {
+                    // Contour where a shallower class meets a deeper one
+                    let contour = neighbors4_wrap(x, y, w, h)
+                        .any(|(nx, ny)| band(height.get(nx, ny)).is_some_and(|nb| nb > b));
+                    if contour {
+                        lerp_color(CHART_WATER, CHART_INK, 0.6)
+                    } else if b == 0 {
+                        if (x + y).is_multiple_of(4) {
+                            lerp_color(CHART_SHALLOW, CHART_INK, 0.35)
+                        } else {
+                            CHART_SHALLOW
+                        }
+                    } else {
+                        CHART_WATER
+                    }
+                }
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&color);
+        }
+    });
+
+    rgba
+}
+
+/// Swatch column with depth labels (meters) at each class boundary.
+fn draw_legend(rgba: &mut [u8], w: usize, h: usize) {
+    let s = (w / 1024).max(1);
+    let (sw, sh) = (10 * s, 8 * s);
+    let n = BAND_COLORS.len();
+    let pad = 4 * s;
+    let label_w = 4 * GLYPH_ADVANCE * s;
+    let (x0, y0) = (pad, h.saturating_sub(pad + n * sh + 2 * pad));
+    if y0 == 0 || w < x0 + sw + label_w + 3 * pad {
+        return;
+    }
+
+    // Backing panel
+    fill_rect(rgba, w, x0 - s, y0 - pad, sw + label_w + 2 * pad, n * sh + 2 * pad, [255, 255, 255, 255]);
+    for (i, &c) in BAND_COLORS.iter().enumerate() {
+        fill_rect(rgba, w, x0, y0 + i * sh, sw, sh, c);
+        if i < DEPTH_BANDS.len() {
+            let label = format!("{}", DEPTH_BANDS[i] as u32);
+            draw_text(rgba, w, x0 + sw + s * 2, y0 + (i + 1) * sh - 3 * s, s, &label, [30, 30, 30, 255]);
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::par::*;
+
+/// How a layer's color combines with the stack beneath it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+}
+
+impl BlendMode {
+    #[inline]
+    fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            BlendMode::Normal => b,
+            BlendMode::Multiply => a * b,
+            BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+            BlendMode::Overlay => {
+                if a < 0.5 {
+                    2.0 * a * b
+                } else {
+                    1.0 - 2.0 * (1.0 - a) * (1.0 - b)
+                }
+            }
+        }
+    }
+}
+
+/// One named RGBA layer. The layer's own alpha channel is respected and
+/// scaled by `opacity`.
+pub struct CompositeLayer {
+    pub name: String,
+    pub rgba: Vec<u8>,
+    pub opacity: f32,
+    pub blend: BlendMode,
+    pub visible: bool,
+}
+
+/// Bottom-to-top stack of same-sized RGBA layers (base terrain, hillshade,
+/// rivers, boundaries, graticule, ...) flattened into one image.
+pub struct Compositor {
+    w: usize,
+    h: usize,
+    layers: Vec<CompositeLayer>,
+}
+
+impl Compositor {
+    pub fn new(w: usize, h: usize) -> Self {
+        Self { w, h, layers: Vec::new() }
+    }
+
+    /// Add a layer on top of the stack.
+    pub fn push(&mut self, name: &str, rgba: Vec<u8>, opacity: f32, blend: BlendMode) -> &mut Self {
+        assert_eq!(rgba.len(), self.w * self.h * 4, "layer '{}' size mismatch", name);
+        self.layers.push(CompositeLayer {
+            name: name.to_string(),
+            rgba,
+            opacity: opacity.clamp(0.0, 1.0),
+            blend,
+            visible: true,
+        });
+        self
+    }
+
+    pub fn layers(&self) -> &[CompositeLayer] {
+        &self.layers
+    }
+
+    pub fn layer_mut(&mut self, name: &str) -> Option<&mut CompositeLayer> {
+        self.layers.iter_mut().find(|l| l.name == name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<CompositeLayer> {
+        let i = self.layers.iter().position(|l| l.name == name)?;
+        Some(self.layers.remove(i))
+    }
+
+    /// Flatten visible layers bottom to top.
+    pub fn flatten(&self) -> Vec<u8> {
+        let mut out = vec![0u8; self.w * self.h * 4];
+        let visible: Vec<&CompositeLayer> = self.layers.iter().filter(|l| l.visible && l.opacity > 0.0).collect();
+
+        out.par_chunks_mut(4).enumerate().for_each(|(i, px)| {
+            let mut dst = [0.0f32; 3];
+            let mut dst_a = 0.0f32;
+            for layer in &visible {
+                let src = &layer.rgba[i * 4..i * 4 + 4];
+                let alpha = src[3] as f32 / 255.0 * layer.opacity;
+                if alpha <= 0.0 {
+                    continue;
+                }
+                for (c, d) in dst.iter_mut().enumerate() {
+                    let b = src[c] as f32 / 255.0;
+                    *d += (layer.blend.apply(*d, b) - *d) * alpha;
+                }
+                dst_a += alpha * (1.0 - dst_a);
+            }
+            for (c, d) in dst.iter().enumerate() {
+                px[c] = (d * 255.0).round() as u8;
+            }
+            px[3] = (dst_a * 255.0).round() as u8;
+        });
+
+        out
+    }
+}
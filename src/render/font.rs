@@ -0,0 +1,50 @@
+/// Fill an axis-aligned rectangle, clipped to the image.
+pub(super) fn fill_rect(rgba: &mut [u8], w: usize, x0: usize, y0: usize, bw: usize, bh: usize, c: [u8; 4]) {
+    for y in y0..y0 + bh {
+        for x in x0..(x0 + bw).min(w) {
+            let i = (y * w + x) * 4;
+            if i + 4 <= rgba.len() {
+                rgba[i..i + 4].copy_from_slice(&c);
+            }
+        }
+    }
+}
+
+/// 3x5 bitmap glyphs, one row per u8 (low 3 bits, MSB = left column).
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const MINUS: [u8; 5] = [0b000, 0b000, 0b111, 0b000, 0b000];
+
+/// Width of one glyph cell (3 px + 1 px spacing) at scale 1.
+pub(super) const GLYPH_ADVANCE: usize = 4;
+
+/// Draw digits and '-' at pixel scale `s`; other characters are skipped.
+pub(super) fn draw_text(rgba: &mut [u8], w: usize, x0: usize, y0: usize, s: usize, text: &str, c: [u8; 4]) {
+    for (k, ch) in text.chars().enumerate() {
+        let glyph = match ch {
+            '-' => &MINUS,
+            _ => match ch.to_digit(10) {
+                Some(d) => &DIGITS[d as usize],
+                None => continue,
+            },
+        };
+        let gx = x0 + k * GLYPH_ADVANCE * s;
+        for (r, bits) in glyph.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) != 0 {
+                    fill_rect(rgba, w, gx + col * s, y0 + r * s, s, s, c);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use super::{
+    BlendMode, Compositor, Palette, apply_ambient_occlusion, render_ambient_occlusion, render_aspect,
+    render_bathymetry, render_boundaries, render_boundary_overlay, render_cultures, render_distance, render_graticule,
+    render_heightmap, render_hillshade, render_map, render_nations, render_nautical, render_normal_map,
+    render_parchment, render_plates, render_population, render_precipitation, render_river_overlay,
+    render_rivers, render_slope, render_temperature,
+};
+use crate::Map;
+use crate::terrain;
+
+/// Every layer `render_layer` can produce from a generated `Map`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LayerKind {
+    Plates,
+    Boundaries,
+    Distance,
+    Heightmap,
+    Normals,
+    Slope,
+    Aspect,
+    AmbientOcclusion,
+    Hillshade,
+    Map,
+    Temperature,
+    Precipitation,
+    Rivers,
+    Population,
+    Nations,
+    Cultures,
+    Parchment,
+    Bathymetry,
+    Nautical,
+}
+
+impl LayerKind {
+    pub const ALL: [LayerKind; 19] = [
+        LayerKind::Plates,
+        LayerKind::Boundaries,
+        LayerKind::Distance,
+        LayerKind::Heightmap,
+        LayerKind::Normals,
+        LayerKind::Slope,
+        LayerKind::Aspect,
+        LayerKind::AmbientOcclusion,
+        LayerKind::Hillshade,
+        LayerKind::Map,
+        LayerKind::Temperature,
+        LayerKind::Precipitation,
+        LayerKind::Rivers,
+        LayerKind::Population,
+        LayerKind::Nations,
+        LayerKind::Cultures,
+        LayerKind::Parchment,
+        LayerKind::Bathymetry,
+        LayerKind::Nautical,
+    ];
+
+    /// Short id, used for file names and the server's layer names.
+    pub fn name(self) -> &'static str {
+        match self {
+            LayerKind::Plates => "plates",
+            LayerKind::Boundaries => "boundaries",
+            LayerKind::Distance => "distance",
+            LayerKind::Heightmap => "heightmap",
+            LayerKind::Normals => "normals",
+            LayerKind::Slope => "slope",
+            LayerKind::Aspect => "aspect",
+            LayerKind::AmbientOcclusion => "ao",
+            LayerKind::Hillshade => "hillshade",
+            LayerKind::Map => "map",
+            LayerKind::Temperature => "temperature",
+            LayerKind::Precipitation => "precipitation",
+            LayerKind::Rivers => "rivers",
+            LayerKind::Population => "population",
+            LayerKind::Nations => "nations",
+            LayerKind::Cultures => "cultures",
+            LayerKind::Parchment => "parchment",
+            LayerKind::Bathymetry => "bathymetry",
+            LayerKind::Nautical => "nautical",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    /// The map layer (see `Map::layer_view`) whose values this kind draws,
+    /// for exporting data instead of colors. None for views derived from
+    /// several layers or from slopes.
+    pub fn grid(self) -> Option<&'static str> {
+        match self {
+            LayerKind::Plates => Some("plate_id"),
+            LayerKind::Boundaries => Some("boundary_type"),
+            LayerKind::Distance => Some("boundary_dist"),
+            LayerKind::Heightmap => Some("height"),
+            LayerKind::Temperature => Some("temperature"),
+            LayerKind::Precipitation => Some("precipitation"),
+            LayerKind::Rivers => Some("river_flow"),
+            LayerKind::Population => Some("population"),
+            LayerKind::Nations => Some("nation_id"),
+            LayerKind::Cultures => Some("culture_id"),
+            LayerKind::Normals
+            | LayerKind::Slope
+            | LayerKind::Aspect
+            | LayerKind::AmbientOcclusion
+            | LayerKind::Hillshade
+            | LayerKind::Map
+            | LayerKind::Parchment
+            | LayerKind::Bathymetry
+            | LayerKind::Nautical => None,
+        }
+    }
+}
+
+/// Palette and overlays for `render_layer`.
+#[derive(Clone, Debug)]
+pub struct RenderStyle {
+    pub palette: Palette,
+    /// Ambient occlusion strength for the terrain layers (map, rivers); 0 = off.
+    pub ambient_occlusion: f32,
+    /// Hillshade multiplied over the layer; 0 = off.
+    pub hillshade: f32,
+    /// Draw river ribbons over the layer (the rivers layer always has them).
+    pub river_overlay: bool,
+    /// Draw classified plate boundaries as antialiased lines.
+    pub boundaries: bool,
+    /// Graticule spacing in degrees, if any.
+    pub graticule: Option<f32>,
+}
+
+impl Default for RenderStyle {
+    fn default() -> Self {
+        Self {
+            palette: Palette::default(),
+            ambient_occlusion: 0.5,
+            hillshade: 0.0,
+            river_overlay: false,
+            boundaries: false,
+            graticule: None,
+        }
+    }
+}
+
+/// Render one layer of an existing map with the given style. Layers that
+/// depend on later stages (rivers, population, nations, cultures) render
+/// from whatever those fields hold, so run the stages first.
+pub fn render_layer(map: &Map, kind: LayerKind, style: &RenderStyle) -> Vec<u8> {
+    let palette = &style.palette;
+    let ao = || terrain::compute_ambient_occlusion(&map.height, terrain::default_ao_radius(map.w));
+
+    let mut rgba = match kind {
+        LayerKind::Plates => render_plates(
+            &map.plate_id,
+            &map.boundary_type,
+            &map.boundary_major,
+            &map.macro_id,
+            map.num_macro,
+        ),
+        LayerKind::Boundaries => render_boundaries(&map.boundary_type, &map.boundary_major, &map.boundary_dist.decode()),
+        LayerKind::Distance => render_distance(&map.boundary_dist.decode()),
+        LayerKind::Heightmap => render_heightmap(&map.height),
+        LayerKind::Normals => render_normal_map(&map.height, 1.0),
+        LayerKind::Slope => render_slope(&terrain::compute_slope(&map.height)),
+        LayerKind::Aspect => {
+            let slope = terrain::compute_slope(&map.height);
+            render_aspect(&terrain::compute_aspect(&map.height), &slope)
+        }
+        LayerKind::AmbientOcclusion => render_ambient_occlusion(&ao()),
+        LayerKind::Hillshade => render_hillshade(&map.height),
+        LayerKind::Map => {
+            let mut rgba = render_map(&map.height, &map.ice, palette);
+            if style.ambient_occlusion > 0.0 {
+                apply_ambient_occlusion(&mut rgba, &ao(), style.ambient_occlusion);
+            }
+            rgba
+        }
+        LayerKind::Temperature => render_temperature(&map.temperature.decode(), palette),
+        LayerKind::Precipitation => render_precipitation(&map.precipitation.decode(), palette),
+        LayerKind::Rivers => {
+            let mut rgba =
+                render_rivers(&map.height, &map.river_flow, &map.precipitation.decode(), &map.temperature.decode(), palette);
+            if style.ambient_occlusion > 0.0 {
+                apply_ambient_occlusion(&mut rgba, &ao(), style.ambient_occlusion);
+            }
+            rgba
+        }
+        LayerKind::Population => render_population(&map.height, &map.population, palette),
+        LayerKind::Nations => render_nations(&map.height, &map.nation_id, &map.sites, palette),
+        LayerKind::Cultures => render_cultures(&map.height, &map.culture_id, palette),
+        LayerKind::Parchment => render_parchment(&map.height, &map.river_flow),
+        LayerKind::Bathymetry => render_bathymetry(&map.height),
+        LayerKind::Nautical => render_nautical(&map.height),
+    };
+
+    let river_overlay = style.river_overlay && kind != LayerKind::Rivers;
+    if style.hillshade > 0.0 || river_overlay || style.boundaries || style.graticule.is_some() {
+        let mut comp = Compositor::new(map.w, map.h);
+        comp.push(kind.name(), rgba, 1.0, BlendMode::Normal);
+        if style.hillshade > 0.0 {
+            comp.push("hillshade", render_hillshade(&map.height), style.hillshade, BlendMode::Multiply);
+        }
+        if river_overlay {
+            comp.push("rivers", render_river_overlay(&map.height, &map.river_flow, palette), 1.0, BlendMode::Normal);
+        }
+        if style.boundaries {
+            let overlay = render_boundary_overlay(&map.plate_id, &map.boundary_type, &map.boundary_major);
+            comp.push("boundaries", overlay, 1.0, BlendMode::Normal);
+        }
+        if let Some(spacing) = style.graticule {
+            comp.push("graticule", render_graticule(map.w, map.h, spacing), 0.5, BlendMode::Normal);
+        }
+        rgba = comp.flatten();
+    }
+
+    rgba
+}
@@ -0,0 +1,777 @@
+pub mod animation;
+pub mod bathymetry;
+pub mod compositor;
+mod font;
+pub mod layer;
+pub mod palette;
+pub mod parchment;
+pub mod profile;
+pub mod resample;
+pub mod tiles;
+
+use crate::grid::{Grid, Rect, neighbors4_wrap, wrap_xy};
+use crate::climate::{ICE_SHEET, ICE_SHELF, SEA_ICE};
+use crate::culture::NO_CULTURE;
+use crate::history::{NO_NATION, Site, SiteKind};
+use crate::par::*;
+use crate::plates::boundary::{CONVERGENT, DIVERGENT, TRANSFORM};
+use crate::noise::{Fbm, gradient_noise_periodic};
+use crate::rng::splitmix32;
+
+pub use bathymetry::{render_bathymetry, render_nautical};
+pub use compositor::{BlendMode, CompositeLayer, Compositor};
+pub use layer::{LayerKind, RenderStyle, render_layer};
+pub use palette::{Palette, PalettePreset, Ramp, lerp_color};
+pub use parchment::render_parchment;
+pub use profile::render_profile;
+
+const ICE_TEXTURE_SEED: u32 = 0x1CE_5EED;
+
+/// Render the final color map. Ice (from `climate::compute_ice`) is drawn
+/// over terrain with a faint crevasse texture on land ice and leads
+/// between sea-ice floes.
+pub fn render_map(height: &Grid<f32>, ice: &Grid<u8>, palette: &Palette) -> Vec<u8> {
+    render_map_window(height, ice, palette, Rect { x: 0, y: 0, w: height.w, h: height.h })
+}
+
+/// `render_map` over the cells of `rect` (columns wrap) only: RGBA of a
+/// window the size of `rect`, textured as in the full render.
+pub fn render_map_window(height: &Grid<f32>, ice: &Grid<u8>, palette: &Palette, rect: Rect) -> Vec<u8> {
+    let (w, h) = (height.w, height.h);
+    let (rw, rh) = (rect.w.min(w), rect.h.min(h.saturating_sub(rect.y)));
+    let mut rgba = vec![0u8; rw * rh * 4];
+    if rw == 0 {
+        return rgba;
+    }
+    let crevasse_noise = Fbm::new(3, 48.0, 2.0, 0.5).with_period(2.0, None);
+
+    rgba.par_chunks_mut(rw * 4)
+        .enumerate()
+        .for_each(|(wy, row)| {
+            let y = rect.y + wy;
+            for wx in 0..rw {
+                let x = (rect.x + wx) % w;
+                let elev = height.get(x, y);
+                let u = x as f32 / w as f32;
+                let v = y as f32 / h as f32;
+                let color = match ice.get(x, y) {
+                    ICE_SHEET => {
+                        let crevasse = crevasse_noise.ridged(u * 2.0, v, ICE_TEXTURE_SEED);
+                        lerp_color(palette.ice_sheet, [120, 140, 160, 255], ((crevasse - 0.85) * 1.5).max(0.0))
+                    }
+                    ICE_SHELF => palette.ice_shelf,
+                    SEA_ICE => {
+                        // Open-water leads along the noise zero-crossings
+                        let lead = gradient_noise_periodic(u * 60.0, v * 30.0, ICE_TEXTURE_SEED ^ 1, 60, None).abs();
+                        let water = palette.water.sample(-elev);
+                        lerp_color(palette.sea_ice, water, (1.0 - lead / 0.06).max(0.0) * 0.6)
+                    }
+                    _ if elev <= 0.0 => palette.water.sample(-elev),
+                    _ => palette.land.sample(elev),
+                };
+
+                let out = &mut row[wx * 4..wx * 4 + 4];
+                out.copy_from_slice(&color);
+            }
+        });
+
+    rgba
+}
+
+/// Diagnostic: render plates colored by macroplate, boundaries distinguished.
+/// Major boundaries (between macroplates) = bright white.
+/// Minor boundaries (within macroplate) = dim gray.
+pub fn render_plates(
+    plate_id: &Grid<u16>,
+    btype: &Grid<u8>,
+    major: &Grid<u8>,
+    macro_id: &[usize],
+    num_macro: usize,
+) -> Vec<u8> {
+    let w = plate_id.w;
+    let h = plate_id.h;
+
+    // Generate a distinct color per macroplate
+    let colors: Vec<[u8; 4]> = (0..num_macro)
+        .map(|i| {
+            let h = splitmix32(i as u32 * 7 + 123);
+            [
+                (h & 0xFF) as u8 | 60,
+                ((h >> 8) & 0xFF) as u8 | 60,
+                ((h >> 16) & 0xFF) as u8 | 60,
+                255,
+            ]
+        })
+        .collect();
+
+    let mut rgba = vec![0u8; w * h * 4];
+    for y in 0..h {
+        for x in 0..w {
+            let i = y * w + x;
+            let color = if btype.data[i] != 0 {
+                if major.data[i] != 0 {
+                    [255, 255, 255, 255] // major boundary = bright white
+                } else {
+                    [140, 140, 140, 255] // minor boundary = dim gray
+                }
+            } else {
+                let pid = plate_id.data[i] as usize;
+                if pid < macro_id.len() {
+                    let mid = macro_id[pid];
+                    if mid < colors.len() {
+                        // Slight shade variation per microplate within macroplate
+                        let shade = splitmix32(pid as u32 * 13 + 7);
+                        let offset = ((shade & 0x1F) as i16 - 16) as i32;
+                        [
+                            (colors[mid][0] as i32 + offset).clamp(0, 255) as u8,
+                            (colors[mid][1] as i32 + offset).clamp(0, 255) as u8,
+                            (colors[mid][2] as i32 + offset).clamp(0, 255) as u8,
+                            255,
+                        ]
+                    } else {
+                        [128, 128, 128, 255]
+                    }
+                } else {
+                    [128, 128, 128, 255]
+                }
+            };
+            rgba[i * 4..i * 4 + 4].copy_from_slice(&color);
+        }
+    }
+    rgba
+}
+
+/// Diagnostic: boundary types as colors.
+/// Major boundaries = bright, minor = dim. Plate interiors carry faint
+/// distance isolines so the distance field can be checked against the
+/// boundaries it was built from.
+pub fn render_boundaries(btype: &Grid<u8>, major: &Grid<u8>, dist: &Grid<f32>) -> Vec<u8> {
+    let w = btype.w;
+    let h = btype.h;
+    let period = distance_isoline_period(w);
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let i = y * w + x;
+            let is_major = major.data[i] != 0;
+            let color = match btype.data[i] {
+                CONVERGENT => if is_major { [220, 50, 50, 255] } else { [120, 40, 40, 255] },
+                DIVERGENT => if is_major { [50, 80, 220, 255] } else { [40, 50, 120, 255] },
+                TRANSFORM => if is_major { [50, 200, 80, 255] } else { [40, 100, 50, 255] },
+                _ => match distance_isoline(dist, x, y, period) {
+                    Some(true) => [80, 80, 80, 255],
+                    Some(false) => [50, 50, 50, 255],
+                    None => [20, 20, 20, 255],
+                },
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&color);
+        }
+    });
+    rgba
+}
+
+/// Plate boundaries as antialiased lines along the cell edges where plate
+/// ids change, on a transparent background: convergent red, divergent
+/// blue, transform green. Major (macroplate) boundaries are thicker and
+/// opaque; minor ones thin and translucent.
+pub fn render_boundary_overlay(plate_id: &Grid<u16>, btype: &Grid<u8>, major: &Grid<u8>) -> Vec<u8> {
+    let w = plate_id.w;
+    let h = plate_id.h;
+    let scale = (w as f32 / 2048.0).max(1.0);
+    let (r_major, r_minor) = (1.1 * scale, 0.5 * scale);
+    let reach = (r_major + 1.0).ceil() as i32;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    // Strongest edge style touching cells a and b
+    let edge_style = |a: usize, b: usize| -> Option<([u8; 3], f32, f32)> {
+        let t = btype.data[a].max(btype.data[b]);
+        let color = match t {
+            CONVERGENT => [230, 50, 40],
+            DIVERGENT => [40, 90, 235],
+            TRANSFORM => [40, 200, 80],
+            _ => return None,
+        };
+        if major.data[a] != 0 || major.data[b] != 0 {
+            Some((color, r_major, 1.0))
+        } else {
+            Some((color, r_minor, 0.5))
+        }
+    };
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(py, row)| {
+        for (px, out) in row.chunks_exact_mut(4).enumerate() {
+            let (cx, cy) = (px as f32 + 0.5, py as f32 + 0.5);
+            let mut best: Option<([u8; 3], f32)> = None;
+            for dy in -reach..=reach {
+                let y = py as i32 + dy;
+                if y < 0 || y >= h as i32 {
+                    continue;
+                }
+                for dx in -reach..=reach {
+                    let xu = (px as i32 + dx).rem_euclid(w as i32) as usize;
+                    let yu = y as usize;
+                    let a = yu * w + xu;
+                    let ex = (px as i32 + dx) as f32;
+                    let ey = y as f32;
+                    // East edge of cell: segment x = ex + 1, y in [ey, ey + 1]
+                    let b = yu * w + (xu + 1) % w;
+                    if plate_id.data[a] != plate_id.data[b]
+                        && let Some((color, r, alpha)) = edge_style(a, b)
+                    {
+                        let d = ((cx - (ex + 1.0)).powi(2) + (cy - cy.clamp(ey, ey + 1.0)).powi(2)).sqrt();
+                        let cov = (r - d + 0.5).clamp(0.0, 1.0) * alpha;
+                        if best.is_none_or(|b| cov > b.1) {
+                            best = Some((color, cov));
+                        }
+                    }
+                    // South edge of cell: segment y = ey + 1, x in [ex, ex + 1]
+                    if yu + 1 < h {
+                        let b = (yu + 1) * w + xu;
+                        if plate_id.data[a] != plate_id.data[b]
+                            && let Some((color, r, alpha)) = edge_style(a, b)
+                        {
+                            let d = ((cy - (ey + 1.0)).powi(2) + (cx - cx.clamp(ex, ex + 1.0)).powi(2)).sqrt();
+                            let cov = (r - d + 0.5).clamp(0.0, 1.0) * alpha;
+                            if best.is_none_or(|b| cov > b.1) {
+                                best = Some((color, cov));
+                            }
+                        }
+                    }
+                }
+            }
+            if let Some((color, cov)) = best {
+                out.copy_from_slice(&[color[0], color[1], color[2], (cov * 255.0).round() as u8]);
+            }
+        }
+    });
+
+    rgba
+}
+
+/// Diagnostic: distance field as directional relief. Hue is the direction
+/// to the nearest boundary (from the field's gradient), brightness is
+/// banded by distance with an isoline every `distance_isoline_period`
+/// cells (every fifth one stronger), and the field is lit from the NW as
+/// if it were terrain. Seams and speckles in the JFA output show up as
+/// hue breaks away from the ridge lines between boundaries.
+pub fn render_distance(dist: &Grid<f32>) -> Vec<u8> {
+    let w = dist.w;
+    let h = dist.h;
+    let period = distance_isoline_period(w);
+    let (lx, ly) = (-std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2);
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let d = dist.get(x, y);
+            if d <= 0.0 || !d.is_finite() {
+                row[x * 4..x * 4 + 4].copy_from_slice(&[255, 255, 255, 255]);
+                continue;
+            }
+
+            // Gradient (+x east, +y north); the boundary lies downhill
+            let at = |dx: i32, dy: i32| {
+                let nx = (x as i32 + dx).rem_euclid(w as i32) as usize;
+                let ny = (y as i32 + dy).clamp(0, h as i32 - 1) as usize;
+                let v = dist.get(nx, ny);
+                if v.is_finite() { v } else { d }
+            };
+            let gx = (at(1, 0) - at(-1, 0)) * 0.5;
+            let gy = (at(0, -1) - at(0, 1)) * 0.5;
+            let hue = (-gy).atan2(-gx) / std::f32::consts::TAU;
+            let base = lerp_color(hue_color(hue), [255, 255, 255, 255], 0.35);
+
+            let band = 0.8 + 0.2 * (d / period).fract();
+            let light = 0.75 + 0.25 * (gx * lx + gy * ly).clamp(-1.0, 1.0);
+            let line = match distance_isoline(dist, x, y, period) {
+                Some(true) => 0.35,
+                Some(false) => 0.7,
+                None => 1.0,
+            };
+            let k = band * light * line;
+            let c = [
+                (base[0] as f32 * k) as u8,
+                (base[1] as f32 * k) as u8,
+                (base[2] as f32 * k) as u8,
+                255,
+            ];
+            row[x * 4..x * 4 + 4].copy_from_slice(&c);
+        }
+    });
+    rgba
+}
+
+/// Isoline spacing (cells) for the distance diagnostics: 8 at 2048 wide.
+fn distance_isoline_period(w: usize) -> f32 {
+    (8.0 * w as f32 / 2048.0).max(2.0)
+}
+
+/// Whether (x, y) sits on the low side of a distance isoline: Some(true)
+/// for every fifth line, Some(false) for the others, None off-line.
+fn distance_isoline(dist: &Grid<f32>, x: usize, y: usize, period: f32) -> Option<bool> {
+    let d = dist.get(x, y);
+    if !d.is_finite() {
+        return None;
+    }
+    let band = (d / period).floor();
+    let crossing = neighbors4_wrap(x, y, dist.w, dist.h)
+        .map(|(nx, ny)| dist.get(nx, ny))
+        .filter(|v| v.is_finite() && (v / period).floor() > band)
+        .map(|v| (v / period).floor() as i64)
+        .max()?;
+    Some(crossing % 5 == 0)
+}
+
+/// Diagnostic: grayscale heightmap.
+pub fn render_heightmap(height: &Grid<f32>) -> Vec<u8> {
+    let s = height.stats();
+    let gray = Ramp::new(&[(0.0, [0, 0, 0, 255]), (1.0, [255, 255, 255, 255])]);
+    render_scalar(height, &gray, s.min, s.max.max(s.min + 1.0))
+}
+
+/// Tangent-space normal map (OpenGL convention: +X east, +Y north, +Z up),
+/// encoded as RGB = normal * 0.5 + 0.5. Uses true meters-per-cell spacing;
+/// `exaggeration` scales vertical relief (1.0 = physically scaled).
+pub fn render_normal_map(height: &Grid<f32>, exaggeration: f32) -> Vec<u8> {
+    let normals = crate::terrain::compute_normals(height, exaggeration);
+    let mut rgba = vec![0u8; normals.data.len() * 4];
+    rgba.par_chunks_mut(4).zip(normals.data.par_iter()).for_each(|(px, n)| {
+        px[0] = ((n[0] * 0.5 + 0.5) * 255.0).round() as u8;
+        px[1] = ((n[1] * 0.5 + 0.5) * 255.0).round() as u8;
+        px[2] = ((n[2] * 0.5 + 0.5) * 255.0).round() as u8;
+        px[3] = 255;
+    });
+    rgba
+}
+
+/// Diagnostic: ambient occlusion as grayscale (white = open, black = enclosed).
+pub fn render_ambient_occlusion(ao: &Grid<f32>) -> Vec<u8> {
+    let mut rgba = vec![0u8; ao.data.len() * 4];
+    rgba.par_chunks_mut(4).zip(ao.data.par_iter()).for_each(|(px, &a)| {
+        let v = (a.clamp(0.0, 1.0) * 255.0).round() as u8;
+        px.copy_from_slice(&[v, v, v, 255]);
+    });
+    rgba
+}
+
+/// Darken an existing render by ambient occlusion. `strength` 0 leaves it
+/// unchanged; 1 takes fully enclosed cells to black.
+pub fn apply_ambient_occlusion(rgba: &mut [u8], ao: &Grid<f32>, strength: f32) {
+    rgba.par_chunks_mut(4).zip(ao.data.par_iter()).for_each(|(px, &a)| {
+        let f = 1.0 - strength * (1.0 - a.clamp(0.0, 1.0));
+        for c in &mut px[..3] {
+            *c = (*c as f32 * f).round() as u8;
+        }
+    });
+}
+
+/// 99th-percentile slope, used to normalize slope renders (the poles'
+/// shrinking E-W cell size produces a few extreme outliers).
+fn slope_norm(slope: &Grid<f32>) -> f32 {
+    slope.percentile(0.99).map_or(1.0, |p| p.max(1e-6))
+}
+
+/// Diagnostic: slope magnitude (degrees), square-root scaled to the 99th
+/// percentile so gentle coarse-resolution slopes stay visible.
+pub fn render_slope(slope: &Grid<f32>) -> Vec<u8> {
+    let max_s = slope_norm(slope);
+    let mut rgba = vec![0u8; slope.data.len() * 4];
+    rgba.par_chunks_mut(4).zip(slope.data.par_iter()).for_each(|(px, &s)| {
+        let t = (s / max_s).sqrt().min(1.0);
+        let c = lerp_color([250, 250, 245, 255], [120, 20, 20, 255], t);
+        px.copy_from_slice(&c);
+    });
+    rgba
+}
+
+/// Diagnostic: aspect as a hue wheel (N red, E yellow-green, S cyan, W purple),
+/// desaturated toward gray on gentle slopes.
+pub fn render_aspect(aspect: &Grid<f32>, slope: &Grid<f32>) -> Vec<u8> {
+    let max_s = slope_norm(slope);
+    let mut rgba = vec![0u8; aspect.data.len() * 4];
+    rgba.par_chunks_mut(4)
+        .zip(aspect.data.par_iter().zip(slope.data.par_iter()))
+        .for_each(|(px, (&a, &s))| {
+            let c = if a < 0.0 {
+                [128, 128, 128, 255]
+            } else {
+                let hue = hue_color(a / 360.0);
+                lerp_color([128, 128, 128, 255], hue, (s / max_s).sqrt().min(1.0))
+            };
+            px.copy_from_slice(&c);
+        });
+    rgba
+}
+
+/// Fully saturated hue in [0, 1).
+#[inline]
+fn hue_color(hue: f32) -> [u8; 4] {
+    let h6 = hue.rem_euclid(1.0) * 6.0;
+    let f = h6 - h6.floor();
+    let (r, g, b) = match h6 as u32 {
+        0 => (1.0, f, 0.0),
+        1 => (1.0 - f, 1.0, 0.0),
+        2 => (0.0, 1.0, f),
+        3 => (0.0, 1.0 - f, 1.0),
+        4 => (f, 0.0, 1.0),
+        _ => (1.0, 0.0, 1.0 - f),
+    };
+    [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8, 255]
+}
+
+/// Heat map of any scalar grid: `min..max` is stretched over the ramp's
+/// stop range, so a ramp can be reused for data in other units. Pass the
+/// ramp's own `domain()` to sample it in its native units.
+pub fn render_scalar<T>(grid: &Grid<T>, ramp: &Ramp, min: f32, max: f32) -> Vec<u8>
+where
+    T: Copy + Default + Into<f32> + Sync,
+{
+    let w = grid.w;
+    let h = grid.h;
+    let color = scalar_color(ramp, min, max);
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            row[x * 4..x * 4 + 4].copy_from_slice(&color(grid.get(x, y).into()));
+        }
+    });
+
+    rgba
+}
+
+/// Value-to-color mapping of `render_scalar`.
+fn scalar_color(ramp: &Ramp, min: f32, max: f32) -> impl Fn(f32) -> [u8; 4] + Sync + '_ {
+    let (lo, hi) = ramp.domain();
+    let range = max - min;
+    let scale = if range.abs() > f32::EPSILON { (hi - lo) / range } else { 0.0 };
+    move |v| ramp.sample(lo + (v - min) * scale)
+}
+
+/// Signed difference grid (e.g. `diff::LayerDiff::delta`): blue below
+/// zero, white at zero, red above, symmetric around zero and scaled to
+/// the largest magnitude.
+pub fn render_diff(delta: &Grid<f32>) -> Vec<u8> {
+    // Floor keeps an unchanged grid white rather than collapsing the ramp
+    let m = delta.data.iter().filter(|v| v.is_finite()).fold(1e-6f32, |m, v| m.max(v.abs()));
+    let ramp = Ramp::new(&[(-1.0, [40, 70, 200, 255]), (0.0, [255, 255, 255, 255]), (1.0, [200, 40, 40, 255])]);
+    render_scalar(delta, &ramp, -m, m)
+}
+
+/// Render temperature map (Celsius).
+pub fn render_temperature(temp: &Grid<f32>, palette: &Palette) -> Vec<u8> {
+    let (lo, hi) = palette.temperature.domain();
+    render_scalar(temp, &palette.temperature, lo, hi)
+}
+
+/// Render precipitation map (mm/year).
+pub fn render_precipitation(precip: &Grid<f32>, palette: &Palette) -> Vec<u8> {
+    let (lo, hi) = palette.precipitation.domain();
+    render_scalar(precip, &palette.precipitation, lo, hi)
+}
+
+/// Light muted terrain (high contrast against dark overlays), with dry
+/// zones tinted: orange for hot deserts, white for cold ones.
+pub fn render_muted_base(
+    height: &Grid<f32>,
+    precipitation: &Grid<f32>,
+    temperature: &Grid<f32>,
+    palette: &Palette,
+) -> Vec<u8> {
+    let w = height.w;
+    let h = height.h;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let elev = height.get(x, y);
+            let precip = precipitation.get(x, y);
+            let temp = temperature.get(x, y);
+
+            let base = if elev <= 0.0 {
+                palette.muted_water
+            } else {
+                let terrain = palette.muted_land.sample(elev);
+                if precip < 400.0 {
+                    let dry_t = 1.0 - (precip / 400.0).clamp(0.0, 1.0);
+                    // Blend between cold (white) and hot (orange) based on temperature
+                    let warm_t = ((temp - 0.0) / 20.0).clamp(0.0, 1.0); // 0=cold, 1=warm
+                    let dry_color = lerp_color(palette.dry_cold, palette.dry_hot, warm_t);
+                    lerp_color(terrain, dry_color, dry_t * 0.8)
+                } else {
+                    terrain
+                }
+            };
+
+            row[x * 4..x * 4 + 4].copy_from_slice(&base);
+        }
+    });
+
+    rgba
+}
+
+/// Rivers alone on a transparent background, for compositing.
+pub fn render_river_overlay(height: &Grid<f32>, river_flow: &Grid<f32>, palette: &Palette) -> Vec<u8> {
+    let w = height.w;
+    let h = height.h;
+    let max_flow = river_flow.data.iter().cloned().fold(0.0f32, f32::max).max(1.0);
+    let network = crate::rivers::extract_network(height, river_flow);
+    let coverage = river_coverage(&network, w, h, max_flow.ln());
+
+    let mut rgba = vec![0u8; w * h * 4];
+    rgba.par_chunks_mut(4).zip(coverage.par_iter()).for_each(|(px, &a)| {
+        px[..3].copy_from_slice(&palette.river[..3]);
+        px[3] = (a * 255.0).round() as u8;
+    });
+    rgba
+}
+
+/// Render rivers overlaid on muted terrain, with dry zones highlighted.
+pub fn render_rivers(
+    height: &Grid<f32>,
+    river_flow: &Grid<f32>,
+    precipitation: &Grid<f32>,
+    temperature: &Grid<f32>,
+    palette: &Palette,
+) -> Vec<u8> {
+    let mut comp = Compositor::new(height.w, height.h);
+    comp.push("base", render_muted_base(height, precipitation, temperature, palette), 1.0, BlendMode::Normal)
+        .push("rivers", render_river_overlay(height, river_flow, palette), 1.0, BlendMode::Normal);
+    comp.flatten()
+}
+
+/// Grayscale hillshade lit from the northwest at 45 degrees, for Multiply
+/// or Overlay compositing. Slopes are normalized like `render_slope` so
+/// relief reads at any resolution.
+pub fn render_hillshade(height: &Grid<f32>) -> Vec<u8> {
+    let slope = crate::terrain::compute_slope(height);
+    let aspect = crate::terrain::compute_aspect(height);
+    let max_s = slope_norm(&slope);
+    let (sun_az, sun_zen) = (315.0f32.to_radians(), 45.0f32.to_radians());
+
+    let mut rgba = vec![0u8; slope.data.len() * 4];
+    rgba.par_chunks_mut(4).enumerate().for_each(|(i, px)| {
+        let s = ((slope.data[i] / max_s).sqrt().min(1.0) * 60.0).to_radians();
+        let a = aspect.data[i].max(0.0).to_radians();
+        let shade = sun_zen.cos() * s.cos() + sun_zen.sin() * s.sin() * (sun_az - a).cos();
+        let v = (shade.clamp(0.0, 1.0) * 255.0).round() as u8;
+        px.copy_from_slice(&[v, v, v, 255]);
+    });
+    rgba
+}
+
+/// Latitude/longitude lines every `spacing_deg` degrees on a transparent
+/// background, with the equator and prime meridian emphasized.
+pub fn render_graticule(w: usize, h: usize, spacing_deg: f32) -> Vec<u8> {
+    let mut rgba = vec![0u8; w * h * 4];
+    let deg_per_px_x = 360.0 / w as f32;
+    let deg_per_px_y = 180.0 / h as f32;
+
+    // A line is drawn in the one pixel whose [lo, hi) span contains it
+    let crosses = |lo: f32, hi: f32, step: f32| (lo / step).ceil() * step < hi;
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        let lat_lo = 90.0 - (y + 1) as f32 * deg_per_px_y;
+        let lat_hi = lat_lo + deg_per_px_y;
+        for x in 0..w {
+            let lon_lo = -180.0 + x as f32 * deg_per_px_x;
+            let lon_hi = lon_lo + deg_per_px_x;
+            // Equator and prime meridian
+            let major = crosses(lat_lo, lat_hi, 180.0) || crosses(lon_lo, lon_hi, 360.0);
+            let alpha = if major {
+                200
+            } else if crosses(lat_lo, lat_hi, spacing_deg) || crosses(lon_lo, lon_hi, spacing_deg) {
+                110
+            } else {
+                0
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&[255, 255, 255, alpha]);
+        }
+    });
+    rgba
+}
+
+/// Rasterize the river network as antialiased ribbons. Returns per-pixel
+/// river opacity. Radius grows with discharge (headwaters are sub-pixel
+/// threads, trunks a few pixels wide) and segments into the sea fade out
+/// over their last step so mouths taper into the coast.
+fn river_coverage(network: &[crate::rivers::RiverSegment], w: usize, h: usize, log_max: f32) -> Vec<f32> {
+    let mut cov = vec![0.0f32; w * h];
+    let style = |flow: f32| {
+        let t = (flow.max(1.0).ln() / log_max).clamp(0.0, 1.0);
+        (0.3 + 1.4 * t * t, 0.7 + 0.3 * t)
+    };
+
+    for seg in network {
+        let last = seg.points.len().saturating_sub(1);
+        for i in 0..last {
+            let (x0, y0) = seg.points[i];
+            let (x1, y1) = seg.points[i + 1];
+            let (r0, a0) = style(seg.flow[i]);
+            let (r1, mut a1) = style(seg.flow[i + 1]);
+            if seg.mouth && i + 1 == last {
+                a1 = 0.0;
+            }
+
+            let (dx, dy) = (x1 - x0, y1 - y0);
+            let len2 = (dx * dx + dy * dy).max(1e-6);
+            let pad = r0.max(r1) + 1.0;
+            let (px0, px1) = ((x0.min(x1) - pad).floor() as i32, (x0.max(x1) + pad).ceil() as i32);
+            let (py0, py1) = ((y0.min(y1) - pad).floor() as i32, (y0.max(y1) + pad).ceil() as i32);
+
+            for py in py0.max(0)..=py1.min(h as i32 - 1) {
+                for px in px0..=px1 {
+                    let (cx, cy) = (px as f32 + 0.5, py as f32 + 0.5);
+                    let t = (((cx - x0) * dx + (cy - y0) * dy) / len2).clamp(0.0, 1.0);
+                    let (qx, qy) = (x0 + dx * t - cx, y0 + dy * t - cy);
+                    let dist = (qx * qx + qy * qy).sqrt();
+                    let r = r0 + (r1 - r0) * t;
+                    let a = (r - dist + 0.5).clamp(0.0, 1.0) * (a0 + (a1 - a0) * t);
+                    if a > 0.0 {
+                        let idx = py as usize * w + px.rem_euclid(w as i32) as usize;
+                        cov[idx] = cov[idx].max(a);
+                    }
+                }
+            }
+        }
+    }
+    cov
+}
+
+/// Render population density on a log scale, ocean shown as flat water.
+pub fn render_population(height: &Grid<f32>, population: &Grid<f32>, palette: &Palette) -> Vec<u8> {
+    let w = population.w;
+    let h = population.h;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    let max_pop = population.data.iter().cloned().fold(0.0f32, f32::max);
+    let log_max = (1.0 + max_pop).ln().max(1e-6);
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let color = if height.get(x, y) <= 0.0 {
+                palette.muted_water
+            } else {
+                palette.population.sample((1.0 + population.get(x, y)).ln() / log_max)
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&color);
+        }
+    });
+
+    rgba
+}
+
+const SITE_CAPITAL: [u8; 4] = [255, 255, 255, 255];
+const SITE_RUIN: [u8; 4] = [40, 30, 30, 255];
+
+/// Render nations as flat colors over land, with darkened borders and site markers.
+pub fn render_nations(
+    height: &Grid<f32>,
+    nation_id: &Grid<u16>,
+    sites: &[Site],
+    palette: &Palette,
+) -> Vec<u8> {
+    let w = nation_id.w;
+    let h = nation_id.h;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let n = nation_id.get(x, y);
+            let color = if height.get(x, y) <= 0.0 {
+                palette.muted_water
+            } else if n == NO_NATION {
+                palette.muted_land.sample(500.0)
+            } else {
+                let hc = splitmix32(n as u32 * 31 + 5);
+                let base = [
+                    ((hc & 0xFF) as u8 >> 1) + 100,
+                    (((hc >> 8) & 0xFF) as u8 >> 1) + 100,
+                    (((hc >> 16) & 0xFF) as u8 >> 1) + 100,
+                    255,
+                ];
+                let on_border = neighbors4_wrap(x, y, w, h).any(|(nx, ny)| {
+                    let m = nation_id.get(nx, ny);
+                    m != n && m != NO_NATION
+                });
+                if on_border { lerp_color(base, [0, 0, 0, 255], 0.6) } else { base }
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&color);
+        }
+    });
+
+    // Site markers: 3x3 dots
+    let radius = (w / 1024).max(1) as i32;
+    for site in sites {
+        let color = match site.kind {
+            SiteKind::Capital => SITE_CAPITAL,
+            SiteKind::Ruin => SITE_RUIN,
+        };
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if let Some((x, y)) = wrap_xy(site.x as i32 + dx, site.y as i32 + dy, w, h) {
+                    let i = (y * w + x) * 4;
+                    rgba[i..i + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    rgba
+}
+
+/// Render culture areas as soft pastel regions over shaded land.
+pub fn render_cultures(height: &Grid<f32>, culture_id: &Grid<u16>, palette: &Palette) -> Vec<u8> {
+    let w = culture_id.w;
+    let h = culture_id.h;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let c = culture_id.get(x, y);
+            let elev = height.get(x, y);
+            let color = if elev <= 0.0 {
+                palette.muted_water
+            } else if c == NO_CULTURE {
+                palette.muted_land.sample(500.0)
+            } else {
+                let hc = splitmix32(c as u32 * 17 + 91);
+                let base = [
+                    ((hc & 0xFF) as u8 >> 2) + 150,
+                    (((hc >> 8) & 0xFF) as u8 >> 2) + 150,
+                    (((hc >> 16) & 0xFF) as u8 >> 2) + 150,
+                    255,
+                ];
+                // Darken with altitude so terrain barriers stay visible
+                lerp_color(base, [60, 50, 40, 255], (elev / 5000.0).min(0.6))
+            };
+            row[x * 4..x * 4 + 4].copy_from_slice(&color);
+        }
+    });
+
+    rgba
+}
+
+/// Bilinear sample of an RGBA image at fractional pixel coordinates,
+/// with E-W wrapping and N-S clamping.
+#[inline]
+fn sample_rgba(rgba: &[u8], w: usize, h: usize, fx: f32, fy: f32) -> [u8; 4] {
+    let x0f = fx.floor();
+    let y0f = fy.floor();
+    let tx = fx - x0f;
+    let ty = fy - y0f;
+    let x0 = (x0f as i64).rem_euclid(w as i64) as usize;
+    let x1 = (x0 + 1) % w;
+    let y0 = (y0f as i64).clamp(0, h as i64 - 1) as usize;
+    let y1 = (y0f as i64 + 1).clamp(0, h as i64 - 1) as usize;
+
+    let px = |x: usize, y: usize, c: usize| rgba[(y * w + x) * 4 + c] as f32;
+    let mut out = [0u8; 4];
+    for (c, o) in out.iter_mut().enumerate() {
+        let top = px(x0, y0, c) + (px(x1, y0, c) - px(x0, y0, c)) * tx;
+        let bot = px(x0, y1, c) + (px(x1, y1, c) - px(x0, y1, c)) * tx;
+        *o = (top + (bot - top) * ty).round() as u8;
+    }
+    out
+}
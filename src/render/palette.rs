@@ -0,0 +1,278 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+#[inline]
+pub fn lerp_color(a: [u8; 4], b: [u8; 4], t: f32) -> [u8; 4] {
+    let t = t.clamp(0.0, 1.0);
+    [
+        (a[0] as f32 + (b[0] as f32 - a[0] as f32) * t).round() as u8,
+        (a[1] as f32 + (b[1] as f32 - a[1] as f32) * t).round() as u8,
+        (a[2] as f32 + (b[2] as f32 - a[2] as f32) * t).round() as u8,
+        255,
+    ]
+}
+
+/// Piecewise-linear color ramp. Stops are (value, rgba) sorted by value.
+/// Values outside the stop range clamp to the end colors. Two stops at the
+/// same value make a hard color step.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ramp {
+    pub stops: Vec<(f32, [u8; 4])>,
+}
+
+impl Ramp {
+    pub fn new(stops: &[(f32, [u8; 4])]) -> Self {
+        Self { stops: stops.to_vec() }
+    }
+
+    #[inline]
+    pub fn sample(&self, v: f32) -> [u8; 4] {
+        let Some(first) = self.stops.first() else {
+            return [0, 0, 0, 255];
+        };
+        if v < first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let (v0, c0) = pair[0];
+            let (v1, c1) = pair[1];
+            if v < v1 {
+                return lerp_color(c0, c1, (v - v0) / (v1 - v0));
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+
+    /// Values of the first and last stops.
+    pub fn domain(&self) -> (f32, f32) {
+        match (self.stops.first(), self.stops.last()) {
+            (Some(a), Some(b)) => (a.0, b.0),
+            _ => (0.0, 1.0),
+        }
+    }
+}
+
+/// Named built-in palettes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PalettePreset {
+    Atlas,
+    Satellite,
+    Grayscale,
+    Colorblind,
+}
+
+impl PalettePreset {
+    pub const ALL: [PalettePreset; 4] = [
+        PalettePreset::Atlas,
+        PalettePreset::Satellite,
+        PalettePreset::Grayscale,
+        PalettePreset::Colorblind,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PalettePreset::Atlas => "atlas",
+            PalettePreset::Satellite => "satellite",
+            PalettePreset::Grayscale => "grayscale",
+            PalettePreset::Colorblind => "colorblind",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+/// All colors used by the renderers.
+///
+/// - `water` is indexed by depth below sea level (meters, positive)
+/// - `land` by elevation above sea level (meters)
+/// - `temperature` by Celsius, `precipitation` by mm/year
+/// - `population` by normalized log density in [0, 1]
+/// - `muted_land` is the light relief base used under rivers and political layers
+///
+/// Fields missing from a palette file fall back to the atlas colors.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub water: Ramp,
+    pub land: Ramp,
+    pub temperature: Ramp,
+    pub precipitation: Ramp,
+    pub population: Ramp,
+    pub muted_land: Ramp,
+    pub muted_water: [u8; 4],
+    pub river: [u8; 4],
+    pub dry_hot: [u8; 4],
+    pub dry_cold: [u8; 4],
+    pub ice_sheet: [u8; 4],
+    pub ice_shelf: [u8; 4],
+    pub sea_ice: [u8; 4],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::atlas()
+    }
+}
+
+impl Palette {
+    pub fn preset(preset: PalettePreset) -> Self {
+        match preset {
+            PalettePreset::Atlas => Self::atlas(),
+            PalettePreset::Satellite => Self::satellite(),
+            PalettePreset::Grayscale => Self::grayscale(),
+            PalettePreset::Colorblind => Self::colorblind(),
+        }
+    }
+
+    /// Load a palette from a JSON file (same shape as the serialized struct).
+    pub fn from_file(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Classic atlas hypsometric tints (the original worldgen look).
+    pub fn atlas() -> Self {
+        Self {
+            water: Ramp::new(&[
+                (0.0, [52, 100, 145, 255]),
+                (750.0, [38, 78, 120, 255]),
+                (2500.0, [32, 55, 92, 255]),
+                (5000.0, [18, 36, 70, 255]),
+            ]),
+            land: Ramp::new(&[
+                (0.0, [210, 200, 160, 255]), // beach
+                (5.0, [210, 200, 160, 255]),
+                (5.0, [70, 130, 62, 255]),
+                (500.0, [140, 180, 100, 255]),
+                (1500.0, [190, 170, 120, 255]),
+                (1500.0, [140, 120, 100, 255]),
+                (3000.0, [165, 152, 140, 255]),
+                (6000.0, [200, 196, 192, 255]),
+            ]),
+            temperature: Ramp::new(&[
+                (-30.0, [220, 230, 255, 255]), // white-blue
+                (0.0, [80, 180, 220, 255]),    // cyan
+                (15.0, [60, 160, 80, 255]),    // green
+                (25.0, [220, 200, 60, 255]),   // yellow
+                (35.0, [200, 50, 30, 255]),    // red
+            ]),
+            precipitation: Ramp::new(&[
+                (0.0, [200, 180, 130, 255]),    // tan/desert
+                (250.0, [210, 200, 80, 255]),   // yellow
+                (1000.0, [60, 160, 70, 255]),   // green
+                (2500.0, [50, 100, 200, 255]),  // blue
+                (4000.0, [20, 40, 120, 255]),   // dark blue
+            ]),
+            population: Ramp::new(&[
+                (0.0, [235, 230, 215, 255]),
+                (0.4, [240, 200, 120, 255]),
+                (0.8, [200, 60, 40, 255]),
+                (1.0, [70, 10, 40, 255]),
+            ]),
+            muted_land: Ramp::new(&[
+                (0.0, [160, 170, 140, 255]),
+                (500.0, [190, 180, 155, 255]),
+                (5000.0, [210, 205, 195, 255]),
+            ]),
+            muted_water: [30, 45, 65, 255],
+            river: [15, 40, 140, 255],
+            dry_hot: [210, 160, 70, 255],
+            dry_cold: [235, 235, 240, 255],
+            ice_sheet: [240, 246, 250, 255],
+            ice_shelf: [215, 232, 242, 255],
+            sea_ice: [200, 218, 232, 255],
+        }
+    }
+
+    /// Natural-color look: dark oceans, vegetated lowlands, bare rock highlands.
+    pub fn satellite() -> Self {
+        Self {
+            water: Ramp::new(&[
+                (0.0, [40, 90, 120, 255]),
+                (200.0, [20, 50, 90, 255]),
+                (2000.0, [10, 25, 60, 255]),
+                (5000.0, [5, 12, 40, 255]),
+            ]),
+            land: Ramp::new(&[
+                (0.0, [180, 170, 130, 255]),
+                (5.0, [60, 90, 45, 255]),
+                (400.0, [80, 105, 55, 255]),
+                (1200.0, [120, 110, 80, 255]),
+                (2500.0, [110, 95, 80, 255]),
+                (4000.0, [150, 140, 130, 255]),
+                (6000.0, [185, 180, 175, 255]),
+            ]),
+            muted_water: [15, 30, 55, 255],
+            river: [30, 70, 120, 255],
+            ..Self::atlas()
+        }
+    }
+
+    /// Single-channel tones for print or analysis.
+    pub fn grayscale() -> Self {
+        let g = |v: u8| [v, v, v, 255];
+        Self {
+            water: Ramp::new(&[(0.0, g(110)), (5000.0, g(20))]),
+            land: Ramp::new(&[(0.0, g(130)), (6000.0, g(250))]),
+            temperature: Ramp::new(&[(-30.0, g(20)), (35.0, g(240))]),
+            precipitation: Ramp::new(&[(0.0, g(240)), (4000.0, g(20))]),
+            population: Ramp::new(&[(0.0, g(240)), (1.0, g(10))]),
+            muted_land: Ramp::new(&[(0.0, g(190)), (5000.0, g(230))]),
+            muted_water: g(60),
+            river: g(10),
+            dry_hot: g(215),
+            dry_cold: g(245),
+            ice_sheet: g(255),
+            ice_shelf: g(235),
+            sea_ice: g(215),
+        }
+    }
+
+    /// Perceptually ordered ramps (viridis/cividis-like) that stay
+    /// distinguishable under common color-vision deficiencies.
+    pub fn colorblind() -> Self {
+        Self {
+            water: Ramp::new(&[
+                (0.0, [120, 160, 200, 255]),
+                (2500.0, [40, 70, 130, 255]),
+                (5000.0, [15, 25, 70, 255]),
+            ]),
+            land: Ramp::new(&[
+                (0.0, [50, 110, 105, 255]),
+                (500.0, [90, 160, 100, 255]),
+                (1500.0, [190, 200, 80, 255]),
+                (3000.0, [240, 220, 120, 255]),
+                (6000.0, [250, 250, 240, 255]),
+            ]),
+            temperature: Ramp::new(&[
+                (-30.0, [0, 34, 78, 255]),
+                (0.0, [90, 100, 120, 255]),
+                (15.0, [150, 145, 120, 255]),
+                (35.0, [253, 231, 55, 255]),
+            ]),
+            precipitation: Ramp::new(&[
+                (0.0, [253, 231, 37, 255]),
+                (500.0, [94, 201, 98, 255]),
+                (1500.0, [33, 145, 140, 255]),
+                (2500.0, [59, 82, 139, 255]),
+                (4000.0, [68, 1, 84, 255]),
+            ]),
+            population: Ramp::new(&[
+                (0.0, [240, 240, 230, 255]),
+                (0.5, [230, 150, 60, 255]),
+                (1.0, [60, 20, 90, 255]),
+            ]),
+            river: [0, 60, 160, 255],
+            dry_hot: [230, 180, 60, 255],
+            ..Self::atlas()
+        }
+    }
+}
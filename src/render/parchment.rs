@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+
+use super::{lerp_color, slope_norm};
+use crate::grid::{Grid, neighbors8_wrap};
+use crate::noise::fbm;
+use crate::par::*;
+use crate::rng::hash2;
+use crate::terrain::{compute_aspect, compute_slope};
+
+const PAPER_LIGHT: [u8; 4] = [240, 226, 188, 255];
+const PAPER_DARK: [u8; 4] = [206, 182, 134, 255];
+const SEA_TINT: [u8; 4] = [196, 196, 170, 255];
+const INK: [u8; 4] = [62, 44, 28, 255];
+const RIVER_SEPIA: [u8; 4] = [112, 78, 44, 255];
+
+const PAPER_NOISE_SEED: u32 = 0x7A9E_5EED;
+const STIPPLE_SEED: u32 = 0x0057_1991;
+
+/// Minimum elevation for a ridge symbol (meters).
+const RIDGE_MIN_ELEV: f32 = 1200.0;
+
+/// Antique-atlas style: mottled parchment, stippled coasts with ripple
+/// lines offshore, hatched shadow slopes, hand-drawn ridge symbols placed
+/// along ridgelines, and sepia rivers.
+pub fn render_parchment(height: &Grid<f32>, river_flow: &Grid<f32>) -> Vec<u8> {
+    let w = height.w;
+    let h = height.h;
+    let scale = w as f32 / 2048.0;
+    let mut rgba = vec![0u8; w * h * 4];
+
+    let coast = coast_distance(height);
+    let slope = compute_slope(height);
+    let aspect = compute_aspect(height);
+    let max_s = slope_norm(&slope);
+    let max_flow = river_flow.data.iter().cloned().fold(0.0f32, f32::max).max(1.0);
+    let log_max = max_flow.ln();
+
+    let ring = (4.0 * scale).max(2.0);
+    let stipple_r = (10.0 * scale).max(4.0);
+
+    rgba.par_chunks_mut(w * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..w {
+            let e = height.get(x, y);
+            let d = coast.get(x, y);
+            let u = x as f32 / w as f32;
+            let v = y as f32 / h as f32;
+
+            // Mottled paper, darkening toward the sheet edges
+            let mottle = 0.5 + 0.5 * fbm(u, v, PAPER_NOISE_SEED, 4, 6.0, 2.0, 0.5);
+            let (ex, ey) = ((u - 0.5).abs() * 2.0, (v - 0.5).abs() * 2.0);
+            let vignette = (ex.max(ey) - 0.75).max(0.0) * 2.0;
+            let mut c = lerp_color(PAPER_LIGHT, PAPER_DARK, (mottle * 0.6 + vignette).min(1.0));
+
+            if e <= 0.0 {
+                c = lerp_color(c, SEA_TINT, 0.45);
+                // Ripple lines parallel to the coast, fading offshore
+                if d >= ring && d < ring * 3.5 && d % ring < 1.0 {
+                    c = lerp_color(c, INK, 0.45 * (1.0 - d / (ring * 3.5)));
+                }
+                // Stipple dots, densest at the shoreline
+                let p = (1.0 - d / stipple_r).max(0.0) * 0.35;
+                if (hash2(x as i32, y as i32, STIPPLE_SEED) & 0xFFFF) as f32 / 65535.0 < p {
+                    c = lerp_color(c, INK, 0.7);
+                }
+            } else if d <= 1.0 {
+                c = INK;
+            } else {
+                // Hatch slopes facing away from a northwest light
+                let a = aspect.get(x, y);
+                if a >= 0.0 {
+                    let facing = (((a - 135.0).to_radians()).cos()).max(0.0);
+                    let t = (slope.get(x, y) / max_s).sqrt().min(1.0) * facing;
+                    let spacing = if t > 0.6 { 3 } else if t > 0.3 { 4 } else { 0 };
+                    if spacing > 0 && (x + y).is_multiple_of(spacing) {
+                        c = lerp_color(c, INK, 0.55);
+                    }
+                }
+            }
+
+            let flow = river_flow.get(x, y);
+            if flow > 0.0 && e > 0.0 {
+                let intensity = (flow.ln() / log_max).clamp(0.0, 1.0);
+                c = lerp_color(c, RIVER_SEPIA, 0.6 + 0.4 * intensity);
+            }
+
+            row[x * 4..x * 4 + 4].copy_from_slice(&c);
+        }
+    });
+
+    draw_ridge_symbols(&mut rgba, height, scale);
+    rgba
+}
+
+/// Distance in cells from each cell to the nearest coastline (a land cell
+/// touching water, or the reverse), by breadth-first search. Capped.
+fn coast_distance(height: &Grid<f32>) -> Grid<f32> {
+    let w = height.w;
+    let h = height.h;
+    let mut dist = Grid::<f32>::new(w, h);
+    for v in &mut dist.data {
+        *v = f32::MAX;
+    }
+    let mut queue = VecDeque::new();
+    for y in 0..h {
+        for x in 0..w {
+            let land = height.get(x, y) > 0.0;
+            if neighbors8_wrap(x, y, w, h).any(|(nx, ny)| (height.get(nx, ny) > 0.0) != land) {
+                dist.set(x, y, 1.0);
+                queue.push_back((x, y));
+            }
+        }
+    }
+    let cap = 64.0;
+    while let Some((x, y)) = queue.pop_front() {
+        let d = dist.get(x, y);
+        if d >= cap {
+            continue;
+        }
+        for (nx, ny) in neighbors8_wrap(x, y, w, h) {
+            let step = if nx != x && ny != y { 1.414 } else { 1.0 };
+            if d + step < dist.get(nx, ny) {
+                dist.set(nx, ny, d + step);
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    dist
+}
+
+/// Stamp "^"-style mountain symbols on the highest ridge cell of each
+/// symbol-sized block. Symbols are drawn north to south so nearer (lower)
+/// ones overlap those behind them, with a paper-filled body and hatching
+/// on the shadowed east flank.
+fn draw_ridge_symbols(rgba: &mut [u8], height: &Grid<f32>, scale: f32) {
+    let w = height.w;
+    let h = height.h;
+    let cell = (12.0 * scale).max(5.0) as usize;
+
+    let is_ridge = |x: usize, y: usize| {
+        let e = height.get(x, y);
+        let (we, ee) = (height.get((x + w - 1) % w, y), height.get((x + 1) % w, y));
+        let (ne, se) = (height.get(x, y.saturating_sub(1)), height.get(x, (y + 1).min(h - 1)));
+        (e >= we && e >= ee) || (e >= ne && e >= se)
+    };
+
+    for by in (0..h).step_by(cell) {
+        for bx in (0..w).step_by(cell) {
+            let mut best: Option<(usize, usize, f32)> = None;
+            for y in by..(by + cell).min(h) {
+                for x in bx..(bx + cell).min(w) {
+                    let e = height.get(x, y);
+                    if e >= RIDGE_MIN_ELEV && best.is_none_or(|b| e > b.2) && is_ridge(x, y) {
+                        best = Some((x, y, e));
+                    }
+                }
+            }
+            let Some((x, y, e)) = best else { continue };
+            let size = cell as f32 * (0.45 + 0.35 * ((e - RIDGE_MIN_ELEV) / 4000.0).clamp(0.0, 1.0));
+            draw_mountain(rgba, w, h, x as i32, y as i32, size);
+        }
+    }
+}
+
+fn draw_mountain(rgba: &mut [u8], w: usize, h: usize, cx: i32, cy: i32, size: f32) {
+    let half = size.max(2.0);
+    let peak_h = half * 1.2;
+    let base_y = cy as f32 + half * 0.4;
+    let top_y = base_y - peak_h;
+
+    // Body: fill the triangle with paper so it occludes what's behind it
+    let y0 = top_y.floor() as i32;
+    let y1 = base_y.ceil() as i32;
+    for py in y0..=y1 {
+        let t = ((py as f32 - top_y) / peak_h).clamp(0.0, 1.0);
+        let span = (half * t).round() as i32;
+        for dx in -span..=span {
+            let shade = if dx > 0 && (dx + py).rem_euclid(2) == 0 { 0.5 } else { 0.0 };
+            let c = lerp_color(PAPER_LIGHT, INK, shade);
+            plot(rgba, w, h, cx + dx, py, c);
+        }
+    }
+
+    // Outline: the two flanks
+    let (tx, ty) = (cx as f32, top_y);
+    line(rgba, w, h, cx as f32 - half, base_y, tx, ty);
+    line(rgba, w, h, tx, ty, cx as f32 + half, base_y);
+}
+
+fn line(rgba: &mut [u8], w: usize, h: usize, x0: f32, y0: f32, x1: f32, y1: f32) {
+    let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as i32;
+    for i in 0..=steps {
+        let t = i as f32 / steps as f32;
+        let x = (x0 + (x1 - x0) * t).round() as i32;
+        let y = (y0 + (y1 - y0) * t).round() as i32;
+        plot(rgba, w, h, x, y, INK);
+    }
+}
+
+#[inline]
+fn plot(rgba: &mut [u8], w: usize, h: usize, x: i32, y: i32, c: [u8; 4]) {
+    if y < 0 || y >= h as i32 {
+        return;
+    }
+    let x = x.rem_euclid(w as i32) as usize;
+    let i = (y as usize * w + x) * 4;
+    rgba[i..i + 4].copy_from_slice(&c);
+}
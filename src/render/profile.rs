@@ -0,0 +1,89 @@
+use super::font::{GLYPH_ADVANCE, draw_text, fill_rect};
+use crate::profile::ProfileSample;
+
+const BG: [u8; 4] = [250, 250, 247, 255];
+const AXIS: [u8; 4] = [60, 60, 60, 255];
+const GROUND: [u8; 4] = [150, 120, 85, 255];
+const SEA: [u8; 4] = [150, 195, 230, 255];
+const TEMP_LINE: [u8; 4] = [210, 50, 40, 255];
+const PRECIP_BAR: [u8; 4] = [70, 120, 210, 255];
+
+/// Cross-section chart: terrain (ground brown, water blue above the seabed)
+/// against distance, temperature as a red line on a -40..40 C scale, and
+/// precipitation as blue bars (0..4000 mm/year) along the bottom. The left
+/// axis is labeled with the elevation range in meters, the bottom right
+/// with total distance in km.
+pub fn render_profile(samples: &[ProfileSample], w: usize, h: usize) -> Vec<u8> {
+    let mut rgba = vec![0u8; w * h * 4];
+    for px in rgba.chunks_exact_mut(4) {
+        px.copy_from_slice(&BG);
+    }
+    if samples.len() < 2 {
+        return rgba;
+    }
+
+    let s = (h / 256).max(1);
+    let left = 7 * GLYPH_ADVANCE * s;
+    let (top, bottom, right) = (4 * s, 10 * s, 4 * s);
+    if w <= left + right + 2 || h <= top + bottom + 2 {
+        return rgba;
+    }
+    let (pw, ph) = (w - left - right, h - top - bottom);
+
+    let lo = samples.iter().map(|p| p.elevation).fold(f32::MAX, f32::min).min(-100.0);
+    let hi = samples.iter().map(|p| p.elevation).fold(f32::MIN, f32::max).max(100.0);
+    let pad = (hi - lo) * 0.05;
+    let (lo, hi) = (lo - pad, hi + pad);
+    let to_y = |e: f32| top + (((hi - e) / (hi - lo)) * (ph - 1) as f32).round().clamp(0.0, (ph - 1) as f32) as usize;
+    let sea_y = to_y(0.0);
+
+    let mut prev_temp_y: Option<usize> = None;
+    for col in 0..pw {
+        let f = col as f32 / (pw - 1).max(1) as f32 * (samples.len() - 1) as f32;
+        let i = (f.floor() as usize).min(samples.len() - 2);
+        let t = f - i as f32;
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        let (a, b) = (&samples[i], &samples[i + 1]);
+        let e = lerp(a.elevation, b.elevation);
+        let x = left + col;
+
+        let ey = to_y(e);
+        if e < 0.0 {
+            vline(&mut rgba, w, x, sea_y, ey, SEA);
+        }
+        vline(&mut rgba, w, x, ey, top + ph, GROUND);
+
+        let precip = (lerp(a.precipitation, b.precipitation) / 4000.0).clamp(0.0, 1.0);
+        let bar = (precip * ph as f32 * 0.25) as usize;
+        if bar > 0 {
+            vline(&mut rgba, w, x, top + ph - bar, top + ph, PRECIP_BAR);
+        }
+
+        let temp = (lerp(a.temperature, b.temperature) + 40.0) / 80.0;
+        let ty = top + ((1.0 - temp.clamp(0.0, 1.0)) * (ph - 1) as f32) as usize;
+        let (y0, y1) = prev_temp_y.map_or((ty, ty), |p| (p.min(ty), p.max(ty)));
+        vline(&mut rgba, w, x, y0, y1 + 1, TEMP_LINE);
+        prev_temp_y = Some(ty);
+    }
+
+    // Axes, sea level, labels
+    fill_rect(&mut rgba, w, left - s, top, s, ph, AXIS);
+    fill_rect(&mut rgba, w, left - s, top + ph, pw + s, s, AXIS);
+    for x in (left..left + pw).step_by(4 * s) {
+        fill_rect(&mut rgba, w, x, sea_y, 2 * s, s, AXIS);
+    }
+    draw_text(&mut rgba, w, s, top, s, &format!("{}", hi.round() as i32), AXIS);
+    draw_text(&mut rgba, w, s, top + ph - 5 * s, s, &format!("{}", lo.round() as i32), AXIS);
+    let dist = format!("{}", samples[samples.len() - 1].distance_km.round() as i32);
+    let label_x = (w - right).saturating_sub(dist.len() * GLYPH_ADVANCE * s);
+    draw_text(&mut rgba, w, label_x, top + ph + 3 * s, s, &dist, AXIS);
+
+    rgba
+}
+
+/// Vertical span [y0, y1) in column x.
+fn vline(rgba: &mut [u8], w: usize, x: usize, y0: usize, y1: usize, c: [u8; 4]) {
+    if y1 > y0 {
+        fill_rect(rgba, w, x, y0, 1, y1 - y0, c);
+    }
+}
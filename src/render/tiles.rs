@@ -0,0 +1,74 @@
+#[cfg(feature = "image")]
+use std::path::Path;
+
+use super::sample_rgba;
+use crate::par::*;
+
+/// Edge length of a slippy-map tile in pixels.
+pub const TILE_SIZE: usize = 256;
+
+/// Deepest zoom a tile can be addressed at (about 16 cm per pixel).
+pub const MAX_ZOOM: u32 = 20;
+
+/// Web Mercator latitude limit (degrees); tiles are square at this bound.
+const MERCATOR_MAX_LAT: f32 = 85.051_13;
+
+/// Render a single 256px Web Mercator tile (standard z/x/y addressing,
+/// y=0 at the north) by bilinear sampling an equirectangular RGBA image.
+/// `z` is at most `MAX_ZOOM`.
+pub fn render_tile(rgba: &[u8], w: usize, h: usize, z: u32, tx: u32, ty: u32) -> Vec<u8> {
+    assert!(z <= MAX_ZOOM, "zoom {} is past MAX_ZOOM", z);
+    let n = (TILE_SIZE << z) as f32;
+    let mut out = vec![0u8; TILE_SIZE * TILE_SIZE * 4];
+
+    out.par_chunks_mut(TILE_SIZE * 4).enumerate().for_each(|(py, row)| {
+        let gy = (ty as usize * TILE_SIZE + py) as f32 + 0.5;
+        let lat = (std::f32::consts::PI * (1.0 - 2.0 * gy / n)).sinh().atan().to_degrees();
+        let lat = lat.clamp(-MERCATOR_MAX_LAT, MERCATOR_MAX_LAT);
+        let fy = (90.0 - lat) / 180.0 * h as f32 - 0.5;
+
+        for px in 0..TILE_SIZE {
+            let gx = (tx as usize * TILE_SIZE + px) as f32 + 0.5;
+            let lon = gx / n * 360.0 - 180.0;
+            let fx = (lon + 180.0) / 360.0 * w as f32 - 0.5;
+            let c = sample_rgba(rgba, w, h, fx, fy);
+            row[px * 4..px * 4 + 4].copy_from_slice(&c);
+        }
+    });
+
+    out
+}
+
+/// Render and write every tile for zoom levels 0..=max_zoom as
+/// `dir/z/x/y.png`, one tile at a time. Returns the number of tiles
+/// written; fails with `InvalidInput` past `MAX_ZOOM`.
+#[cfg(feature = "image")]
+pub fn write_pyramid(dir: &Path, rgba: &[u8], w: usize, h: usize, max_zoom: u32) -> std::io::Result<usize> {
+    if max_zoom > MAX_ZOOM {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("zoom {} is past the deepest, {}", max_zoom, MAX_ZOOM),
+        ));
+    }
+    let mut written = 0;
+    for z in 0..=max_zoom {
+        let count = 1u32 << z;
+        for tx in 0..count {
+            let tile_dir = dir.join(z.to_string()).join(tx.to_string());
+            std::fs::create_dir_all(&tile_dir)?;
+            for ty in 0..count {
+                let tile = render_tile(rgba, w, h, z, tx, ty);
+                image::save_buffer(
+                    tile_dir.join(format!("{}.png", ty)),
+                    &tile,
+                    TILE_SIZE as u32,
+                    TILE_SIZE as u32,
+                    image::ColorType::Rgba8,
+                )
+                .map_err(std::io::Error::other)?;
+                written += 1;
+            }
+        }
+    }
+    Ok(written)
+}
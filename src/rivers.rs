@@ -0,0 +1,139 @@
+use crate::grid::Grid;
+
+const NONE: usize = usize::MAX;
+
+/// A run of river cells between confluences with a constant Strahler order.
+#[derive(Clone, Debug)]
+pub struct RiverSegment {
+    /// Cell-center coordinates, downstream order. x may run past the map
+    /// edge where a river crosses the E-W seam (wrap when plotting).
+    pub points: Vec<(f32, f32)>,
+    /// Discharge at each point.
+    pub flow: Vec<f32>,
+    /// Strahler stream order (1 = headwater).
+    pub order: u8,
+    /// The segment ends in the sea; its last point is the ocean cell.
+    pub mouth: bool,
+}
+
+/// Vectorize the river grid into polyline segments.
+///
+/// Each river cell drains to the neighboring river cell with the largest
+/// discharge above its own, or to an adjacent ocean cell at a mouth.
+/// Segments break wherever a tributary joins or the order changes, so every
+/// segment has a single order and consecutive segments share an endpoint.
+pub fn extract_network(height: &Grid<f32>, river_flow: &Grid<f32>) -> Vec<RiverSegment> {
+    let w = height.w;
+    let h = height.h;
+    let n = w * h;
+
+    let mut cells: Vec<usize> =
+        (0..n).filter(|&i| river_flow.data[i] > 0.0 && height.data[i] > 0.0).collect();
+
+    let mut down = vec![NONE; n];
+    let mut sea = vec![NONE; n];
+    for &i in &cells {
+        let mut best = river_flow.data[i];
+        for (nx, ny) in height.neighbors8(i % w, i / w) {
+            let j = ny * w + nx;
+            if height.data[j] <= 0.0 {
+                if sea[i] == NONE {
+                    sea[i] = j;
+                }
+                continue;
+            }
+            if river_flow.data[j] > best {
+                best = river_flow.data[j];
+                down[i] = j;
+            }
+        }
+    }
+
+    let mut upstream = vec![0u8; n];
+    for &i in &cells {
+        if down[i] != NONE {
+            upstream[down[i]] = upstream[down[i]].saturating_add(1);
+        }
+    }
+
+    // Strahler order. Discharge strictly increases downstream, so ascending
+    // flow visits every tributary before the cell it joins.
+    cells.sort_unstable_by(|&a, &b| river_flow.data[a].total_cmp(&river_flow.data[b]));
+    let mut order = vec![0u8; n];
+    let mut max_in = vec![0u8; n];
+    let mut max_count = vec![0u8; n];
+    for &i in &cells {
+        order[i] = match (upstream[i], max_count[i]) {
+            (0, _) => 1,
+            (_, c) if c >= 2 => max_in[i].saturating_add(1),
+            _ => max_in[i],
+        };
+        let d = down[i];
+        if d != NONE {
+            if order[i] > max_in[d] {
+                max_in[d] = order[i];
+                max_count[d] = 1;
+            } else if order[i] == max_in[d] {
+                max_count[d] = max_count[d].saturating_add(1);
+            }
+        }
+    }
+
+    // A cell continues its upstream segment if it has exactly one tributary
+    // of the same order; every other river cell starts a segment.
+    let mut continues = vec![false; n];
+    for &i in &cells {
+        let d = down[i];
+        if d != NONE && upstream[d] == 1 && order[d] == order[i] {
+            continues[d] = true;
+        }
+    }
+
+    let mut segments = Vec::new();
+    for &start in &cells {
+        if continues[start] {
+            continue;
+        }
+        let mut points = vec![((start % w) as f32 + 0.5, (start / w) as f32 + 0.5)];
+        let mut flow = vec![river_flow.data[start]];
+        let mut mouth = false;
+        let mut cur = start;
+        loop {
+            let d = down[cur];
+            let next = if d != NONE { d } else { sea[cur] };
+            if next == NONE {
+                break;
+            }
+            let prev_x = points[points.len() - 1].0;
+            points.push((unwrap_x(next % w, prev_x, w), (next / w) as f32 + 0.5));
+            if d == NONE {
+                flow.push(river_flow.data[cur]);
+                mouth = true;
+                break;
+            }
+            flow.push(river_flow.data[d]);
+            if !continues[d] {
+                break;
+            }
+            cur = d;
+        }
+        segments.push(RiverSegment { points, flow, order: order[start], mouth });
+    }
+
+    segments
+}
+
+/// Cell-center x for column `x`, shifted by whole map widths to be nearest
+/// `prev` so polylines stay continuous across the E-W seam.
+#[inline]
+fn unwrap_x(x: usize, prev: f32, w: usize) -> f32 {
+    let mut fx = x as f32 + 0.5;
+    let wf = w as f32;
+    while fx - prev > wf * 0.5 {
+        fx -= wf;
+    }
+    while prev - fx > wf * 0.5 {
+        fx += wf;
+    }
+    fx
+}
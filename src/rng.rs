@@ -1,5 +1,7 @@
 /// Deterministic RNG based on splitmix64/32. No stateful RNG in inner loops.
 
+use crate::fmath;
+
 #[inline]
 pub fn splitmix64(mut x: u64) -> u64 {
     x = x.wrapping_add(0x9E3779B97F4A7C15);
@@ -33,7 +35,50 @@ pub fn hash2(ix: i32, iy: i32, seed: u32) -> u32 {
     h
 }
 
+/// Hierarchical seeds: each named child hashes its parent's seed with the
+/// name, so every subsystem gets its own deterministic stream without a
+/// hand-picked salt, and adding or changing draws in one stage cannot shift
+/// another's. Paths read like "elevation" / "ridge".
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeedTree {
+    seed: u64,
+}
+
+impl SeedTree {
+    /// Root of the tree for a world seed.
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Subtree for `name`.
+    pub fn child(&self, name: &str) -> Self {
+        // FNV-1a of the name
+        let hash = name.bytes().fold(0xCBF2_9CE4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01B3));
+        Self { seed: splitmix64(splitmix64(self.seed) ^ hash) }
+    }
+
+    /// Subtree for the `i`th of a family of siblings (per plate, per epoch).
+    pub fn index(&self, i: u64) -> Self {
+        Self { seed: splitmix64(splitmix64(self.seed) ^ splitmix64(i)) }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 32-bit seed for the noise functions.
+    pub fn seed_u32(&self) -> u32 {
+        self.seed as u32
+    }
+
+    /// Sequential RNG seeded from this node.
+    pub fn rng(&self) -> Rng {
+        Rng::new(self.seed)
+    }
+}
+
 /// Simple sequential RNG for plate generation (not used in pixel inner loops).
+#[derive(Clone)]
 pub struct Rng {
     state: u64,
 }
@@ -42,25 +87,145 @@ impl Rng {
     pub fn new(seed: u64) -> Self {
         Self { state: seed }
     }
+}
 
-    pub fn next_u64(&mut self) -> u64 {
+impl RngCore for Rng {
+    fn next_u64(&mut self) -> u64 {
         self.state = splitmix64(self.state);
         self.state
     }
+}
+
+/// Source of random bits for the sequential (non-pixel) stages. Only
+/// `next_u64` is required; the rest derive from it. Implement it to drive
+/// plate generation from an RNG of your own, e.g. one shared with a game's
+/// save state. `Rng` is the default.
+pub trait RngCore {
+    fn next_u64(&mut self) -> u64;
 
-    pub fn next_u32(&mut self) -> u32 {
+    fn next_u32(&mut self) -> u32 {
         self.next_u64() as u32
     }
 
-    pub fn next_f32(&mut self) -> f32 {
+    fn next_f32(&mut self) -> f32 {
         (self.next_u32() >> 8) as f32 / 16777216.0
     }
 
-    pub fn range_f32(&mut self, lo: f32, hi: f32) -> f32 {
+    fn range_f32(&mut self, lo: f32, hi: f32) -> f32 {
         lo + self.next_f32() * (hi - lo)
     }
 
-    pub fn range_usize(&mut self, max: usize) -> usize {
-        (self.next_u64() % max as u64) as usize
+    /// Uniform in `0..max`, without modulo bias: Lemire's multiply-high,
+    /// redrawing the few values that would favor low results.
+    fn range_usize(&mut self, max: usize) -> usize {
+        assert!(max > 0, "range_usize of an empty range");
+        let n = max as u64;
+        let mut m = self.next_u64() as u128 * n as u128;
+        if (m as u64) < n {
+            let threshold = n.wrapping_neg() % n;
+            while (m as u64) < threshold {
+                m = self.next_u64() as u128 * n as u128;
+            }
+        }
+        (m >> 64) as usize
+    }
+
+    /// Normal (Gaussian) sample by Box-Muller.
+    fn normal(&mut self, mean: f32, std_dev: f32) -> f32 {
+        // 1 - u keeps the log argument in (0, 1]
+        let r = (-2.0 * fmath::ln(1.0 - self.next_f32())).sqrt();
+        let theta = self.next_f32() * std::f32::consts::TAU;
+        mean + std_dev * r * fmath::cos(theta)
+    }
+
+    /// Exponential sample with the given mean (waiting times, sizes with
+    /// many small and few large).
+    fn exponential(&mut self, mean: f32) -> f32 {
+        -mean * fmath::ln(1.0 - self.next_f32())
+    }
+
+    /// Fisher-Yates shuffle in place.
+    fn shuffle<T>(&mut self, items: &mut [T])
+    where
+        Self: Sized,
+    {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range_usize(i + 1));
+        }
+    }
+
+    /// Index drawn with probability proportional to its weight. Negative
+    /// weights count as zero; `None` if no weight is positive.
+    fn choose_weighted(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().map(|w| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut r = self.next_f32() * total;
+        let mut last = None;
+        for (i, &w) in weights.iter().enumerate() {
+            if w <= 0.0 {
+                continue;
+            }
+            if r < w {
+                return Some(i);
+            }
+            r -= w;
+            last = Some(i);
+        }
+        // Rounding left r just past the end
+        last
+    }
+}
+
+impl<R: RngCore + ?Sized> RngCore for &mut R {
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replays fixed draws, to steer `range_usize` into its redraw branch.
+    struct Replay(Vec<u64>);
+
+    impl RngCore for Replay {
+        fn next_u64(&mut self) -> u64 {
+            self.0.remove(0)
+        }
+    }
+
+    #[test]
+    fn range_usize_stays_in_range_and_covers_it() {
+        let mut rng = Rng::new(7);
+        for max in [1, 2, 3, 7, 10, 1000] {
+            let mut seen = vec![0usize; max];
+            for _ in 0..max * 200 {
+                seen[rng.range_usize(max)] += 1;
+            }
+            // ~200 each; a biased or off-by-one draw leaves some far off
+            assert!(seen.iter().all(|&n| (100..300).contains(&n)), "max {max}: {seen:?}");
+        }
+    }
+
+    #[test]
+    fn range_usize_redraws_biased_values() {
+        // For max = 3, multiply-high results with a low half under
+        // 2^64 mod 3 = 1 are redrawn: 0 maps to low 0, so it's skipped
+        let mut rng = Replay(vec![0, u64::MAX]);
+        assert_eq!(rng.range_usize(3), 2);
+        assert!(rng.0.is_empty());
+
+        // Values past the threshold are kept on the first draw
+        let mut rng = Replay(vec![u64::MAX / 3 + 1]);
+        assert_eq!(rng.range_usize(3), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "empty range")]
+    fn range_usize_rejects_an_empty_range() {
+        Rng::new(1).range_usize(0);
     }
 }
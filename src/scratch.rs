@@ -0,0 +1,151 @@
+//! Reusable buffers for the large temporaries of generation (distance
+//! transforms, blur copies, hi-res hydrology grids). A `Scratch` pool is
+//! made active on the thread running a `Pipeline`; code on that thread
+//! takes buffers from it with `take`/`copy_of` and hands them back with
+//! `give`, so repeated runs of the same size stop reallocating. With no
+//! active pool (e.g. on worker threads) these fall back to plain
+//! allocation.
+
+use std::cell::RefCell;
+
+use crate::grid::Grid;
+
+/// Buffers kept per element type; past this the smallest is dropped.
+const MAX_SHELVED: usize = 8;
+
+/// Idle buffers, by element type.
+#[derive(Default)]
+pub(crate) struct Scratch {
+    f32s: Vec<Vec<f32>>,
+    u8s: Vec<Vec<u8>>,
+    u16s: Vec<Vec<u16>>,
+    u32s: Vec<Vec<u32>>,
+    bools: Vec<Vec<bool>>,
+}
+
+impl Scratch {
+    /// Bytes held by idle buffers.
+    pub(crate) fn bytes(&self) -> usize {
+        fn sum<T>(shelf: &[Vec<T>]) -> usize {
+            shelf.iter().map(|v| v.capacity() * size_of::<T>()).sum()
+        }
+        sum(&self.f32s) + sum(&self.u8s) + sum(&self.u16s) + sum(&self.u32s) + sum(&self.bools)
+    }
+}
+
+/// Element types the pool keeps buffers of.
+pub(crate) trait Pooled: Copy + Default + 'static {
+    fn shelf(scratch: &mut Scratch) -> &mut Vec<Vec<Self>>;
+}
+
+macro_rules! pooled {
+    ($($t:ty => $field:ident),*) => {
+        $(impl Pooled for $t {
+            fn shelf(scratch: &mut Scratch) -> &mut Vec<Vec<Self>> {
+                &mut scratch.$field
+            }
+        })*
+    };
+}
+
+pooled!(f32 => f32s, u8 => u8s, u16 => u16s, u32 => u32s, bool => bools);
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Scratch>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with `scratch` as this thread's pool; buffers given back
+/// during `f` end up in `scratch`.
+pub(crate) fn with<R>(scratch: &mut Scratch, f: impl FnOnce() -> R) -> R {
+    struct Restore<'a> {
+        scratch: &'a mut Scratch,
+        outer: Option<Scratch>,
+    }
+    impl Drop for Restore<'_> {
+        fn drop(&mut self) {
+            *self.scratch = ACTIVE.replace(self.outer.take()).unwrap_or_default();
+        }
+    }
+    let outer = ACTIVE.replace(Some(std::mem::take(scratch)));
+    let _restore = Restore { scratch, outer };
+    f()
+}
+
+/// A buffer of `len` copies of `fill`, reusing the smallest idle one that
+/// fits.
+pub(crate) fn take<T: Pooled>(len: usize, fill: T) -> Vec<T> {
+    let mut v = reuse::<T>(len).unwrap_or_default();
+    v.clear();
+    v.resize(len, fill);
+    v
+}
+
+/// A pooled copy of `src`.
+pub(crate) fn copy_of<T: Pooled>(src: &[T]) -> Vec<T> {
+    let mut v = reuse::<T>(src.len()).unwrap_or_default();
+    v.clear();
+    v.extend_from_slice(src);
+    v
+}
+
+/// A pooled copy of `grid`, with its topology and metric.
+pub(crate) fn copy_grid<T: Pooled>(grid: &Grid<T>) -> Grid<T> {
+    Grid::from_vec(grid.w, grid.h, copy_of(&grid.data)).with_topology(grid.topology).with_metric(grid.metric)
+}
+
+fn reuse<T: Pooled>(len: usize) -> Option<Vec<T>> {
+    ACTIVE.with_borrow_mut(|active| {
+        let shelf = T::shelf(active.as_mut()?);
+        let best = (0..shelf.len()).filter(|&i| shelf[i].capacity() >= len).min_by_key(|&i| shelf[i].capacity())?;
+        Some(shelf.swap_remove(best))
+    })
+}
+
+/// Hand a buffer back to the pool (dropped if no pool is active).
+pub(crate) fn give<T: Pooled>(v: Vec<T>) {
+    if v.capacity() == 0 {
+        return;
+    }
+    ACTIVE.with_borrow_mut(|active| {
+        if let Some(active) = active {
+            let shelf = T::shelf(active);
+            shelf.push(v);
+            if shelf.len() > MAX_SHELVED {
+                let smallest = (0..shelf.len()).min_by_key(|&i| shelf[i].capacity()).expect("shelf is not empty");
+                shelf.swap_remove(smallest);
+            }
+        }
+    });
+}
+
+/// Outputs whose buffers can go back to the pool.
+pub(crate) trait Recycle {
+    fn recycle(self);
+}
+
+impl<T: Pooled> Recycle for Vec<T> {
+    fn recycle(self) {
+        give(self);
+    }
+}
+
+impl<T: Pooled> Recycle for Grid<T> {
+    fn recycle(self) {
+        give(self.data);
+    }
+}
+
+impl<R: Recycle> Recycle for Option<R> {
+    fn recycle(self) {
+        if let Some(r) = self {
+            r.recycle();
+        }
+    }
+}
+
+impl<A: Recycle, B: Recycle> Recycle for (A, B) {
+    fn recycle(self) {
+        self.0.recycle();
+        self.1.recycle();
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+
+use crate::grid::cell_latlon;
+use crate::par::*;
+use crate::terrain::cell_size_m;
+use crate::{Map, climate, history, rivers};
+
+/// Water bodies smaller than this (km^2) count as lakes; larger ones as
+/// oceans and seas. The Caspian Sea, at about 370,000 km^2, is a lake.
+pub const LAKE_MAX_KM2: f64 = 500_000.0;
+
+/// A layer's value at one cell.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Extreme {
+    pub value: f32,
+    pub lat: f32,
+    pub lon: f32,
+}
+
+/// Lowest and highest cells of a layer, and its area-weighted mean.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct LayerSummary {
+    pub min: Extreme,
+    pub max: Extreme,
+    pub mean: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WaterSummary {
+    /// Water bodies of at least `LAKE_MAX_KM2`.
+    pub oceans: usize,
+    pub largest_ocean_km2: f64,
+    pub lakes: usize,
+    pub lake_area_km2: f64,
+}
+
+/// The river network (see `rivers::extract_network`); all zero before the
+/// rivers stage has run.
+#[derive(Clone, Debug, Serialize)]
+pub struct RiverSummary {
+    pub segments: usize,
+    /// Rivers reaching the sea, each draining its own basin.
+    pub basins: usize,
+    /// Highest Strahler order.
+    pub max_order: u8,
+    pub length_km: f64,
+    pub max_flow: f32,
+}
+
+/// One-look summary of a world, for overview panels and logs.
+#[derive(Clone, Debug, Serialize)]
+pub struct WorldStatistics {
+    pub width: usize,
+    pub height: usize,
+    /// Share of the surface (by area, not cells) above sea level.
+    pub land_fraction: f64,
+    pub land_area_km2: f64,
+    pub water_area_km2: f64,
+    /// Share of the surface under an ice sheet, ice shelf or sea ice.
+    pub ice_fraction: f64,
+    /// Meters; highest peak and deepest trench.
+    pub elevation: LayerSummary,
+    /// Degrees C, over the whole surface.
+    pub temperature: LayerSummary,
+    /// mm/year, over land. None on a world without land.
+    pub land_precipitation: Option<LayerSummary>,
+    pub landmasses: usize,
+    pub largest_landmass_km2: f64,
+    pub water: WaterSummary,
+    pub rivers: RiverSummary,
+    pub population: f64,
+    /// Nations holding territory.
+    pub nations: usize,
+    pub cultures: usize,
+}
+
+impl Map {
+    /// Land and water areas, layer extremes, landmass, water body and river
+    /// summaries. Areas use true cell areas, so polar rows weigh less.
+    pub fn statistics(&self) -> WorldStatistics {
+        let (w, h) = (self.w, self.h);
+        let row_km2: Vec<f64> = (0..h)
+            .map(|y| {
+                let (dx, dy) = cell_size_m(y, w, h);
+                dx as f64 * dy as f64 * 1e-6
+            })
+            .collect();
+        let total_km2: f64 = row_km2.iter().sum::<f64>() * w as f64;
+        let area_where = |keep: &(dyn Fn(usize) -> bool + Sync)| -> f64 {
+            (0..w * h).filter(|&i| keep(i)).fold(0.0, |a, i| a + row_km2[i / w])
+        };
+
+        let land_area_km2 = area_where(&|i| self.height.data[i] > 0.0);
+        let ice_km2 = area_where(&|i| self.ice.data[i] != climate::ICE_NONE);
+
+        let temperature = self.temperature.decode();
+        let precipitation = self.precipitation.decode();
+        let summary = |values: &[f32], keep: &(dyn Fn(usize) -> bool + Sync)| {
+            summarize(values, &row_km2, w, h, keep)
+        };
+        let all = |_: usize| true;
+
+        let (_, landmasses) = self.label_landmasses();
+        let (_, waters) = self.label_oceans();
+        let (lakes, oceans): (Vec<_>, Vec<_>) = waters.iter().partition(|r| r.area_km2 < LAKE_MAX_KM2);
+
+        let mut nations: Vec<u16> =
+            self.nation_id.data.iter().copied().filter(|&n| n != history::NO_NATION).collect();
+        nations.sort_unstable();
+        nations.dedup();
+
+        WorldStatistics {
+            width: w,
+            height: h,
+            land_fraction: land_area_km2 / total_km2,
+            land_area_km2,
+            water_area_km2: total_km2 - land_area_km2,
+            ice_fraction: ice_km2 / total_km2,
+            elevation: summary(&self.height.data, &all).expect("maps have cells"),
+            temperature: summary(&temperature.data, &all).expect("maps have cells"),
+            land_precipitation: summary(&precipitation.data, &|i| self.height.data[i] > 0.0),
+            landmasses: landmasses.len(),
+            largest_landmass_km2: landmasses.iter().map(|r| r.area_km2).fold(0.0, f64::max),
+            water: WaterSummary {
+                oceans: oceans.len(),
+                largest_ocean_km2: oceans.iter().map(|r| r.area_km2).fold(0.0, f64::max),
+                lakes: lakes.len(),
+                lake_area_km2: lakes.iter().map(|r| r.area_km2).fold(0.0, |a, b| a + b),
+            },
+            rivers: self.river_summary(),
+            population: self.population.data.iter().fold(0.0, |a, &p| a + p as f64),
+            nations: nations.len(),
+            cultures: self.cultures.len(),
+        }
+    }
+
+    fn river_summary(&self) -> RiverSummary {
+        let (w, h) = (self.w, self.h);
+        let network = rivers::extract_network(&self.height, &self.river_flow);
+        // Folded from +0.0: an empty f64 `sum` is -0.0
+        let length_m: f64 = network
+            .iter()
+            .flat_map(|s| s.points.windows(2))
+            .map(|p| {
+                let ((x0, y0), (x1, y1)) = (p[0], p[1]);
+                let row = (((y0 + y1) * 0.5).round().max(0.0) as usize).min(h - 1);
+                let (dx, dy) = cell_size_m(row, w, h);
+                (((x1 - x0) * dx) as f64).hypot(((y1 - y0) * dy) as f64)
+            })
+            .fold(0.0, |a, b| a + b);
+        RiverSummary {
+            segments: network.len(),
+            basins: network.iter().filter(|s| s.mouth).count(),
+            max_order: network.iter().map(|s| s.order).max().unwrap_or(0),
+            length_km: length_m * 1e-3,
+            max_flow: self.river_flow.data.iter().copied().fold(0.0, f32::max),
+        }
+    }
+}
+
+/// Extremes and area-weighted mean of the cells passing `keep`. Rows are
+/// summed in parallel and added in order, so the mean doesn't depend on
+/// how the work is split.
+fn summarize(
+    values: &[f32],
+    row_km2: &[f64],
+    w: usize,
+    h: usize,
+    keep: &(dyn Fn(usize) -> bool + Sync),
+) -> Option<LayerSummary> {
+    let rows: Vec<Option<(usize, usize, f64, f64)>> = (0..h)
+        .into_par_iter()
+        .map(|y| {
+            let mut row: Option<(usize, usize, f64, f64)> = None;
+            for i in (y * w..(y + 1) * w).filter(|&i| keep(i) && !values[i].is_nan()) {
+                let v = values[i];
+                let (lo, hi, sum, area) = row.get_or_insert((i, i, 0.0, 0.0));
+                if v < values[*lo] {
+                    *lo = i;
+                }
+                if v > values[*hi] {
+                    *hi = i;
+                }
+                *sum += v as f64 * row_km2[y];
+                *area += row_km2[y];
+            }
+            row
+        })
+        .collect();
+    let (lo, hi, sum, area) = rows.into_iter().flatten().reduce(|a, b| {
+        let lo = if values[b.0] < values[a.0] { b.0 } else { a.0 };
+        let hi = if values[b.1] > values[a.1] { b.1 } else { a.1 };
+        (lo, hi, a.2 + b.2, a.3 + b.3)
+    })?;
+    let extreme = |i: usize| {
+        let (lat, lon) = cell_latlon(i % w, i / w, w, h);
+        Extreme { value: values[i], lat, lon }
+    };
+    Some(LayerSummary { min: extreme(lo), max: extreme(hi), mean: (sum / area) as f32 })
+}
@@ -0,0 +1,142 @@
+use crate::fmath;
+use crate::grid::{Grid, Rect};
+
+/// Equatorial circumference of an Earth-sized planet (meters).
+pub const PLANET_CIRCUMFERENCE_M: f32 = 40_075_000.0;
+
+/// Cell size in meters (east-west, north-south) at row `y` of an
+/// equirectangular grid. E-W spacing shrinks with cos(latitude).
+#[inline]
+pub fn cell_size_m(y: usize, w: usize, h: usize) -> (f32, f32) {
+    let lat = (0.5 - (y as f32 + 0.5) / h as f32) * std::f32::consts::PI;
+    let dx = PLANET_CIRCUMFERENCE_M / w as f32 * fmath::cos(lat).max(0.01);
+    let dy = PLANET_CIRCUMFERENCE_M * 0.5 / h as f32;
+    (dx, dy)
+}
+
+/// Elevation gradient (dz/dx east, dz/dy north) in meters per meter,
+/// by central differences. E-W wraps, N-S clamps at the poles.
+#[inline]
+pub fn gradient(height: &Grid<f32>, x: usize, y: usize) -> (f32, f32) {
+    let w = height.w;
+    let h = height.h;
+    let (cx, cy) = cell_size_m(y, w, h);
+    let e = height.get((x + 1) % w, y);
+    let wv = height.get((x + w - 1) % w, y);
+    let yn = y.saturating_sub(1);
+    let ys = (y + 1).min(h - 1);
+    let n = height.get(x, yn);
+    let s = height.get(x, ys);
+    let dzdx = (e - wv) / (2.0 * cx);
+    // Row index grows southward, so north is -y
+    let dzdy = (n - s) / ((ys - yn).max(1) as f32 * cy);
+    (dzdx, dzdy)
+}
+
+/// Per-cell unit surface normals in tangent space (x east, y north, z up).
+/// `exaggeration` multiplies vertical relief (1.0 = physically scaled).
+pub fn compute_normals(height: &Grid<f32>, exaggeration: f32) -> Grid<[f32; 3]> {
+    Grid::par_from_fn(height.w, height.h, |x, y| {
+        let (gx, gy) = gradient(height, x, y);
+        let nx = -gx * exaggeration;
+        let ny = -gy * exaggeration;
+        let len = (nx * nx + ny * ny + 1.0).sqrt();
+        [nx / len, ny / len, 1.0 / len]
+    })
+}
+
+/// Slope angle in degrees from horizontal.
+pub fn compute_slope(height: &Grid<f32>) -> Grid<f32> {
+    Grid::par_from_fn(height.w, height.h, |x, y| {
+        let (gx, gy) = gradient(height, x, y);
+        fmath::atan((gx * gx + gy * gy).sqrt()).to_degrees()
+    })
+}
+
+/// Aspect: compass direction the slope faces (downhill), in degrees
+/// clockwise from north in [0, 360). Flat cells get -1.
+pub fn compute_aspect(height: &Grid<f32>) -> Grid<f32> {
+    Grid::par_from_fn(height.w, height.h, |x, y| {
+        let (gx, gy) = gradient(height, x, y);
+        if gx == 0.0 && gy == 0.0 {
+            -1.0
+        } else {
+            // Downhill = -gradient; atan2(east, north) gives compass bearing
+            fmath::atan2(-gx, -gy).to_degrees().rem_euclid(360.0)
+        }
+    })
+}
+
+/// AO search radius in cells: 16 at 2048 wide, scaled with resolution.
+pub fn default_ao_radius(w: usize) -> usize {
+    (16.0 * w as f32 / 2048.0).ceil().max(2.0) as usize
+}
+
+/// Horizon-based ambient occlusion: 1 = open sky, 0 = as enclosed as the
+/// most occluded 1% of cells. Eight directions are marched at doubling
+/// distances up to `radius` cells, so narrow valleys and broad basins both
+/// darken. The sea surface counts as flat. Distances use the N-S cell size
+/// on both axes: relief is generated in map space, so true E-W spacing
+/// would make every polar slope look like a cliff.
+pub fn compute_ambient_occlusion(height: &Grid<f32>, radius: usize) -> Grid<f32> {
+    let (w, h) = (height.w, height.h);
+    let radius = radius.max(1);
+    let (_, cell_m) = cell_size_m(0, w, h);
+    let mut occ = Grid::<f32>::new(w, h);
+    occ.par_fill_with(|x, y| occlusion(height, x, y, radius, cell_m));
+
+    let norm = occ.percentile(0.99).map_or(1.0, |p| p.max(1e-6));
+    for v in &mut occ.data {
+        *v = 1.0 - (*v / norm).min(1.0);
+    }
+    occ
+}
+
+/// `compute_ambient_occlusion` over the cells of `rect` (columns wrap)
+/// only, as a window the size of `rect`. Horizons are marched through the
+/// whole of `height`; the 1% normalization comes from an even sample of
+/// at most `AO_NORM_SAMPLES` cells across the map rather than every cell.
+pub fn compute_ambient_occlusion_window(height: &Grid<f32>, rect: Rect, radius: usize) -> Grid<f32> {
+    let (w, h) = (height.w, height.h);
+    let radius = radius.max(1);
+    let (_, cell_m) = cell_size_m(0, w, h);
+    let mut occ = Grid::<f32>::new(rect.w.min(w), rect.h.min(h.saturating_sub(rect.y)));
+    occ.par_fill_with(|x, y| occlusion(height, (rect.x + x) % w, rect.y + y, radius, cell_m));
+
+    let stride = ((w * h) as f32 / AO_NORM_SAMPLES as f32).sqrt().ceil().max(1.0) as usize;
+    let sample = Grid::par_from_fn(w.div_ceil(stride), h.div_ceil(stride), |x, y| {
+        occlusion(height, x * stride, y * stride, radius, cell_m)
+    });
+    let norm = sample.percentile(0.99).map_or(1.0, |p| p.max(1e-6));
+    occ.par_map(|v| 1.0 - (v / norm).min(1.0))
+}
+
+/// Cells sampled for the normalization of `compute_ambient_occlusion_window`.
+const AO_NORM_SAMPLES: usize = 1 << 16;
+
+/// Mean sine of the horizon angle over eight directions at cell (x, y),
+/// before normalization.
+fn occlusion(height: &Grid<f32>, x: usize, y: usize, radius: usize, cell_m: f32) -> f32 {
+    const DIRS: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+    let (w, h) = (height.w, height.h);
+    let e = height.get(x, y).max(0.0);
+    let mut sum = 0.0;
+    for &(dx, dy) in &DIRS {
+        let step_m = cell_m * ((dx * dx + dy * dy) as f32).sqrt();
+        let mut max_tan = 0.0f32;
+        let mut d = 1usize;
+        while d <= radius {
+            let sy = y as i32 + dy * d as i32;
+            if sy < 0 || sy >= h as i32 {
+                break;
+            }
+            let sx = (x as i32 + dx * d as i32).rem_euclid(w as i32) as usize;
+            let se = height.get(sx, sy as usize).max(0.0);
+            max_tan = max_tan.max((se - e) / (step_m * d as f32));
+            d *= 2;
+        }
+        // sin(atan(t))
+        sum += max_tan / (1.0 + max_tan * max_tan).sqrt();
+    }
+    sum / DIRS.len() as f32
+}
@@ -0,0 +1,24 @@
+//! How many threads generation uses. The stages run on rayon's global
+//! pool, one worker per core unless `RAYON_NUM_THREADS` says otherwise.
+//! An application embedding the generator can cap that pool once with
+//! `set_global_threads`, or keep generation off it entirely with a pool
+//! of its own: `Pipeline::set_thread_pool`, or `pool.install(|| ...)`
+//! around any of the `generate*` functions.
+
+pub use rayon::{ThreadPool, ThreadPoolBuildError};
+
+/// Size rayon's global pool. Call it before the first generation: the
+/// global pool is built on first use and can't be resized after, which
+/// is reported as an error.
+pub fn set_global_threads(threads: usize) -> Result<(), ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()
+}
+
+/// A pool of `threads` workers (named `worldgen-<n>`) for
+/// `Pipeline::set_thread_pool` or `ThreadPool::install`.
+pub fn pool(threads: usize) -> Result<ThreadPool, ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("worldgen-{}", i))
+        .build()
+}